@@ -0,0 +1,22 @@
+-- Surveys for SURVEY_BACKEND=postgres.  Everything else still lives in MySQL, see survey-manager-infra/migrations/mysql.
+-- Kept out of the directory above so the MySQL container doesn't try to run it.
+
+CREATE TABLE survey (
+	id VARCHAR(64) PRIMARY KEY,
+	version BIGINT NOT NULL,
+	author VARCHAR(64) NOT NULL,
+	title VARCHAR(128) NOT NULL,
+	category VARCHAR(64) NOT NULL,
+	created_on BIGINT NOT NULL,
+	-- Set when an organization owns the survey, copied out of survey_data like the columns above.
+	organization_id VARCHAR(64) NULL,
+	survey_data JSONB NOT NULL
+);
+
+CREATE INDEX survey_author ON survey (author);
+CREATE INDEX survey_organization ON survey (organization_id);
+
+-- Answers containment queries on survey_data, e.g. which surveys are shared with someone:
+--   survey_data @> '{"collaborators": [{"username": "..."}]}'
+-- so collaborators don't need a table of their own like they do in MySQL.
+CREATE INDEX survey_data_contents ON survey USING GIN (survey_data jsonb_path_ops);
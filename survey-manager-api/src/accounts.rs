@@ -0,0 +1,35 @@
+use actix_web::web;
+use futures::Future;
+use survey_manager_core::app_services::accounts::{RegisterUserCommand, LoginCommand, RefreshSessionCommand, LogoutCommand};
+use survey_manager_core::dtos::TokenPairDTO;
+use crate::generate;
+use crate::error::ApiError;
+
+pub fn register_user_async(
+    cmd: RegisterUserCommand,
+) -> impl Future<Item = String, Error = ApiError> {
+    web::block(move || generate::handle_account_command(cmd) )
+        .from_err()
+}
+
+pub fn login_async(
+    cmd: LoginCommand,
+) -> impl Future<Item = TokenPairDTO, Error = ApiError> {
+    web::block(move || generate::handle_account_command(cmd) )
+        .from_err()
+}
+
+/// A replayed or refused token still commits what the refresh recorded before it's turned away.
+pub fn refresh_session_async(
+    cmd: RefreshSessionCommand,
+) -> impl Future<Item = TokenPairDTO, Error = ApiError> {
+    web::block(move || generate::handle_account_command(cmd)?.into_pair() )
+        .from_err()
+}
+
+pub fn logout_async(
+    cmd: LogoutCommand,
+) -> impl Future<Item = (), Error = ApiError> {
+    web::block(move || generate::handle_account_command(cmd) )
+        .from_err()
+}
@@ -1,32 +1,28 @@
 use futures::Future;
-use survey_manager_core::app_services::{Payload, decode_payload};
+use survey_manager_core::app_services::Payload;
 use actix_web::web;
+use futures::future::{Either, ok, err};
 use crate::error::{TokenError, ApiError};
-use crate::inputs::{CreateSurveyDTO, UpdateSurveyDTO};
-use survey_manager_core::app_services::commands::{CreateSurveyCommand, UpdateSurveyCommand};
-use std::convert::TryInto;
+use crate::extractors::MaybePrincipal;
+use crate::generate;
 
 pub fn decode_payload_async(
     token: String,
 ) -> impl Future<Item = Payload, Error = ApiError> {
-    web::block(move || decode_payload(&token).map_err(|_| TokenError::TokenExpired) )
+    web::block(move || generate::authenticate(&token) )
         .from_err()
 }
 
-pub fn try_into_create_cmd_async(
-    dto: CreateSurveyDTO,
-) -> impl Future<Item = CreateSurveyCommand, Error = ApiError> {
-    web::block(move || {
-        dto.try_into()
-    })
-        .from_err()
-}
-
-pub fn try_into_update_cmd_async(
-    dto: UpdateSurveyDTO,
-) -> impl Future<Item = UpdateSurveyCommand, Error = ApiError> {
-    web::block(move || {
-        dto.try_into()
-    })
-        .from_err()
+/// Resolves the caller on routes that used to take their token in the request body.  The
+/// `Authorization` header wins, the body token is only consulted without one.  The flag is true
+/// when the deprecated body token was used, so the response can warn about it.
+pub fn principal_or_body_token_async(
+    principal: MaybePrincipal,
+    body_token: Option<String>,
+) -> impl Future<Item = (Payload, bool), Error = ApiError> {
+    match (principal.into_inner(), body_token) {
+        (Some(principal), _) => Either::A(ok((principal.into_inner(), false))),
+        (None, Some(token)) => Either::B(decode_payload_async(token).map(|payload| (payload, true))),
+        (None, None) => Either::A(err(TokenError::MissingBearer.into())),
+    }
 }
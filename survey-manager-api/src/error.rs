@@ -1,4 +1,5 @@
 use survey_manager_core::Error as SMError;
+use survey_manager_core::app_services::token::TokenRejection;
 use actix_web::{ResponseError, HttpResponse, http};
 use actix_web::dev::HttpResponseBuilder;
 use actix_web::error::BlockingError;
@@ -63,7 +64,11 @@ impl ResponseError for CoreError {
                 HttpResponse::new(http::StatusCode::INTERNAL_SERVER_ERROR)
             }
             SMError::ValidationError {..} => HttpResponse::new(http::StatusCode::UNPROCESSABLE_ENTITY),
+            SMError::InvalidFields {..} => HttpResponse::new(http::StatusCode::UNPROCESSABLE_ENTITY),
+            SMError::InvalidDocument {..} => HttpResponse::new(http::StatusCode::BAD_REQUEST),
             SMError::ResourceNotFound {..} => HttpResponse::new(http::StatusCode::NOT_FOUND),
+            SMError::AlreadyExists {..} => HttpResponse::new(http::StatusCode::CONFLICT),
+            SMError::StaleVersion {..} => HttpResponse::new(http::StatusCode::CONFLICT),
             SMError::NotAuthorized => HttpResponse::new(http::StatusCode::FORBIDDEN),
             SMError::UnknownFailure => HttpResponse::new(http::StatusCode::INTERNAL_SERVER_ERROR),
             SMError::ConcurrencyFailure => HttpResponse::new(http::StatusCode::INTERNAL_SERVER_ERROR),
@@ -76,7 +81,11 @@ impl ResponseError for CoreError {
                 HttpResponseBuilder::new(http::StatusCode::INTERNAL_SERVER_ERROR).json(error_struct)
             }
             SMError::ValidationError {..} => HttpResponseBuilder::new(http::StatusCode::UNPROCESSABLE_ENTITY).json(error_struct),
+            SMError::InvalidFields {..} => HttpResponseBuilder::new(http::StatusCode::UNPROCESSABLE_ENTITY).json(error_struct),
+            SMError::InvalidDocument {..} => HttpResponseBuilder::new(http::StatusCode::BAD_REQUEST).json(error_struct),
             SMError::ResourceNotFound {..} => HttpResponseBuilder::new(http::StatusCode::NOT_FOUND).json(error_struct),
+            SMError::AlreadyExists {..} => HttpResponseBuilder::new(http::StatusCode::CONFLICT).json(error_struct),
+            SMError::StaleVersion {..} => HttpResponseBuilder::new(http::StatusCode::CONFLICT).json(error_struct),
             SMError::NotAuthorized => HttpResponseBuilder::new(http::StatusCode::FORBIDDEN).json(error_struct),
             SMError::UnknownFailure => HttpResponse::new(http::StatusCode::INTERNAL_SERVER_ERROR),
             SMError::ConcurrencyFailure => HttpResponse::new(http::StatusCode::INTERNAL_SERVER_ERROR),
@@ -112,26 +121,86 @@ pub enum TokenError {
 
     #[display(fmt = "Token has expired.")]
     TokenExpired,
+
+    #[display(fmt = "Token has been revoked.")]
+    TokenRevoked,
+
+    #[display(fmt = "Token is malformed.")]
+    TokenMalformed,
+
+    #[display(fmt = "Token is signed by an unknown key.")]
+    UnknownSigningKey,
+
+    #[display(fmt = "Token signature is invalid.")]
+    BadSignature,
+
+    #[display(fmt = "Token was issued by an untrusted issuer.")]
+    WrongIssuer,
+
+    #[display(fmt = "Token is meant for another audience.")]
+    WrongAudience,
+
+    #[display(fmt = "Token is not valid yet.")]
+    TokenNotYetValid,
+}
+
+impl From<TokenRejection> for TokenError {
+    fn from(rejection: TokenRejection) -> Self {
+        match rejection {
+            TokenRejection::Malformed => TokenError::TokenMalformed,
+            TokenRejection::UnknownKey => TokenError::UnknownSigningKey,
+            TokenRejection::BadSignature => TokenError::BadSignature,
+            TokenRejection::WrongIssuer => TokenError::WrongIssuer,
+            TokenRejection::WrongAudience => TokenError::WrongAudience,
+            TokenRejection::Expired => TokenError::TokenExpired,
+            TokenRejection::NotYetValid => TokenError::TokenNotYetValid,
+        }
+    }
 }
 
 
 #[derive(Serialize)]
 pub struct ErrorJson {
     error: String,
+    // Only present when validation failed, one entry per field that was rejected.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<FieldErrorJson>,
+}
+
+#[derive(Serialize)]
+pub struct FieldErrorJson {
+    /// JSON pointer to the rejected field, e.g. `/questions/2/choices/0/title`.
+    path: String,
+    message: String,
 }
 
 impl From<&TokenError> for ErrorJson {
     fn from(err: &TokenError) -> Self {
         ErrorJson {
             error: format!("{}", err),
+            errors: vec![],
         }
     }
 }
 
 impl From<&CoreError> for ErrorJson {
     fn from(err: &CoreError) -> Self {
+        if let SMError::InvalidFields { errors } = &err.0 {
+            return ErrorJson {
+                error: format!("{} field(s) failed to validate", errors.len()),
+                errors: errors.iter()
+                    .map(|e| {
+                        FieldErrorJson {
+                            path: e.path.clone(),
+                            message: format!("{}", e.error),
+                        }
+                    }).collect(),
+            };
+        }
+
         ErrorJson {
             error: format!("{}", err),
+            errors: vec![],
         }
     }
 }
@@ -139,7 +208,8 @@ impl From<&CoreError> for ErrorJson {
 impl From<&ApiError> for ErrorJson {
     fn from(err: &ApiError) -> Self {
         ErrorJson {
-            error: format!("{}", err)
+            error: format!("{}", err),
+            errors: vec![],
         }
     }
 }
@@ -151,7 +221,7 @@ impl ResponseError for TokenError {
             TokenError::MissingBearer => {
                 HttpResponse::new(http::StatusCode::BAD_REQUEST)
             }
-            TokenError::TokenExpired => {
+            _ => {
                 HttpResponse::new(http::StatusCode::UNAUTHORIZED)
             }
         }
@@ -161,7 +231,7 @@ impl ResponseError for TokenError {
             TokenError::MissingBearer => {
                 HttpResponseBuilder::new(http::StatusCode::BAD_REQUEST).json(ErrorJson::from(self))
             }
-            TokenError::TokenExpired => {
+            _ => {
                 HttpResponseBuilder::new(http::StatusCode::UNAUTHORIZED).json(ErrorJson::from(self))
             }
         }
@@ -1,7 +1,11 @@
 use actix_web::{FromRequest, HttpRequest, Error, http::header};
 use std::sync::Arc;
+use futures::Future;
+use futures::future::{ok, err};
 use crate::error::TokenError;
+use crate::async_utils::decode_payload_async;
 use actix_web::dev::Payload;
+use survey_manager_core::app_services::token::Payload as Claims;
 
 pub struct Token {
     inner: String
@@ -99,6 +103,86 @@ impl Default for TokenConfig {
     }
 }
 
+/// The authenticated caller.  Extracting it reads the bearer token from the `Authorization`
+/// header, verifies it and checks it against the revocation list, so handlers get the claims
+/// directly and never decode a token themselves.
+///
+/// A request without a token is rejected with 400, one with a bad token with 401.
+pub struct Principal {
+    claims: Claims,
+}
+
+impl Principal {
+    pub fn username(&self) -> &String {
+        &self.claims.username
+    }
+
+    pub fn user_id(&self) -> &String {
+        &self.claims.user_id
+    }
+
+    /// Deconstruct to the verified claims.
+    pub fn into_inner(self) -> Claims {
+        self.claims
+    }
+}
+
+impl From<Claims> for Principal {
+    fn from(claims: Claims) -> Self {
+        Principal { claims }
+    }
+}
+
+impl std::ops::Deref for Principal {
+    type Target = Claims;
+
+    fn deref(&self) -> &Claims {
+        &self.claims
+    }
+}
+
+impl FromRequest for Principal {
+    type Error = Error;
+    type Future = Box<dyn Future<Item = Self, Error = Error>>;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        match token_from_req(req) {
+            Some(token) => Box::new(
+                decode_payload_async(token)
+                    .map(Principal::from)
+                    .from_err()
+            ),
+            None => Box::new(err(TokenError::MissingBearer.into())),
+        }
+    }
+}
+
+/// The caller on a route that also serves anonymous requests.  No `Authorization` header gives
+/// `None`, but a token that is present and bad is still rejected.  Prefer this over
+/// `Option<Principal>`, which quietly treats an expired or revoked token as anonymous.
+pub struct MaybePrincipal(Option<Principal>);
+
+impl MaybePrincipal {
+    pub fn into_inner(self) -> Option<Principal> {
+        self.0
+    }
+}
+
+impl FromRequest for MaybePrincipal {
+    type Error = Error;
+    type Future = Box<dyn Future<Item = Self, Error = Error>>;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        if req.headers().get(header::AUTHORIZATION).is_none() {
+            return Box::new(ok(MaybePrincipal(None)));
+        }
+
+        Box::new(Principal::from_request(req, payload).map(|p| MaybePrincipal(Some(p))))
+    }
+}
+
 pub fn token_from_req(req: &HttpRequest) -> Option<String> {
     let headers =
         if let Some(h) =  req
@@ -1,17 +1,249 @@
-use survey_manager_infra::mysql_repos::{MysqlSurveyWriteRepository, MysqlSurveyDTOsRepository};
-use survey_manager_core::app_services::commands::SurveyCommandsHandler;
+use survey_manager_infra::mysql_repos::{MysqlUserRepository, MysqlRefreshTokenRepository, MysqlOrganizationRepository, MysqlAuditLog, MysqlInvitationRepository, MysqlResponseRepository, MysqlPartialResponseRepository, MysqlSurveySnapshotRepository, MysqlConnection, mysql_pool, check_mysql_config};
+use survey_manager_infra::survey_backends::{SurveyBackend, SurveyWriteRepository, SurveyDTOsRepository};
+use survey_manager_infra::mailers::FileSpoolMailer;
+use survey_manager_infra::redis_stores::RedisRevocationList;
+use survey_manager_core::app_services::accounts::AccountCommandsHandler;
+use survey_manager_core::app_services::organizations::OrganizationCommandsHandler;
+use survey_manager_core::app_services::moderation::{ModerationCommandsHandler, ModerationQueriesHandler};
+use survey_manager_core::app_services::invitations::{InvitationCommandsHandler, InvitationQueriesHandler, InvitationSigner};
+use survey_manager_core::app_services::token::Payload;
+use survey_manager_core::Error as SMError;
+use domain_patterns::command::{Command, Handles};
+use crate::error::TokenError;
+use survey_manager_core::app_services::commands::{SurveyCommandsHandler, SurveyCommands};
 use survey_manager_core::app_services::queries::SurveyQueriesHandler;
-use survey_manager_infra::cache_repo_decorators::{RedisSurveyReadCacheRepository, RedisSurveyWriteCacheRepository};
+use survey_manager_core::app_services::responses::{ResponseCommandsHandler, ResponseConfig};
+use survey_manager_core::app_services::results::ResultsQueriesHandler;
+use survey_manager_infra::cache_repo_decorators::{RedisSurveyReadCacheRepository, RedisSurveyWriteCacheRepository, TieredSurveyReadRepository, local_cache, spawn_invalidation_listener, cache_config, RevocationFallback};
+use survey_manager_core::app_services::token::{TokenService, TokenConfig, TokenConfigError};
+use survey_manager_infra::utils::HttpJwksSource;
+use survey_manager_infra::utils::redis_pool::check_redis_config;
 
-pub fn command_handler() -> SurveyCommandsHandler<RedisSurveyWriteCacheRepository<MysqlSurveyWriteRepository>> {
-    let primary_repo = MysqlSurveyWriteRepository::new();
-    let cache_writing_repo = RedisSurveyWriteCacheRepository::new(primary_repo);
-    SurveyCommandsHandler::new(cache_writing_repo)
+lazy_static! {
+    static ref TOKEN_SERVICE: TokenService = {
+        build_token_service().unwrap_or_else(|e| panic!("invalid token configuration: {}", e))
+    };
+
+    static ref INVITATION_SIGNER: InvitationSigner = {
+        InvitationSigner::from_env().unwrap_or_else(|e| panic!("invalid invitation configuration: {}", e))
+    };
+
+    static ref RESPONSE_CONFIG: ResponseConfig = ResponseConfig::from_env();
+
+    static ref SURVEY_BACKEND: SurveyBackend = {
+        SurveyBackend::from_env().unwrap_or_else(|e| panic!("invalid survey backend configuration: {}", e))
+    };
+}
+
+fn build_token_service() -> Result<TokenService, TokenConfigError> {
+    let config = TokenConfig::from_env()?;
+    // A local JWKS file takes precedence, `TokenService::new` handles that case itself.
+    match config.jwks.as_ref().filter(|jwks| jwks.path.is_none()).and_then(|jwks| jwks.url.clone()) {
+        Some(url) => {
+            let source = HttpJwksSource::new(url.clone())
+                .map_err(|e| TokenConfigError::JwksSourceUnavailable { url, reason: e.to_string() })?;
+            TokenService::with_jwks_source(config, Box::new(source))
+        },
+        None => TokenService::new(config),
+    }
+}
+
+/// The token service shared by every request.  Keys are parsed once, on first use, so call this
+/// during startup to surface configuration problems before the server starts accepting requests.
+pub fn token_service() -> &'static TokenService {
+    &TOKEN_SERVICE
+}
+
+/// Signs and checks invitation links.  Like `token_service`, call this during startup to surface
+/// configuration problems early.
+pub fn invitation_signer() -> &'static InvitationSigner {
+    &INVITATION_SIGNER
+}
+
+/// Where surveys are stored, from `SURVEY_BACKEND`.  Like `token_service`, call this during startup.
+pub fn survey_backend() -> SurveyBackend {
+    *SURVEY_BACKEND
+}
+
+/// Checks `DATABASE_URL`.  Like `token_service`, call this during startup.  MySQL itself being
+/// down doesn't fail it, the pool is built on first use and requests fail until MySQL is back.
+pub fn check_database_config() {
+    check_mysql_config().unwrap_or_else(|e| panic!("invalid database configuration: {}", e));
+}
+
+/// Checks `CACHE_URL`.  Like `token_service`, call this during startup.  Once it passes the
+/// handlers below build even while Redis is down, they bypass it until it's back.  A bad
+/// `CACHE_URL` that slips past this fails the request rather than the process.
+pub fn check_cache_config() {
+    check_redis_config().unwrap_or_else(|e| panic!("invalid cache configuration: {}", e));
+}
+
+/// Subscribes this node's in-process survey cache to invalidations published by writes on every
+/// node.  Call once during startup, the listener runs for the life of the process and keeps
+/// reconnecting if Redis goes away.
+pub fn start_cache_invalidations() {
+    let local = local_cache();
+    if local.is_enabled() {
+        spawn_invalidation_listener(local)
+            .unwrap_or_else(|e| panic!("could not subscribe to cache invalidations: {}", e));
+    }
+}
+
+/// Verifies `token` and checks it hasn't been revoked by a logout.
+pub fn authenticate(token: &str) -> Result<Payload, TokenError> {
+    let payload = token_service().decode_payload(token)?;
+
+    if let Some(jti) = &payload.jti {
+        // Redis being unreachable is left to `CACHE_REVOCATION_FALLBACK`, refusing the token unless
+        // it's set to allow.  `check_cache_config` has vetted `CACHE_URL`, so `new` can't fail here.
+        let fallback = cache_config().revocation_fallback;
+        let revoked = match RedisRevocationList::new() {
+            Ok(mut revocations) => revocations.is_revoked_or(jti, fallback),
+            Err(_) => fallback == RevocationFallback::Reject,
+        };
+        if revoked {
+            return Err(TokenError::TokenRevoked);
+        }
+    }
+
+    Ok(payload)
+}
+
+pub type AccountHandler = AccountCommandsHandler<'static, MysqlUserRepository, MysqlRefreshTokenRepository, RedisRevocationList, MysqlOrganizationRepository>;
+
+/// Handles `cmd` with users, refresh tokens and organizations in one unit of work, so a refresh
+/// can't mark its token used without issuing the next one.  The handler is rebuilt for each retry
+/// of a deadlock, see `MysqlPool::in_unit_of_work`.
+pub fn handle_account_command<C, T>(cmd: C) -> Result<T, SMError> where
+    C: Command + Clone,
+    AccountHandler: Handles<C, Result = Result<T, SMError>>,
+{
+    mysql_pool()?.in_unit_of_work(|unit| account_handler(unit.connection())?.handle(cmd.clone()))
+}
+
+fn account_handler(conn: MysqlConnection) -> Result<AccountHandler, SMError> {
+    Ok(AccountCommandsHandler::new(
+        MysqlUserRepository::with_connection(conn.clone()),
+        MysqlRefreshTokenRepository::with_connection(conn.clone()),
+        RedisRevocationList::new()?,
+        MysqlOrganizationRepository::with_connection(conn),
+        token_service(),
+    ))
+}
+
+/// Surveys go through the write cache so a moderator's delete clears cached copies like any other.
+/// Each repository commits on its own, the audit entry has to stick even when the action fails.
+pub fn moderation_command_handler() -> Result<ModerationCommandsHandler<RedisSurveyWriteCacheRepository<SurveyWriteRepository>, MysqlUserRepository, MysqlRefreshTokenRepository, MysqlAuditLog>, SMError> {
+    Ok(ModerationCommandsHandler::new(
+        RedisSurveyWriteCacheRepository::new(survey_backend().write_repository()?)?,
+        MysqlUserRepository::new()?,
+        MysqlRefreshTokenRepository::new()?,
+        MysqlAuditLog::new()?,
+    ))
+}
+
+pub fn moderation_query_handler() -> Result<ModerationQueriesHandler<SurveyWriteRepository, MysqlAuditLog>, SMError> {
+    Ok(ModerationQueriesHandler::new(survey_backend().write_repository()?, MysqlAuditLog::new()?))
+}
+
+/// Mail goes to a spool directory, `MAIL_SPOOL_DIR` or `./mail-spool`, until a real transport is
+/// wired in here.  Mail already sent can't be rolled back, so there's no unit of work to retry.
+pub fn invitation_command_handler() -> Result<InvitationCommandsHandler<'static, SurveyWriteRepository, MysqlInvitationRepository, FileSpoolMailer>, SMError> {
+    let spool_dir = std::env::var("MAIL_SPOOL_DIR").unwrap_or("./mail-spool".to_string());
+    Ok(InvitationCommandsHandler::new(
+        survey_backend().write_repository()?,
+        MysqlInvitationRepository::new()?,
+        FileSpoolMailer::new(spool_dir),
+        invitation_signer(),
+    ))
+}
+
+pub fn invitation_query_handler() -> Result<InvitationQueriesHandler<SurveyWriteRepository, MysqlInvitationRepository>, SMError> {
+    Ok(InvitationQueriesHandler::new(survey_backend().write_repository()?, MysqlInvitationRepository::new()?))
+}
+
+pub type ResponseHandler = ResponseCommandsHandler<'static, SurveyWriteRepository, MysqlResponseRepository, MysqlPartialResponseRepository, MysqlSurveySnapshotRepository, MysqlInvitationRepository>;
+
+/// Handles `cmd` with responses, partial responses, survey snapshots and invitations in one unit of
+/// work, so a response, the version it answered, the partial it finishes and the link's invitation
+/// being completed commit together.  Responses live in MySQL whichever backend holds the surveys,
+/// which are only read here.
+pub fn handle_response_command<C, T>(cmd: C) -> Result<T, SMError> where
+    C: Command + Clone,
+    ResponseHandler: Handles<C, Result = Result<T, SMError>>,
+{
+    mysql_pool()?.in_unit_of_work(|unit| response_handler(unit.connection())?.handle(cmd.clone()))
+}
+
+fn response_handler(conn: MysqlConnection) -> Result<ResponseHandler, SMError> {
+    Ok(ResponseCommandsHandler::new(
+        survey_backend().write_repository()?,
+        MysqlResponseRepository::with_connection(conn.clone()),
+        MysqlPartialResponseRepository::with_connection(conn.clone()),
+        MysqlSurveySnapshotRepository::with_connection(conn.clone()),
+        MysqlInvitationRepository::with_connection(conn),
+        invitation_signer(),
+        &RESPONSE_CONFIG,
+    ))
+}
+
+pub fn results_query_handler() -> Result<ResultsQueriesHandler<SurveyWriteRepository, MysqlResponseRepository, MysqlPartialResponseRepository, MysqlSurveySnapshotRepository>, SMError> {
+    Ok(ResultsQueriesHandler::new(
+        survey_backend().write_repository()?,
+        MysqlResponseRepository::new()?,
+        MysqlPartialResponseRepository::new()?,
+        MysqlSurveySnapshotRepository::new()?,
+    ))
+}
+
+/// Handles `cmd` in a unit of work, so a membership change is read and written in one transaction.
+pub fn handle_organization_command<C, T>(cmd: C) -> Result<T, SMError> where
+    C: Command + Clone,
+    OrganizationCommandsHandler<MysqlOrganizationRepository>: Handles<C, Result = Result<T, SMError>>,
+{
+    mysql_pool()?.in_unit_of_work(|unit| {
+        OrganizationCommandsHandler::new(MysqlOrganizationRepository::with_connection(unit.connection()))
+            .handle(cmd.clone())
+    })
+}
+
+/// Handles `cmd` in a unit of work when surveys are stored in MySQL, so a survey is read and written
+/// back in one transaction and nothing can change in between.  The cache is only touched once
+/// that's committed.  Other backends can't share MySQL's transactions, so there each write commits
+/// on its own.
+pub fn handle_survey_command(cmd: SurveyCommands) -> Result<String, SMError> {
+    let backend = survey_backend();
+    if !backend.uses_mysql() {
+        return command_handler()?.handle(cmd);
+    }
+
+    let mut committed = None;
+    let id = mysql_pool()?.in_unit_of_work(|unit| {
+        let repo = backend.write_repository_on(unit.connection())?;
+        let mut handler = SurveyCommandsHandler::new(RedisSurveyWriteCacheRepository::new(repo)?.deferred());
+        let id = handler.handle(cmd.clone())?;
+        // Replaced on a retry, so only the attempt that committed gets to write the cache.
+        committed = Some(handler.into_repository());
+        Ok::<_, SMError>(id)
+    })?;
+
+    if let Some(mut cache_writing_repo) = committed {
+        cache_writing_repo.flush();
+    }
+    Ok(id)
+}
+
+/// Each write commits on its own, for survey backends other than MySQL.  See `handle_survey_command`.
+pub fn command_handler() -> Result<SurveyCommandsHandler<RedisSurveyWriteCacheRepository<SurveyWriteRepository>>, SMError> {
+    let primary_repo = survey_backend().write_repository()?;
+    let cache_writing_repo = RedisSurveyWriteCacheRepository::new(primary_repo)?;
+    Ok(SurveyCommandsHandler::new(cache_writing_repo))
 }
 
 /// Produces a query handler that is built with a wrapped repo, that's wrapped for caching abilities.
-pub fn query_handler() -> SurveyQueriesHandler<RedisSurveyReadCacheRepository<MysqlSurveyDTOsRepository>> {
-    let primary_repo = MysqlSurveyDTOsRepository::new();
-    let cache_enhanced_repo = RedisSurveyReadCacheRepository::new(primary_repo);
-    SurveyQueriesHandler::new(cache_enhanced_repo)
+/// Reads check this process's memory first, then Redis, then the survey backend.
+pub fn query_handler() -> Result<SurveyQueriesHandler<TieredSurveyReadRepository<RedisSurveyReadCacheRepository<SurveyDTOsRepository>>>, SMError> {
+    let primary_repo = survey_backend().read_repository()?;
+    let cache_enhanced_repo = RedisSurveyReadCacheRepository::new(primary_repo)?;
+    Ok(SurveyQueriesHandler::new(TieredSurveyReadRepository::new(cache_enhanced_repo)))
 }
@@ -0,0 +1,56 @@
+use serde::Deserialize;
+use survey_manager_core::app_services::accounts::{RegisterUserCommand, LoginCommand, RefreshSessionCommand};
+
+#[derive(Deserialize)]
+pub struct RegisterUserDTO {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Deserialize)]
+pub struct LoginDTO {
+    pub username: String,
+    pub password: String,
+    /// Id of the organization to act for.  Leave out for a personal session.
+    #[serde(default)]
+    pub organization: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct RefreshDTO {
+    pub refresh_token: String,
+}
+
+/// Optional body for logout.  Supplying the refresh token ends the whole session rather than just
+/// the access token in the header.
+#[derive(Deserialize)]
+pub struct LogoutDTO {
+    pub refresh_token: Option<String>,
+}
+
+impl Into<RegisterUserCommand> for RegisterUserDTO {
+    fn into(self) -> RegisterUserCommand {
+        RegisterUserCommand {
+            username: self.username,
+            password: self.password,
+        }
+    }
+}
+
+impl Into<LoginCommand> for LoginDTO {
+    fn into(self) -> LoginCommand {
+        LoginCommand {
+            username: self.username,
+            password: self.password,
+            organization: self.organization,
+        }
+    }
+}
+
+impl Into<RefreshSessionCommand> for RefreshDTO {
+    fn into(self) -> RefreshSessionCommand {
+        RefreshSessionCommand {
+            refresh_token: self.refresh_token,
+        }
+    }
+}
@@ -0,0 +1,7 @@
+use serde::Deserialize;
+
+/// Email addresses to send the survey to.
+#[derive(Deserialize)]
+pub struct CreateInvitationsDTO {
+    pub recipients: Vec<String>,
+}
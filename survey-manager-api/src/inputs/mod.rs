@@ -1,2 +1,12 @@
 pub mod survey;
-pub use survey::*;
\ No newline at end of file
+pub use survey::*;
+pub mod account;
+pub use account::*;
+pub mod organization;
+pub use organization::*;
+pub mod moderation;
+pub use moderation::*;
+pub mod invitation;
+pub use invitation::*;
+pub mod response;
+pub use response::*;
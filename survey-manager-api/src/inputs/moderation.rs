@@ -0,0 +1,20 @@
+use serde::Deserialize;
+
+/// Every moderation request says why, for the audit log.
+#[derive(Deserialize)]
+pub struct ReasonDTO {
+    pub reason: String,
+}
+
+/// The same, for GET requests where there's no body.
+#[derive(Deserialize)]
+pub struct ReasonParams {
+    pub reason: String,
+}
+
+#[derive(Deserialize)]
+pub struct AuditLogParams {
+    pub target: Option<String>,
+    pub page: Option<usize>,
+    pub page_size: Option<usize>,
+}
@@ -0,0 +1,11 @@
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct CreateOrganizationDTO {
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+pub struct AddMemberDTO {
+    pub role: String,
+}
@@ -0,0 +1,35 @@
+use serde::Deserialize;
+use survey_manager_core::app_services::responses::Answer;
+
+/// A complete response, one answer per question.  Surveys that only take responses through
+/// links need the link's token as well.  With a resume token, the answers saved under it count
+/// and only the rest need sending.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmitResponseDTO {
+    pub answers: Vec<Answer>,
+    #[serde(default)]
+    pub link_token: Option<String>,
+    #[serde(default)]
+    pub resume_token: Option<String>,
+    /// Version of the survey that was answered.  Left out, the answers are taken as being to the
+    /// current version.
+    #[serde(default)]
+    pub survey_version: Option<u64>,
+}
+
+/// Answers to save without submitting.  Leave out the resume token to start a new partial
+/// response.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SaveProgressDTO {
+    pub answers: Vec<Answer>,
+    #[serde(default)]
+    pub link_token: Option<String>,
+    #[serde(default)]
+    pub resume_token: Option<String>,
+    /// Version of the survey that was answered.  Left out, the answers are taken as being to the
+    /// current version.
+    #[serde(default)]
+    pub survey_version: Option<u64>,
+}
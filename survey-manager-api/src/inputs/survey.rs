@@ -1,12 +1,14 @@
 use serde::Deserialize;
 use survey_manager_core::app_services::commands::{CreateSurveyCommand, CreateQuestionCommand, CreateChoiceCommand, UpdateSurveyCommand, PatchQuestion, PatchChoice};
-use std::convert::{Into, TryInto};
-use survey_manager_core::app_services::decode_payload;
-use crate::error::TokenError;
+use survey_manager_core::app_services::token::OrganizationClaim;
+use std::convert::Into;
 
 #[derive(Deserialize)]
 pub struct CreateSurveyDTO {
-    pub token: String,
+    /// Deprecated.  Send the token in the `Authorization` header instead.  Still honored when the
+    /// header is absent so older clients keep working.
+    #[serde(default)]
+    pub token: Option<String>,
     pub title: String,
     pub description: String,
     pub category: String,
@@ -28,27 +30,22 @@ pub struct CreateChoiceDTO {
     pub title: String,
 }
 
-impl TryInto<CreateSurveyCommand> for CreateSurveyDTO {
-    type Error = TokenError;
-
-    fn try_into(self) -> Result<CreateSurveyCommand, Self::Error> {
-        let author = decode_payload(&self.token)
-            .map_err(|_| TokenError::TokenExpired )?
-            .username;
-
+impl CreateSurveyDTO {
+    /// Builds the command on behalf of `author`, who must already be authenticated.
+    pub fn into_command(self, author: String) -> CreateSurveyCommand {
         let questions: Vec<CreateQuestionCommand> = self.questions
             .into_iter()
             .map(|q| {
                 q.into()
             }).collect();
 
-        Ok(CreateSurveyCommand {
+        CreateSurveyCommand {
             author,
             title: self.title,
             description: self.description,
             category: self.category,
             questions,
-        })
+        }
     }
 }
 
@@ -80,7 +77,10 @@ impl Into<CreateChoiceCommand> for CreateChoiceDTO {
 
 #[derive(Deserialize)]
 pub struct UpdateSurveyDTO {
-    pub token: String,
+    /// Deprecated.  Send the token in the `Authorization` header instead.  Still honored when the
+    /// header is absent so older clients keep working.
+    #[serde(default)]
+    pub token: Option<String>,
     pub id: String,
     pub title: Option<String>,
     pub description: Option<String>,
@@ -107,14 +107,10 @@ pub struct UpdateChoiceDTO {
     pub title: Option<String>,
 }
 
-impl TryInto<UpdateSurveyCommand> for UpdateSurveyDTO {
-    type Error = TokenError;
-
-    fn try_into(self) -> Result<UpdateSurveyCommand, Self::Error> {
-        let author = decode_payload(&self.token)
-            .map_err(|_| TokenError::TokenExpired )?
-            .username;
-
+impl UpdateSurveyDTO {
+    /// Builds the command on behalf of `author`, who must already be authenticated, acting for
+    /// `organization` if their token names one.
+    pub fn into_command(self, author: String, organization: Option<OrganizationClaim>) -> UpdateSurveyCommand {
         let questions = if let Some(q) = self.questions {
             Some(q.into_iter()
                 .map(|q| {
@@ -124,14 +120,15 @@ impl TryInto<UpdateSurveyCommand> for UpdateSurveyDTO {
             None
         };
 
-        Ok(UpdateSurveyCommand {
+        UpdateSurveyCommand {
             id: self.id,
             author,
+            organization,
             title: self.title,
             description: self.description,
             category: self.category,
             questions,
-        })
+        }
     }
 }
 
@@ -165,3 +162,21 @@ impl Into<PatchChoice> for UpdateChoiceDTO {
         }
     }
 }
+
+#[derive(Deserialize)]
+pub struct ShareSurveyDTO {
+    pub role: String,
+}
+
+/// Names who the survey goes to, either a user or an organization id.
+#[derive(Deserialize)]
+pub struct TransferSurveyDTO {
+    pub to_user: Option<String>,
+    pub to_organization: Option<String>,
+}
+
+/// Who may answer the survey: `anyone`, `link` or `authenticated`.
+#[derive(Deserialize)]
+pub struct ResponsePolicyDTO {
+    pub policy: String,
+}
@@ -4,14 +4,23 @@ extern crate serde_derive;
 #[macro_use]
 extern crate derive_more;
 
+#[macro_use]
+extern crate lazy_static;
+
 pub mod inputs;
 pub mod commands;
+pub mod accounts;
+pub mod organizations;
+pub mod moderation;
+pub mod invitations;
 pub mod queries;
 pub mod generate;
 pub mod extractors;
 pub mod responders;
 pub mod async_utils;
 pub mod error;
+pub mod responses;
+pub mod migrate;
 
 #[cfg(test)]
 mod tests {
@@ -1,156 +1,960 @@
-use actix_web::{web, App, Error as AWError, HttpResponse, HttpServer, Result};
+use actix_web::{web, App, Error as AWError, HttpRequest, HttpResponse, HttpServer, Result, http::header};
 use survey_manager_api::commands::{handle_command_async};
-use survey_manager_api::inputs::{CreateSurveyDTO, UpdateSurveyDTO};
-use survey_manager_core::app_services::commands::{CreateSurveyCommand, UpdateSurveyCommand, RemoveSurveyCommand};
+use survey_manager_api::inputs::{CreateSurveyDTO, UpdateSurveyDTO, ShareSurveyDTO, TransferSurveyDTO, ResponsePolicyDTO, RegisterUserDTO, LoginDTO, RefreshDTO, LogoutDTO, CreateOrganizationDTO, AddMemberDTO, ReasonDTO, ReasonParams, AuditLogParams, CreateInvitationsDTO, SubmitResponseDTO, SaveProgressDTO};
+use survey_manager_api::accounts::{register_user_async, login_async, refresh_session_async, logout_async};
+use survey_manager_api::organizations::handle_organization_command_async;
+use survey_manager_api::moderation::{handle_moderation_command_async, handle_moderation_query_async};
+use survey_manager_api::invitations::{handle_invitation_command_async, handle_invitation_query_async};
+use survey_manager_core::app_services::accounts::LogoutCommand;
+use survey_manager_core::app_services::commands::{RemoveSurveyCommand, ImportSurveyCommand, ShareSurveyCommand, RevokeAccessCommand, TransferSurveyOwnershipCommand, SetResponsePolicyCommand, SetPublishedCommand};
+use survey_manager_core::app_services::organizations::{CreateOrganizationCommand, AddMemberCommand, RemoveMemberCommand};
+use survey_manager_core::app_services::moderation::{DeleteAnySurveyCommand, WithdrawSurveyCommand, ReinstateSurveyCommand, LockAuthorCommand, UnlockAuthorCommand, ViewAnySurveyQuery, AuditLogQuery};
+use survey_manager_core::app_services::invitations::{CreateInvitationsCommand, OpenInvitationCommand, InvitationStatsQuery};
+use survey_manager_core::app_services::responses::{SubmitResponseCommand, SaveProgressCommand};
+use survey_manager_core::app_services::results::{ExportResultsQuery, ResponseFunnelQuery, ResultsFormat, ResultsMode};
 use survey_manager_core::app_services::token::*;
+use survey_manager_core::dtos::DefinitionFormat;
+use survey_manager_core::policy::{authorize_moderation, ModerationPermission};
+use survey_manager_infra::cache_repo_decorators::cache_metrics;
 use futures::Future;
+use futures::future::result;
 use serde_derive::{Serialize, Deserialize};
 use dotenv::dotenv;
+#[cfg(feature = "dev-auth")]
 use uuid::Uuid;
-use survey_manager_core::app_services::queries::{FindSurveyQuery, FindSurveysByAuthorQuery};
+use survey_manager_core::app_services::queries::{FindSurveyQuery, FindSurveysByAuthorQuery, ExportSurveyQuery, PageConfig};
 use survey_manager_api::queries::{handle_queries_async};
-use survey_manager_api::extractors::{Token as BearerToken};
+use survey_manager_api::extractors::{Principal, MaybePrincipal};
 use survey_manager_api::responders::{SurveyIdResponder, GetSurveyResponder};
-use survey_manager_api::async_utils::{decode_payload_async, try_into_create_cmd_async, try_into_update_cmd_async};
+use survey_manager_api::async_utils::principal_or_body_token_async;
+use survey_manager_api::responses::{handle_response_command_async, export_results_async, response_funnel_async, respondent};
+use survey_manager_api::error::ApiError;
+use survey_manager_api::generate;
+use survey_manager_api::migrate;
 
 // For grabbing a token from get_token endpoint.
+#[cfg(feature = "dev-auth")]
 #[derive(Serialize)]
 struct Token {
     token: String,
 }
 
+#[derive(Serialize)]
+struct IdResponse {
+    id: String,
+}
+
 #[derive(Deserialize)]
 pub struct SurveyId {
     id: String,
 }
 
+#[derive(Deserialize)]
+pub struct InvitationToken {
+    token: String,
+}
+
+#[derive(Deserialize)]
+pub struct CollaboratorPath {
+    id: String,
+    username: String,
+}
+
+#[derive(Deserialize)]
+pub struct MemberPath {
+    id: String,
+    username: String,
+}
+
+#[derive(Deserialize)]
+pub struct AuthorPath {
+    username: String,
+}
+
+#[derive(Deserialize)]
+pub struct FormatParams {
+    format: Option<String>,
+}
+
+// Definition documents default to json when no format is asked for.
+fn definition_format(params: FormatParams) -> std::result::Result<DefinitionFormat, ApiError> {
+    match params.format {
+        Some(f) => f.parse().map_err(ApiError::from),
+        None => Ok(DefinitionFormat::default()),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ResultsParams {
+    format: Option<String>,
+    mode: Option<String>,
+}
+
+// Results exports default to csv, read as answered, when nothing else is asked for.
+fn results_options(params: ResultsParams) -> std::result::Result<(ResultsFormat, ResultsMode), ApiError> {
+    let format = match params.format {
+        Some(f) => f.parse()?,
+        None => ResultsFormat::default(),
+    };
+    let mode = match params.mode {
+        Some(m) => m.parse()?,
+        None => ResultsMode::default(),
+    };
+    Ok((format, mode))
+}
+
+// Responses to requests authenticated by the deprecated body token say so, so client owners notice.
+fn warn_if_body_token(mut response: HttpResponse, used_body_token: bool) -> HttpResponse {
+    if used_body_token {
+        response.headers_mut().insert(
+            header::WARNING,
+            header::HeaderValue::from_static("299 - \"token in request body is deprecated, use the Authorization header\""),
+        );
+    }
+    response
+}
+
 fn create_survey(
+    principal: MaybePrincipal,
     dto: web::Json<CreateSurveyDTO>,
 ) -> impl Future<Item = HttpResponse, Error = AWError> {
-    try_into_create_cmd_async(dto.into_inner())
+    let mut dto = dto.into_inner();
+    let body_token = dto.token.take();
+
+    principal_or_body_token_async(principal, body_token)
         .from_err()
-        .and_then(move |cmd: CreateSurveyCommand| {
-            handle_command_async(cmd.into())
+        .and_then(move |(Payload{username, ..}, used_body_token)| {
+            handle_command_async(dto.into_command(username).into())
                 .from_err()
                 .and_then(move |res| {
-                    SurveyIdResponder::new(res).respond()
+                    Ok(warn_if_body_token(SurveyIdResponder::new(res).respond(), used_body_token))
                 })
         })
 }
 
 fn update_survey(
+    principal: MaybePrincipal,
     dto: web::Json<UpdateSurveyDTO>,
 ) -> impl Future<Item = HttpResponse, Error = AWError> {
-    try_into_update_cmd_async(dto.into_inner())
+    let mut dto = dto.into_inner();
+    let body_token = dto.token.take();
+
+    principal_or_body_token_async(principal, body_token)
         .from_err()
-        .and_then(move |cmd: UpdateSurveyCommand| {
-            handle_command_async(cmd.into())
+        .and_then(move |(Payload{username, org, ..}, used_body_token)| {
+            handle_command_async(dto.into_command(username, org).into())
                 .from_err()
                 .and_then(move |res| {
-                    SurveyIdResponder::new(res).respond()
+                    Ok(warn_if_body_token(SurveyIdResponder::new(res).respond(), used_body_token))
                 })
         })
 }
 
 fn remove_survey(
-    token: BearerToken,
+    principal: Principal,
+    params: web::Path<SurveyId>,
+) -> impl Future<Item = HttpResponse, Error = AWError> {
+    let Payload{username, org, ..} = principal.into_inner();
+    let remove_survey_cmd = RemoveSurveyCommand {
+        id: params.into_inner().id,
+        requesting_author: username,
+        organization: org,
+    };
+
+    handle_command_async(remove_survey_cmd.into())
+        .from_err()
+        .and_then(move |_| {
+            // TODO: Replace with json response.
+            Ok(HttpResponse::Ok().body("Deleted"))
+        })
+}
+
+fn find_survey(
+    principal: Principal,
     params: web::Path<SurveyId>,
 ) -> impl Future<Item = HttpResponse, Error = AWError> {
     let id = params.into_inner().id;
+    let Payload{username, org, ..} = principal.into_inner();
+    let find_survey_query = FindSurveyQuery {
+        id: id.clone(),
+        requesting_author: username,
+        organization: org,
+    };
 
-    decode_payload_async(token.into_inner())
+    handle_queries_async(find_survey_query.into())
         .from_err()
-        .and_then(move |Payload{username, ..}| {
-            let remove_survey_cmd = RemoveSurveyCommand {
-                id: id.clone(),
-                requesting_author: username,
+        .and_then(move |res| {
+            Ok(GetSurveyResponder::new(res, id).respond())
+        })
+}
+
+fn find_authors_surveys(
+    principal: Principal,
+) -> impl Future<Item = HttpResponse, Error = AWError> {
+    let Payload{username, org, ..} = principal.into_inner();
+    let find_authors_surveys = FindSurveysByAuthorQuery { author: username, organization: org, page_config: None };
+
+    handle_queries_async(find_authors_surveys.into())
+        .from_err()
+        .and_then(move |res| {
+            Ok(HttpResponse::Ok()
+                .content_type("application/json")
+                .body(res))
+        })
+}
+
+fn import_survey(
+    principal: Principal,
+    params: web::Query<FormatParams>,
+    document: String,
+) -> impl Future<Item = HttpResponse, Error = AWError> {
+    let author = principal.into_inner().username;
+
+    result(definition_format(params.into_inner()))
+        .from_err()
+        .and_then(move |format| {
+            let import_survey_cmd = ImportSurveyCommand {
+                author,
+                format,
+                document,
             };
 
-            handle_command_async(remove_survey_cmd.into())
+            handle_command_async(import_survey_cmd.into())
                 .from_err()
-                .and_then(move |_| {
-                    // TODO: Replace with json response.
-                    Ok(HttpResponse::Ok().body("Deleted"))
+                .and_then(move |res| {
+                    Ok(SurveyIdResponder::new(res).respond())
                 })
         })
 }
 
-fn find_survey(
-    token: BearerToken,
+fn export_survey(
+    principal: Principal,
     params: web::Path<SurveyId>,
+    query: web::Query<FormatParams>,
 ) -> impl Future<Item = HttpResponse, Error = AWError> {
     let id = params.into_inner().id;
+    let Payload{username, org, ..} = principal.into_inner();
 
-    decode_payload_async(token.into_inner())
+    result(definition_format(query.into_inner()))
         .from_err()
-        .and_then(move |Payload{username, ..}| {
-            let find_survey_query = FindSurveyQuery {
-                id: id.clone(),
+        .and_then(move |format| {
+            let export_survey_query = ExportSurveyQuery {
+                id,
                 requesting_author: username,
+                organization: org,
+                format,
             };
 
-            handle_queries_async(find_survey_query.into())
+            handle_queries_async(export_survey_query.into())
                 .from_err()
                 .and_then(move |res| {
-                    Ok(GetSurveyResponder::new(res, id).respond())
+                    Ok(HttpResponse::Ok()
+                        .content_type(format.media_type())
+                        .body(res))
                 })
         })
 }
 
-fn find_authors_surveys(
-    token: BearerToken,
+fn share_survey(
+    principal: Principal,
+    params: web::Path<CollaboratorPath>,
+    dto: web::Json<ShareSurveyDTO>,
+) -> impl Future<Item = HttpResponse, Error = AWError> {
+    let CollaboratorPath { id, username } = params.into_inner();
+    let Payload{username: requesting_author, org, ..} = principal.into_inner();
+    let share_survey_cmd = ShareSurveyCommand {
+        id,
+        requesting_author,
+        organization: org,
+        username,
+        role: dto.into_inner().role,
+    };
+
+    handle_command_async(share_survey_cmd.into())
+        .from_err()
+        .and_then(move |res| {
+            Ok(SurveyIdResponder::new(res).respond())
+        })
+}
+
+fn revoke_access(
+    principal: Principal,
+    params: web::Path<CollaboratorPath>,
+) -> impl Future<Item = HttpResponse, Error = AWError> {
+    let CollaboratorPath { id, username } = params.into_inner();
+    let Payload{username: requesting_author, org, ..} = principal.into_inner();
+    let revoke_access_cmd = RevokeAccessCommand {
+        id,
+        requesting_author,
+        organization: org,
+        username,
+    };
+
+    handle_command_async(revoke_access_cmd.into())
+        .from_err()
+        .and_then(move |res| {
+            Ok(SurveyIdResponder::new(res).respond())
+        })
+}
+
+fn transfer_survey(
+    principal: Principal,
+    params: web::Path<SurveyId>,
+    dto: web::Json<TransferSurveyDTO>,
+) -> impl Future<Item = HttpResponse, Error = AWError> {
+    let Payload{username, org, ..} = principal.into_inner();
+    let TransferSurveyDTO { to_user, to_organization } = dto.into_inner();
+    let transfer_cmd = TransferSurveyOwnershipCommand {
+        id: params.into_inner().id,
+        requesting_author: username,
+        organization: org,
+        to_user,
+        to_organization,
+    };
+
+    handle_command_async(transfer_cmd.into())
+        .from_err()
+        .and_then(move |res| {
+            Ok(SurveyIdResponder::new(res).respond())
+        })
+}
+
+fn set_response_policy(
+    principal: Principal,
+    params: web::Path<SurveyId>,
+    dto: web::Json<ResponsePolicyDTO>,
+) -> impl Future<Item = HttpResponse, Error = AWError> {
+    let Payload{username, org, ..} = principal.into_inner();
+    let set_policy_cmd = SetResponsePolicyCommand {
+        id: params.into_inner().id,
+        requesting_author: username,
+        organization: org,
+        policy: dto.into_inner().policy,
+    };
+
+    handle_command_async(set_policy_cmd.into())
+        .from_err()
+        .and_then(move |res| {
+            Ok(SurveyIdResponder::new(res).respond())
+        })
+}
+
+fn publish_survey(
+    principal: Principal,
+    params: web::Path<SurveyId>,
+) -> impl Future<Item = HttpResponse, Error = AWError> {
+    set_published(principal, params.into_inner().id, true)
+}
+
+fn unpublish_survey(
+    principal: Principal,
+    params: web::Path<SurveyId>,
+) -> impl Future<Item = HttpResponse, Error = AWError> {
+    set_published(principal, params.into_inner().id, false)
+}
+
+fn set_published(
+    principal: Principal,
+    id: String,
+    published: bool,
+) -> impl Future<Item = HttpResponse, Error = AWError> {
+    let Payload{username, org, ..} = principal.into_inner();
+    let publish_cmd = SetPublishedCommand {
+        id,
+        requesting_author: username,
+        organization: org,
+        published,
+    };
+
+    handle_command_async(publish_cmd.into())
+        .from_err()
+        .and_then(move |res| {
+            Ok(SurveyIdResponder::new(res).respond())
+        })
+}
+
+fn create_invitations(
+    principal: Principal,
+    params: web::Path<SurveyId>,
+    dto: web::Json<CreateInvitationsDTO>,
+) -> impl Future<Item = HttpResponse, Error = AWError> {
+    let Payload{username, org, ..} = principal.into_inner();
+    let create_invitations_cmd = CreateInvitationsCommand {
+        survey_id: params.into_inner().id,
+        requesting_author: username,
+        organization: org,
+        recipients: dto.into_inner().recipients,
+    };
+
+    handle_invitation_command_async(create_invitations_cmd.into())
+        .from_err()
+        .and_then(move |res| {
+            Ok(HttpResponse::Created()
+                .content_type("application/json")
+                .body(res))
+        })
+}
+
+fn invitation_stats(
+    principal: Principal,
+    params: web::Path<SurveyId>,
+) -> impl Future<Item = HttpResponse, Error = AWError> {
+    let Payload{username, org, ..} = principal.into_inner();
+    let stats_query = InvitationStatsQuery {
+        survey_id: params.into_inner().id,
+        requesting_author: username,
+        organization: org,
+    };
+
+    handle_invitation_query_async(stats_query.into())
+        .from_err()
+        .and_then(move |res| {
+            Ok(HttpResponse::Ok()
+                .content_type("application/json")
+                .body(res))
+        })
+}
+
+/// Where invitation links land.  No login, the signed token is the credential.
+fn open_invitation(
+    params: web::Path<InvitationToken>,
+) -> impl Future<Item = HttpResponse, Error = AWError> {
+    let open_cmd = OpenInvitationCommand {
+        token: params.into_inner().token,
+    };
+
+    handle_invitation_command_async(open_cmd.into())
+        .from_err()
+        .and_then(move |res| {
+            Ok(SurveyIdResponder::new(res).respond())
+        })
+}
+
+fn submit_response(
+    req: HttpRequest,
+    principal: MaybePrincipal,
+    params: web::Path<SurveyId>,
+    dto: web::Json<SubmitResponseDTO>,
+) -> impl Future<Item = HttpResponse, Error = AWError> {
+    let SubmitResponseDTO { answers, link_token, resume_token, survey_version } = dto.into_inner();
+    let user_id = principal.into_inner().map(|p| p.user_id().clone());
+    let (respondent, new_cookie) = respondent(&req, user_id, link_token);
+    let submit_cmd = SubmitResponseCommand {
+        survey_id: params.into_inner().id,
+        survey_version,
+        respondent,
+        resume_token,
+        answers,
+    };
+
+    handle_response_command_async(submit_cmd.into())
+        .from_err()
+        .and_then(move |id| {
+            let mut created = HttpResponse::Created();
+            if let Some(cookie) = new_cookie {
+                created.cookie(cookie);
+            }
+            Ok(created.json(IdResponse { id }))
+        })
+}
+
+/// Saves answers part way through a response.  The body that comes back holds the resume token
+/// to save more with, or to submit.
+fn save_progress(
+    req: HttpRequest,
+    principal: MaybePrincipal,
+    params: web::Path<SurveyId>,
+    dto: web::Json<SaveProgressDTO>,
+) -> impl Future<Item = HttpResponse, Error = AWError> {
+    let SaveProgressDTO { answers, link_token, resume_token, survey_version } = dto.into_inner();
+    let user_id = principal.into_inner().map(|p| p.user_id().clone());
+    let (respondent, new_cookie) = respondent(&req, user_id, link_token);
+    let save_cmd = SaveProgressCommand {
+        survey_id: params.into_inner().id,
+        survey_version,
+        respondent,
+        resume_token,
+        answers,
+    };
+
+    handle_response_command_async(save_cmd.into())
+        .from_err()
+        .and_then(move |res| {
+            let mut saved = HttpResponse::Ok();
+            if let Some(cookie) = new_cookie {
+                saved.cookie(cookie);
+            }
+            Ok(saved.content_type("application/json").body(res))
+        })
+}
+
+fn response_funnel(
+    principal: Principal,
+    params: web::Path<SurveyId>,
+) -> impl Future<Item = HttpResponse, Error = AWError> {
+    let Payload{username, org, ..} = principal.into_inner();
+    let funnel_query = ResponseFunnelQuery {
+        survey_id: params.into_inner().id,
+        requesting_author: username,
+        organization: org,
+    };
+
+    response_funnel_async(funnel_query)
+        .from_err()
+        .and_then(move |res| {
+            Ok(HttpResponse::Ok()
+                .content_type("application/json")
+                .body(res))
+        })
+}
+
+/// Streams every response as a download, see `export_results_async`.
+fn export_results(
+    principal: Principal,
+    params: web::Path<SurveyId>,
+    query: web::Query<ResultsParams>,
 ) -> impl Future<Item = HttpResponse, Error = AWError> {
-    decode_payload_async(token.into_inner())
+    let Payload{username, org, ..} = principal.into_inner();
+    let survey_id = params.into_inner().id;
+
+    result(results_options(query.into_inner()))
         .from_err()
-        .and_then(move |Payload{username, ..}| {
-            let find_authors_surveys = FindSurveysByAuthorQuery { author: username, page_config: None };
+        .and_then(move |(format, mode)| {
+            let export_query = ExportResultsQuery {
+                survey_id,
+                requesting_author: username,
+                organization: org,
+                format,
+                mode,
+            };
 
-            handle_queries_async(find_authors_surveys.into())
+            export_results_async(export_query)
                 .from_err()
-                .and_then(move |res| {
-                    Ok(HttpResponse::Ok()
-                        .content_type("application/json")
-                        .body(res))
-                })
         })
 }
 
+fn create_organization(
+    principal: Principal,
+    dto: web::Json<CreateOrganizationDTO>,
+) -> impl Future<Item = HttpResponse, Error = AWError> {
+    let create_org_cmd = CreateOrganizationCommand {
+        name: dto.into_inner().name,
+        creator: principal.into_inner().username,
+    };
+
+    handle_organization_command_async(create_org_cmd.into())
+        .from_err()
+        .and_then(move |id| {
+            Ok(HttpResponse::Created().json(IdResponse { id }))
+        })
+}
+
+fn add_member(
+    principal: Principal,
+    params: web::Path<MemberPath>,
+    dto: web::Json<AddMemberDTO>,
+) -> impl Future<Item = HttpResponse, Error = AWError> {
+    let MemberPath { id, username } = params.into_inner();
+    let add_member_cmd = AddMemberCommand {
+        id,
+        requesting_user: principal.into_inner().username,
+        username,
+        role: dto.into_inner().role,
+    };
+
+    handle_organization_command_async(add_member_cmd.into())
+        .from_err()
+        .and_then(move |_| {
+            Ok(HttpResponse::NoContent().finish())
+        })
+}
+
+fn remove_member(
+    principal: Principal,
+    params: web::Path<MemberPath>,
+) -> impl Future<Item = HttpResponse, Error = AWError> {
+    let MemberPath { id, username } = params.into_inner();
+    let remove_member_cmd = RemoveMemberCommand {
+        id,
+        requesting_user: principal.into_inner().username,
+        username,
+    };
+
+    handle_organization_command_async(remove_member_cmd.into())
+        .from_err()
+        .and_then(move |_| {
+            Ok(HttpResponse::NoContent().finish())
+        })
+}
+
+fn register_user(
+    dto: web::Json<RegisterUserDTO>,
+) -> impl Future<Item = HttpResponse, Error = AWError> {
+    register_user_async(dto.into_inner().into())
+        .from_err()
+        .and_then(move |id| {
+            Ok(HttpResponse::Created().json(IdResponse { id }))
+        })
+}
+
+fn login(
+    dto: web::Json<LoginDTO>,
+) -> impl Future<Item = HttpResponse, Error = AWError> {
+    login_async(dto.into_inner().into())
+        .from_err()
+        .and_then(move |tokens| {
+            Ok(HttpResponse::Ok().json(tokens))
+        })
+}
+
+fn refresh(
+    dto: web::Json<RefreshDTO>,
+) -> impl Future<Item = HttpResponse, Error = AWError> {
+    refresh_session_async(dto.into_inner().into())
+        .from_err()
+        .and_then(move |tokens| {
+            Ok(HttpResponse::Ok().json(tokens))
+        })
+}
+
+fn logout(
+    principal: Principal,
+    dto: Option<web::Json<LogoutDTO>>,
+) -> impl Future<Item = HttpResponse, Error = AWError> {
+    let Payload{user_id, jti, exp, ..} = principal.into_inner();
+    let logout_cmd = LogoutCommand {
+        user_id,
+        jti,
+        exp,
+        refresh_token: dto.and_then(|dto| dto.into_inner().refresh_token),
+    };
+
+    logout_async(logout_cmd)
+        .from_err()
+        .and_then(move |_| {
+            Ok(HttpResponse::NoContent().finish())
+        })
+}
+
+fn moderator_view_survey(
+    principal: Principal,
+    params: web::Path<SurveyId>,
+    query: web::Query<ReasonParams>,
+) -> impl Future<Item = HttpResponse, Error = AWError> {
+    let Payload{username, roles, ..} = principal.into_inner();
+    let view_query = ViewAnySurveyQuery {
+        id: params.into_inner().id,
+        moderator: username,
+        roles,
+        reason: query.into_inner().reason,
+    };
+
+    handle_moderation_query_async(view_query.into())
+        .from_err()
+        .and_then(move |res| {
+            Ok(HttpResponse::Ok()
+                .content_type("application/json")
+                .body(res))
+        })
+}
+
+fn moderator_delete_survey(
+    principal: Principal,
+    params: web::Path<SurveyId>,
+    dto: web::Json<ReasonDTO>,
+) -> impl Future<Item = HttpResponse, Error = AWError> {
+    let Payload{username, roles, ..} = principal.into_inner();
+    let delete_cmd = DeleteAnySurveyCommand {
+        id: params.into_inner().id,
+        moderator: username,
+        roles,
+        reason: dto.into_inner().reason,
+    };
+
+    handle_moderation_command_async(delete_cmd.into())
+        .from_err()
+        .and_then(move |_| {
+            Ok(HttpResponse::NoContent().finish())
+        })
+}
+
+fn withdraw_survey(
+    principal: Principal,
+    params: web::Path<SurveyId>,
+    dto: web::Json<ReasonDTO>,
+) -> impl Future<Item = HttpResponse, Error = AWError> {
+    let Payload{username, roles, ..} = principal.into_inner();
+    let withdraw_cmd = WithdrawSurveyCommand {
+        id: params.into_inner().id,
+        moderator: username,
+        roles,
+        reason: dto.into_inner().reason,
+    };
+
+    handle_moderation_command_async(withdraw_cmd.into())
+        .from_err()
+        .and_then(move |_| {
+            Ok(HttpResponse::NoContent().finish())
+        })
+}
+
+fn reinstate_survey(
+    principal: Principal,
+    params: web::Path<SurveyId>,
+    dto: web::Json<ReasonDTO>,
+) -> impl Future<Item = HttpResponse, Error = AWError> {
+    let Payload{username, roles, ..} = principal.into_inner();
+    let reinstate_cmd = ReinstateSurveyCommand {
+        id: params.into_inner().id,
+        moderator: username,
+        roles,
+        reason: dto.into_inner().reason,
+    };
+
+    handle_moderation_command_async(reinstate_cmd.into())
+        .from_err()
+        .and_then(move |_| {
+            Ok(HttpResponse::NoContent().finish())
+        })
+}
+
+fn lock_author(
+    principal: Principal,
+    params: web::Path<AuthorPath>,
+    dto: web::Json<ReasonDTO>,
+) -> impl Future<Item = HttpResponse, Error = AWError> {
+    let Payload{username, roles, ..} = principal.into_inner();
+    let lock_cmd = LockAuthorCommand {
+        username: params.into_inner().username,
+        moderator: username,
+        roles,
+        reason: dto.into_inner().reason,
+    };
+
+    handle_moderation_command_async(lock_cmd.into())
+        .from_err()
+        .and_then(move |_| {
+            Ok(HttpResponse::NoContent().finish())
+        })
+}
+
+fn unlock_author(
+    principal: Principal,
+    params: web::Path<AuthorPath>,
+    dto: web::Json<ReasonDTO>,
+) -> impl Future<Item = HttpResponse, Error = AWError> {
+    let Payload{username, roles, ..} = principal.into_inner();
+    let unlock_cmd = UnlockAuthorCommand {
+        username: params.into_inner().username,
+        moderator: username,
+        roles,
+        reason: dto.into_inner().reason,
+    };
+
+    handle_moderation_command_async(unlock_cmd.into())
+        .from_err()
+        .and_then(move |_| {
+            Ok(HttpResponse::NoContent().finish())
+        })
+}
+
+fn audit_log(
+    principal: Principal,
+    params: web::Query<AuditLogParams>,
+) -> impl Future<Item = HttpResponse, Error = AWError> {
+    let AuditLogParams { target, page, page_size } = params.into_inner();
+    let audit_query = AuditLogQuery {
+        roles: principal.into_inner().roles,
+        target,
+        page_config: Some(PageConfig {
+            page_num: page.unwrap_or(1),
+            page_size: page_size.unwrap_or(50),
+        }),
+    };
+
+    handle_moderation_query_async(audit_query.into())
+        .from_err()
+        .and_then(move |res| {
+            Ok(HttpResponse::Ok()
+                .content_type("application/json")
+                .body(res))
+        })
+}
+
+/// Hit and miss counters for the survey caches on this node.
+fn cache_stats(
+    principal: Principal,
+) -> Result<HttpResponse, AWError> {
+    authorize_moderation(&principal.into_inner().roles, ModerationPermission::ReadMetrics)
+        .map_err(ApiError::from)?;
+    Ok(HttpResponse::Ok().json(cache_metrics()))
+}
+
+/// Hands a token for "test_user" to anyone who asks.  Only compiled with the `dev-auth` feature.
+#[cfg(feature = "dev-auth")]
 fn get_token(
 ) -> Result<HttpResponse, AWError> {
     let fake_user_id = Uuid::new_v4();
-    let token_str = create_token("test_user".to_string(), fake_user_id.to_string());
+    let token_str = generate::token_service().create_token("test_user".to_string(), fake_user_id.to_string())
+        .map_err(ApiError::from)?;
     let token = Token { token: token_str, };
     Ok(HttpResponse::Ok().json(token))
 }
 
 fn main() -> std::io::Result<()> {
     dotenv().ok();
+    env_logger::init();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("migrate") {
+        std::process::exit(migrate::run(&args[1..]));
+    }
 
     let addr = match std::env::var("SERVER_HOST") {
         Ok(host) => host,
         Err(_) => "0.0.0.0:8000".to_string(),
     };
 
+    // Bring the schema up to date first when asked to, everything below may need it.
+    migrate::migrate_on_startup();
+
+    // Load signing keys and the invitation secret, pick the survey backend, check the database and
+    // cache config and subscribe to cache invalidations, now so bad config stops startup instead of
+    // the first request.  Redis itself being down doesn't, requests bypass it until it's back.
+    generate::token_service();
+    generate::invitation_signer();
+    generate::survey_backend();
+    generate::check_database_config();
+    generate::check_cache_config();
+    generate::start_cache_invalidations();
+
     println!("Starting http server: {}", &addr);
 
     // Start http server
     HttpServer::new(move || {
-        App::new()
+        let app = App::new()
             .service(
                 web::resource("/survey")
                     .route(web::get().to_async(find_authors_surveys))
                     .route(web::post().to_async(create_survey))
                     .route(web::patch().to_async(update_survey)),
             )
+            // Registered ahead of /survey/{id} so "import" isn't taken for an id.
+            .service(
+                web::resource("/survey/import")
+                    .route(web::post().to_async(import_survey)),
+            )
+            .service(
+                web::resource("/survey/{id}/export")
+                    .route(web::get().to_async(export_survey)),
+            )
+            .service(
+                web::resource("/survey/{id}/collaborators/{username}")
+                    .route(web::put().to_async(share_survey))
+                    .route(web::delete().to_async(revoke_access)),
+            )
+            .service(
+                web::resource("/survey/{id}/transfer")
+                    .route(web::post().to_async(transfer_survey)),
+            )
+            .service(
+                web::resource("/survey/{id}/invitations")
+                    .route(web::post().to_async(create_invitations)),
+            )
+            .service(
+                web::resource("/survey/{id}/invitations/stats")
+                    .route(web::get().to_async(invitation_stats)),
+            )
+            .service(
+                web::resource("/invitations/{token}")
+                    .route(web::get().to_async(open_invitation)),
+            )
+            .service(
+                web::resource("/survey/{id}/responses")
+                    .route(web::post().to_async(submit_response)),
+            )
+            .service(
+                web::resource("/survey/{id}/responses/progress")
+                    .route(web::post().to_async(save_progress)),
+            )
+            .service(
+                web::resource("/survey/{id}/results/funnel")
+                    .route(web::get().to_async(response_funnel)),
+            )
+            .service(
+                web::resource("/survey/{id}/results/export")
+                    .route(web::get().to_async(export_results)),
+            )
+            .service(
+                web::resource("/survey/{id}/response-policy")
+                    .route(web::put().to_async(set_response_policy)),
+            )
+            .service(
+                web::resource("/survey/{id}/published")
+                    .route(web::put().to_async(publish_survey))
+                    .route(web::delete().to_async(unpublish_survey)),
+            )
             .service(
                 web::resource("/survey/{id}")
                     .route(web::get().to_async(find_survey))
                     .route(web::delete().to_async(remove_survey)),
             )
             .service(
-                web::resource("/token")
-                    .route(web::get().to(get_token)),
+                web::resource("/organizations")
+                    .route(web::post().to_async(create_organization)),
+            )
+            .service(
+                web::resource("/organizations/{id}/members/{username}")
+                    .route(web::put().to_async(add_member))
+                    .route(web::delete().to_async(remove_member)),
             )
+            .service(
+                web::resource("/admin/surveys/{id}")
+                    .route(web::get().to_async(moderator_view_survey))
+                    .route(web::delete().to_async(moderator_delete_survey)),
+            )
+            .service(
+                web::resource("/admin/surveys/{id}/withdrawal")
+                    .route(web::put().to_async(withdraw_survey))
+                    .route(web::delete().to_async(reinstate_survey)),
+            )
+            .service(
+                web::resource("/admin/authors/{username}/lock")
+                    .route(web::put().to_async(lock_author))
+                    .route(web::delete().to_async(unlock_author)),
+            )
+            .service(
+                web::resource("/admin/audit")
+                    .route(web::get().to_async(audit_log)),
+            )
+            .service(
+                web::resource("/admin/cache-stats")
+                    .route(web::get().to(cache_stats)),
+            )
+            .service(
+                web::resource("/users")
+                    .route(web::post().to_async(register_user)),
+            )
+            .service(
+                web::resource("/auth/login")
+                    .route(web::post().to_async(login)),
+            )
+            .service(
+                web::resource("/auth/refresh")
+                    .route(web::post().to_async(refresh)),
+            )
+            .service(
+                web::resource("/auth/logout")
+                    .route(web::post().to_async(logout)),
+            );
+
+        #[cfg(feature = "dev-auth")]
+        let app = app.service(
+            web::resource("/token")
+                .route(web::get().to(get_token)),
+        );
+
+        app
     })
         .bind(&addr)?
         .run()
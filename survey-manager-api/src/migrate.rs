@@ -0,0 +1,79 @@
+//! The `migrate` subcommand, which manages the MySQL schema.
+//!
+//! ```text
+//! survey-manager-api migrate              # apply pending migrations
+//! survey-manager-api migrate status       # list migrations and whether they've been applied
+//! survey-manager-api migrate down <n>     # revert migrations newer than n
+//! ```
+use survey_manager_infra::mysql_repos::{MysqlMigrator, MigrationState};
+use survey_manager_infra::Error;
+
+/// Runs `migrate` with the arguments that followed it, and returns the exit code.
+pub fn run(args: &[String]) -> i32 {
+    let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+    let result = match args.as_slice() {
+        [] | ["up"] => up(),
+        ["status"] => status(),
+        ["down", target] => match target.parse() {
+            Ok(target) => down(target),
+            Err(_) => return usage(),
+        },
+        _ => return usage(),
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("migration failed: {}", e);
+            1
+        },
+    }
+}
+
+/// Applies pending migrations when `MIGRATE_ON_STARTUP` is `true`.  Call during startup, before
+/// anything touches the database.
+pub fn migrate_on_startup() {
+    if std::env::var("MIGRATE_ON_STARTUP").is_ok_and(|v| v == "true") {
+        up().unwrap_or_else(|e| panic!("could not migrate the database: {}", e));
+    }
+}
+
+fn up() -> Result<(), Error> {
+    let applied = MysqlMigrator::new()?.up()?;
+    if applied.is_empty() {
+        println!("schema is up to date");
+    }
+    for version in applied {
+        println!("applied {}", version);
+    }
+    Ok(())
+}
+
+fn down(target: u32) -> Result<(), Error> {
+    let reverted = MysqlMigrator::new()?.down_to(target)?;
+    if reverted.is_empty() {
+        println!("nothing to revert");
+    }
+    for version in reverted {
+        println!("reverted {}", version);
+    }
+    Ok(())
+}
+
+fn status() -> Result<(), Error> {
+    for status in MysqlMigrator::new()?.status()? {
+        let state = match status.state {
+            MigrationState::Pending => "pending",
+            MigrationState::Applied => "applied",
+            MigrationState::Modified => "modified since applied",
+            MigrationState::Unknown => "applied by a newer build",
+        };
+        println!("{:04}\t{}\t{}", status.version, status.name, state);
+    }
+    Ok(())
+}
+
+fn usage() -> i32 {
+    eprintln!("usage: survey-manager-api migrate [up | status | down <version>]");
+    2
+}
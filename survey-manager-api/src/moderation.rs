@@ -0,0 +1,21 @@
+use actix_web::web;
+use futures::Future;
+use survey_manager_core::app_services::moderation::{ModerationCommands, ModerationQueries};
+use domain_patterns::command::Handles;
+use domain_patterns::query::HandlesQuery;
+use crate::generate;
+use crate::error::ApiError;
+
+pub fn handle_moderation_command_async(
+    cmd: ModerationCommands,
+) -> impl Future<Item = String, Error = ApiError> {
+    web::block(move || generate::moderation_command_handler().and_then(|mut handler| handler.handle(cmd)) )
+        .from_err()
+}
+
+pub fn handle_moderation_query_async(
+    query: ModerationQueries,
+) -> impl Future<Item = String, Error = ApiError> {
+    web::block(move || generate::moderation_query_handler().and_then(|mut handler| handler.handle(query)) )
+        .from_err()
+}
@@ -0,0 +1,12 @@
+use actix_web::web;
+use futures::Future;
+use survey_manager_core::app_services::organizations::OrganizationCommands;
+use crate::generate;
+use crate::error::ApiError;
+
+pub fn handle_organization_command_async(
+    cmd: OrganizationCommands,
+) -> impl Future<Item = String, Error = ApiError> {
+    web::block(move || generate::handle_organization_command(cmd) )
+        .from_err()
+}
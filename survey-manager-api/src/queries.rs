@@ -8,6 +8,6 @@ use crate::error::ApiError;
 pub fn handle_queries_async(
     query: SurveyQueries,
 ) -> impl Future<Item = String, Error = ApiError> {
-    web::block(move || generate::query_handler().handle(query) )
+    web::block(move || generate::query_handler().and_then(|mut handler| handler.handle(query)) )
         .from_err()
 }
@@ -0,0 +1,230 @@
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use actix_web::{web, HttpRequest, HttpResponse, HttpMessage, http::header, http::Cookie};
+use actix_web::web::Bytes;
+use futures::{Future, Sink, Stream};
+use futures::sync::mpsc;
+use survey_manager_core::app_services::responses::ResponseCommands;
+use survey_manager_core::app_services::results::{ExportResultsQuery, ResponseFunnelQuery};
+use domain_patterns::query::HandlesQuery;
+use survey_manager_core::value_objects::Respondent;
+use survey_manager_core::Error as SMError;
+use uuid::Uuid;
+use crate::generate;
+use crate::error::ApiError;
+
+// Bytes gathered before they're sent on as a chunk of the body.
+const CHUNK_SIZE: usize = 16 * 1024;
+
+// Chunks that may be waiting on the client before the export stops to let it catch up.
+const CHUNKS_IN_FLIGHT: usize = 4;
+
+// Tells anonymous respondents apart, along with their ip address.
+pub const RESPONDENT_COOKIE: &str = "respondent";
+
+pub fn handle_response_command_async(
+    cmd: ResponseCommands,
+) -> impl Future<Item = String, Error = ApiError> {
+    web::block(move || generate::handle_response_command(cmd))
+        .from_err()
+}
+
+pub fn response_funnel_async(
+    query: ResponseFunnelQuery,
+) -> impl Future<Item = String, Error = ApiError> {
+    web::block(move || generate::results_query_handler().and_then(|mut handler| handler.handle(query)) )
+        .from_err()
+}
+
+/// What `req` tells us about who's responding, for the survey's response policy to judge.  A
+/// request without a respondent cookie is given a new one, which comes back to be set on the
+/// response.
+pub fn respondent(req: &HttpRequest, user_id: Option<String>, link_token: Option<String>) -> (Respondent, Option<Cookie<'static>>) {
+    let (cookie, new_cookie) = match req.cookie(RESPONDENT_COOKIE) {
+        Some(cookie) => (cookie.value().to_string(), None),
+        None => {
+            let value = Uuid::new_v4().to_string();
+            let cookie = Cookie::build(RESPONDENT_COOKIE, value.clone())
+                .path("/")
+                .http_only(true)
+                .permanent()
+                .finish();
+            (value, Some(cookie))
+        },
+    };
+
+    let respondent = Respondent {
+        user_id,
+        link_token,
+        ip_address: req.connection_info().remote().map(without_port),
+        cookie: Some(cookie),
+    };
+    (respondent, new_cookie)
+}
+
+// The same respondent connects from a different port each time.
+fn without_port(remote: &str) -> String {
+    remote.parse::<SocketAddr>()
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or_else(|_| remote.to_string())
+}
+
+/// Streams a survey's results as a chunked download.  The query is checked before anything is
+/// sent, so a survey that's missing or off limits is still a plain error response.  After that
+/// the export runs on the blocking pool, writing into a channel the response body reads from.
+/// Only a few chunks are ever buffered, and a client that goes away stops the export.
+///
+/// An export that fails part way ends the body early, the client sees a truncated download
+/// rather than a status code.
+pub fn export_results_async(
+    query: ExportResultsQuery,
+) -> impl Future<Item = HttpResponse, Error = ApiError> {
+    web::block(move || {
+        let mut handler = generate::results_query_handler()?;
+        let export = handler.handle(query)?;
+        Ok::<_, SMError>((handler, export))
+    })
+        .from_err()
+        .map(|(mut handler, export)| {
+            let filename = format!("results-{}.{}", export.survey_id, export.format.extension());
+            let media_type = export.format.media_type();
+            let (sender, receiver) = mpsc::channel(CHUNKS_IN_FLIGHT);
+
+            // Yields a single empty chunk once the export is written, which is dropped below.  It's
+            // part of the body so the export's failure, if any, reaches the response.
+            let writing = web::block(move || handler.write_export(&export, ChunkWriter::new(sender)))
+                .map(|_| Bytes::new())
+                .map_err(|e| {
+                    log::warn!("results export stopped part way: {}", e);
+                    ApiError::from(e)
+                })
+                .into_stream();
+
+            let body = receiver
+                .map_err(|_| ApiError::ThreadFailure)
+                .select(writing)
+                .filter(|chunk| !chunk.is_empty());
+
+            HttpResponse::Ok()
+                .content_type(media_type)
+                .header(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename))
+                .streaming(body)
+        })
+}
+
+// Sends what's written down the channel a chunk at a time.  Sending waits while the channel is
+// full, which is what keeps a slow client from having the whole export buffered for it.
+struct ChunkWriter {
+    sender: Option<mpsc::Sender<Bytes>>,
+    buf: Vec<u8>,
+}
+
+impl ChunkWriter {
+    fn new(sender: mpsc::Sender<Bytes>) -> ChunkWriter {
+        ChunkWriter {
+            sender: Some(sender),
+            buf: Vec::with_capacity(CHUNK_SIZE),
+        }
+    }
+
+    fn send(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+
+        let chunk = Bytes::from(std::mem::replace(&mut self.buf, Vec::with_capacity(CHUNK_SIZE)));
+        // The sender is only missing after an earlier send found the client gone.
+        let sender = self.sender.take().ok_or_else(client_gone)?;
+        self.sender = Some(sender.send(chunk).wait().map_err(|_| client_gone())?);
+        Ok(())
+    }
+}
+
+fn client_gone() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "the client stopped reading the export")
+}
+
+impl Write for ChunkWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        if self.buf.len() >= CHUNK_SIZE {
+            self.send()?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn a_returning_respondent_keeps_their_cookie() {
+        let req = TestRequest::default()
+            .cookie(Cookie::new(RESPONDENT_COOKIE, "known"))
+            .header("x-forwarded-for", "203.0.113.9")
+            .to_http_request();
+
+        let (respondent, new_cookie) = respondent(&req, None, None);
+
+        assert!(new_cookie.is_none());
+        assert_eq!(respondent.cookie, Some("known".to_string()));
+        assert_eq!(respondent.ip_address, Some("203.0.113.9".to_string()));
+    }
+
+    #[test]
+    fn a_new_respondent_is_given_a_cookie() {
+        let req = TestRequest::default().to_http_request();
+
+        let (respondent, new_cookie) = respondent(&req, Some("user-1".to_string()), None);
+
+        assert_eq!(respondent.cookie, new_cookie.map(|c| c.value().to_string()));
+        assert_eq!(respondent.user_id, Some("user-1".to_string()));
+    }
+
+    #[test]
+    fn ports_are_dropped_from_addresses() {
+        assert_eq!(without_port("192.0.2.1:40100"), "192.0.2.1");
+        assert_eq!(without_port("[2001:db8::1]:443"), "2001:db8::1");
+        assert_eq!(without_port("192.0.2.1"), "192.0.2.1");
+    }
+
+    #[test]
+    fn writes_arrive_in_order_a_chunk_at_a_time() {
+        let (sender, receiver) = mpsc::channel(CHUNKS_IN_FLIGHT);
+        let writer = std::thread::spawn(move || {
+            let mut out = ChunkWriter::new(sender);
+            for n in 0..10_000u32 {
+                out.write_all(format!("{}\n", n).as_bytes()).unwrap();
+            }
+            out.flush().unwrap();
+        });
+
+        let chunks: Vec<Bytes> = receiver.wait().map(|chunk| chunk.unwrap()).collect();
+        writer.join().unwrap();
+
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|chunk| chunk.len() <= CHUNK_SIZE + 8));
+        let body: Vec<u8> = chunks.iter().flat_map(|chunk| chunk.to_vec()).collect();
+        let expected: String = (0..10_000u32).map(|n| format!("{}\n", n)).collect();
+        assert_eq!(body, expected.into_bytes());
+    }
+
+    #[test]
+    fn writing_fails_once_the_client_is_gone() {
+        let (sender, receiver) = mpsc::channel(CHUNKS_IN_FLIGHT);
+        drop(receiver);
+
+        let mut out = ChunkWriter::new(sender);
+        let failed = out.write_all(&[0; CHUNK_SIZE]).unwrap_err();
+
+        assert_eq!(failed.kind(), io::ErrorKind::BrokenPipe);
+        // And it stays failed, rather than buffering everything else for nobody.
+        assert!(out.write_all(&[0; CHUNK_SIZE]).is_err());
+    }
+}
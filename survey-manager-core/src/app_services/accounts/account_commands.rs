@@ -0,0 +1,32 @@
+use domain_patterns::command::Command;
+use domain_patterns::message::Message;
+
+#[derive(Clone, Command)]
+pub struct RegisterUserCommand {
+    pub username: String,
+    pub password: String,
+}
+
+/// Logs in, optionally acting for `organization`.  The user has to be a member of it, and their
+/// role there goes into the token.
+#[derive(Clone, Command)]
+pub struct LoginCommand {
+    pub username: String,
+    pub password: String,
+    pub organization: Option<String>,
+}
+
+#[derive(Clone, Command)]
+pub struct RefreshSessionCommand {
+    pub refresh_token: String,
+}
+
+/// Ends a session.  The access token named by `jti` is revoked until it would have expired anyway,
+/// and if the refresh token is supplied its whole family goes with it.
+#[derive(Clone, Command)]
+pub struct LogoutCommand {
+    pub user_id: String,
+    pub jti: Option<String>,
+    pub exp: i64,
+    pub refresh_token: Option<String>,
+}
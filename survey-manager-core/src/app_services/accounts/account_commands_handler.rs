@@ -0,0 +1,473 @@
+use domain_patterns::collections::Repository;
+use domain_patterns::command::Handles;
+use domain_patterns::models::Entity;
+use uuid::Uuid;
+use crate::errors::Error::{NotAuthorized, RepoFailure, AlreadyExists};
+use crate::errors::Result;
+use crate::user::User;
+use crate::organization::Organization;
+use crate::dtos::TokenPairDTO;
+use crate::app_services::token::{TokenService, OrganizationClaim};
+use crate::app_services::repository_contracts::{UserRepository, RefreshTokenRepository, RevocationList};
+use crate::app_services::accounts::{
+    RegisterUserCommand, LoginCommand, RefreshSessionCommand, LogoutCommand,
+    RefreshTokenRecord, RefreshOutcome, hash_refresh_token,
+};
+
+pub struct AccountCommandsHandler<'a, U, R, L, O> where
+    U: UserRepository,
+    R: RefreshTokenRepository,
+    L: RevocationList,
+    O: Repository<Organization>,
+{
+    users: U,
+    refresh_tokens: R,
+    revocations: L,
+    organizations: O,
+    token_service: &'a TokenService,
+}
+
+impl<'a, U, R, L, O> AccountCommandsHandler<'a, U, R, L, O> where
+    U: UserRepository,
+    R: RefreshTokenRepository,
+    L: RevocationList,
+    O: Repository<Organization>,
+{
+    pub fn new(users: U, refresh_tokens: R, revocations: L, organizations: O, token_service: &'a TokenService) -> AccountCommandsHandler<'a, U, R, L, O> {
+        AccountCommandsHandler {
+            users,
+            refresh_tokens,
+            revocations,
+            organizations,
+            token_service,
+        }
+    }
+
+    // The claim is rebuilt from the stored organization every time, so a member who was removed or
+    // demoted can't keep their old role by refreshing.
+    fn organization_claim(&mut self, organization_id: &Option<String>, username: &str) -> Result<Option<OrganizationClaim>> {
+        let organization_id = match organization_id {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+
+        let org = self.organizations.get(organization_id)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?;
+
+        match org.and_then(|org| org.role_of(username)) {
+            Some(role) => Ok(Some(OrganizationClaim { id: organization_id.clone(), role: role.to_string() })),
+            None => Err(NotAuthorized),
+        }
+    }
+
+    fn issue_pair(&mut self, family_id: String, user: &User, organization_id: Option<String>) -> Result<TokenPairDTO> {
+        let (user_id, username) = (user.id(), user.username().to_string());
+        let org = self.organization_claim(&organization_id, &username)?;
+        let access_token = self.token_service.create_session_token(username.clone(), user_id.clone(), org, user.roles())?;
+        let (refresh_token, record) = RefreshTokenRecord::issue(
+            family_id,
+            user_id,
+            username,
+            organization_id,
+            self.token_service.refresh_ttl(),
+        );
+
+        self.refresh_tokens.insert(&record)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?;
+
+        Ok(TokenPairDTO {
+            access_token,
+            refresh_token,
+            token_type: "Bearer".to_string(),
+            expires_in: self.token_service.ttl(),
+        })
+    }
+
+    fn revoke_family(&mut self, family_id: &String) -> Result<()> {
+        self.refresh_tokens.revoke_family(family_id)
+            .map_err(|e| RepoFailure { source: Box::new(e) })
+    }
+}
+
+impl<'a, U, R, L, O> Handles<RegisterUserCommand> for AccountCommandsHandler<'a, U, R, L, O> where
+    U: UserRepository,
+    R: RefreshTokenRepository,
+    L: RevocationList,
+    O: Repository<Organization>,
+{
+    type Result = Result<String>;
+
+    fn handle(&mut self, msg: RegisterUserCommand) -> Result<String> {
+        let user = User::register(&msg)?;
+
+        self.users.insert(&user)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?
+            .ok_or(AlreadyExists { resource: format!("user {}", &msg.username) })
+    }
+}
+
+impl<'a, U, R, L, O> Handles<LoginCommand> for AccountCommandsHandler<'a, U, R, L, O> where
+    U: UserRepository,
+    R: RefreshTokenRepository,
+    L: RevocationList,
+    O: Repository<Organization>,
+{
+    type Result = Result<TokenPairDTO>;
+
+    fn handle(&mut self, msg: LoginCommand) -> Result<TokenPairDTO> {
+        let user = self.users.get_by_username(&msg.username)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?;
+
+        // Unknown users, wrong passwords and locked accounts all look the same from outside.
+        let user = match user {
+            Some(user) if user.verify_password(&msg.password) && !*user.locked() => user,
+            _ => return Err(NotAuthorized),
+        };
+
+        self.issue_pair(Uuid::new_v4().to_string(), &user, msg.organization)
+    }
+}
+
+impl<'a, U, R, L, O> Handles<RefreshSessionCommand> for AccountCommandsHandler<'a, U, R, L, O> where
+    U: UserRepository,
+    R: RefreshTokenRepository,
+    L: RevocationList,
+    O: Repository<Organization>,
+{
+    type Result = Result<RefreshOutcome>;
+
+    fn handle(&mut self, msg: RefreshSessionCommand) -> Result<RefreshOutcome> {
+        let token_hash = hash_refresh_token(&msg.refresh_token);
+        let record = self.refresh_tokens.get(&token_hash)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?
+            .ok_or(NotAuthorized)?;
+
+        if record.revoked || record.is_expired() {
+            return Err(NotAuthorized);
+        }
+
+        // A token that has already been redeemed is being replayed, either by whoever copied it or
+        // by the user after the copy was redeemed first.  We can't tell which, so end the session.
+        let first_use = !record.used && self.refresh_tokens.mark_used(&token_hash)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?;
+        if !first_use {
+            self.revoke_family(&record.family_id)?;
+            return Ok(RefreshOutcome::Reused);
+        }
+
+        // Roles and the lock are read fresh, so a demoted or locked user doesn't keep either by
+        // refreshing.  The token stays spent either way.
+        let user = self.users.get_by_username(&record.username)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?;
+        let user = match user {
+            Some(user) if user.id() == record.user_id && !*user.locked() => user,
+            _ => return Ok(RefreshOutcome::Refused),
+        };
+
+        match self.issue_pair(record.family_id, &user, record.organization_id) {
+            Ok(pair) => Ok(RefreshOutcome::Refreshed(pair)),
+            Err(NotAuthorized) => Ok(RefreshOutcome::Refused),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl<'a, U, R, L, O> Handles<LogoutCommand> for AccountCommandsHandler<'a, U, R, L, O> where
+    U: UserRepository,
+    R: RefreshTokenRepository,
+    L: RevocationList,
+    O: Repository<Organization>,
+{
+    type Result = Result<()>;
+
+    fn handle(&mut self, msg: LogoutCommand) -> Result<()> {
+        if let Some(jti) = &msg.jti {
+            self.revocations.revoke(jti, msg.exp)
+                .map_err(|e| RepoFailure { source: Box::new(e) })?;
+        }
+
+        if let Some(refresh_token) = &msg.refresh_token {
+            let record = self.refresh_tokens.get(&hash_refresh_token(refresh_token))
+                .map_err(|e| RepoFailure { source: Box::new(e) })?;
+
+            // Someone else's refresh token is silently ignored rather than letting one user log
+            // out another.
+            if let Some(record) = record {
+                if record.user_id == msg.user_id {
+                    self.revoke_family(&record.family_id)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::rc::Rc;
+    use crate::app_services::token::{TokenConfig, KeyConfig};
+    use crate::errors::Error;
+
+    // In memory stand ins for the storage contracts.  The maps are shared so tests can look at what
+    // the handler stored after handing it ownership.
+    #[derive(Clone, Default)]
+    struct Users(Rc<RefCell<HashMap<String, User>>>);
+
+    #[derive(Clone, Default)]
+    struct RefreshTokens(Rc<RefCell<HashMap<String, RefreshTokenRecord>>>);
+
+    #[derive(Clone, Default)]
+    struct Revocations(Rc<RefCell<HashMap<String, i64>>>);
+
+    #[derive(Clone, Default)]
+    struct Organizations(Rc<RefCell<HashMap<String, Organization>>>);
+
+    impl UserRepository for Users {
+        type Error = std::fmt::Error;
+
+        fn insert(&mut self, user: &User) -> std::result::Result<Option<String>, Self::Error> {
+            let mut users = self.0.borrow_mut();
+            let username = user.username().to_string();
+            if users.contains_key(&username) {
+                return Ok(None);
+            }
+            users.insert(username, user.clone());
+            Ok(Some(user.id()))
+        }
+
+        fn get_by_username(&mut self, username: &String) -> std::result::Result<Option<User>, Self::Error> {
+            Ok(self.0.borrow().get(username).cloned())
+        }
+
+        fn update(&mut self, user: &User) -> std::result::Result<Option<String>, Self::Error> {
+            Ok(self.0.borrow_mut().insert(user.username().to_string(), user.clone()).map(|_| user.id()))
+        }
+    }
+
+    impl RefreshTokenRepository for RefreshTokens {
+        type Error = std::fmt::Error;
+
+        fn insert(&mut self, record: &RefreshTokenRecord) -> std::result::Result<(), Self::Error> {
+            self.0.borrow_mut().insert(record.token_hash.clone(), record.clone());
+            Ok(())
+        }
+
+        fn get(&mut self, token_hash: &String) -> std::result::Result<Option<RefreshTokenRecord>, Self::Error> {
+            Ok(self.0.borrow().get(token_hash).cloned())
+        }
+
+        fn mark_used(&mut self, token_hash: &String) -> std::result::Result<bool, Self::Error> {
+            match self.0.borrow_mut().get_mut(token_hash) {
+                Some(record) if !record.used => {
+                    record.used = true;
+                    Ok(true)
+                },
+                _ => Ok(false),
+            }
+        }
+
+        fn revoke_family(&mut self, family_id: &String) -> std::result::Result<(), Self::Error> {
+            for record in self.0.borrow_mut().values_mut().filter(|r| &r.family_id == family_id) {
+                record.revoked = true;
+            }
+            Ok(())
+        }
+
+        fn revoke_user(&mut self, user_id: &String) -> std::result::Result<(), Self::Error> {
+            for record in self.0.borrow_mut().values_mut().filter(|r| &r.user_id == user_id) {
+                record.revoked = true;
+            }
+            Ok(())
+        }
+    }
+
+    impl RevocationList for Revocations {
+        type Error = std::fmt::Error;
+
+        fn revoke(&mut self, jti: &String, expires_at: i64) -> std::result::Result<(), Self::Error> {
+            self.0.borrow_mut().insert(jti.clone(), expires_at);
+            Ok(())
+        }
+
+        fn is_revoked(&mut self, jti: &String) -> std::result::Result<bool, Self::Error> {
+            Ok(self.0.borrow().contains_key(jti))
+        }
+    }
+
+    impl Repository<Organization> for Organizations {
+        type Error = std::fmt::Error;
+
+        fn insert(&mut self, org: &Organization) -> std::result::Result<Option<String>, Self::Error> {
+            self.0.borrow_mut().insert(org.id(), org.clone());
+            Ok(Some(org.id()))
+        }
+
+        fn get(&mut self, key: &String) -> std::result::Result<Option<Organization>, Self::Error> {
+            Ok(self.0.borrow().get(key).cloned())
+        }
+
+        fn get_paged(&mut self, _page_num: usize, _page_size: usize) -> std::result::Result<Option<Vec<Organization>>, Self::Error> {
+            Ok(Some(self.0.borrow().values().cloned().collect()))
+        }
+
+        fn update(&mut self, org: &Organization) -> std::result::Result<Option<String>, Self::Error> {
+            Ok(self.0.borrow_mut().insert(org.id(), org.clone()).map(|_| org.id()))
+        }
+
+        fn remove(&mut self, key: &String) -> std::result::Result<Option<String>, Self::Error> {
+            Ok(self.0.borrow_mut().remove(key).map(|_| key.clone()))
+        }
+    }
+
+    fn token_service() -> TokenService {
+        TokenService::new(TokenConfig {
+            issuer: "https://surveys.test".to_string(),
+            audience: "survey-manager".to_string(),
+            ttl: 60,
+            refresh_ttl: 600,
+            leeway: 0,
+            signing_key: Some(KeyConfig {
+                kid: "test".to_string(),
+                algorithm: "HS256".to_string(),
+                secret: Some("a secret only the tests know".to_string()),
+                private_key_path: None,
+                public_key_path: None,
+            }),
+            verification_keys: vec![],
+            jwks: None,
+        }).unwrap()
+    }
+
+    type TestHandler<'a> = AccountCommandsHandler<'a, Users, RefreshTokens, Revocations, Organizations>;
+
+    fn handler_with_user(service: &TokenService, refresh_tokens: RefreshTokens) -> TestHandler<'_> {
+        let mut handler = AccountCommandsHandler::new(
+            Users::default(),
+            refresh_tokens,
+            Revocations::default(),
+            Organizations::default(),
+            service,
+        );
+        handler.handle(RegisterUserCommand {
+            username: "test_user".to_string(),
+            password: "correct horse battery".to_string(),
+        }).unwrap();
+        handler
+    }
+
+    fn login(handler: &mut TestHandler) -> TokenPairDTO {
+        handler.handle(LoginCommand {
+            username: "test_user".to_string(),
+            password: "correct horse battery".to_string(),
+            organization: None,
+        }).unwrap()
+    }
+
+    fn refresh(handler: &mut TestHandler, pair: &TokenPairDTO) -> Result<TokenPairDTO> {
+        handler.handle(RefreshSessionCommand { refresh_token: pair.refresh_token.clone() })?.into_pair()
+    }
+
+    fn record(refresh_tokens: &RefreshTokens, pair: &TokenPairDTO) -> RefreshTokenRecord {
+        refresh_tokens.0.borrow()[&hash_refresh_token(&pair.refresh_token)].clone()
+    }
+
+    #[test]
+    fn refreshing_rotates_the_refresh_token() {
+        let service = token_service();
+        let refresh_tokens = RefreshTokens::default();
+        let mut handler = handler_with_user(&service, refresh_tokens.clone());
+
+        let first = login(&mut handler);
+        let second = refresh(&mut handler, &first).unwrap();
+        let third = refresh(&mut handler, &second).unwrap();
+
+        assert_ne!(first.refresh_token, second.refresh_token);
+        assert_ne!(second.refresh_token, third.refresh_token);
+        assert!(service.decode_payload(&third.access_token).is_ok());
+
+        // Redeemed tokens are retired, and every token descends from the same login.
+        assert!(record(&refresh_tokens, &first).used);
+        assert!(record(&refresh_tokens, &second).used);
+        assert!(!record(&refresh_tokens, &third).used);
+        assert_eq!(record(&refresh_tokens, &first).family_id, record(&refresh_tokens, &third).family_id);
+    }
+
+    #[test]
+    fn reusing_a_redeemed_token_revokes_its_family() {
+        let service = token_service();
+        let refresh_tokens = RefreshTokens::default();
+        let mut handler = handler_with_user(&service, refresh_tokens.clone());
+
+        let first = login(&mut handler);
+        let second = refresh(&mut handler, &first).unwrap();
+
+        // Reported as an outcome, not an error, so the revocation commits with the unit of work.
+        match handler.handle(RefreshSessionCommand { refresh_token: first.refresh_token.clone() }) {
+            Ok(RefreshOutcome::Reused) => (),
+            _ => panic!("a replayed refresh token must be refused"),
+        }
+
+        // The newest token in the family goes too, since it may be the one that was stolen.
+        assert!(record(&refresh_tokens, &second).revoked);
+        match refresh(&mut handler, &second) {
+            Err(Error::NotAuthorized) => (),
+            _ => panic!("tokens in a revoked family must be refused"),
+        }
+    }
+
+    #[test]
+    fn a_refused_refresh_still_spends_the_token() {
+        let service = token_service();
+        let refresh_tokens = RefreshTokens::default();
+        let mut handler = handler_with_user(&service, refresh_tokens.clone());
+        let pair = login(&mut handler);
+
+        handler.users.0.borrow_mut().get_mut("test_user").unwrap().lock().unwrap();
+
+        match handler.handle(RefreshSessionCommand { refresh_token: pair.refresh_token.clone() }) {
+            Ok(RefreshOutcome::Refused) => (),
+            _ => panic!("a locked user's refresh must be refused"),
+        }
+        assert!(record(&refresh_tokens, &pair).used);
+    }
+
+    #[test]
+    fn reuse_leaves_other_sessions_alone() {
+        let service = token_service();
+        let refresh_tokens = RefreshTokens::default();
+        let mut handler = handler_with_user(&service, refresh_tokens.clone());
+
+        let stolen = login(&mut handler);
+        let other_device = login(&mut handler);
+        refresh(&mut handler, &stolen).unwrap();
+        assert!(refresh(&mut handler, &stolen).is_err());
+
+        assert!(!record(&refresh_tokens, &other_device).revoked);
+        assert!(refresh(&mut handler, &other_device).is_ok());
+    }
+
+    #[test]
+    fn logging_out_revokes_the_family() {
+        let service = token_service();
+        let refresh_tokens = RefreshTokens::default();
+        let mut handler = handler_with_user(&service, refresh_tokens.clone());
+
+        let first = login(&mut handler);
+        let second = refresh(&mut handler, &first).unwrap();
+        let user_id = record(&refresh_tokens, &second).user_id;
+
+        handler.handle(LogoutCommand {
+            user_id,
+            jti: None,
+            exp: 0,
+            refresh_token: Some(second.refresh_token.clone()),
+        }).unwrap();
+
+        assert!(record(&refresh_tokens, &first).revoked);
+        assert!(refresh(&mut handler, &second).is_err());
+    }
+}
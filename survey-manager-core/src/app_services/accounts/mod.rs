@@ -0,0 +1,14 @@
+//! Registration, login and the token lifecycle that follows it.
+//!
+//! Logging in hands out a short lived access token and a long lived refresh token.  Refresh tokens
+//! are single use: redeeming one returns a new pair and retires the old token.  Every token
+//! descended from the same login shares a family, and if a retired token is ever presented again
+//! we assume it was stolen and revoke the whole family, logging out both the thief and the user.
+pub mod account_commands;
+pub use account_commands::*;
+
+pub mod refresh_token;
+pub use refresh_token::*;
+
+pub mod account_commands_handler;
+pub use account_commands_handler::*;
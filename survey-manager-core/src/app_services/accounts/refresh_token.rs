@@ -0,0 +1,76 @@
+use sha2::{Sha256, Digest};
+use uuid::Uuid;
+use chrono::Utc;
+use crate::errors::{Error, Result};
+use crate::dtos::TokenPairDTO;
+
+/// What we keep about an issued refresh token.  Only a hash of the token itself is stored, so a
+/// leaked table can't be replayed.
+#[derive(Clone)]
+pub struct RefreshTokenRecord {
+    pub token_hash: String,
+    /// Shared by every token descended from the same login.
+    pub family_id: String,
+    pub user_id: String,
+    pub username: String,
+    /// The organization the session logged in to.  Membership is checked again on every refresh.
+    pub organization_id: Option<String>,
+    pub expires_at: i64,
+    /// Set once the token has been redeemed.  Seeing a used token again means it was copied.
+    pub used: bool,
+    /// Set on every token in a family when the family is logged out or caught being reused.
+    pub revoked: bool,
+}
+
+impl RefreshTokenRecord {
+    /// Mints a new refresh token, returning the token to hand to the client and the record to store.
+    pub fn issue(family_id: String, user_id: String, username: String, organization_id: Option<String>, ttl: i64) -> (String, RefreshTokenRecord) {
+        // Two v4 uuids give 244 random bits, well beyond guessing range.
+        let token = format!("{}{}", Uuid::new_v4().to_simple(), Uuid::new_v4().to_simple());
+
+        let record = RefreshTokenRecord {
+            token_hash: hash_refresh_token(&token),
+            family_id,
+            user_id,
+            username,
+            organization_id,
+            expires_at: Utc::now().timestamp() + ttl,
+            used: false,
+            revoked: false,
+        };
+
+        (token, record)
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now().timestamp()
+    }
+}
+
+/// How redeeming a refresh token went.  Refusals that record something come back as outcomes rather
+/// than errors, so the unit of work the refresh runs in commits the revoked family or the spent
+/// token instead of rolling it back.
+pub enum RefreshOutcome {
+    /// A new pair.  The redeemed token is retired.
+    Refreshed(TokenPairDTO),
+    /// The token had already been redeemed, so every token in its family was revoked.
+    Reused,
+    /// The token was spent, but its user is gone or locked, or no longer in the session's
+    /// organization.
+    Refused,
+}
+
+impl RefreshOutcome {
+    /// The new pair, or `NotAuthorized` for either refusal.  Call once the outcome is committed.
+    pub fn into_pair(self) -> Result<TokenPairDTO> {
+        match self {
+            RefreshOutcome::Refreshed(pair) => Ok(pair),
+            RefreshOutcome::Reused | RefreshOutcome::Refused => Err(Error::NotAuthorized),
+        }
+    }
+}
+
+/// Refresh tokens are high entropy, so a plain sha256 is enough to keep them out of storage.
+pub fn hash_refresh_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
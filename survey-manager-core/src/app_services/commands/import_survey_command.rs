@@ -0,0 +1,11 @@
+use domain_patterns::command::Command;
+use domain_patterns::message::Message;
+use crate::dtos::DefinitionFormat;
+
+/// Creates a new survey owned by `author` from a portable `SurveyDefinition` document.
+#[derive(Clone, Command)]
+pub struct ImportSurveyCommand {
+    pub author: String,
+    pub format: DefinitionFormat,
+    pub document: String,
+}
@@ -12,3 +12,21 @@ pub use survey_commands_handler::*;
 
 pub mod remove_survey_command;
 pub use remove_survey_command::*;
+
+pub mod import_survey_command;
+pub use import_survey_command::*;
+
+pub mod share_survey_command;
+pub use share_survey_command::*;
+
+pub mod revoke_access_command;
+pub use revoke_access_command::*;
+
+pub mod transfer_survey_ownership_command;
+pub use transfer_survey_ownership_command::*;
+
+pub mod set_response_policy_command;
+pub use set_response_policy_command::*;
+
+pub mod set_published_command;
+pub use set_published_command::*;
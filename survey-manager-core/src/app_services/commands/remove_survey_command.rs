@@ -1,8 +1,10 @@
 use domain_patterns::command::Command;
 use domain_patterns::message::Message;
+use crate::app_services::token::OrganizationClaim;
 
 #[derive(Clone, Command)]
 pub struct RemoveSurveyCommand {
     pub id: String,
     pub requesting_author: String,
+    pub organization: Option<OrganizationClaim>,
 }
@@ -0,0 +1,11 @@
+use domain_patterns::command::Command;
+use domain_patterns::message::Message;
+use crate::app_services::token::OrganizationClaim;
+
+#[derive(Clone, Command)]
+pub struct RevokeAccessCommand {
+    pub id: String,
+    pub requesting_author: String,
+    pub organization: Option<OrganizationClaim>,
+    pub username: String,
+}
@@ -0,0 +1,12 @@
+use domain_patterns::command::Command;
+use domain_patterns::message::Message;
+use crate::app_services::token::OrganizationClaim;
+
+/// Opens a survey for responses, or closes it again.
+#[derive(Clone, Command)]
+pub struct SetPublishedCommand {
+    pub id: String,
+    pub requesting_author: String,
+    pub organization: Option<OrganizationClaim>,
+    pub published: bool,
+}
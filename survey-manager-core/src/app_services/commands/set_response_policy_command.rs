@@ -0,0 +1,12 @@
+use domain_patterns::command::Command;
+use domain_patterns::message::Message;
+use crate::app_services::token::OrganizationClaim;
+
+/// Sets who may answer a survey: `anyone`, `link` holders or `authenticated` users.
+#[derive(Clone, Command)]
+pub struct SetResponsePolicyCommand {
+    pub id: String,
+    pub requesting_author: String,
+    pub organization: Option<OrganizationClaim>,
+    pub policy: String,
+}
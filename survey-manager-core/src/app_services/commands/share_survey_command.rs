@@ -0,0 +1,13 @@
+use domain_patterns::command::Command;
+use domain_patterns::message::Message;
+use crate::app_services::token::OrganizationClaim;
+
+/// Grants `username` the `role` (editor, viewer or analyst) on a survey.
+#[derive(Clone, Command)]
+pub struct ShareSurveyCommand {
+    pub id: String,
+    pub requesting_author: String,
+    pub organization: Option<OrganizationClaim>,
+    pub username: String,
+    pub role: String,
+}
@@ -1,12 +1,18 @@
 use domain_patterns::command::Command;
 use domain_patterns::message::Message;
-use crate::app_services::commands::{CreateSurveyCommand, UpdateSurveyCommand, RemoveSurveyCommand};
+use crate::app_services::commands::{CreateSurveyCommand, UpdateSurveyCommand, RemoveSurveyCommand, ImportSurveyCommand, ShareSurveyCommand, RevokeAccessCommand, TransferSurveyOwnershipCommand, SetResponsePolicyCommand, SetPublishedCommand};
 
 #[derive(Clone, Command)]
 pub enum SurveyCommands {
     CreateSurveyCommand(CreateSurveyCommand),
     UpdateSurveyCommand(UpdateSurveyCommand),
     RemoveSurveyCommand(RemoveSurveyCommand),
+    ImportSurveyCommand(ImportSurveyCommand),
+    ShareSurveyCommand(ShareSurveyCommand),
+    RevokeAccessCommand(RevokeAccessCommand),
+    TransferSurveyOwnershipCommand(TransferSurveyOwnershipCommand),
+    SetResponsePolicyCommand(SetResponsePolicyCommand),
+    SetPublishedCommand(SetPublishedCommand),
 }
 
 // Implementations to automatically turn each variant into the parent enum.
@@ -27,3 +33,39 @@ impl From<RemoveSurveyCommand> for SurveyCommands {
         SurveyCommands::RemoveSurveyCommand(cmd)
     }
 }
+
+impl From<ImportSurveyCommand> for SurveyCommands {
+    fn from(cmd: ImportSurveyCommand) -> Self {
+        SurveyCommands::ImportSurveyCommand(cmd)
+    }
+}
+
+impl From<ShareSurveyCommand> for SurveyCommands {
+    fn from(cmd: ShareSurveyCommand) -> Self {
+        SurveyCommands::ShareSurveyCommand(cmd)
+    }
+}
+
+impl From<RevokeAccessCommand> for SurveyCommands {
+    fn from(cmd: RevokeAccessCommand) -> Self {
+        SurveyCommands::RevokeAccessCommand(cmd)
+    }
+}
+
+impl From<TransferSurveyOwnershipCommand> for SurveyCommands {
+    fn from(cmd: TransferSurveyOwnershipCommand) -> Self {
+        SurveyCommands::TransferSurveyOwnershipCommand(cmd)
+    }
+}
+
+impl From<SetResponsePolicyCommand> for SurveyCommands {
+    fn from(cmd: SetResponsePolicyCommand) -> Self {
+        SurveyCommands::SetResponsePolicyCommand(cmd)
+    }
+}
+
+impl From<SetPublishedCommand> for SurveyCommands {
+    fn from(cmd: SetPublishedCommand) -> Self {
+        SurveyCommands::SetPublishedCommand(cmd)
+    }
+}
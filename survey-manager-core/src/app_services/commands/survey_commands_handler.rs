@@ -1,9 +1,12 @@
 use domain_patterns::collections::Repository;
-use crate::errors::Error::{ResourceNotFound, NotAuthorized, RepoFailure, ConcurrencyFailure};
+use crate::errors::Error::{ResourceNotFound, RepoFailure, ConcurrencyFailure, NotAuthorized};
 use crate::errors::Result;
 use domain_patterns::command::Handles;
-use crate::survey::Survey;
-use crate::app_services::commands::{CreateSurveyCommand, UpdateSurveyCommand, SurveyCommands, RemoveSurveyCommand};
+use crate::survey::{Survey, FieldCase};
+use crate::app_services::commands::{CreateSurveyCommand, UpdateSurveyCommand, SurveyCommands, RemoveSurveyCommand, ImportSurveyCommand, ShareSurveyCommand, RevokeAccessCommand, TransferSurveyOwnershipCommand, SetResponsePolicyCommand, SetPublishedCommand};
+use crate::dtos::SurveyDefinition;
+use crate::policy::{authorize, Actor, Permission};
+use crate::value_objects::ValidationError;
 
 
 pub struct SurveyCommandsHandler<T> where
@@ -20,6 +23,32 @@ impl<T> SurveyCommandsHandler<T> where
             repo,
         }
     }
+
+    /// Hands back the repository, for whatever has to follow once its writes have committed.
+    pub fn into_repository(self) -> T {
+        self.repo
+    }
+
+    fn load(&mut self, id: &String) -> Result<Survey> {
+        self.repo.get(id)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?
+            .ok_or(ResourceNotFound { resource: format!("survey with id {}", id) })
+    }
+
+    fn save(&mut self, survey: &Survey) -> Result<String> {
+        let s_id = self.repo.update(survey)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?;
+
+        if let Some(s) = s_id {
+            return Ok(s);
+        }
+
+        // If we got here then repo.update returned None.  This would only happen if there was no valid
+        // survey to update, which could only have happened if the survey was deleted between the time
+        // that we retrieved it with repo.get, and updated it with repo.update. Any other database errors
+        // would have been returned inside the mapped RepoFailure error on the update.
+        Err(ConcurrencyFailure)
+    }
 }
 
 impl<T: Repository<Survey>> Handles<CreateSurveyCommand> for SurveyCommandsHandler<T> {
@@ -41,28 +70,11 @@ impl<T: Repository<Survey>> Handles<UpdateSurveyCommand> for SurveyCommandsHandl
     type Result = Result<String>;
 
     fn handle(&mut self, msg: UpdateSurveyCommand) -> Result<String> {
-        let mut survey = self.repo.get(&msg.id)
-            .map_err(|e| RepoFailure { source: Box::new(e) })?
-            .ok_or(ResourceNotFound { resource: format!("survey with id {}", &msg.id) })?;
-
-        if !survey.belongs_to(&msg.author) {
-            return Err(NotAuthorized.into());
-        }
+        let mut survey = self.load(&msg.id)?;
+        authorize(&survey, &Actor::new(&msg.author, &msg.organization), Permission::Edit)?;
 
         survey.try_update(msg)?;
-
-        let s_id = self.repo.update(&survey)
-            .map_err(|e| RepoFailure { source: Box::new(e) })?;
-
-        if let Some(s) = s_id {
-            return Ok(s);
-        }
-
-        // If we got here then repo.update returned None.  This would only happen if there was no valid
-        // survey to update, which could only have happened if the survey was deleted between the time
-        // that we retrieved it with repo.get, and updated it with repo.update. Any other database errors
-        // would have been returned inside the mapped RepoFailure error on the update.
-        Err(ConcurrencyFailure)
+        self.save(&survey)
     }
 }
 
@@ -72,13 +84,8 @@ impl<T> Handles<RemoveSurveyCommand> for SurveyCommandsHandler<T>
     type Result = Result<String>;
 
     fn handle(&mut self, msg: RemoveSurveyCommand) -> Self::Result {
-        let survey = self.repo.get(&msg.id)
-            .map_err(|e| RepoFailure { source: Box::new(e) })?
-            .ok_or(ResourceNotFound { resource: format!("survey with id {}", &msg.id) })?;
-
-        if !survey.belongs_to(&msg.requesting_author) {
-            return Err(NotAuthorized.into());
-        }
+        let survey = self.load(&msg.id)?;
+        authorize(&survey, &Actor::new(&msg.requesting_author, &msg.organization), Permission::Delete)?;
 
         let s_id = self.repo.remove(&msg.id)
             .map_err(|e| RepoFailure { source: Box::new(e) })?;
@@ -87,6 +94,100 @@ impl<T> Handles<RemoveSurveyCommand> for SurveyCommandsHandler<T>
     }
 }
 
+impl<T: Repository<Survey>> Handles<ImportSurveyCommand> for SurveyCommandsHandler<T> {
+    type Result = Result<String>;
+
+    fn handle(&mut self, msg: ImportSurveyCommand) -> Result<String> {
+        let definition = SurveyDefinition::parse(&msg.document, msg.format)?;
+        let new_survey = Survey::new_from_document(&definition.into_create_command(msg.author), FieldCase::Camel)?;
+
+        let s_id = self.repo.insert(&new_survey)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?;
+
+        // Safe to unwrap for the same reason as in the create handler.
+        Ok(s_id.unwrap())
+    }
+}
+
+impl<T: Repository<Survey>> Handles<ShareSurveyCommand> for SurveyCommandsHandler<T> {
+    type Result = Result<String>;
+
+    fn handle(&mut self, msg: ShareSurveyCommand) -> Result<String> {
+        let mut survey = self.load(&msg.id)?;
+        authorize(&survey, &Actor::new(&msg.requesting_author, &msg.organization), Permission::Share)?;
+
+        survey.share(&msg.username, &msg.role)?;
+        self.save(&survey)
+    }
+}
+
+impl<T: Repository<Survey>> Handles<RevokeAccessCommand> for SurveyCommandsHandler<T> {
+    type Result = Result<String>;
+
+    fn handle(&mut self, msg: RevokeAccessCommand) -> Result<String> {
+        let mut survey = self.load(&msg.id)?;
+        authorize(&survey, &Actor::new(&msg.requesting_author, &msg.organization), Permission::Share)?;
+
+        survey.revoke_access(&msg.username)?;
+        self.save(&survey)
+    }
+}
+
+impl<T: Repository<Survey>> Handles<TransferSurveyOwnershipCommand> for SurveyCommandsHandler<T> {
+    type Result = Result<String>;
+
+    fn handle(&mut self, msg: TransferSurveyOwnershipCommand) -> Result<String> {
+        let mut survey = self.load(&msg.id)?;
+        let actor = Actor::new(&msg.requesting_author, &msg.organization);
+        authorize(&survey, &actor, Permission::Transfer)?;
+
+        match (&msg.to_user, &msg.to_organization) {
+            (Some(username), None) => survey.transfer_to_user(username)?,
+            (None, Some(org_id)) => {
+                // Owning a survey isn't enough to push it on an organization, the caller has to
+                // run that organization too.
+                match actor.org_role(org_id) {
+                    Some(role) if role.can_manage() => survey.transfer_to_organization(org_id)?,
+                    _ => return Err(NotAuthorized),
+                }
+            },
+            _ => return Err(ValidationError::TransferValidationError {
+                msg: "name either a user or an organization to transfer to, not both".to_string(),
+            }.into()),
+        }
+
+        self.save(&survey)
+    }
+}
+
+impl<T: Repository<Survey>> Handles<SetResponsePolicyCommand> for SurveyCommandsHandler<T> {
+    type Result = Result<String>;
+
+    fn handle(&mut self, msg: SetResponsePolicyCommand) -> Result<String> {
+        let mut survey = self.load(&msg.id)?;
+        authorize(&survey, &Actor::new(&msg.requesting_author, &msg.organization), Permission::Edit)?;
+
+        survey.change_response_policy(&msg.policy)?;
+        self.save(&survey)
+    }
+}
+
+impl<T: Repository<Survey>> Handles<SetPublishedCommand> for SurveyCommandsHandler<T> {
+    type Result = Result<String>;
+
+    fn handle(&mut self, msg: SetPublishedCommand) -> Result<String> {
+        let mut survey = self.load(&msg.id)?;
+        authorize(&survey, &Actor::new(&msg.requesting_author, &msg.organization), Permission::Edit)?;
+
+        if msg.published {
+            survey.publish()?;
+        } else {
+            survey.unpublish()?;
+        }
+        self.save(&survey)
+    }
+}
+
 impl<T: Repository<Survey>> Handles<SurveyCommands> for SurveyCommandsHandler<T> {
     type Result = Result<String>;
 
@@ -95,6 +196,12 @@ impl<T: Repository<Survey>> Handles<SurveyCommands> for SurveyCommandsHandler<T>
             SurveyCommands::CreateSurveyCommand(cmd) => self.handle(cmd),
             SurveyCommands::UpdateSurveyCommand(cmd) => self.handle(cmd),
             SurveyCommands::RemoveSurveyCommand(cmd) => self.handle(cmd),
+            SurveyCommands::ImportSurveyCommand(cmd) => self.handle(cmd),
+            SurveyCommands::ShareSurveyCommand(cmd) => self.handle(cmd),
+            SurveyCommands::RevokeAccessCommand(cmd) => self.handle(cmd),
+            SurveyCommands::TransferSurveyOwnershipCommand(cmd) => self.handle(cmd),
+            SurveyCommands::SetResponsePolicyCommand(cmd) => self.handle(cmd),
+            SurveyCommands::SetPublishedCommand(cmd) => self.handle(cmd),
         }
     }
 }
@@ -0,0 +1,15 @@
+use domain_patterns::command::Command;
+use domain_patterns::message::Message;
+use crate::app_services::token::OrganizationClaim;
+
+/// Hands a survey to another user or to an organization.  Exactly one of `to_user` and
+/// `to_organization` must be set.  Moving a survey into an organization also needs the caller to
+/// be an owner or admin of that organization, acting for it through `organization`.
+#[derive(Clone, Command)]
+pub struct TransferSurveyOwnershipCommand {
+    pub id: String,
+    pub requesting_author: String,
+    pub organization: Option<OrganizationClaim>,
+    pub to_user: Option<String>,
+    pub to_organization: Option<String>,
+}
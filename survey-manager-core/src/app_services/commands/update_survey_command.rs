@@ -3,12 +3,14 @@ use domain_patterns::message::Message;
 use crate::app_services::commands::{CreateQuestionCommand, CreateChoiceCommand};
 use std::convert::TryInto;
 use crate::errors::Error;
+use crate::app_services::token::OrganizationClaim;
 use crate::value_objects::ValidationError::{ContentValidationError, ContentTypeValidationError, TitleValidationError, MissingChoicesError, MissingQuestionTypeError};
 
 #[derive(Clone, Command)]
 pub struct UpdateSurveyCommand {
     pub id: String,
     pub author: String,
+    pub organization: Option<OrganizationClaim>,
     pub title: Option<String>,
     pub description: Option<String>,
     pub category: Option<String>,
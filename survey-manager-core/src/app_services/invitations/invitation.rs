@@ -0,0 +1,89 @@
+use serde::{Serialize, Deserialize};
+use uuid::Uuid;
+use chrono::Utc;
+
+/// How far a recipient has got with an invitation.  Stored as the `as_str` form.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InvitationStatus {
+    Sent,
+    Opened,
+    Completed,
+}
+
+impl InvitationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InvitationStatus::Sent => "sent",
+            InvitationStatus::Opened => "opened",
+            InvitationStatus::Completed => "completed",
+        }
+    }
+
+    /// Reads back a stored status.  Anything unrecognized is treated as still sent.
+    pub fn parse(status: &str) -> InvitationStatus {
+        match status {
+            "opened" => InvitationStatus::Opened,
+            "completed" => InvitationStatus::Completed,
+            _ => InvitationStatus::Sent,
+        }
+    }
+}
+
+/// What we keep about an invitation.  The link itself isn't stored, it can be rebuilt from the id
+/// and expiry by whoever holds the signing secret.
+#[derive(Clone)]
+pub struct InvitationRecord {
+    pub id: String,
+    pub survey_id: String,
+    pub recipient: String,
+    pub status: InvitationStatus,
+    pub created_at: i64,
+    pub expires_at: i64,
+}
+
+impl InvitationRecord {
+    pub fn new(survey_id: String, recipient: String, ttl: i64) -> InvitationRecord {
+        let created_at = Utc::now().timestamp();
+
+        InvitationRecord {
+            id: Uuid::new_v4().to_string(),
+            survey_id,
+            recipient,
+            status: InvitationStatus::Sent,
+            created_at,
+            expires_at: created_at + ttl,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now().timestamp()
+    }
+}
+
+/// An invitation as handed back to the survey's owner, link included so it can also be passed on
+/// by hand.
+#[derive(Serialize, Deserialize)]
+pub struct CreatedInvitation {
+    pub id: String,
+    pub recipient: String,
+    pub link: String,
+    pub expires_at: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CreatedInvitations {
+    pub invitations: Vec<CreatedInvitation>,
+}
+
+/// Counts of a survey's invitations by status.  `expired` counts invitations that ran out before
+/// being completed, they're also included under whichever status they reached.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct InvitationStats {
+    pub survey_id: String,
+    pub total: u64,
+    pub sent: u64,
+    pub opened: u64,
+    pub completed: u64,
+    pub expired: u64,
+}
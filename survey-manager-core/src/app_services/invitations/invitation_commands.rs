@@ -0,0 +1,37 @@
+use domain_patterns::command::Command;
+use domain_patterns::message::Message;
+use crate::app_services::token::OrganizationClaim;
+
+/// Mails a single use link to answer the survey to each of `recipients`.  Needs the right to
+/// share the survey.
+#[derive(Clone, Command)]
+pub struct CreateInvitationsCommand {
+    pub survey_id: String,
+    pub requesting_author: String,
+    pub organization: Option<OrganizationClaim>,
+    pub recipients: Vec<String>,
+}
+
+/// Records that a recipient followed their link.  Anyone holding the link may send this.
+#[derive(Clone, Command)]
+pub struct OpenInvitationCommand {
+    pub token: String,
+}
+
+#[derive(Clone, Command)]
+pub enum InvitationCommands {
+    CreateInvitationsCommand(CreateInvitationsCommand),
+    OpenInvitationCommand(OpenInvitationCommand),
+}
+
+impl From<CreateInvitationsCommand> for InvitationCommands {
+    fn from(cmd: CreateInvitationsCommand) -> Self {
+        InvitationCommands::CreateInvitationsCommand(cmd)
+    }
+}
+
+impl From<OpenInvitationCommand> for InvitationCommands {
+    fn from(cmd: OpenInvitationCommand) -> Self {
+        InvitationCommands::OpenInvitationCommand(cmd)
+    }
+}
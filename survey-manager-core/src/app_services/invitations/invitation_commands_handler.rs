@@ -0,0 +1,149 @@
+use domain_patterns::collections::Repository;
+use domain_patterns::command::Handles;
+use domain_patterns::models::Entity;
+use std::convert::TryFrom;
+use crate::errors::Error::{ResourceNotFound, RepoFailure};
+use crate::errors::{Result, FieldErrors};
+use crate::survey::Survey;
+use crate::policy::{authorize, Actor, Permission};
+use crate::value_objects::{Email, ValidationError};
+use crate::app_services::repository_contracts::InvitationRepository;
+use crate::app_services::invitations::{
+    CreateInvitationsCommand, OpenInvitationCommand, InvitationCommands,
+    InvitationRecord, InvitationSigner, InvitationEmail, Mailer, CreatedInvitation, CreatedInvitations,
+};
+
+// Keeps one request from tying up the mailer for minutes.  Larger lists can be sent in batches.
+const MAX_RECIPIENTS: usize = 500;
+
+pub struct InvitationCommandsHandler<'a, S, I, M> where
+    S: Repository<Survey>,
+    I: InvitationRepository,
+    M: Mailer,
+{
+    surveys: S,
+    invitations: I,
+    mailer: M,
+    signer: &'a InvitationSigner,
+}
+
+impl<'a, S, I, M> InvitationCommandsHandler<'a, S, I, M> where
+    S: Repository<Survey>,
+    I: InvitationRepository,
+    M: Mailer,
+{
+    pub fn new(surveys: S, invitations: I, mailer: M, signer: &'a InvitationSigner) -> InvitationCommandsHandler<'a, S, I, M> {
+        InvitationCommandsHandler {
+            surveys,
+            invitations,
+            mailer,
+            signer,
+        }
+    }
+
+    // The signature is checked before storage is touched, so forged links cost us nothing.
+    fn load_from_token(&mut self, token: &str) -> Result<InvitationRecord> {
+        let id = self.signer.verify(token)?;
+
+        self.invitations.get(&id)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?
+            .ok_or(ResourceNotFound { resource: format!("invitation with id {}", id) })
+    }
+}
+
+impl<'a, S, I, M> Handles<CreateInvitationsCommand> for InvitationCommandsHandler<'a, S, I, M> where
+    S: Repository<Survey>,
+    I: InvitationRepository,
+    M: Mailer,
+{
+    // Json of `CreatedInvitations`.
+    type Result = Result<String>;
+
+    fn handle(&mut self, msg: CreateInvitationsCommand) -> Result<String> {
+        let survey = self.surveys.get(&msg.survey_id)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?
+            .ok_or(ResourceNotFound { resource: format!("survey with id {}", msg.survey_id) })?;
+        authorize(&survey, &Actor::new(&msg.requesting_author, &msg.organization), Permission::Share)?;
+
+        if msg.recipients.is_empty() || msg.recipients.len() > MAX_RECIPIENTS {
+            return Err(ValidationError::InvitationValidationError {
+                msg: format!("name between 1 and {} recipients", MAX_RECIPIENTS),
+            }.into());
+        }
+
+        let mut errors = FieldErrors::new();
+        let mut recipients: Vec<String> = vec![];
+        for (idx, recipient) in msg.recipients.iter().enumerate() {
+            if let Some(email) = errors.check(format!("/recipients/{}", idx), Email::try_from(recipient.trim().to_string()))? {
+                // Someone listed twice only gets one invitation.
+                let email = email.to_string();
+                if !recipients.iter().any(|r| r.eq_ignore_ascii_case(&email)) {
+                    recipients.push(email);
+                }
+            }
+        }
+        errors.into_result()?;
+
+        // Each invitation is stored before it's mailed, so a link can never arrive for an
+        // invitation we don't know about.  If the mailer fails part way, the ones before it
+        // have gone out and the rest are left as they were.
+        let mut created = vec![];
+        for recipient in recipients {
+            let invitation = InvitationRecord::new(survey.id(), recipient, self.signer.ttl());
+            self.invitations.insert(&invitation)
+                .map_err(|e| RepoFailure { source: Box::new(e) })?;
+
+            let link = self.signer.link(&invitation);
+            self.mailer.send(&InvitationEmail {
+                invitation_id: invitation.id.clone(),
+                to: invitation.recipient.clone(),
+                survey_title: survey.title().to_string(),
+                link: link.clone(),
+                expires_at: invitation.expires_at,
+            }).map_err(|e| RepoFailure { source: Box::new(e) })?;
+
+            created.push(CreatedInvitation {
+                id: invitation.id,
+                recipient: invitation.recipient,
+                link,
+                expires_at: invitation.expires_at,
+            });
+        }
+
+        Ok(serde_json::to_string(&CreatedInvitations { invitations: created }).unwrap())
+    }
+}
+
+impl<'a, S, I, M> Handles<OpenInvitationCommand> for InvitationCommandsHandler<'a, S, I, M> where
+    S: Repository<Survey>,
+    I: InvitationRepository,
+    M: Mailer,
+{
+    // Id of the survey the link is for.
+    type Result = Result<String>;
+
+    fn handle(&mut self, msg: OpenInvitationCommand) -> Result<String> {
+        let invitation = self.load_from_token(&msg.token)?;
+
+        // Opening a link again, or after completing it, isn't an error.  The status just stays put.
+        self.invitations.mark_opened(&invitation.id)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?;
+
+        Ok(invitation.survey_id)
+    }
+}
+
+impl<'a, S, I, M> Handles<InvitationCommands> for InvitationCommandsHandler<'a, S, I, M> where
+    S: Repository<Survey>,
+    I: InvitationRepository,
+    M: Mailer,
+{
+    type Result = Result<String>;
+
+    fn handle(&mut self, msg: InvitationCommands) -> Result<String> {
+        match msg {
+            InvitationCommands::CreateInvitationsCommand(cmd) => self.handle(cmd),
+            InvitationCommands::OpenInvitationCommand(cmd) => self.handle(cmd),
+        }
+    }
+}
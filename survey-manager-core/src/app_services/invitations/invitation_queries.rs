@@ -0,0 +1,21 @@
+use domain_patterns::query::Query;
+use crate::app_services::token::OrganizationClaim;
+
+/// Counts a survey's invitations by status.  Needs the right to view the survey's results.
+#[derive(Query)]
+pub struct InvitationStatsQuery {
+    pub survey_id: String,
+    pub requesting_author: String,
+    pub organization: Option<OrganizationClaim>,
+}
+
+#[derive(Query)]
+pub enum InvitationQueries {
+    InvitationStatsQuery(InvitationStatsQuery),
+}
+
+impl From<InvitationStatsQuery> for InvitationQueries {
+    fn from(query: InvitationStatsQuery) -> Self {
+        InvitationQueries::InvitationStatsQuery(query)
+    }
+}
@@ -0,0 +1,61 @@
+use domain_patterns::collections::Repository;
+use domain_patterns::query::HandlesQuery;
+use crate::errors::Error::{ResourceNotFound, RepoFailure};
+use crate::errors::Result;
+use crate::survey::Survey;
+use crate::policy::{authorize, Actor, Permission};
+use crate::app_services::repository_contracts::InvitationRepository;
+use crate::app_services::invitations::{InvitationStatsQuery, InvitationQueries};
+
+pub struct InvitationQueriesHandler<S, I> where
+    S: Repository<Survey>,
+    I: InvitationRepository,
+{
+    surveys: S,
+    invitations: I,
+}
+
+impl<S, I> InvitationQueriesHandler<S, I> where
+    S: Repository<Survey>,
+    I: InvitationRepository,
+{
+    pub fn new(surveys: S, invitations: I) -> InvitationQueriesHandler<S, I> {
+        InvitationQueriesHandler {
+            surveys,
+            invitations,
+        }
+    }
+}
+
+impl<S, I> HandlesQuery<InvitationStatsQuery> for InvitationQueriesHandler<S, I> where
+    S: Repository<Survey>,
+    I: InvitationRepository,
+{
+    // Json of `InvitationStats`.
+    type Result = Result<String>;
+
+    fn handle(&mut self, query: InvitationStatsQuery) -> Self::Result {
+        let survey = self.surveys.get(&query.survey_id)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?
+            .ok_or(ResourceNotFound { resource: format!("survey with id {}", query.survey_id) })?;
+        authorize(&survey, &Actor::new(&query.requesting_author, &query.organization), Permission::ViewResults)?;
+
+        let stats = self.invitations.stats(&query.survey_id)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?;
+
+        Ok(serde_json::to_string(&stats).unwrap())
+    }
+}
+
+impl<S, I> HandlesQuery<InvitationQueries> for InvitationQueriesHandler<S, I> where
+    S: Repository<Survey>,
+    I: InvitationRepository,
+{
+    type Result = Result<String>;
+
+    fn handle(&mut self, query: InvitationQueries) -> Self::Result {
+        match query {
+            InvitationQueries::InvitationStatsQuery(q) => self.handle(q),
+        }
+    }
+}
@@ -0,0 +1,17 @@
+/// A message inviting `to` to answer a survey.
+pub struct InvitationEmail {
+    pub invitation_id: String,
+    pub to: String,
+    pub survey_title: String,
+    pub link: String,
+    pub expires_at: i64,
+}
+
+/// Somewhere invitation emails can be handed off for delivery.  Implementations decide how they
+/// go out, an SMTP relay, a provider's API, or a local spool for testing.
+pub trait Mailer {
+    /// Error type that likely corresponds to an underlying transport error.
+    type Error: 'static + std::error::Error + std::fmt::Display + Send;
+
+    fn send(&mut self, email: &InvitationEmail) -> Result<(), Self::Error>;
+}
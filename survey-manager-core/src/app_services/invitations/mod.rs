@@ -0,0 +1,26 @@
+//! Invitations send a survey to a list of recipients, each with their own link.
+//!
+//! A link carries an HMAC signed token naming the invitation and when it expires, so forged or
+//! expired links are turned away before storage is touched.  Every invitation moves from sent, to
+//! opened when the link is followed, to completed when a response is recorded against it.  A link
+//! completes once, which is what makes it single use.
+pub mod invitation;
+pub use invitation::*;
+
+pub mod signer;
+pub use signer::*;
+
+pub mod mailer;
+pub use mailer::*;
+
+pub mod invitation_commands;
+pub use invitation_commands::*;
+
+pub mod invitation_queries;
+pub use invitation_queries::*;
+
+pub mod invitation_commands_handler;
+pub use invitation_commands_handler::*;
+
+pub mod invitation_queries_handler;
+pub use invitation_queries_handler::*;
@@ -0,0 +1,248 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use chrono::Utc;
+use snafu::Snafu;
+use crate::errors::{Error, Result};
+use crate::value_objects::ValidationError;
+use crate::app_services::invitations::InvitationRecord;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// Anything shorter is within reach of brute force against a captured link.
+const MIN_SECRET_LENGTH: usize = 32;
+
+/// Errors raised while loading invitation configuration.  Like token configuration these only
+/// happen at startup.
+#[derive(Debug, Snafu)]
+pub enum InvitationConfigError {
+    #[snafu(display("environment variable {} must be set", name))]
+    MissingInvitationVariable {
+        name: String,
+    },
+
+    #[snafu(display("invitation secret must be at least {} bytes", min))]
+    WeakSecret {
+        min: usize,
+    },
+}
+
+/// Invitation settings, read from `INVITATION_SECRET`, `INVITATION_LINK_BASE` and optionally
+/// `INVITATION_TTL`.
+pub struct InvitationConfig {
+    /// Key links are signed with.  Changing it invalidates every link already sent.
+    pub secret: String,
+    /// Where links point, the token is appended as the last path segment.  For example
+    /// `https://surveys.libellis.com/invitations`.
+    pub link_base: String,
+    /// Lifetime of links in seconds.
+    pub ttl: i64,
+}
+
+// Two weeks, long enough for a recipient to get round to it.
+fn default_invitation_ttl() -> i64 {
+    1_209_600
+}
+
+impl InvitationConfig {
+    pub fn from_env() -> std::result::Result<InvitationConfig, InvitationConfigError> {
+        let required = |name: &str| std::env::var(name)
+            .map_err(|_| InvitationConfigError::MissingInvitationVariable { name: name.to_string() });
+
+        Ok(InvitationConfig {
+            secret: required("INVITATION_SECRET")?,
+            link_base: required("INVITATION_LINK_BASE")?,
+            ttl: std::env::var("INVITATION_TTL").ok()
+                .and_then(|ttl| ttl.parse().ok())
+                .unwrap_or(default_invitation_ttl()),
+        })
+    }
+}
+
+/// Mints and checks invitation links.  A token reads `<invitation id>.<expires at>.<signature>`,
+/// where the signature is an HMAC-SHA256 over the first two parts.
+pub struct InvitationSigner {
+    secret: Vec<u8>,
+    link_base: String,
+    ttl: i64,
+}
+
+impl InvitationSigner {
+    pub fn new(config: InvitationConfig) -> std::result::Result<InvitationSigner, InvitationConfigError> {
+        if config.secret.len() < MIN_SECRET_LENGTH {
+            return Err(InvitationConfigError::WeakSecret { min: MIN_SECRET_LENGTH });
+        }
+
+        Ok(InvitationSigner {
+            secret: config.secret.into_bytes(),
+            link_base: config.link_base.trim_end_matches('/').to_string(),
+            ttl: config.ttl,
+        })
+    }
+
+    /// Convenience for `InvitationSigner::new(InvitationConfig::from_env()?)`.
+    pub fn from_env() -> std::result::Result<InvitationSigner, InvitationConfigError> {
+        InvitationSigner::new(InvitationConfig::from_env()?)
+    }
+
+    /// Lifetime of new links in seconds.
+    pub fn ttl(&self) -> i64 {
+        self.ttl
+    }
+
+    /// The link to send the recipient of `invitation`.
+    pub fn link(&self, invitation: &InvitationRecord) -> String {
+        let claims = format!("{}.{}", invitation.id, invitation.expires_at);
+        format!("{}/{}.{}", self.link_base, claims, self.signature(&claims))
+    }
+
+    /// Checks the signature and expiry of `token`, returning the invitation id it names.  Whether
+    /// the invitation is still open is up to the caller, this only proves we issued the link.
+    pub fn verify(&self, token: &str) -> Result<String> {
+        let mut parts = token.rsplitn(2, '.');
+        let (signature, claims) = match (parts.next(), parts.next()) {
+            (Some(signature), Some(claims)) => (signature, claims),
+            _ => return Err(Error::NotAuthorized),
+        };
+
+        let signature = decode_hex(signature).ok_or(Error::NotAuthorized)?;
+        self.mac(claims).verify(&signature)
+            .map_err(|_| Error::NotAuthorized)?;
+
+        // Only trusted now the signature checks out.
+        let mut claims = claims.splitn(2, '.');
+        let (id, expires_at) = match (claims.next(), claims.next().and_then(|e| e.parse::<i64>().ok())) {
+            (Some(id), Some(expires_at)) => (id, expires_at),
+            _ => return Err(Error::NotAuthorized),
+        };
+
+        if expires_at <= Utc::now().timestamp() {
+            return Err(ValidationError::InvitationValidationError {
+                msg: "the link has expired".to_string(),
+            }.into());
+        }
+
+        Ok(id.to_string())
+    }
+
+    fn signature(&self, claims: &str) -> String {
+        format!("{:x}", self.mac(claims).result().code())
+    }
+
+    fn mac(&self, claims: &str) -> HmacSha256 {
+        // Hmac takes keys of any length, so this can't fail.
+        let mut mac = HmacSha256::new_varkey(&self.secret).unwrap();
+        mac.input(claims.as_bytes());
+        mac
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| hex.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "an invitation secret of at least 32 bytes";
+    const LINK_BASE: &str = "https://surveys.test/invitations";
+
+    fn signer(secret: &str) -> InvitationSigner {
+        InvitationSigner::new(InvitationConfig {
+            secret: secret.to_string(),
+            link_base: format!("{}/", LINK_BASE),
+            ttl: 60,
+        }).unwrap()
+    }
+
+    fn new_invitation(ttl: i64) -> InvitationRecord {
+        InvitationRecord::new("test_survey_id".to_string(), "someone@example.com".to_string(), ttl)
+    }
+
+    // The token is everything after the link base.
+    fn token_for(signer: &InvitationSigner, invitation: &InvitationRecord) -> String {
+        let link = signer.link(invitation);
+        link.trim_start_matches(&format!("{}/", LINK_BASE)).to_string()
+    }
+
+    fn is_not_authorized(result: Result<String>) -> bool {
+        matches!(result, Err(Error::NotAuthorized))
+    }
+
+    #[test]
+    fn links_verify_to_their_invitation() {
+        let signer = signer(SECRET);
+        let invitation = new_invitation(60);
+
+        let link = signer.link(&invitation);
+
+        assert!(link.starts_with(&format!("{}/{}.", LINK_BASE, invitation.id)));
+        assert_eq!(signer.verify(&token_for(&signer, &invitation)).unwrap(), invitation.id);
+    }
+
+    #[test]
+    fn tampered_tokens_are_rejected() {
+        let signer = signer(SECRET);
+        let invitation = new_invitation(60);
+        let token = token_for(&signer, &invitation);
+        let last = token.chars().last().unwrap();
+
+        let other_id = token.replacen(&invitation.id, &new_invitation(60).id, 1);
+        let later_expiry = token.replacen(
+            &invitation.expires_at.to_string(),
+            &(invitation.expires_at + 3600).to_string(),
+            1,
+        );
+        let flipped_signature = format!("{}{}", &token[..token.len() - 1], if last == '0' { '1' } else { '0' });
+
+        for tampered in [other_id, later_expiry, flipped_signature] {
+            assert!(is_not_authorized(signer.verify(&tampered)), "{}", tampered);
+        }
+    }
+
+    #[test]
+    fn malformed_tokens_are_rejected() {
+        let signer = signer(SECRET);
+
+        for malformed in ["", "no-dots", "id.123.zz", "id.123.abc", "id.not-a-number."] {
+            assert!(is_not_authorized(signer.verify(malformed)), "{}", malformed);
+        }
+    }
+
+    #[test]
+    fn tokens_signed_with_another_secret_are_rejected() {
+        let other = signer("a different secret, also long enough");
+        let token = token_for(&other, &new_invitation(60));
+
+        assert!(is_not_authorized(signer(SECRET).verify(&token)));
+    }
+
+    #[test]
+    fn expired_tokens_are_rejected() {
+        let signer = signer(SECRET);
+        let token = token_for(&signer, &new_invitation(-1));
+
+        match signer.verify(&token) {
+            Err(Error::ValidationError { .. }) => (),
+            _ => panic!("an expired link should fail validation"),
+        }
+    }
+
+    #[test]
+    fn short_secrets_are_refused() {
+        let config = InvitationConfig {
+            secret: "too short".to_string(),
+            link_base: LINK_BASE.to_string(),
+            ttl: 60,
+        };
+
+        assert!(InvitationSigner::new(config).is_err());
+    }
+}
@@ -3,6 +3,12 @@ pub use token::*;
 
 pub mod commands;
 pub mod queries;
+pub mod accounts;
+pub mod organizations;
+pub mod moderation;
+pub mod invitations;
+pub mod responses;
+pub mod results;
 
 // Holds specific repository contracts that aren't pulled from the generic ones in domain_patterns crates.
 pub mod repository_contracts;
@@ -0,0 +1,87 @@
+use serde::{Serialize, Deserialize};
+use uuid::Uuid;
+use chrono::Utc;
+use crate::value_objects::ValidationError;
+use crate::errors::Error::RepoFailure;
+use crate::errors::Result;
+use crate::app_services::repository_contracts::AuditLog;
+
+const MAX_REASON_LENGTH: usize = 500;
+
+/// What a moderator did.  Stored as the `as_str` form.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AuditAction {
+    ViewSurvey,
+    DeleteSurvey,
+    WithdrawSurvey,
+    ReinstateSurvey,
+    LockAuthor,
+    UnlockAuthor,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::ViewSurvey => "view_survey",
+            AuditAction::DeleteSurvey => "delete_survey",
+            AuditAction::WithdrawSurvey => "withdraw_survey",
+            AuditAction::ReinstateSurvey => "reinstate_survey",
+            AuditAction::LockAuthor => "lock_author",
+            AuditAction::UnlockAuthor => "unlock_author",
+        }
+    }
+}
+
+/// One line of the audit log.  `target` is a survey id or a username, depending on `action`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub id: String,
+    pub actor: String,
+    pub action: String,
+    pub target: String,
+    pub reason: String,
+    pub occurred: i64,
+}
+
+impl AuditEntry {
+    /// A new entry stamped with the current time.  The reason must already be checked with
+    /// `moderation_reason`.
+    pub fn record(actor: &str, action: AuditAction, target: &str, reason: &str) -> AuditEntry {
+        AuditEntry {
+            id: Uuid::new_v4().to_string(),
+            actor: actor.to_string(),
+            action: action.as_str().to_string(),
+            target: target.to_string(),
+            reason: reason.to_string(),
+            occurred: Utc::now().timestamp(),
+        }
+    }
+}
+
+/// A page of the audit log, newest first.
+#[derive(Serialize, Deserialize)]
+pub struct AuditEntries {
+    pub entries: Vec<AuditEntry>,
+}
+
+/// Trims `reason` and checks there's something left to record.
+pub fn moderation_reason(reason: &str) -> Result<String> {
+    let reason = reason.trim();
+    let len = reason.chars().count();
+
+    if len == 0 || len > MAX_REASON_LENGTH {
+        return Err(
+            ValidationError::ReasonValidationError {
+                msg: format!("length must be between 1 and {}", MAX_REASON_LENGTH),
+            }.into()
+        );
+    }
+
+    Ok(reason.to_string())
+}
+
+/// Writes an entry, surfacing a failure as `RepoFailure` so the caller stops before acting.
+pub fn append_audit<A: AuditLog>(log: &mut A, moderator: &str, action: AuditAction, target: &str, reason: &str) -> Result<()> {
+    log.append(&AuditEntry::record(moderator, action, target, reason))
+        .map_err(|e| RepoFailure { source: Box::new(e) })
+}
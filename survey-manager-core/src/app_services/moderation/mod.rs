@@ -0,0 +1,20 @@
+//! Site administration that reaches past survey ownership.
+//!
+//! Admins get nothing from the ordinary survey policy.  Everything they can do instead goes
+//! through the moderation handlers, which check `policy::authorize_moderation`, insist on a reason,
+//! and write an `AuditEntry` before acting.  If the entry can't be written the action doesn't
+//! happen, so the log never misses anything that was done.
+pub mod audit;
+pub use audit::*;
+
+pub mod moderation_commands;
+pub use moderation_commands::*;
+
+pub mod moderation_queries;
+pub use moderation_queries::*;
+
+pub mod moderation_commands_handler;
+pub use moderation_commands_handler::*;
+
+pub mod moderation_queries_handler;
+pub use moderation_queries_handler::*;
@@ -0,0 +1,89 @@
+use domain_patterns::command::Command;
+use domain_patterns::message::Message;
+
+// Every moderation message carries the moderator's name and the roles from their token, so the
+// handler can check them against `policy::authorize_moderation`, and the reason for the audit log.
+
+#[derive(Clone, Command)]
+pub struct DeleteAnySurveyCommand {
+    pub id: String,
+    pub moderator: String,
+    pub roles: Vec<String>,
+    pub reason: String,
+}
+
+/// Force-unpublishes a survey.  Its owners can't publish it again until a moderator reinstates
+/// it.
+#[derive(Clone, Command)]
+pub struct WithdrawSurveyCommand {
+    pub id: String,
+    pub moderator: String,
+    pub roles: Vec<String>,
+    pub reason: String,
+}
+
+/// Lifts a withdrawal, leaving the survey unpublished for its owners to decide about.
+#[derive(Clone, Command)]
+pub struct ReinstateSurveyCommand {
+    pub id: String,
+    pub moderator: String,
+    pub roles: Vec<String>,
+    pub reason: String,
+}
+
+/// Stops `username` logging in and ends their sessions.  Access tokens already issued stay valid
+/// until they expire, which is at most the configured token lifetime.
+#[derive(Clone, Command)]
+pub struct LockAuthorCommand {
+    pub username: String,
+    pub moderator: String,
+    pub roles: Vec<String>,
+    pub reason: String,
+}
+
+#[derive(Clone, Command)]
+pub struct UnlockAuthorCommand {
+    pub username: String,
+    pub moderator: String,
+    pub roles: Vec<String>,
+    pub reason: String,
+}
+
+#[derive(Clone, Command)]
+pub enum ModerationCommands {
+    DeleteAnySurveyCommand(DeleteAnySurveyCommand),
+    WithdrawSurveyCommand(WithdrawSurveyCommand),
+    ReinstateSurveyCommand(ReinstateSurveyCommand),
+    LockAuthorCommand(LockAuthorCommand),
+    UnlockAuthorCommand(UnlockAuthorCommand),
+}
+
+impl From<DeleteAnySurveyCommand> for ModerationCommands {
+    fn from(cmd: DeleteAnySurveyCommand) -> Self {
+        ModerationCommands::DeleteAnySurveyCommand(cmd)
+    }
+}
+
+impl From<WithdrawSurveyCommand> for ModerationCommands {
+    fn from(cmd: WithdrawSurveyCommand) -> Self {
+        ModerationCommands::WithdrawSurveyCommand(cmd)
+    }
+}
+
+impl From<ReinstateSurveyCommand> for ModerationCommands {
+    fn from(cmd: ReinstateSurveyCommand) -> Self {
+        ModerationCommands::ReinstateSurveyCommand(cmd)
+    }
+}
+
+impl From<LockAuthorCommand> for ModerationCommands {
+    fn from(cmd: LockAuthorCommand) -> Self {
+        ModerationCommands::LockAuthorCommand(cmd)
+    }
+}
+
+impl From<UnlockAuthorCommand> for ModerationCommands {
+    fn from(cmd: UnlockAuthorCommand) -> Self {
+        ModerationCommands::UnlockAuthorCommand(cmd)
+    }
+}
@@ -0,0 +1,190 @@
+use domain_patterns::collections::Repository;
+use domain_patterns::command::Handles;
+use domain_patterns::models::Entity;
+use crate::errors::Error::{ResourceNotFound, RepoFailure, ConcurrencyFailure};
+use crate::errors::Result;
+use crate::survey::Survey;
+use crate::user::User;
+use crate::policy::{authorize_moderation, ModerationPermission};
+use crate::app_services::repository_contracts::{UserRepository, RefreshTokenRepository, AuditLog};
+use crate::app_services::moderation::{
+    DeleteAnySurveyCommand, WithdrawSurveyCommand, ReinstateSurveyCommand, LockAuthorCommand, UnlockAuthorCommand, ModerationCommands,
+    AuditAction, moderation_reason, append_audit,
+};
+
+pub struct ModerationCommandsHandler<S, U, R, A> where
+    S: Repository<Survey>,
+    U: UserRepository,
+    R: RefreshTokenRepository,
+    A: AuditLog,
+{
+    surveys: S,
+    users: U,
+    refresh_tokens: R,
+    audit_log: A,
+}
+
+impl<S, U, R, A> ModerationCommandsHandler<S, U, R, A> where
+    S: Repository<Survey>,
+    U: UserRepository,
+    R: RefreshTokenRepository,
+    A: AuditLog,
+{
+    pub fn new(surveys: S, users: U, refresh_tokens: R, audit_log: A) -> ModerationCommandsHandler<S, U, R, A> {
+        ModerationCommandsHandler {
+            surveys,
+            users,
+            refresh_tokens,
+            audit_log,
+        }
+    }
+
+    fn load_survey(&mut self, id: &String) -> Result<Survey> {
+        self.surveys.get(id)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?
+            .ok_or(ResourceNotFound { resource: format!("survey with id {}", id) })
+    }
+
+    fn save_survey(&mut self, survey: &Survey) -> Result<String> {
+        self.surveys.update(survey)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?
+            .ok_or(ConcurrencyFailure)
+    }
+
+    fn load_user(&mut self, username: &String) -> Result<User> {
+        self.users.get_by_username(username)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?
+            .ok_or(ResourceNotFound { resource: format!("user {}", username) })
+    }
+
+    fn save_user(&mut self, user: &User) -> Result<String> {
+        self.users.update(user)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?
+            .ok_or(ConcurrencyFailure)
+    }
+}
+
+impl<S, U, R, A> Handles<DeleteAnySurveyCommand> for ModerationCommandsHandler<S, U, R, A> where
+    S: Repository<Survey>,
+    U: UserRepository,
+    R: RefreshTokenRepository,
+    A: AuditLog,
+{
+    type Result = Result<String>;
+
+    fn handle(&mut self, msg: DeleteAnySurveyCommand) -> Result<String> {
+        authorize_moderation(&msg.roles, ModerationPermission::DeleteAnySurvey)?;
+        let reason = moderation_reason(&msg.reason)?;
+        let survey = self.load_survey(&msg.id)?;
+
+        append_audit(&mut self.audit_log, &msg.moderator, AuditAction::DeleteSurvey, &survey.id(), &reason)?;
+
+        self.surveys.remove(&msg.id)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?
+            // Removed by someone else since we loaded it.
+            .ok_or(ConcurrencyFailure)
+    }
+}
+
+impl<S, U, R, A> Handles<WithdrawSurveyCommand> for ModerationCommandsHandler<S, U, R, A> where
+    S: Repository<Survey>,
+    U: UserRepository,
+    R: RefreshTokenRepository,
+    A: AuditLog,
+{
+    type Result = Result<String>;
+
+    fn handle(&mut self, msg: WithdrawSurveyCommand) -> Result<String> {
+        authorize_moderation(&msg.roles, ModerationPermission::WithdrawAnySurvey)?;
+        let reason = moderation_reason(&msg.reason)?;
+        let mut survey = self.load_survey(&msg.id)?;
+        survey.withdraw()?;
+
+        append_audit(&mut self.audit_log, &msg.moderator, AuditAction::WithdrawSurvey, &survey.id(), &reason)?;
+
+        self.save_survey(&survey)
+    }
+}
+
+impl<S, U, R, A> Handles<ReinstateSurveyCommand> for ModerationCommandsHandler<S, U, R, A> where
+    S: Repository<Survey>,
+    U: UserRepository,
+    R: RefreshTokenRepository,
+    A: AuditLog,
+{
+    type Result = Result<String>;
+
+    fn handle(&mut self, msg: ReinstateSurveyCommand) -> Result<String> {
+        authorize_moderation(&msg.roles, ModerationPermission::WithdrawAnySurvey)?;
+        let reason = moderation_reason(&msg.reason)?;
+        let mut survey = self.load_survey(&msg.id)?;
+        survey.reinstate()?;
+
+        append_audit(&mut self.audit_log, &msg.moderator, AuditAction::ReinstateSurvey, &survey.id(), &reason)?;
+
+        self.save_survey(&survey)
+    }
+}
+
+impl<S, U, R, A> Handles<LockAuthorCommand> for ModerationCommandsHandler<S, U, R, A> where
+    S: Repository<Survey>,
+    U: UserRepository,
+    R: RefreshTokenRepository,
+    A: AuditLog,
+{
+    type Result = Result<String>;
+
+    fn handle(&mut self, msg: LockAuthorCommand) -> Result<String> {
+        authorize_moderation(&msg.roles, ModerationPermission::LockAuthor)?;
+        let reason = moderation_reason(&msg.reason)?;
+        let mut user = self.load_user(&msg.username)?;
+        user.lock()?;
+
+        append_audit(&mut self.audit_log, &msg.moderator, AuditAction::LockAuthor, &msg.username, &reason)?;
+
+        let u_id = self.save_user(&user)?;
+        self.refresh_tokens.revoke_user(&u_id)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?;
+
+        Ok(u_id)
+    }
+}
+
+impl<S, U, R, A> Handles<UnlockAuthorCommand> for ModerationCommandsHandler<S, U, R, A> where
+    S: Repository<Survey>,
+    U: UserRepository,
+    R: RefreshTokenRepository,
+    A: AuditLog,
+{
+    type Result = Result<String>;
+
+    fn handle(&mut self, msg: UnlockAuthorCommand) -> Result<String> {
+        authorize_moderation(&msg.roles, ModerationPermission::LockAuthor)?;
+        let reason = moderation_reason(&msg.reason)?;
+        let mut user = self.load_user(&msg.username)?;
+        user.unlock()?;
+
+        append_audit(&mut self.audit_log, &msg.moderator, AuditAction::UnlockAuthor, &msg.username, &reason)?;
+
+        self.save_user(&user)
+    }
+}
+
+impl<S, U, R, A> Handles<ModerationCommands> for ModerationCommandsHandler<S, U, R, A> where
+    S: Repository<Survey>,
+    U: UserRepository,
+    R: RefreshTokenRepository,
+    A: AuditLog,
+{
+    type Result = Result<String>;
+
+    fn handle(&mut self, msg: ModerationCommands) -> Result<String> {
+        match msg {
+            ModerationCommands::DeleteAnySurveyCommand(cmd) => self.handle(cmd),
+            ModerationCommands::WithdrawSurveyCommand(cmd) => self.handle(cmd),
+            ModerationCommands::ReinstateSurveyCommand(cmd) => self.handle(cmd),
+            ModerationCommands::LockAuthorCommand(cmd) => self.handle(cmd),
+            ModerationCommands::UnlockAuthorCommand(cmd) => self.handle(cmd),
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use domain_patterns::query::Query;
+use crate::app_services::queries::PageConfig;
+
+/// Reads a survey regardless of who owns it.  Unlike other queries this writes to the audit log.
+#[derive(Query)]
+pub struct ViewAnySurveyQuery {
+    pub id: String,
+    pub moderator: String,
+    pub roles: Vec<String>,
+    pub reason: String,
+}
+
+/// Pages through the audit log, newest first, optionally only entries about `target`.
+#[derive(Query)]
+pub struct AuditLogQuery {
+    pub roles: Vec<String>,
+    pub target: Option<String>,
+    pub page_config: Option<PageConfig>,
+}
+
+#[derive(Query)]
+pub enum ModerationQueries {
+    ViewAnySurveyQuery(ViewAnySurveyQuery),
+    AuditLogQuery(AuditLogQuery),
+}
+
+impl From<ViewAnySurveyQuery> for ModerationQueries {
+    fn from(query: ViewAnySurveyQuery) -> Self {
+        ModerationQueries::ViewAnySurveyQuery(query)
+    }
+}
+
+impl From<AuditLogQuery> for ModerationQueries {
+    fn from(query: AuditLogQuery) -> Self {
+        ModerationQueries::AuditLogQuery(query)
+    }
+}
@@ -0,0 +1,88 @@
+use domain_patterns::collections::Repository;
+use domain_patterns::query::HandlesQuery;
+use crate::errors::Error::{ResourceNotFound, RepoFailure};
+use crate::errors::Result;
+use crate::survey::Survey;
+use crate::dtos::SurveyDTO;
+use crate::policy::{authorize_moderation, ModerationPermission};
+use crate::app_services::queries::PageConfig;
+use crate::app_services::repository_contracts::AuditLog;
+use crate::app_services::moderation::{
+    ViewAnySurveyQuery, AuditLogQuery, ModerationQueries,
+    AuditEntries, AuditAction, moderation_reason, append_audit,
+};
+
+// Reads the write side's repository rather than the read model, which only finds surveys through
+// their author or collaborators.
+pub struct ModerationQueriesHandler<S, A> where
+    S: Repository<Survey>,
+    A: AuditLog,
+{
+    surveys: S,
+    audit_log: A,
+}
+
+impl<S, A> ModerationQueriesHandler<S, A> where
+    S: Repository<Survey>,
+    A: AuditLog,
+{
+    pub fn new(surveys: S, audit_log: A) -> ModerationQueriesHandler<S, A> {
+        ModerationQueriesHandler {
+            surveys,
+            audit_log,
+        }
+    }
+}
+
+impl<S, A> HandlesQuery<ViewAnySurveyQuery> for ModerationQueriesHandler<S, A> where
+    S: Repository<Survey>,
+    A: AuditLog,
+{
+    // Pure json, the same shape `FindSurveyQuery` returns.
+    type Result = Result<String>;
+
+    fn handle(&mut self, query: ViewAnySurveyQuery) -> Self::Result {
+        authorize_moderation(&query.roles, ModerationPermission::ViewAnySurvey)?;
+        let reason = moderation_reason(&query.reason)?;
+        let survey = self.surveys.get(&query.id)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?
+            .ok_or(ResourceNotFound { resource: format!("survey with id {}", query.id) })?;
+
+        append_audit(&mut self.audit_log, &query.moderator, AuditAction::ViewSurvey, &query.id, &reason)?;
+
+        Ok(serde_json::to_string(&SurveyDTO::from(&survey)).unwrap())
+    }
+}
+
+impl<S, A> HandlesQuery<AuditLogQuery> for ModerationQueriesHandler<S, A> where
+    S: Repository<Survey>,
+    A: AuditLog,
+{
+    type Result = Result<String>;
+
+    fn handle(&mut self, query: AuditLogQuery) -> Self::Result {
+        authorize_moderation(&query.roles, ModerationPermission::ReadAuditLog)?;
+
+        let PageConfig { page_num, page_size } = query.page_config
+            .unwrap_or(PageConfig { page_num: 1, page_size: 50 });
+
+        let entries = self.audit_log.list(query.target.as_ref(), page_num, page_size)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?;
+
+        Ok(serde_json::to_string(&AuditEntries { entries }).unwrap())
+    }
+}
+
+impl<S, A> HandlesQuery<ModerationQueries> for ModerationQueriesHandler<S, A> where
+    S: Repository<Survey>,
+    A: AuditLog,
+{
+    type Result = Result<String>;
+
+    fn handle(&mut self, query: ModerationQueries) -> Self::Result {
+        match query {
+            ModerationQueries::ViewAnySurveyQuery(q) => self.handle(q),
+            ModerationQueries::AuditLogQuery(q) => self.handle(q),
+        }
+    }
+}
@@ -0,0 +1,7 @@
+//! Creating organizations and managing who belongs to them.  Surveys move into an organization
+//! through `TransferSurveyOwnershipCommand` on the survey side.
+pub mod organization_commands;
+pub use organization_commands::*;
+
+pub mod organization_commands_handler;
+pub use organization_commands_handler::*;
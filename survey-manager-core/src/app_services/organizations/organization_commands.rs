@@ -0,0 +1,51 @@
+use domain_patterns::command::Command;
+use domain_patterns::message::Message;
+
+/// Creates an organization with `creator` as its first owner.
+#[derive(Clone, Command)]
+pub struct CreateOrganizationCommand {
+    pub name: String,
+    pub creator: String,
+}
+
+/// Adds `username` to the organization with `role` (owner, admin or member), or changes the role
+/// they already have.
+#[derive(Clone, Command)]
+pub struct AddMemberCommand {
+    pub id: String,
+    pub requesting_user: String,
+    pub username: String,
+    pub role: String,
+}
+
+#[derive(Clone, Command)]
+pub struct RemoveMemberCommand {
+    pub id: String,
+    pub requesting_user: String,
+    pub username: String,
+}
+
+#[derive(Clone, Command)]
+pub enum OrganizationCommands {
+    CreateOrganizationCommand(CreateOrganizationCommand),
+    AddMemberCommand(AddMemberCommand),
+    RemoveMemberCommand(RemoveMemberCommand),
+}
+
+impl From<CreateOrganizationCommand> for OrganizationCommands {
+    fn from(cmd: CreateOrganizationCommand) -> Self {
+        OrganizationCommands::CreateOrganizationCommand(cmd)
+    }
+}
+
+impl From<AddMemberCommand> for OrganizationCommands {
+    fn from(cmd: AddMemberCommand) -> Self {
+        OrganizationCommands::AddMemberCommand(cmd)
+    }
+}
+
+impl From<RemoveMemberCommand> for OrganizationCommands {
+    fn from(cmd: RemoveMemberCommand) -> Self {
+        OrganizationCommands::RemoveMemberCommand(cmd)
+    }
+}
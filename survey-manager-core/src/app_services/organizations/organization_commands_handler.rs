@@ -0,0 +1,83 @@
+use domain_patterns::collections::Repository;
+use domain_patterns::command::Handles;
+use crate::errors::Error::{ResourceNotFound, RepoFailure, ConcurrencyFailure};
+use crate::errors::Result;
+use crate::organization::Organization;
+use crate::app_services::organizations::{CreateOrganizationCommand, AddMemberCommand, RemoveMemberCommand, OrganizationCommands};
+
+pub struct OrganizationCommandsHandler<T> where
+    T: Repository<Organization>
+{
+    repo: T,
+}
+
+impl<T> OrganizationCommandsHandler<T> where
+    T: Repository<Organization>
+{
+    pub fn new(repo: T) -> OrganizationCommandsHandler<T> {
+        OrganizationCommandsHandler {
+            repo,
+        }
+    }
+
+    fn load(&mut self, id: &String) -> Result<Organization> {
+        self.repo.get(id)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?
+            .ok_or(ResourceNotFound { resource: format!("organization with id {}", id) })
+    }
+
+    fn save(&mut self, org: &Organization) -> Result<String> {
+        self.repo.update(org)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?
+            // Deleted between load and save.
+            .ok_or(ConcurrencyFailure)
+    }
+}
+
+impl<T: Repository<Organization>> Handles<CreateOrganizationCommand> for OrganizationCommandsHandler<T> {
+    type Result = Result<String>;
+
+    fn handle(&mut self, msg: CreateOrganizationCommand) -> Result<String> {
+        let org = Organization::new(&msg)?;
+
+        let o_id = self.repo.insert(&org)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?;
+
+        // Safe to unwrap.  Ids are fresh v4 uuids, so a duplicate key would be a database error.
+        Ok(o_id.unwrap())
+    }
+}
+
+impl<T: Repository<Organization>> Handles<AddMemberCommand> for OrganizationCommandsHandler<T> {
+    type Result = Result<String>;
+
+    fn handle(&mut self, msg: AddMemberCommand) -> Result<String> {
+        let mut org = self.load(&msg.id)?;
+
+        org.add_member(&msg.requesting_user, &msg.username, &msg.role)?;
+        self.save(&org)
+    }
+}
+
+impl<T: Repository<Organization>> Handles<RemoveMemberCommand> for OrganizationCommandsHandler<T> {
+    type Result = Result<String>;
+
+    fn handle(&mut self, msg: RemoveMemberCommand) -> Result<String> {
+        let mut org = self.load(&msg.id)?;
+
+        org.remove_member(&msg.requesting_user, &msg.username)?;
+        self.save(&org)
+    }
+}
+
+impl<T: Repository<Organization>> Handles<OrganizationCommands> for OrganizationCommandsHandler<T> {
+    type Result = Result<String>;
+
+    fn handle(&mut self, msg: OrganizationCommands) -> Result<String> {
+        match msg {
+            OrganizationCommands::CreateOrganizationCommand(cmd) => self.handle(cmd),
+            OrganizationCommands::AddMemberCommand(cmd) => self.handle(cmd),
+            OrganizationCommands::RemoveMemberCommand(cmd) => self.handle(cmd),
+        }
+    }
+}
@@ -1,8 +1,12 @@
 use domain_patterns::query::Query;
+use crate::app_services::token::OrganizationClaim;
 
+/// Lists the caller's surveys.  When `organization` is set the listing is the organization's
+/// surveys instead, plus anything shared with the caller directly.
 #[derive(Query)]
 pub struct FindSurveysByAuthorQuery {
     pub author: String,
+    pub organization: Option<OrganizationClaim>,
     pub page_config: Option<PageConfig>,
 }
 
@@ -0,0 +1,12 @@
+use domain_patterns::query::Query;
+use crate::dtos::DefinitionFormat;
+use crate::app_services::token::OrganizationClaim;
+
+/// Renders a survey as a portable `SurveyDefinition` document.
+#[derive(Query)]
+pub struct ExportSurveyQuery {
+    pub id: String,
+    pub requesting_author: String,
+    pub organization: Option<OrganizationClaim>,
+    pub format: DefinitionFormat,
+}
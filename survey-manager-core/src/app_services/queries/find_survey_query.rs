@@ -1,7 +1,9 @@
 use domain_patterns::query::Query;
+use crate::app_services::token::OrganizationClaim;
 
 #[derive(Query)]
 pub struct FindSurveyQuery {
     pub id: String,
     pub requesting_author: String,
+    pub organization: Option<OrganizationClaim>,
 }
\ No newline at end of file
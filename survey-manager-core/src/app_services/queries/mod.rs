@@ -6,3 +6,6 @@ pub use authors_surveys_query::*;
 
 pub mod survey_queries;
 pub use survey_queries::*;
+
+pub mod export_survey_query;
+pub use export_survey_query::*;
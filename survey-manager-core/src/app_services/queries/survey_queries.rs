@@ -1,13 +1,16 @@
 use domain_patterns::query::{Query, HandlesQuery};
-use crate::app_services::queries::{FindSurveysByAuthorQuery, FindSurveyQuery, PageConfig};
+use crate::app_services::queries::{FindSurveysByAuthorQuery, FindSurveyQuery, PageConfig, ExportSurveyQuery};
+use crate::dtos::SurveyDefinition;
 use crate::app_services::repository_contracts::SurveyDTOReadRepository;
 use crate::Error;
 use crate::errors::Error::RepoFailure;
+use crate::policy::{authorize, Actor, Permission};
 
 #[derive(Query)]
 pub enum SurveyQueries {
     FindSurveyQuery(FindSurveyQuery),
     FindAuthorsSurveysQuery(FindSurveysByAuthorQuery),
+    ExportSurveyQuery(ExportSurveyQuery),
 }
 
 impl From<FindSurveyQuery> for SurveyQueries {
@@ -22,6 +25,12 @@ impl From<FindSurveysByAuthorQuery> for SurveyQueries {
     }
 }
 
+impl From<ExportSurveyQuery> for SurveyQueries {
+    fn from(query: ExportSurveyQuery) -> Self {
+        SurveyQueries::ExportSurveyQuery(query)
+    }
+}
+
 pub struct SurveyQueriesHandler<T>
     where T: SurveyDTOReadRepository
 {
@@ -46,13 +55,17 @@ impl<T> HandlesQuery<FindSurveyQuery> for SurveyQueriesHandler<T>
     // json anyways.
     type Result = Result<String, Error>;
 
+    // A survey the caller can't see is reported as not found, so its existence isn't given away.
     fn handle(&mut self, query: FindSurveyQuery) -> Self::Result {
+        let org_id = query.organization.as_ref().map(|org| &org.id);
         let results = self.repo
-            .get_survey_for_author(&query.id, &query.requesting_author)
+            .get_survey_for_author(&query.id, &query.requesting_author, org_id)
             .map_err(|e| RepoFailure { source: Box::new(e) })?;
 
         if let Some(survey) = results {
-            return Ok(serde_json::to_string(&survey).unwrap());
+            if authorize(&survey, &Actor::new(&query.requesting_author, &query.organization), Permission::View).is_ok() {
+                return Ok(serde_json::to_string(&survey).unwrap());
+            }
         }
 
         Err(
@@ -78,9 +91,10 @@ impl<T> HandlesQuery<FindSurveysByAuthorQuery> for SurveyQueriesHandler<T>
             upper = page_num * page_size;
         }
 
-        let results = self.repo
-            .get_surveys_by_author(&query.author)
-            .map_err(|e| RepoFailure { source: Box::new(e) })?;
+        let results = match &query.organization {
+            Some(org) => self.repo.get_surveys_by_organization(&org.id, &query.author),
+            None => self.repo.get_surveys_by_author(&query.author),
+        }.map_err(|e| RepoFailure { source: Box::new(e) })?;
 
         if let Some(surveys) = results {
             return Ok(serde_json::to_string(&surveys.into_bounded(lower, upper)).unwrap());
@@ -94,6 +108,32 @@ impl<T> HandlesQuery<FindSurveysByAuthorQuery> for SurveyQueriesHandler<T>
     }
 }
 
+impl<T> HandlesQuery<ExportSurveyQuery> for SurveyQueriesHandler<T>
+    where T: SurveyDTOReadRepository
+{
+    // String in this case is the rendered definition document, in whichever format was asked for.
+    type Result = Result<String, Error>;
+
+    fn handle(&mut self, query: ExportSurveyQuery) -> Self::Result {
+        let org_id = query.organization.as_ref().map(|org| &org.id);
+        let results = self.repo
+            .get_survey_for_author(&query.id, &query.requesting_author, org_id)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?;
+
+        if let Some(survey) = results {
+            if authorize(&survey, &Actor::new(&query.requesting_author, &query.organization), Permission::View).is_ok() {
+                return SurveyDefinition::from(survey).to_document(query.format);
+            }
+        }
+
+        Err(
+            Error::ResourceNotFound {
+                resource: format!("survey with id {} by author {}", query.id, query.requesting_author)
+            }
+        )
+    }
+}
+
 impl<T> HandlesQuery<SurveyQueries> for SurveyQueriesHandler<T>
     where T: SurveyDTOReadRepository
 {
@@ -105,6 +145,7 @@ impl<T> HandlesQuery<SurveyQueries> for SurveyQueriesHandler<T>
         match query {
             SurveyQueries::FindAuthorsSurveysQuery(q) => self.handle(q),
             SurveyQueries::FindSurveyQuery(q) => self.handle(q),
+            SurveyQueries::ExportSurveyQuery(q) => self.handle(q),
         }
     }
 }
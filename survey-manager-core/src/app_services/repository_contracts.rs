@@ -1,4 +1,9 @@
 use crate::dtos::{SurveyDTO, SurveyDTOs};
+use crate::user::User;
+use crate::app_services::accounts::RefreshTokenRecord;
+use crate::app_services::moderation::AuditEntry;
+use crate::app_services::invitations::{InvitationRecord, InvitationStats};
+use crate::app_services::responses::{ResponseRecord, PartialResponse};
 
 /// A trait that provides a collection like abstraction over read only database access.
 ///
@@ -8,21 +13,158 @@ pub trait SurveyDTOReadRepository {
     /// Error type that likely corresponds to an underlying database error.
     type Error: 'static + std::error::Error + std::fmt::Display + Send;
 
-    /// Returns the SurveyDTO corresponding to the supplied key as an owned type.
+    /// Returns the SurveyDTO corresponding to the supplied key as an owned type, if `author` owns it,
+    /// it has been shared with them, or it belongs to `organization_id`.  Callers still check the
+    /// role through `policy::authorize`.
     ///
     /// # Failure case
     ///
     /// If we fail to communicate with the underlying storage, then an error is returned.
-    fn get_survey_for_author(&mut self, id: &String, author: &String) -> Result<Option<SurveyDTO>, Self::Error>;
+    fn get_survey_for_author(&mut self, id: &String, author: &String, organization_id: Option<&String>) -> Result<Option<SurveyDTO>, Self::Error>;
 
 
     /// Returns a `Vec<ListViewSurveyDTO>`, based on the supplied `page_num` and `page_size`.
     /// The page_num should start at 1, but is up to the implementer to design as they see fit.
     /// This is returned as a unique type because the inner `ListViewSurveyDTO` is trimmed down,
     /// and intended for a list view where questions and choices aren't necessary data.
+    /// Surveys shared with `author` are included, with `role` set to the role they were given.
     ///
     /// # Failure case
     ///
     /// If we fail to communicate with the underlying storage, then an error is returned.
     fn get_surveys_by_author(&mut self, author: &String) -> Result<Option<SurveyDTOs>, Self::Error>;
+
+    /// Like `get_surveys_by_author`, but the surveys owned by `organization_id` take the place of
+    /// the member's personal ones.  Surveys shared with `member` directly are still included.
+    ///
+    /// # Failure case
+    ///
+    /// If we fail to communicate with the underlying storage, then an error is returned.
+    fn get_surveys_by_organization(&mut self, organization_id: &String, member: &String) -> Result<Option<SurveyDTOs>, Self::Error>;
+}
+
+/// Storage for user accounts.  Usernames are unique.
+pub trait UserRepository {
+    /// Error type that likely corresponds to an underlying database error.
+    type Error: 'static + std::error::Error + std::fmt::Display + Send;
+
+    /// Stores a new user and returns its id, or `None` if the username is already taken.
+    fn insert(&mut self, user: &User) -> Result<Option<String>, Self::Error>;
+
+    fn get_by_username(&mut self, username: &String) -> Result<Option<User>, Self::Error>;
+
+    /// Saves changes to an existing user.  Returns `None` if there was no user to update.
+    fn update(&mut self, user: &User) -> Result<Option<String>, Self::Error>;
+}
+
+/// Storage for issued refresh tokens, keyed by the hash of the token.
+pub trait RefreshTokenRepository {
+    /// Error type that likely corresponds to an underlying database error.
+    type Error: 'static + std::error::Error + std::fmt::Display + Send;
+
+    fn insert(&mut self, record: &RefreshTokenRecord) -> Result<(), Self::Error>;
+
+    fn get(&mut self, token_hash: &String) -> Result<Option<RefreshTokenRecord>, Self::Error>;
+
+    /// Marks a token as redeemed.  Returns false if it was already marked, so two requests racing
+    /// with the same token can't both succeed.  Implementations must do this atomically.
+    fn mark_used(&mut self, token_hash: &String) -> Result<bool, Self::Error>;
+
+    fn revoke_family(&mut self, family_id: &String) -> Result<(), Self::Error>;
+
+    /// Revokes every refresh token issued to `user_id`, across all of their sessions.
+    fn revoke_user(&mut self, user_id: &String) -> Result<(), Self::Error>;
+}
+
+/// Access tokens that were revoked before they expired, identified by their `jti` claim.
+pub trait RevocationList {
+    /// Error type that likely corresponds to an underlying storage error.
+    type Error: 'static + std::error::Error + std::fmt::Display + Send;
+
+    /// Remembers `jti` as revoked.  Past `expires_at` the token is rejected anyway, so it can be
+    /// forgotten then.
+    fn revoke(&mut self, jti: &String, expires_at: i64) -> Result<(), Self::Error>;
+
+    fn is_revoked(&mut self, jti: &String) -> Result<bool, Self::Error>;
+}
+
+/// The record of administrative actions.  Entries are only ever added, never changed or removed.
+pub trait AuditLog {
+    /// Error type that likely corresponds to an underlying storage error.
+    type Error: 'static + std::error::Error + std::fmt::Display + Send;
+
+    fn append(&mut self, entry: &AuditEntry) -> Result<(), Self::Error>;
+
+    /// Entries newest first.  `page_num` starts at 1.  With `target` set, only entries about it.
+    fn list(&mut self, target: Option<&String>, page_num: usize, page_size: usize) -> Result<Vec<AuditEntry>, Self::Error>;
+}
+
+/// Storage for survey invitations.
+pub trait InvitationRepository {
+    /// Error type that likely corresponds to an underlying database error.
+    type Error: 'static + std::error::Error + std::fmt::Display + Send;
+
+    fn insert(&mut self, invitation: &InvitationRecord) -> Result<(), Self::Error>;
+
+    fn get(&mut self, id: &String) -> Result<Option<InvitationRecord>, Self::Error>;
+
+    /// Moves a sent invitation to opened.  Returns false if it had already been opened or completed.
+    fn mark_opened(&mut self, id: &String) -> Result<bool, Self::Error>;
+
+    /// Moves an invitation to completed.  Returns false if it already was, so two submissions
+    /// racing with the same link can't both succeed.  Implementations must do this atomically.
+    fn mark_completed(&mut self, id: &String) -> Result<bool, Self::Error>;
+
+    fn stats(&mut self, survey_id: &String) -> Result<InvitationStats, Self::Error>;
+}
+
+/// Storage for survey responses.
+pub trait ResponseRepository {
+    /// Error type that likely corresponds to an underlying database error.
+    type Error: 'static + std::error::Error + std::fmt::Display + Send;
+
+    /// Stores `response` and returns its id, or `None` if the survey already has a response from
+    /// the same respondent.
+    fn insert(&mut self, response: &ResponseRecord) -> Result<Option<String>, Self::Error>;
+
+    /// Up to `limit` of the survey's responses in the order they were stored, starting after the
+    /// response with id `after`, or from the first one.  Responses stored while a caller pages
+    /// through are picked up on a later page rather than shifting the pages already read.
+    fn list(&mut self, survey_id: &String, after: Option<&String>, limit: usize) -> Result<Vec<ResponseRecord>, Self::Error>;
+}
+
+/// Storage for responses that have been started but not submitted.  They're kept once submitted
+/// or abandoned, for the funnel statistics.
+pub trait PartialResponseRepository {
+    /// Error type that likely corresponds to an underlying database error.
+    type Error: 'static + std::error::Error + std::fmt::Display + Send;
+
+    fn insert(&mut self, partial: &PartialResponse) -> Result<(), Self::Error>;
+
+    fn get(&mut self, token_hash: &str) -> Result<Option<PartialResponse>, Self::Error>;
+
+    /// Saves the partial's answers and expiry.  Returns false if it had been submitted meanwhile.
+    fn update(&mut self, partial: &PartialResponse) -> Result<bool, Self::Error>;
+
+    /// Records the response a partial became.  Returns false if it already had one, so two
+    /// submissions racing with the same token can't both succeed.  Implementations must do this
+    /// atomically.
+    fn mark_submitted(&mut self, token_hash: &str, response_id: &str) -> Result<bool, Self::Error>;
+
+    /// Up to `limit` of the survey's partials in the order they were started, starting after the
+    /// one with hash `after`, or from the first one.
+    fn list(&mut self, survey_id: &str, after: Option<&str>, limit: usize) -> Result<Vec<PartialResponse>, Self::Error>;
+}
+
+/// The definition of each version of a survey that has been answered, so results can be read
+/// against the survey as respondents saw it.
+pub trait SurveySnapshotRepository {
+    /// Error type that likely corresponds to an underlying database error.
+    type Error: 'static + std::error::Error + std::fmt::Display + Send;
+
+    /// Keeps `definition` as the snapshot of its version.  A version is only ever kept once,
+    /// saving it again leaves the first snapshot in place.
+    fn save(&mut self, definition: &SurveyDTO) -> Result<(), Self::Error>;
+
+    fn get(&mut self, survey_id: &str, version: u64) -> Result<Option<SurveyDTO>, Self::Error>;
 }
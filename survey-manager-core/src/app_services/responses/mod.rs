@@ -0,0 +1,17 @@
+//! Responses are what respondents answered.  A response is checked against the survey it answers
+//! when it's submitted, and stored as given.  A respondent can also save a response part way
+//! through and come back to it, see `PartialResponse`.
+pub mod response;
+pub use response::*;
+
+pub mod partial_response;
+pub use partial_response::*;
+
+pub mod response_config;
+pub use response_config::*;
+
+pub mod response_commands;
+pub use response_commands::*;
+
+pub mod response_commands_handler;
+pub use response_commands_handler::*;
@@ -0,0 +1,203 @@
+use serde::{Serialize, Deserialize};
+use sha2::{Sha256, Digest};
+use uuid::Uuid;
+use chrono::Utc;
+use domain_patterns::models::{Entity, AggregateRoot};
+use crate::errors::{Error, Result, FieldErrors};
+use crate::survey::Survey;
+use crate::value_objects::{ValidationError, Respondent};
+use crate::app_services::responses::{Answer, admit, check_answers, check_survey_version};
+
+/// A response that's been started but not submitted yet.  It's saved a few answers at a time,
+/// each checked on its own, and picked up again with the resume token handed out when it was
+/// started.  Only a hash of the token is stored, like refresh tokens.
+///
+/// A partial belongs to the version of the survey it was started on.  Once the survey changes it
+/// can't be resumed, its answers may no longer fit.
+#[derive(Clone)]
+pub struct PartialResponse {
+    pub token_hash: String,
+    pub survey_id: String,
+    pub survey_version: u64,
+    /// Key from `ResponsePolicy::admit`.  Only the same respondent can carry on with it.
+    pub respondent: String,
+    pub answers: Vec<Answer>,
+    pub started_on: i64,
+    /// Pushed back on every save.  Past it the partial is abandoned.
+    pub expires_at: i64,
+    /// Set once it's been submitted, to the id of the response it became.
+    pub response_id: Option<String>,
+}
+
+/// Handed back on every save, the token is needed to save more or to submit.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedProgress {
+    pub resume_token: String,
+    pub expires_at: i64,
+}
+
+impl PartialResponse {
+    /// Starts a partial response with `answers`, returning the resume token to hand to the
+    /// respondent along with the partial to store.  Only the answers given are checked, and like
+    /// `ResponseRecord::submit` every problem is reported at once.
+    pub fn start(survey: &Survey, respondent: &Respondent, answers: Vec<Answer>, ttl: i64) -> Result<(String, PartialResponse)> {
+        let respondent = admit(survey, respondent)?;
+        let mut errors = FieldErrors::new();
+        check_answers(&mut errors, survey, &answers)?;
+        errors.into_result()?;
+
+        // Two v4 uuids give 244 random bits, well beyond guessing range.
+        let token = format!("{}{}", Uuid::new_v4().to_simple(), Uuid::new_v4().to_simple());
+        let now = Utc::now().timestamp();
+        let partial = PartialResponse {
+            token_hash: hash_resume_token(&token),
+            survey_id: survey.id(),
+            survey_version: survey.version(),
+            respondent,
+            answers,
+            started_on: now,
+            expires_at: now + ttl,
+            response_id: None,
+        };
+
+        Ok((token, partial))
+    }
+
+    /// Saves more answers.  An answer to a question that was already answered replaces it.
+    pub fn record(&mut self, survey: &Survey, respondent: &Respondent, answers: Vec<Answer>, ttl: i64) -> Result<()> {
+        self.check_resumable(survey, respondent)?;
+        let mut errors = FieldErrors::new();
+        check_answers(&mut errors, survey, &answers)?;
+        errors.into_result()?;
+
+        self.answers = self.merged(answers);
+        self.expires_at = Utc::now().timestamp() + ttl;
+        Ok(())
+    }
+
+    /// Checks `respondent` can carry on with this partial on `survey` as it is now.
+    pub fn check_resumable(&self, survey: &Survey, respondent: &Respondent) -> Result<()> {
+        if self.response_id.is_some() {
+            return resume_error("the response has already been submitted");
+        }
+        if self.is_expired() {
+            return resume_error("the response was abandoned, start again");
+        }
+        if self.survey_id != survey.id() {
+            return Err(Error::NotAuthorized);
+        }
+        check_survey_version(survey, Some(self.survey_version))?;
+        if admit(survey, respondent)? != self.respondent {
+            return Err(Error::NotAuthorized);
+        }
+
+        Ok(())
+    }
+
+    /// The answers saved so far with `answers` on top.
+    pub fn merged(&self, answers: Vec<Answer>) -> Vec<Answer> {
+        let mut merged = self.answers.clone();
+        for answer in answers {
+            match merged.iter_mut().find(|a| a.question_id == answer.question_id) {
+                Some(saved) => *saved = answer,
+                None => merged.push(answer),
+            }
+        }
+        merged
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now().timestamp()
+    }
+}
+
+/// Resume tokens are high entropy, so a plain sha256 is enough to keep them out of storage.
+pub fn hash_resume_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+fn resume_error(msg: &str) -> Result<()> {
+    Err(ValidationError::ResumeValidationError { msg: msg.to_string() }.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app_services::commands::{CreateSurveyCommand, CreateQuestionCommand, CreateChoiceCommand};
+
+    fn test_survey() -> Survey {
+        let question = |title: &str| CreateQuestionCommand {
+            question_type: "multiple_choice".to_string(),
+            title: title.to_string(),
+            choices: ["first choice", "second choice"].iter()
+                .map(|title| CreateChoiceCommand {
+                    content: None,
+                    content_type: "text".to_string(),
+                    title: title.to_string(),
+                }).collect(),
+        };
+
+        Survey::new(&CreateSurveyCommand {
+            author: "test_author".to_string(),
+            title: "test survey title".to_string(),
+            description: "test survey description".to_string(),
+            category: "music".to_string(),
+            questions: vec![question("first question"), question("second question")],
+        }).unwrap()
+    }
+
+    fn respondent(cookie: &str) -> Respondent {
+        Respondent {
+            ip_address: Some("192.0.2.1".to_string()),
+            cookie: Some(cookie.to_string()),
+            ..Respondent::default()
+        }
+    }
+
+    // Answers question `q` with its choice `c`.
+    fn answer(survey: &Survey, q: usize, c: usize) -> Answer {
+        let question = &survey.questions()[q];
+        Answer {
+            question_id: question.id(),
+            choice_ids: vec![question.choices()[c].id()],
+        }
+    }
+
+    #[test]
+    fn later_answers_replace_earlier_ones() {
+        let survey = test_survey();
+        let (token, mut partial) = PartialResponse::start(&survey, &respondent("a"), vec![answer(&survey, 0, 0)], 60).unwrap();
+
+        partial.record(&survey, &respondent("a"), vec![answer(&survey, 1, 1), answer(&survey, 0, 1)], 60).unwrap();
+
+        assert_eq!(partial.token_hash, hash_resume_token(&token));
+        assert_eq!(partial.answers, vec![answer(&survey, 0, 1), answer(&survey, 1, 1)]);
+    }
+
+    #[test]
+    fn only_the_same_respondent_can_resume() {
+        let survey = test_survey();
+        let (_, mut partial) = PartialResponse::start(&survey, &respondent("a"), vec![], 60).unwrap();
+
+        match partial.record(&survey, &respondent("b"), vec![answer(&survey, 0, 0)], 60) {
+            Err(Error::NotAuthorized) => (),
+            other => panic!("expected someone else to be refused, got {:?}", other.err().map(|e| e.to_string())),
+        }
+    }
+
+    #[test]
+    fn a_partial_cant_be_resumed_once_abandoned_or_the_survey_changes() {
+        let mut survey = test_survey();
+        let (_, mut partial) = PartialResponse::start(&survey, &respondent("a"), vec![], 60).unwrap();
+        partial.expires_at = Utc::now().timestamp() - 1;
+        assert!(partial.check_resumable(&survey, &respondent("a")).is_err());
+
+        partial.expires_at = Utc::now().timestamp() + 60;
+        survey.change_response_policy(&"anyone".to_string()).unwrap();
+        match partial.check_resumable(&survey, &respondent("a")) {
+            Err(Error::StaleVersion { .. }) => (),
+            other => panic!("expected a changed survey to stop the resume, got {:?}", other.err().map(|e| e.to_string())),
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use serde::{Serialize, Deserialize};
+use uuid::Uuid;
+use chrono::Utc;
+use domain_patterns::models::{Entity, AggregateRoot};
+use crate::errors::{Error, Result, FieldErrors};
+use crate::survey::{Survey, Question};
+use crate::value_objects::{QuestionType, ValidationError, Respondent};
+
+/// The answer to one question.  A multiple choice question takes exactly one choice, a ranked
+/// question takes the choices it ranks, best first.  Not every choice has to be ranked.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Answer {
+    pub question_id: String,
+    pub choice_ids: Vec<String>,
+}
+
+/// A response to a survey, as it's stored.  Answers are kept in the order they were given, the
+/// survey decides the order they're reported in.
+#[derive(Clone)]
+pub struct ResponseRecord {
+    pub id: String,
+    pub survey_id: String,
+    /// Key from `ResponsePolicy::admit`, a survey stores one response per key.  Responses stored
+    /// before policies were enforced have none.
+    pub respondent: Option<String>,
+    /// Version of the survey that was answered.  Responses stored before versions were recorded
+    /// have none.
+    pub survey_version: Option<u64>,
+    pub answers: Vec<Answer>,
+    pub submitted_on: i64,
+}
+
+impl ResponseRecord {
+    /// Checks `answers` against `survey` and builds the response.  Every question has to be
+    /// answered, and like `Survey::new` every problem is reported at once in an
+    /// `Error::InvalidFields`.  Paths follow the submitted body, e.g. `/answers/1/choiceIds/0`.
+    /// A survey that isn't published takes no responses at all, and `respondent` has to be
+    /// admitted by the survey's response policy.
+    pub fn submit(survey: &Survey, respondent: &Respondent, answers: Vec<Answer>) -> Result<ResponseRecord> {
+        let respondent = admit(survey, respondent)?;
+
+        let mut errors = FieldErrors::new();
+        check_answers(&mut errors, survey, &answers)?;
+        for question in survey.questions() {
+            if !answers.iter().any(|a| a.question_id == question.id()) {
+                errors.check("/answers", answer_error(format!("question {} wasn't answered", question.id())))?;
+            }
+        }
+        errors.into_result()?;
+
+        Ok(ResponseRecord {
+            id: Uuid::new_v4().to_string(),
+            survey_id: survey.id(),
+            respondent: Some(respondent),
+            survey_version: Some(survey.version()),
+            answers,
+            submitted_on: Utc::now().timestamp(),
+        })
+    }
+
+    /// The answer given to `question_id`, if it was answered.
+    pub fn answer_to(&self, question_id: &str) -> Option<&Answer> {
+        self.answers.iter().find(|a| a.question_id == question_id)
+    }
+}
+
+/// Checks answers given to version `answered` of `survey` still fit it, which they only do while
+/// the survey is unchanged.  Callers that don't know which version was answered pass `None`.
+pub fn check_survey_version(survey: &Survey, answered: Option<u64>) -> Result<()> {
+    match answered {
+        Some(submitted) if submitted != survey.version() => Err(Error::StaleVersion {
+            submitted,
+            current: survey.version(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+// Checks `survey` is taking responses and `respondent` may give one, returning their key.
+pub(crate) fn admit(survey: &Survey, respondent: &Respondent) -> Result<String> {
+    if !survey.publication().accepts_responses() {
+        return Err(ValidationError::RespondentValidationError {
+            msg: "the survey isn't taking responses".to_string(),
+        }.into());
+    }
+    survey.response_policy().admit(respondent)
+}
+
+// Checks each answer on its own terms, without minding questions that weren't answered.
+pub(crate) fn check_answers(errors: &mut FieldErrors, survey: &Survey, answers: &[Answer]) -> Result<()> {
+    for (idx, answer) in answers.iter().enumerate() {
+        let path = format!("/answers/{}", idx);
+        if answers[..idx].iter().any(|a| a.question_id == answer.question_id) {
+            errors.check(format!("{}/questionId", path), answer_error("the question is answered more than once".to_string()))?;
+            continue;
+        }
+
+        match survey.questions().iter().find(|q| q.id() == answer.question_id) {
+            Some(question) => check_choices(errors, &path, question, &answer.choice_ids)?,
+            None => {
+                errors.check(format!("{}/questionId", path), answer_error("not a question on this survey".to_string()))?;
+            },
+        }
+    }
+
+    Ok(())
+}
+
+fn check_choices(errors: &mut FieldErrors, path: &str, question: &Question, choice_ids: &[String]) -> Result<()> {
+    let expected = match question.kind() {
+        QuestionType::MultipleChoice => choice_ids.len() == 1,
+        QuestionType::Ranked => !choice_ids.is_empty() && choice_ids.len() <= question.choices().len(),
+    };
+    if !expected {
+        let msg = match question.kind() {
+            QuestionType::MultipleChoice => "pick exactly one choice".to_string(),
+            QuestionType::Ranked => format!("rank between 1 and {} choices", question.choices().len()),
+        };
+        errors.check(format!("{}/choiceIds", path), answer_error(msg))?;
+    }
+
+    for (idx, choice_id) in choice_ids.iter().enumerate() {
+        let choice_path = format!("{}/choiceIds/{}", path, idx);
+        if !question.choices().iter().any(|c| &c.id() == choice_id) {
+            errors.check(choice_path, answer_error("not a choice of this question".to_string()))?;
+        } else if choice_ids[..idx].contains(choice_id) {
+            errors.check(choice_path, answer_error("the choice is given more than once".to_string()))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn answer_error(msg: String) -> Result<()> {
+    Err(ValidationError::AnswerValidationError { msg }.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app_services::commands::{CreateSurveyCommand, CreateQuestionCommand, CreateChoiceCommand};
+
+    fn question(question_type: &str, title: &str, choices: &[&str]) -> CreateQuestionCommand {
+        CreateQuestionCommand {
+            question_type: question_type.to_string(),
+            title: title.to_string(),
+            choices: choices.iter()
+                .map(|title| CreateChoiceCommand {
+                    content: None,
+                    content_type: "text".to_string(),
+                    title: title.to_string(),
+                }).collect(),
+        }
+    }
+
+    fn test_survey() -> Survey {
+        Survey::new(&CreateSurveyCommand {
+            author: "test_author".to_string(),
+            title: "test survey title".to_string(),
+            description: "test survey description".to_string(),
+            category: "music".to_string(),
+            questions: vec![
+                question("multiple_choice", "favorite album", &["first album", "second album"]),
+                question("ranked", "rank the albums", &["first album", "second album", "third album"]),
+            ],
+        }).unwrap()
+    }
+
+    // Question and choice ids, in the survey's order.
+    fn ids(survey: &Survey) -> Vec<(String, Vec<String>)> {
+        survey.questions().iter()
+            .map(|q| (q.id(), q.choices().iter().map(|c| c.id()).collect()))
+            .collect()
+    }
+
+    fn answer(question_id: &str, choice_ids: &[&String]) -> Answer {
+        Answer {
+            question_id: question_id.to_string(),
+            choice_ids: choice_ids.iter().map(|id| id.to_string()).collect(),
+        }
+    }
+
+    fn respondent() -> Respondent {
+        Respondent {
+            ip_address: Some("192.0.2.1".to_string()),
+            cookie: Some("test cookie".to_string()),
+            ..Respondent::default()
+        }
+    }
+
+    fn failed_paths(result: Result<ResponseRecord>) -> Vec<String> {
+        match result {
+            Err(Error::InvalidFields { errors }) => errors.into_iter().map(|e| e.path).collect(),
+            Err(e) => panic!("expected field errors, got {}", e),
+            Ok(_) => panic!("expected field errors, the response was accepted"),
+        }
+    }
+
+    #[test]
+    fn a_complete_response_is_accepted() {
+        let survey = test_survey();
+        let ids = ids(&survey);
+        let answers = vec![
+            answer(&ids[0].0, &[&ids[0].1[1]]),
+            answer(&ids[1].0, &[&ids[1].1[2], &ids[1].1[0]]),
+        ];
+
+        let response = ResponseRecord::submit(&survey, &respondent(), answers.clone()).unwrap();
+
+        assert_eq!(response.survey_id, survey.id());
+        assert_eq!(response.survey_version, Some(survey.version()));
+        assert_eq!(response.answers, answers);
+        assert!(response.respondent.unwrap().starts_with("fingerprint:"));
+    }
+
+    #[test]
+    fn the_response_policy_decides_who_may_answer() {
+        let mut survey = test_survey();
+        let ids = ids(&survey);
+        let answers = vec![
+            answer(&ids[0].0, &[&ids[0].1[1]]),
+            answer(&ids[1].0, &[&ids[1].1[0]]),
+        ];
+        survey.change_response_policy(&"authenticated".to_string()).unwrap();
+
+        match ResponseRecord::submit(&survey, &respondent(), answers.clone()) {
+            Err(Error::NotAuthorized) => (),
+            Err(e) => panic!("expected an anonymous respondent to be refused, got {}", e),
+            Ok(_) => panic!("an anonymous respondent answered a survey for logged in users"),
+        }
+
+        let user = Respondent { user_id: Some("user-1".to_string()), ..respondent() };
+        let response = ResponseRecord::submit(&survey, &user, answers).unwrap();
+        assert_eq!(response.respondent, Some("user:user-1".to_string()));
+    }
+
+    #[test]
+    fn an_unpublished_survey_takes_no_responses() {
+        let mut survey = test_survey();
+        let ids = ids(&survey);
+        let answers = vec![
+            answer(&ids[0].0, &[&ids[0].1[1]]),
+            answer(&ids[1].0, &[&ids[1].1[0]]),
+        ];
+        survey.unpublish().unwrap();
+
+        match ResponseRecord::submit(&survey, &respondent(), answers) {
+            Err(Error::ValidationError { source: ValidationError::RespondentValidationError { .. } }) => (),
+            Err(e) => panic!("expected the survey to refuse responses, got {}", e),
+            Ok(_) => panic!("an unpublished survey took a response"),
+        }
+    }
+
+    #[test]
+    fn every_question_has_to_be_answered() {
+        let survey = test_survey();
+        let ids = ids(&survey);
+
+        assert_eq!(failed_paths(ResponseRecord::submit(&survey, &respondent(), vec![answer(&ids[0].0, &[&ids[0].1[0]])])), vec!["/answers"]);
+    }
+
+    #[test]
+    fn every_bad_answer_is_reported_at_once() {
+        let survey = test_survey();
+        let ids = ids(&survey);
+        let stranger = "not-a-question".to_string();
+        let answers = vec![
+            // Two picks on a multiple choice question.
+            answer(&ids[0].0, &[&ids[0].1[0], &ids[0].1[1]]),
+            // A choice of the other question, then a choice ranked twice.
+            answer(&ids[1].0, &[&ids[0].1[0], &ids[1].1[1], &ids[1].1[1]]),
+            answer(&ids[1].0, &[&ids[1].1[0]]),
+            answer(&stranger, &[&ids[1].1[0]]),
+        ];
+
+        assert_eq!(
+            failed_paths(ResponseRecord::submit(&survey, &respondent(), answers)),
+            vec![
+                "/answers/0/choiceIds",
+                "/answers/1/choiceIds/0",
+                "/answers/1/choiceIds/2",
+                "/answers/2/questionId",
+                "/answers/3/questionId",
+            ],
+        );
+    }
+}
@@ -0,0 +1,46 @@
+use domain_patterns::command::Command;
+use domain_patterns::message::Message;
+use crate::app_services::responses::Answer;
+use crate::value_objects::Respondent;
+
+/// Records a complete response to a survey.  `respondent` is whatever the request knows about
+/// who sent it, a link token in it is checked by the handler.  With a `resume_token` the answers
+/// saved so far count too, and `answers` only has to hold the rest.  `survey_version` is the
+/// version the respondent was shown, if they say.  A survey that's changed since turns it away.
+#[derive(Clone, Command)]
+pub struct SubmitResponseCommand {
+    pub survey_id: String,
+    pub survey_version: Option<u64>,
+    pub respondent: Respondent,
+    pub resume_token: Option<String>,
+    pub answers: Vec<Answer>,
+}
+
+/// Saves answers without submitting, starting a partial response or adding to the one
+/// `resume_token` names.  `survey_version` is checked like `SubmitResponseCommand`'s.
+#[derive(Clone, Command)]
+pub struct SaveProgressCommand {
+    pub survey_id: String,
+    pub survey_version: Option<u64>,
+    pub respondent: Respondent,
+    pub resume_token: Option<String>,
+    pub answers: Vec<Answer>,
+}
+
+#[derive(Clone, Command)]
+pub enum ResponseCommands {
+    SubmitResponseCommand(SubmitResponseCommand),
+    SaveProgressCommand(SaveProgressCommand),
+}
+
+impl From<SubmitResponseCommand> for ResponseCommands {
+    fn from(cmd: SubmitResponseCommand) -> Self {
+        ResponseCommands::SubmitResponseCommand(cmd)
+    }
+}
+
+impl From<SaveProgressCommand> for ResponseCommands {
+    fn from(cmd: SaveProgressCommand) -> Self {
+        ResponseCommands::SaveProgressCommand(cmd)
+    }
+}
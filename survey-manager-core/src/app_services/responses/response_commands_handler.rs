@@ -0,0 +1,207 @@
+use domain_patterns::collections::Repository;
+use domain_patterns::command::Handles;
+use domain_patterns::models::Entity;
+use crate::errors::Error::{ResourceNotFound, RepoFailure, AlreadyExists, NotAuthorized};
+use crate::errors::Result;
+use crate::survey::Survey;
+use crate::value_objects::{ResponsePolicy, Respondent, ValidationError};
+use crate::app_services::repository_contracts::{ResponseRepository, PartialResponseRepository, SurveySnapshotRepository, InvitationRepository};
+use crate::app_services::invitations::{InvitationRecord, InvitationSigner};
+use crate::app_services::responses::{
+    SubmitResponseCommand, SaveProgressCommand, ResponseCommands, ResponseRecord, PartialResponse,
+    SavedProgress, ResponseConfig, hash_resume_token, check_survey_version,
+};
+use crate::dtos::SurveyDTO;
+
+pub struct ResponseCommandsHandler<'a, S, R, P, V, I> where
+    S: Repository<Survey>,
+    R: ResponseRepository,
+    P: PartialResponseRepository,
+    V: SurveySnapshotRepository,
+    I: InvitationRepository,
+{
+    surveys: S,
+    responses: R,
+    partials: P,
+    snapshots: V,
+    invitations: I,
+    signer: &'a InvitationSigner,
+    config: &'a ResponseConfig,
+}
+
+impl<'a, S, R, P, V, I> ResponseCommandsHandler<'a, S, R, P, V, I> where
+    S: Repository<Survey>,
+    R: ResponseRepository,
+    P: PartialResponseRepository,
+    V: SurveySnapshotRepository,
+    I: InvitationRepository,
+{
+    pub fn new(
+        surveys: S,
+        responses: R,
+        partials: P,
+        snapshots: V,
+        invitations: I,
+        signer: &'a InvitationSigner,
+        config: &'a ResponseConfig,
+    ) -> ResponseCommandsHandler<'a, S, R, P, V, I> {
+        ResponseCommandsHandler {
+            surveys,
+            responses,
+            partials,
+            snapshots,
+            invitations,
+            signer,
+            config,
+        }
+    }
+
+    fn load_survey(&mut self, id: &String) -> Result<Survey> {
+        self.surveys.get(id)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?
+            .ok_or(ResourceNotFound { resource: format!("survey with id {}", id) })
+    }
+
+    fn load_partial(&mut self, token: &str) -> Result<PartialResponse> {
+        self.partials.get(&hash_resume_token(token))
+            .map_err(|e| RepoFailure { source: Box::new(e) })?
+            .ok_or(ResourceNotFound { resource: "partial response".to_string() })
+    }
+
+    // The policy only sees that a link is there, this is where it's checked to be a real one for
+    // this survey.
+    fn invitation_for(&mut self, survey: &Survey, respondent: &Respondent) -> Result<Option<InvitationRecord>> {
+        let token = match (survey.response_policy(), &respondent.link_token) {
+            (ResponsePolicy::LinkHolders, Some(token)) => token,
+            _ => return Ok(None),
+        };
+
+        let id = self.signer.verify(token)?;
+        let invitation = self.invitations.get(&id)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?
+            .ok_or(ResourceNotFound { resource: format!("invitation with id {}", id) })?;
+
+        if invitation.survey_id != survey.id() {
+            return Err(NotAuthorized);
+        }
+        Ok(Some(invitation))
+    }
+}
+
+impl<'a, S, R, P, V, I> Handles<SubmitResponseCommand> for ResponseCommandsHandler<'a, S, R, P, V, I> where
+    S: Repository<Survey>,
+    R: ResponseRepository,
+    P: PartialResponseRepository,
+    V: SurveySnapshotRepository,
+    I: InvitationRepository,
+{
+    // Id of the stored response.
+    type Result = Result<String>;
+
+    fn handle(&mut self, msg: SubmitResponseCommand) -> Result<String> {
+        let survey = self.load_survey(&msg.survey_id)?;
+        check_survey_version(&survey, msg.survey_version)?;
+        let invitation = self.invitation_for(&survey, &msg.respondent)?;
+
+        let (partial, answers) = match &msg.resume_token {
+            Some(token) => {
+                let partial = self.load_partial(token)?;
+                partial.check_resumable(&survey, &msg.respondent)?;
+                let answers = partial.merged(msg.answers);
+                (Some(partial), answers)
+            },
+            None => (None, msg.answers),
+        };
+
+        let response = ResponseRecord::submit(&survey, &msg.respondent, answers)?;
+        let id = self.responses.insert(&response)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?
+            .ok_or(AlreadyExists { resource: "a response from this respondent".to_string() })?;
+        // Results are read against the survey as it was answered, so that has to be kept.
+        self.snapshots.save(&SurveyDTO::from(&survey))
+            .map_err(|e| RepoFailure { source: Box::new(e) })?;
+
+        if let Some(partial) = partial {
+            let submitted = self.partials.mark_submitted(&partial.token_hash, &id)
+                .map_err(|e| RepoFailure { source: Box::new(e) })?;
+            if !submitted {
+                return Err(ValidationError::ResumeValidationError {
+                    msg: "the response has already been submitted".to_string(),
+                }.into());
+            }
+        }
+
+        // Answering through a link completes its invitation, and this is the only place that
+        // happens.  Losing the race to another submission of the same link refuses this one, and
+        // the unit of work it runs in drops the response stored above.
+        if let Some(invitation) = invitation {
+            let completed = self.invitations.mark_completed(&invitation.id)
+                .map_err(|e| RepoFailure { source: Box::new(e) })?;
+            if !completed {
+                return Err(ValidationError::InvitationValidationError {
+                    msg: "the link has already been used".to_string(),
+                }.into());
+            }
+        }
+
+        Ok(id)
+    }
+}
+
+impl<'a, S, R, P, V, I> Handles<SaveProgressCommand> for ResponseCommandsHandler<'a, S, R, P, V, I> where
+    S: Repository<Survey>,
+    R: ResponseRepository,
+    P: PartialResponseRepository,
+    V: SurveySnapshotRepository,
+    I: InvitationRepository,
+{
+    // Json of `SavedProgress`.
+    type Result = Result<String>;
+
+    fn handle(&mut self, msg: SaveProgressCommand) -> Result<String> {
+        let survey = self.load_survey(&msg.survey_id)?;
+        check_survey_version(&survey, msg.survey_version)?;
+        self.invitation_for(&survey, &msg.respondent)?;
+        let ttl = self.config.partial_ttl;
+
+        let (token, partial) = match msg.resume_token {
+            Some(token) => {
+                let mut partial = self.load_partial(&token)?;
+                partial.record(&survey, &msg.respondent, msg.answers, ttl)?;
+                let updated = self.partials.update(&partial)
+                    .map_err(|e| RepoFailure { source: Box::new(e) })?;
+                if !updated {
+                    return Err(ValidationError::ResumeValidationError {
+                        msg: "the response has already been submitted".to_string(),
+                    }.into());
+                }
+                (token, partial)
+            },
+            None => {
+                let (token, partial) = PartialResponse::start(&survey, &msg.respondent, msg.answers, ttl)?;
+                self.partials.insert(&partial)
+                    .map_err(|e| RepoFailure { source: Box::new(e) })?;
+                (token, partial)
+            },
+        };
+
+        Ok(serde_json::to_string(&SavedProgress { resume_token: token, expires_at: partial.expires_at }).unwrap())
+    }
+}
+
+impl<'a, S, R, P, V, I> Handles<ResponseCommands> for ResponseCommandsHandler<'a, S, R, P, V, I> where
+    S: Repository<Survey>,
+    R: ResponseRepository,
+    P: PartialResponseRepository,
+    V: SurveySnapshotRepository,
+    I: InvitationRepository,
+{
+    type Result = Result<String>;
+
+    fn handle(&mut self, msg: ResponseCommands) -> Result<String> {
+        match msg {
+            ResponseCommands::SubmitResponseCommand(cmd) => self.handle(cmd),
+            ResponseCommands::SaveProgressCommand(cmd) => self.handle(cmd),
+        }
+    }
+}
@@ -0,0 +1,29 @@
+/// Response settings, read from `RESPONSE_PARTIAL_TTL`.
+pub struct ResponseConfig {
+    /// Seconds a partial response can go untouched before it's abandoned and can no longer be
+    /// resumed.  Every save starts the count again.
+    pub partial_ttl: i64,
+}
+
+// A week, time enough to come back to a long survey.
+fn default_partial_ttl() -> i64 {
+    604_800
+}
+
+impl ResponseConfig {
+    pub fn from_env() -> ResponseConfig {
+        ResponseConfig {
+            partial_ttl: std::env::var("RESPONSE_PARTIAL_TTL").ok()
+                .and_then(|ttl| ttl.parse().ok())
+                .unwrap_or(default_partial_ttl()),
+        }
+    }
+}
+
+impl Default for ResponseConfig {
+    fn default() -> Self {
+        ResponseConfig {
+            partial_ttl: default_partial_ttl(),
+        }
+    }
+}
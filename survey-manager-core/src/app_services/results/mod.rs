@@ -0,0 +1,24 @@
+//! Results are a survey's responses, read back out for whoever may see them.
+//!
+//! Exports are written a page of responses at a time onto any `Write`, so a caller can stream a
+//! survey with any number of responses without holding them all.
+pub mod results_format;
+pub use results_format::*;
+
+pub mod results_table;
+pub use results_table::*;
+
+pub mod results_writers;
+pub use results_writers::*;
+
+pub mod xlsx_writer;
+pub use xlsx_writer::*;
+
+pub mod response_funnel;
+pub use response_funnel::*;
+
+pub mod results_queries;
+pub use results_queries::*;
+
+pub mod results_queries_handler;
+pub use results_queries_handler::*;
@@ -0,0 +1,164 @@
+use serde::{Serialize, Deserialize};
+use domain_patterns::models::Entity;
+use crate::survey::Survey;
+use crate::app_services::responses::{ResponseRecord, PartialResponse};
+
+/// How far respondents got with a survey.  A response counts as started when it was first saved,
+/// or when it was submitted in one go.  Partials that are still open count as in progress, those
+/// that ran out as abandoned, and each abandoned one is put down to the first question, in the
+/// survey's order, it left unanswered.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseFunnel {
+    pub started: u64,
+    pub finished: u64,
+    pub in_progress: u64,
+    pub abandoned: u64,
+    pub questions: Vec<QuestionDropOff>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuestionDropOff {
+    pub question_id: String,
+    pub title: String,
+    /// Responses, finished or not, that answered the question.
+    pub answered: u64,
+    /// Abandoned responses that stopped here.
+    pub dropped_off: u64,
+}
+
+impl ResponseFunnel {
+    /// An empty funnel with a row for each of the survey's questions.
+    pub fn new(survey: &Survey) -> ResponseFunnel {
+        ResponseFunnel {
+            started: 0,
+            finished: 0,
+            in_progress: 0,
+            abandoned: 0,
+            questions: survey.questions().iter()
+                .map(|q| QuestionDropOff {
+                    question_id: q.id(),
+                    title: q.title().to_string(),
+                    answered: 0,
+                    dropped_off: 0,
+                }).collect(),
+        }
+    }
+
+    /// Counts a submitted response.  Whether it was started is counted through its partial, if it
+    /// had one, so `from_partial` says whether it did.
+    pub fn count_response(&mut self, response: &ResponseRecord, from_partial: bool) {
+        self.finished += 1;
+        if !from_partial {
+            self.started += 1;
+        }
+        for question in self.questions.iter_mut() {
+            if response.answer_to(&question.question_id).is_some() {
+                question.answered += 1;
+            }
+        }
+    }
+
+    /// Counts a partial response.  A submitted one is only counted as started, its answers are
+    /// counted through the response it became.
+    pub fn count_partial(&mut self, partial: &PartialResponse) {
+        self.started += 1;
+        if partial.response_id.is_some() {
+            return;
+        }
+
+        let answered = |question: &QuestionDropOff| partial.answers.iter().any(|a| a.question_id == question.question_id);
+        for question in self.questions.iter_mut().filter(|q| answered(q)) {
+            question.answered += 1;
+        }
+
+        if !partial.is_expired() {
+            self.in_progress += 1;
+            return;
+        }
+        self.abandoned += 1;
+        if let Some(question) = self.questions.iter_mut().find(|q| !answered(q)) {
+            question.dropped_off += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use domain_patterns::models::AggregateRoot;
+    use crate::app_services::commands::{CreateSurveyCommand, CreateQuestionCommand, CreateChoiceCommand};
+    use crate::app_services::responses::Answer;
+
+    fn test_survey() -> Survey {
+        let question = |title: &str| CreateQuestionCommand {
+            question_type: "multiple_choice".to_string(),
+            title: title.to_string(),
+            choices: vec![CreateChoiceCommand {
+                content: None,
+                content_type: "text".to_string(),
+                title: "the only choice".to_string(),
+            }],
+        };
+
+        Survey::new(&CreateSurveyCommand {
+            author: "test_author".to_string(),
+            title: "test survey title".to_string(),
+            description: "test survey description".to_string(),
+            category: "music".to_string(),
+            questions: vec![question("first question"), question("second question"), question("third question")],
+        }).unwrap()
+    }
+
+    // Answers to the first `n` questions.
+    fn answers(survey: &Survey, n: usize) -> Vec<Answer> {
+        survey.questions()[..n].iter()
+            .map(|q| Answer { question_id: q.id(), choice_ids: vec![q.choices()[0].id()] })
+            .collect()
+    }
+
+    fn partial(survey: &Survey, n: usize, expires_in: i64, response_id: Option<&str>) -> PartialResponse {
+        PartialResponse {
+            token_hash: format!("hash {}", n),
+            survey_id: survey.id(),
+            survey_version: survey.version(),
+            respondent: format!("respondent {}", n),
+            answers: answers(survey, n),
+            started_on: 0,
+            expires_at: Utc::now().timestamp() + expires_in,
+            response_id: response_id.map(|id| id.to_string()),
+        }
+    }
+
+    #[test]
+    fn abandoned_responses_drop_off_at_their_first_unanswered_question() {
+        let survey = test_survey();
+        let response = ResponseRecord {
+            id: "response".to_string(),
+            survey_id: survey.id(),
+            survey_version: Some(survey.version()),
+            respondent: None,
+            answers: answers(&survey, 3),
+            submitted_on: 0,
+        };
+        let mut funnel = ResponseFunnel::new(&survey);
+
+        // Submitted in one go, and once after saving part way.
+        funnel.count_response(&response, false);
+        funnel.count_response(&response, true);
+        funnel.count_partial(&partial(&survey, 1, 60, Some("response")));
+        // Still going, then given up at the second question twice and the first once.
+        funnel.count_partial(&partial(&survey, 2, 60, None));
+        funnel.count_partial(&partial(&survey, 1, -60, None));
+        funnel.count_partial(&partial(&survey, 1, -60, None));
+        funnel.count_partial(&partial(&survey, 0, -60, None));
+
+        assert_eq!((funnel.started, funnel.finished, funnel.in_progress, funnel.abandoned), (6, 2, 1, 3));
+        let answered: Vec<u64> = funnel.questions.iter().map(|q| q.answered).collect();
+        let dropped: Vec<u64> = funnel.questions.iter().map(|q| q.dropped_off).collect();
+        assert_eq!(answered, vec![5, 3, 2]);
+        assert_eq!(dropped, vec![1, 2, 0]);
+    }
+}
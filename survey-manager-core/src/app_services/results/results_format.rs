@@ -0,0 +1,72 @@
+use std::str::FromStr;
+use crate::errors::{Error, Result};
+
+/// Formats a results export can be written in.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum ResultsFormat {
+    #[default]
+    Csv,
+    Ndjson,
+    Xlsx,
+}
+
+impl ResultsFormat {
+    /// Media type to advertise when serving an export in this format.
+    pub fn media_type(&self) -> &'static str {
+        match self {
+            ResultsFormat::Csv => "text/csv",
+            ResultsFormat::Ndjson => "application/x-ndjson",
+            ResultsFormat::Xlsx => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        }
+    }
+
+    /// Extension for the file an export is saved as.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ResultsFormat::Csv => "csv",
+            ResultsFormat::Ndjson => "ndjson",
+            ResultsFormat::Xlsx => "xlsx",
+        }
+    }
+}
+
+impl FromStr for ResultsFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_ref() {
+            "csv" => Ok(ResultsFormat::Csv),
+            "ndjson" | "jsonl" => Ok(ResultsFormat::Ndjson),
+            "xlsx" => Ok(ResultsFormat::Xlsx),
+            other => Err(Error::InvalidDocument {
+                msg: format!("unsupported results format '{}', expected csv, ndjson or xlsx", other),
+            }),
+        }
+    }
+}
+
+/// Which version of a survey a response is read against in an export.  Columns always follow the
+/// survey as it is now.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum ResultsMode {
+    /// Answers read as the respondent saw them, with the choice titles of the version answered.
+    #[default]
+    AsAnswered,
+    /// Answers carried over to the current version, with its choice titles.  Only a question
+    /// whose choices were no more than renamed since is carried over, others are left empty.
+    Current,
+}
+
+impl FromStr for ResultsMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_ref() {
+            "as_answered" => Ok(ResultsMode::AsAnswered),
+            "current" => Ok(ResultsMode::Current),
+            other => Err(Error::InvalidDocument {
+                msg: format!("unsupported results mode '{}', expected as_answered or current", other),
+            }),
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use domain_patterns::query::Query;
+use crate::app_services::token::OrganizationClaim;
+use crate::app_services::results::{ResultsFormat, ResultsMode};
+
+/// Exports a survey's responses.  Needs the right to view the survey's results.  Handling it
+/// only checks that and lays out the export, `ResultsQueriesHandler::write_export` writes it.
+#[derive(Query)]
+pub struct ExportResultsQuery {
+    pub survey_id: String,
+    pub requesting_author: String,
+    pub organization: Option<OrganizationClaim>,
+    pub format: ResultsFormat,
+    pub mode: ResultsMode,
+}
+
+/// How far respondents got with a survey, see `ResponseFunnel`.  Needs the right to view the
+/// survey's results.
+#[derive(Query)]
+pub struct ResponseFunnelQuery {
+    pub survey_id: String,
+    pub requesting_author: String,
+    pub organization: Option<OrganizationClaim>,
+}
@@ -0,0 +1,171 @@
+use std::io::Write;
+use std::collections::{HashSet, HashMap};
+use std::collections::hash_map::Entry;
+use std::convert::TryFrom;
+use domain_patterns::collections::Repository;
+use domain_patterns::query::HandlesQuery;
+use crate::errors::Error::{ResourceNotFound, RepoFailure};
+use crate::errors::Result;
+use crate::survey::Survey;
+use crate::policy::{authorize, Actor, Permission};
+use crate::app_services::repository_contracts::{ResponseRepository, PartialResponseRepository, SurveySnapshotRepository};
+use crate::app_services::results::{ExportResultsQuery, ResponseFunnelQuery, ResultsFormat, ResultsTable, ResponseFunnel, results_writer};
+
+// Responses read from storage at a time while exporting or counting.
+const EXPORT_PAGE_SIZE: usize = 500;
+
+/// An export that's been authorized and laid out, ready to be written.
+pub struct ResultsExport {
+    pub survey_id: String,
+    pub format: ResultsFormat,
+    pub table: ResultsTable,
+}
+
+pub struct ResultsQueriesHandler<S, R, P, V> where
+    S: Repository<Survey>,
+    R: ResponseRepository,
+    P: PartialResponseRepository,
+    V: SurveySnapshotRepository,
+{
+    surveys: S,
+    responses: R,
+    partials: P,
+    snapshots: V,
+}
+
+impl<S, R, P, V> ResultsQueriesHandler<S, R, P, V> where
+    S: Repository<Survey>,
+    R: ResponseRepository,
+    P: PartialResponseRepository,
+    V: SurveySnapshotRepository,
+{
+    pub fn new(surveys: S, responses: R, partials: P, snapshots: V) -> ResultsQueriesHandler<S, R, P, V> {
+        ResultsQueriesHandler {
+            surveys,
+            responses,
+            partials,
+            snapshots,
+        }
+    }
+
+    fn load_survey(&mut self, id: &String) -> Result<Survey> {
+        self.surveys.get(id)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?
+            .ok_or(ResourceNotFound { resource: format!("survey with id {}", id) })
+    }
+
+    // The definition of `version` of the survey, if it was kept.
+    fn load_snapshot(&mut self, survey_id: &str, version: u64) -> Result<Option<Survey>> {
+        let definition = self.snapshots.get(survey_id, version)
+            .map_err(|e| RepoFailure { source: Box::new(e) })?;
+        definition.map(Survey::try_from).transpose()
+    }
+
+    /// Writes every response of `export` to `out`, reading them a page at a time.  Fails part way
+    /// through if `out` does, e.g. when the client downloading it goes away.
+    ///
+    /// Responses to earlier versions are read against the definition kept for them.  Those with
+    /// no version recorded, or whose definition wasn't kept, are read against the current one.
+    pub fn write_export<W: Write>(&mut self, export: &ResultsExport, out: W) -> Result<()> {
+        let io_failure = |e: std::io::Error| RepoFailure { source: Box::new(e) };
+        let mut writer = results_writer(export.format, out);
+        writer.write_header(export.table.headers()).map_err(io_failure)?;
+
+        let mut versions: HashMap<u64, Option<Survey>> = HashMap::new();
+        let mut after: Option<String> = None;
+        loop {
+            let page = self.responses.list(&export.survey_id, after.as_ref(), EXPORT_PAGE_SIZE)
+                .map_err(|e| RepoFailure { source: Box::new(e) })?;
+            for response in &page {
+                let answered = match response.survey_version {
+                    Some(version) if version != export.table.version() => {
+                        if let Entry::Vacant(entry) = versions.entry(version) {
+                            entry.insert(self.load_snapshot(&export.survey_id, version)?);
+                        }
+                        versions[&version].as_ref()
+                    },
+                    _ => None,
+                };
+                writer.write_row(&export.table.row(response, answered)).map_err(io_failure)?;
+            }
+
+            if page.len() < EXPORT_PAGE_SIZE {
+                break;
+            }
+            after = page.last().map(|r| r.id.clone());
+        }
+
+        writer.finish().map_err(io_failure)
+    }
+}
+
+impl<S, R, P, V> HandlesQuery<ExportResultsQuery> for ResultsQueriesHandler<S, R, P, V> where
+    S: Repository<Survey>,
+    R: ResponseRepository,
+    P: PartialResponseRepository,
+    V: SurveySnapshotRepository,
+{
+    type Result = Result<ResultsExport>;
+
+    fn handle(&mut self, query: ExportResultsQuery) -> Self::Result {
+        let survey = self.load_survey(&query.survey_id)?;
+        authorize(&survey, &Actor::new(&query.requesting_author, &query.organization), Permission::ViewResults)?;
+
+        Ok(ResultsExport {
+            survey_id: query.survey_id,
+            format: query.format,
+            table: ResultsTable::new(&survey, query.mode),
+        })
+    }
+}
+
+impl<S, R, P, V> HandlesQuery<ResponseFunnelQuery> for ResultsQueriesHandler<S, R, P, V> where
+    S: Repository<Survey>,
+    R: ResponseRepository,
+    P: PartialResponseRepository,
+    V: SurveySnapshotRepository,
+{
+    // Json of `ResponseFunnel`.
+    type Result = Result<String>;
+
+    fn handle(&mut self, query: ResponseFunnelQuery) -> Self::Result {
+        let survey = self.load_survey(&query.survey_id)?;
+        authorize(&survey, &Actor::new(&query.requesting_author, &query.organization), Permission::ViewResults)?;
+        let mut funnel = ResponseFunnel::new(&survey);
+
+        // Partials go first, to learn which responses were started through one.
+        let mut from_partials = HashSet::new();
+        let mut after: Option<String> = None;
+        loop {
+            let page = self.partials.list(&query.survey_id, after.as_deref(), EXPORT_PAGE_SIZE)
+                .map_err(|e| RepoFailure { source: Box::new(e) })?;
+            for partial in &page {
+                funnel.count_partial(partial);
+                if let Some(response_id) = &partial.response_id {
+                    from_partials.insert(response_id.clone());
+                }
+            }
+
+            if page.len() < EXPORT_PAGE_SIZE {
+                break;
+            }
+            after = page.last().map(|p| p.token_hash.clone());
+        }
+
+        let mut after: Option<String> = None;
+        loop {
+            let page = self.responses.list(&query.survey_id, after.as_ref(), EXPORT_PAGE_SIZE)
+                .map_err(|e| RepoFailure { source: Box::new(e) })?;
+            for response in &page {
+                funnel.count_response(response, from_partials.contains(&response.id));
+            }
+
+            if page.len() < EXPORT_PAGE_SIZE {
+                break;
+            }
+            after = page.last().map(|r| r.id.clone());
+        }
+
+        Ok(serde_json::to_string(&funnel).unwrap())
+    }
+}
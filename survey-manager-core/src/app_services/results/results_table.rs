@@ -0,0 +1,257 @@
+use chrono::{TimeZone, Utc, SecondsFormat};
+use domain_patterns::models::{Entity, AggregateRoot};
+use crate::survey::{Survey, Question};
+use crate::value_objects::QuestionType;
+use crate::app_services::responses::ResponseRecord;
+use crate::app_services::results::ResultsMode;
+
+/// How a survey's responses are laid out in an export.  One row per response, and after the
+/// response's id and submission time one column per question, in the survey's order.  A ranked
+/// question takes a column per rank instead, `<title> [rank 1]` holding the choice ranked first.
+/// Cells hold choice titles, and are empty where nothing was answered.
+///
+/// The columns follow the survey's current version.  A response to an earlier version is read
+/// against that version's definition, as `mode` says.
+pub struct ResultsTable {
+    version: u64,
+    mode: ResultsMode,
+    headers: Vec<String>,
+    questions: Vec<QuestionColumns>,
+}
+
+struct QuestionColumns {
+    id: String,
+    kind: QuestionType,
+    // Ids and titles of the question's choices.
+    choices: Vec<(String, String)>,
+    // Columns the question takes, one per rank for a ranked question.
+    width: usize,
+    ranked: bool,
+}
+
+impl ResultsTable {
+    pub fn new(survey: &Survey, mode: ResultsMode) -> ResultsTable {
+        let mut headers = vec!["Response id".to_string(), "Submitted on".to_string()];
+        let mut questions = vec![];
+        for question in survey.questions() {
+            let title = question.title().to_string();
+            let ranked = *question.kind() == QuestionType::Ranked;
+            let width = if ranked { question.choices().len() } else { 1 };
+            if ranked {
+                headers.extend((1..=width).map(|rank| format!("{} [rank {}]", title, rank)));
+            } else {
+                headers.push(title);
+            }
+
+            questions.push(QuestionColumns {
+                id: question.id(),
+                kind: question.kind().clone(),
+                choices: choice_titles(question),
+                width,
+                ranked,
+            });
+        }
+
+        ResultsTable {
+            version: survey.version(),
+            mode,
+            headers: unique_headers(headers),
+            questions,
+        }
+    }
+
+    pub fn headers(&self) -> &[String] {
+        &self.headers
+    }
+
+    /// Version of the survey the columns were laid out from.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// The cells of `response`'s row, one per header.  `answered` is the version of the survey
+    /// the response was to, when that's not the one the table was laid out from.
+    pub fn row(&self, response: &ResponseRecord, answered: Option<&Survey>) -> Vec<Option<String>> {
+        let mut cells = Vec::with_capacity(self.headers.len());
+        cells.push(Some(response.id.clone()));
+        cells.push(Some(Utc.timestamp(response.submitted_on, 0).to_rfc3339_opts(SecondsFormat::Secs, true)));
+
+        for question in &self.questions {
+            let choice_ids = response.answer_to(&question.id)
+                .map(|a| a.choice_ids.as_slice())
+                .unwrap_or(&[]);
+            // A multiple choice answer only ever has one choice, so both kinds fill columns in order.
+            debug_assert!(question.ranked || choice_ids.len() <= 1);
+            let titles = match answered {
+                Some(survey) => self.answered_titles(question, survey),
+                None => Some(question.choices.clone()),
+            };
+            for rank in 0..question.width {
+                cells.push(titles.as_ref()
+                    .and_then(|titles| choice_ids.get(rank).map(|id| choice_title(titles, id))));
+            }
+        }
+
+        cells
+    }
+
+    // Choice titles to read an answer to `question` given on the `answered` version with, or none
+    // if it can't be read into the question's columns.
+    fn answered_titles(&self, question: &QuestionColumns, answered: &Survey) -> Option<Vec<(String, String)>> {
+        let then = answered.questions().iter().find(|q| q.id() == question.id)?;
+        let then_choices = choice_titles(then);
+        match self.mode {
+            ResultsMode::AsAnswered => Some(then_choices),
+            ResultsMode::Current => {
+                let same_choices = then_choices.len() == question.choices.len()
+                    && then_choices.iter().all(|(id, _)| question.choices.iter().any(|(current, _)| current == id));
+                if *then.kind() == question.kind && same_choices {
+                    Some(question.choices.clone())
+                } else {
+                    None
+                }
+            },
+        }
+    }
+}
+
+fn choice_titles(question: &Question) -> Vec<(String, String)> {
+    question.choices().iter()
+        .map(|c| (c.id(), c.title().to_string()))
+        .collect()
+}
+
+// Falls back to the id for a choice the survey no longer has.
+fn choice_title(titles: &[(String, String)], choice_id: &str) -> String {
+    titles.iter()
+        .find(|(id, _)| id == choice_id)
+        .map(|(_, title)| title.clone())
+        .unwrap_or(choice_id.to_string())
+}
+
+// Two questions may share a title, and spreadsheet tools mishandle repeated headers.  Later
+// repeats get a number, `Title (2)`.
+fn unique_headers(headers: Vec<String>) -> Vec<String> {
+    let mut unique: Vec<String> = Vec::with_capacity(headers.len());
+    for header in headers {
+        let mut candidate = header.clone();
+        let mut n = 2;
+        while unique.contains(&candidate) {
+            candidate = format!("{} ({})", header, n);
+            n += 1;
+        }
+        unique.push(candidate);
+    }
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app_services::commands::{CreateSurveyCommand, CreateQuestionCommand, CreateChoiceCommand};
+    use std::convert::TryFrom;
+    use crate::app_services::responses::Answer;
+    use crate::dtos::SurveyDTO;
+
+    fn question(question_type: &str, title: &str, choices: &[&str]) -> CreateQuestionCommand {
+        CreateQuestionCommand {
+            question_type: question_type.to_string(),
+            title: title.to_string(),
+            choices: choices.iter()
+                .map(|title| CreateChoiceCommand {
+                    content: None,
+                    content_type: "text".to_string(),
+                    title: title.to_string(),
+                }).collect(),
+        }
+    }
+
+    fn test_survey() -> Survey {
+        Survey::new(&CreateSurveyCommand {
+            author: "test_author".to_string(),
+            title: "test survey title".to_string(),
+            description: "test survey description".to_string(),
+            category: "music".to_string(),
+            questions: vec![
+                question("multiple_choice", "favorite album", &["first album", "second album"]),
+                question("ranked", "favorite album", &["first album", "second album", "third album"]),
+            ],
+        }).unwrap()
+    }
+
+    #[test]
+    fn ranked_questions_take_a_column_per_rank_and_repeated_titles_are_numbered() {
+        let table = ResultsTable::new(&test_survey(), ResultsMode::AsAnswered);
+
+        assert_eq!(table.headers(), &[
+            "Response id",
+            "Submitted on",
+            "favorite album",
+            "favorite album [rank 1]",
+            "favorite album [rank 2]",
+            "favorite album [rank 3]",
+        ][..]);
+    }
+
+    #[test]
+    fn rows_hold_choice_titles_in_rank_order_and_leave_the_rest_empty() {
+        let survey = test_survey();
+        let ranked = &survey.questions()[1];
+        let response = ResponseRecord {
+            id: "response".to_string(),
+            survey_id: survey.id(),
+            survey_version: Some(survey.version()),
+            respondent: None,
+            answers: vec![Answer {
+                question_id: ranked.id(),
+                choice_ids: vec![ranked.choices()[2].id(), ranked.choices()[0].id()],
+            }],
+            submitted_on: 1_577_836_800,
+        };
+
+        assert_eq!(ResultsTable::new(&survey, ResultsMode::AsAnswered).row(&response, None), vec![
+            Some("response".to_string()),
+            Some("2020-01-01T00:00:00Z".to_string()),
+            None,
+            Some("third album".to_string()),
+            Some("first album".to_string()),
+            None,
+        ]);
+    }
+
+    #[test]
+    fn earlier_versions_are_read_as_answered_or_carried_over_when_only_renamed() {
+        let mut current = SurveyDTO::from(&test_survey());
+        current.version += 1;
+        let current = Survey::try_from(current).unwrap();
+        // The version answered had other titles on the first question, and the ranked one had a
+        // choice that's since been removed.
+        let mut then = SurveyDTO::from(&current);
+        then.version -= 1;
+        then.questions[0].choices[0].title = "first album (old)".to_string();
+        then.questions[1].choices.truncate(2);
+        let then = Survey::try_from(then).unwrap();
+        let (multiple, ranked) = (&current.questions()[0], &current.questions()[1]);
+        let response = ResponseRecord {
+            id: "response".to_string(),
+            survey_id: current.id(),
+            survey_version: Some(then.version()),
+            respondent: None,
+            answers: vec![
+                Answer { question_id: multiple.id(), choice_ids: vec![multiple.choices()[0].id()] },
+                Answer { question_id: ranked.id(), choice_ids: vec![ranked.choices()[1].id()] },
+            ],
+            submitted_on: 1_577_836_800,
+        };
+
+        let as_answered = ResultsTable::new(&current, ResultsMode::AsAnswered).row(&response, Some(&then));
+        assert_eq!(as_answered[2..], [
+            Some("first album (old)".to_string()),
+            Some("second album".to_string()),
+            None,
+            None,
+        ][..]);
+        let carried_over = ResultsTable::new(&current, ResultsMode::Current).row(&response, Some(&then));
+        assert_eq!(carried_over[2..], [Some("first album".to_string()), None, None, None][..]);
+    }
+}
@@ -0,0 +1,118 @@
+use std::io::{self, Write};
+use crate::app_services::results::{ResultsFormat, XlsxWriter};
+
+/// Writes an export a row at a time, so it can be streamed out as it's read instead of being
+/// built up in memory.  `finish` must be called once the last row is in, some formats only
+/// become readable then.
+pub trait ResultsWriter {
+    fn write_header(&mut self, headers: &[String]) -> io::Result<()>;
+
+    fn write_row(&mut self, cells: &[Option<String>]) -> io::Result<()>;
+
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+
+/// A writer for `format` over `out`.
+pub fn results_writer<'a, W: Write + 'a>(format: ResultsFormat, out: W) -> Box<dyn ResultsWriter + 'a> {
+    match format {
+        ResultsFormat::Csv => Box::new(CsvWriter::new(out)),
+        ResultsFormat::Ndjson => Box::new(NdjsonWriter::new(out)),
+        ResultsFormat::Xlsx => Box::new(XlsxWriter::new(out)),
+    }
+}
+
+/// RFC 4180 csv, headers first.  Empty cells are empty fields.
+pub struct CsvWriter<W: Write> {
+    csv: csv::Writer<W>,
+}
+
+impl<W: Write> CsvWriter<W> {
+    pub fn new(out: W) -> CsvWriter<W> {
+        CsvWriter {
+            csv: csv::Writer::from_writer(out),
+        }
+    }
+}
+
+impl<W: Write> ResultsWriter for CsvWriter<W> {
+    fn write_header(&mut self, headers: &[String]) -> io::Result<()> {
+        self.csv.write_record(headers).map_err(io::Error::other)
+    }
+
+    fn write_row(&mut self, cells: &[Option<String>]) -> io::Result<()> {
+        self.csv.write_record(cells.iter().map(|c| c.as_ref().map(|c| c.as_str()).unwrap_or("")))
+            .map_err(io::Error::other)
+    }
+
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        self.csv.flush()
+    }
+}
+
+/// One json object per line, keyed by header in header order.  Empty cells are null.
+pub struct NdjsonWriter<W: Write> {
+    out: W,
+    headers: Vec<String>,
+}
+
+impl<W: Write> NdjsonWriter<W> {
+    pub fn new(out: W) -> NdjsonWriter<W> {
+        NdjsonWriter {
+            out,
+            headers: vec![],
+        }
+    }
+}
+
+impl<W: Write> ResultsWriter for NdjsonWriter<W> {
+    // There's no header line, the headers key every object instead.
+    fn write_header(&mut self, headers: &[String]) -> io::Result<()> {
+        self.headers = headers.iter()
+            .map(|h| serde_json::to_string(h).unwrap())
+            .collect();
+        Ok(())
+    }
+
+    // Written out by hand, rather than through a map, to keep the keys in order.
+    fn write_row(&mut self, cells: &[Option<String>]) -> io::Result<()> {
+        let fields = self.headers.iter()
+            .zip(cells)
+            .map(|(key, cell)| format!("{}:{}", key, serde_json::to_string(cell).unwrap()))
+            .collect::<Vec<_>>();
+        writeln!(self.out, "{{{}}}", fields.join(","))
+    }
+
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export(format: ResultsFormat) -> String {
+        let mut out = vec![];
+        {
+            let mut writer = results_writer(format, &mut out);
+            writer.write_header(&["Id".to_string(), "Best, \"album\"".to_string()]).unwrap();
+            writer.write_row(&[Some("1".to_string()), Some("Lux".to_string())]).unwrap();
+            writer.write_row(&[Some("2".to_string()), None]).unwrap();
+            writer.finish().unwrap();
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn csv_quotes_what_needs_it_and_leaves_empty_cells_empty() {
+        assert_eq!(export(ResultsFormat::Csv), "Id,\"Best, \"\"album\"\"\"\n1,Lux\n2,\n");
+    }
+
+    #[test]
+    fn ndjson_keys_each_line_by_header_in_order() {
+        assert_eq!(
+            export(ResultsFormat::Ndjson),
+            "{\"Id\":\"1\",\"Best, \\\"album\\\"\":\"Lux\"}\n{\"Id\":\"2\",\"Best, \\\"album\\\"\":null}\n",
+        );
+    }
+}
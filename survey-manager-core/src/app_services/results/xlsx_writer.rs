@@ -0,0 +1,344 @@
+use std::io::{self, Write};
+use flate2::Compression;
+use flate2::write::DeflateEncoder;
+use crate::app_services::results::ResultsWriter;
+
+// The parts of a workbook that don't depend on the data.  Cells are written as inline strings, so
+// there's no shared string table to hold back until the end.
+const CONTENT_TYPES: &str = concat!(
+    r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+    r#"<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">"#,
+    r#"<Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>"#,
+    r#"<Default Extension="xml" ContentType="application/xml"/>"#,
+    r#"<Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/>"#,
+    r#"<Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/>"#,
+    r#"</Types>"#,
+);
+
+const ROOT_RELS: &str = concat!(
+    r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+    r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#,
+    r#"<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/>"#,
+    r#"</Relationships>"#,
+);
+
+const WORKBOOK: &str = concat!(
+    r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+    r#"<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships">"#,
+    r#"<sheets><sheet name="Results" sheetId="1" r:id="rId1"/></sheets>"#,
+    r#"</workbook>"#,
+);
+
+const WORKBOOK_RELS: &str = concat!(
+    r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+    r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#,
+    r#"<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/>"#,
+    r#"</Relationships>"#,
+);
+
+const SHEET_START: &str = concat!(
+    r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+    r#"<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>"#,
+);
+
+const SHEET_END: &str = "</sheetData></worksheet>";
+
+/// A single sheet workbook, the header as its first row.  The sheet is compressed and written out
+/// as rows arrive, nothing but the zip's directory is held until `finish`.
+pub struct XlsxWriter<W: Write> {
+    zip: ZipStream<W>,
+    started: bool,
+}
+
+impl<W: Write> XlsxWriter<W> {
+    pub fn new(out: W) -> XlsxWriter<W> {
+        XlsxWriter {
+            zip: ZipStream::new(out),
+            started: false,
+        }
+    }
+
+    fn write_cells<'a>(&mut self, cells: impl Iterator<Item = Option<&'a str>>) -> io::Result<()> {
+        let mut row = String::from("<row>");
+        for cell in cells {
+            match cell {
+                Some(text) => {
+                    row.push_str(r#"<c t="inlineStr"><is><t xml:space="preserve">"#);
+                    push_escaped(&mut row, text);
+                    row.push_str("</t></is></c>");
+                },
+                None => row.push_str("<c/>"),
+            }
+        }
+        row.push_str("</row>");
+        self.zip.write_all(row.as_bytes())
+    }
+}
+
+impl<W: Write> ResultsWriter for XlsxWriter<W> {
+    fn write_header(&mut self, headers: &[String]) -> io::Result<()> {
+        for (name, part) in &[
+            ("[Content_Types].xml", CONTENT_TYPES),
+            ("_rels/.rels", ROOT_RELS),
+            ("xl/workbook.xml", WORKBOOK),
+            ("xl/_rels/workbook.xml.rels", WORKBOOK_RELS),
+        ] {
+            self.zip.start_entry(name)?;
+            self.zip.write_all(part.as_bytes())?;
+            self.zip.finish_entry()?;
+        }
+
+        self.zip.start_entry("xl/worksheets/sheet1.xml")?;
+        self.zip.write_all(SHEET_START.as_bytes())?;
+        self.started = true;
+        self.write_cells(headers.iter().map(|h| Some(h.as_str())))
+    }
+
+    fn write_row(&mut self, cells: &[Option<String>]) -> io::Result<()> {
+        self.write_cells(cells.iter().map(|c| c.as_ref().map(|c| c.as_str())))
+    }
+
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        if !self.started {
+            self.write_header(&[])?;
+        }
+        self.zip.write_all(SHEET_END.as_bytes())?;
+        self.zip.finish_entry()?;
+        self.zip.finish()
+    }
+}
+
+// Escapes xml's special characters, and drops control characters xml 1.0 can't hold at all.
+fn push_escaped(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\t' | '\n' | '\r' => out.push(c),
+            c if (c as u32) < 0x20 || c == '\u{fffe}' || c == '\u{ffff}' => {},
+            c => out.push(c),
+        }
+    }
+}
+
+// Local and central headers all carry the same fixed time, 1980-01-01 00:00 in dos format.  A
+// streamed export has no meaningful modification time to give its parts.
+const DOS_TIME: u16 = 0;
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+// Sizes and crc follow each entry's data in a descriptor (bit 3), and names are utf-8 (bit 11).
+const FLAGS: u16 = 0x0808;
+const DEFLATE: u16 = 8;
+const VERSION: u16 = 20;
+
+struct ZipEntry {
+    name: String,
+    crc: u32,
+    compressed: u64,
+    size: u64,
+    offset: u64,
+}
+
+struct OpenEntry {
+    entry: ZipEntry,
+    hasher: crc32fast::Hasher,
+    deflate: DeflateEncoder<Vec<u8>>,
+}
+
+// A zip archive written front to back without seeking, which is what lets it go straight onto
+// the wire.  There's no zip64, so no single part or the archive as a whole may reach 4GiB.
+struct ZipStream<W: Write> {
+    out: W,
+    written: u64,
+    entries: Vec<ZipEntry>,
+    open: Option<OpenEntry>,
+}
+
+impl<W: Write> ZipStream<W> {
+    fn new(out: W) -> ZipStream<W> {
+        ZipStream {
+            out,
+            written: 0,
+            entries: vec![],
+            open: None,
+        }
+    }
+
+    fn emit(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.out.write_all(bytes)?;
+        self.written += bytes.len() as u64;
+        Ok(())
+    }
+
+    fn start_entry(&mut self, name: &str) -> io::Result<()> {
+        let offset = self.written;
+        let mut header = vec![];
+        put_u32(&mut header, 0x0403_4b50);
+        put_u16(&mut header, VERSION);
+        put_u16(&mut header, FLAGS);
+        put_u16(&mut header, DEFLATE);
+        put_u16(&mut header, DOS_TIME);
+        put_u16(&mut header, DOS_DATE);
+        // Crc and sizes, left to the data descriptor.
+        put_u32(&mut header, 0);
+        put_u32(&mut header, 0);
+        put_u32(&mut header, 0);
+        put_u16(&mut header, name.len() as u16);
+        put_u16(&mut header, 0);
+        header.extend_from_slice(name.as_bytes());
+        self.emit(&header)?;
+
+        self.open = Some(OpenEntry {
+            entry: ZipEntry { name: name.to_string(), crc: 0, compressed: 0, size: 0, offset },
+            hasher: crc32fast::Hasher::new(),
+            deflate: DeflateEncoder::new(vec![], Compression::default()),
+        });
+        Ok(())
+    }
+
+    fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        let mut open = self.open.take().expect("an entry is open");
+        open.hasher.update(data);
+        open.entry.size += data.len() as u64;
+        open.deflate.write_all(data)?;
+
+        // Whatever the encoder has produced so far goes straight out.
+        let compressed = std::mem::take(open.deflate.get_mut());
+        open.entry.compressed += compressed.len() as u64;
+        self.open = Some(open);
+        self.emit(&compressed)
+    }
+
+    fn finish_entry(&mut self) -> io::Result<()> {
+        let OpenEntry { mut entry, hasher, deflate } = self.open.take().expect("an entry is open");
+        let rest = deflate.finish()?;
+        entry.compressed += rest.len() as u64;
+        entry.crc = hasher.finalize();
+        self.emit(&rest)?;
+
+        let mut descriptor = vec![];
+        put_u32(&mut descriptor, 0x0807_4b50);
+        put_u32(&mut descriptor, entry.crc);
+        put_u32(&mut descriptor, fits(entry.compressed)?);
+        put_u32(&mut descriptor, fits(entry.size)?);
+        self.emit(&descriptor)?;
+
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        let directory_offset = self.written;
+        let mut directory = vec![];
+        for entry in &self.entries {
+            put_u32(&mut directory, 0x0201_4b50);
+            put_u16(&mut directory, VERSION);
+            put_u16(&mut directory, VERSION);
+            put_u16(&mut directory, FLAGS);
+            put_u16(&mut directory, DEFLATE);
+            put_u16(&mut directory, DOS_TIME);
+            put_u16(&mut directory, DOS_DATE);
+            put_u32(&mut directory, entry.crc);
+            put_u32(&mut directory, fits(entry.compressed)?);
+            put_u32(&mut directory, fits(entry.size)?);
+            put_u16(&mut directory, entry.name.len() as u16);
+            // Extra field and comment lengths, disk number, internal and external attributes.
+            put_u16(&mut directory, 0);
+            put_u16(&mut directory, 0);
+            put_u16(&mut directory, 0);
+            put_u16(&mut directory, 0);
+            put_u32(&mut directory, 0);
+            put_u32(&mut directory, fits(entry.offset)?);
+            directory.extend_from_slice(entry.name.as_bytes());
+        }
+
+        let mut end = vec![];
+        put_u32(&mut end, 0x0605_4b50);
+        put_u16(&mut end, 0);
+        put_u16(&mut end, 0);
+        put_u16(&mut end, self.entries.len() as u16);
+        put_u16(&mut end, self.entries.len() as u16);
+        put_u32(&mut end, directory.len() as u32);
+        put_u32(&mut end, fits(directory_offset)?);
+        put_u16(&mut end, 0);
+
+        self.emit(&directory)?;
+        self.emit(&end)?;
+        self.out.flush()
+    }
+}
+
+fn fits(value: u64) -> io::Result<u32> {
+    if value > u32::MAX as u64 {
+        return Err(io::Error::other("export is too large for xlsx"));
+    }
+    Ok(value as u32)
+}
+
+fn put_u16(buf: &mut Vec<u8>, value: u16) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use flate2::read::DeflateDecoder;
+
+    fn u16_at(buf: &[u8], at: usize) -> usize {
+        u16::from_le_bytes([buf[at], buf[at + 1]]) as usize
+    }
+
+    fn u32_at(buf: &[u8], at: usize) -> usize {
+        u32::from_le_bytes([buf[at], buf[at + 1], buf[at + 2], buf[at + 3]]) as usize
+    }
+
+    // Reads every part back through the central directory, checking each one's crc.
+    fn unzip(archive: &[u8]) -> Vec<(String, String)> {
+        let end = archive.len() - 22;
+        assert_eq!(u32_at(archive, end), 0x0605_4b50);
+        let mut at = u32_at(archive, end + 16);
+        let mut parts = vec![];
+        for _ in 0..u16_at(archive, end + 10) {
+            assert_eq!(u32_at(archive, at), 0x0201_4b50);
+            let crc = u32_at(archive, at + 16) as u32;
+            let compressed = u32_at(archive, at + 20);
+            let name_len = u16_at(archive, at + 28);
+            let offset = u32_at(archive, at + 42);
+            let name = String::from_utf8(archive[at + 46..at + 46 + name_len].to_vec()).unwrap();
+
+            let data = offset + 30 + u16_at(archive, offset + 26);
+            let mut content = String::new();
+            DeflateDecoder::new(&archive[data..data + compressed]).read_to_string(&mut content).unwrap();
+            assert_eq!(crc32fast::hash(content.as_bytes()), crc, "crc of {}", name);
+
+            parts.push((name, content));
+            at += 46 + name_len;
+        }
+        parts
+    }
+
+    #[test]
+    fn writes_a_workbook_with_one_row_per_response() {
+        let mut out = vec![];
+        {
+            let mut writer: Box<dyn ResultsWriter> = Box::new(XlsxWriter::new(&mut out));
+            writer.write_header(&["Id".to_string(), "R&B <best>".to_string()]).unwrap();
+            writer.write_row(&[Some("1".to_string()), None]).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let parts = unzip(&out);
+        let names: Vec<&str> = parts.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["[Content_Types].xml", "_rels/.rels", "xl/workbook.xml", "xl/_rels/workbook.xml.rels", "xl/worksheets/sheet1.xml"]);
+        assert!(parts[4].1.contains(
+            r#"<row><c t="inlineStr"><is><t xml:space="preserve">Id</t></is></c><c t="inlineStr"><is><t xml:space="preserve">R&amp;B &lt;best&gt;</t></is></c></row><row><c t="inlineStr"><is><t xml:space="preserve">1</t></is></c><c/></row></sheetData>"#
+        ));
+    }
+}
@@ -0,0 +1,285 @@
+use snafu::Snafu;
+use std::path::PathBuf;
+
+/// Errors raised while loading token configuration or the key material it points at.  These only
+/// ever happen at startup, so they carry enough detail to fix the config without digging.
+#[derive(Debug, Snafu)]
+pub enum TokenConfigError {
+    #[snafu(display("could not read {}: {}", path.display(), source))]
+    UnreadableFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("token config file is not valid json: {}", source))]
+    MalformedConfig {
+        source: serde_json::Error,
+    },
+
+    #[snafu(display("environment variable {} must be set", name))]
+    MissingVariable {
+        name: String,
+    },
+
+    #[snafu(display("environment variable {} must be a whole number of seconds, got '{}'", name, value))]
+    InvalidSeconds {
+        name: String,
+        value: String,
+    },
+
+    #[snafu(display("key '{}' uses unsupported algorithm '{}', expected HS256, RS256 or ES256", kid, algorithm))]
+    UnsupportedAlgorithm {
+        kid: String,
+        algorithm: String,
+    },
+
+    #[snafu(display("key '{}' is missing {}", kid, what))]
+    MissingKeyMaterial {
+        kid: String,
+        what: String,
+    },
+
+    #[snafu(display("key '{}' could not be loaded: {}", kid, source))]
+    InvalidKey {
+        kid: String,
+        source: jsonwebtoken::errors::Error,
+    },
+
+    #[snafu(display("jwks url {} needs an http source, build the service with TokenService::with_jwks_source", url))]
+    JwksSourceRequired {
+        url: String,
+    },
+
+    #[snafu(display("could not set up fetching jwks from {}: {}", url, reason))]
+    JwksSourceUnavailable {
+        url: String,
+        reason: String,
+    },
+
+    #[snafu(display("no signing key or jwks configured, tokens could never be verified"))]
+    NoKeys,
+}
+
+/// Token settings, loaded either from the json file named by `TOKEN_CONFIG_FILE` or from
+/// individual `JWT_*` environment variables.
+///
+/// ```json
+/// {
+///   "issuer": "https://surveys.libellis.com",
+///   "audience": "survey-manager",
+///   "signingKey": { "kid": "2019-10", "algorithm": "RS256",
+///                   "privateKeyPath": "/etc/survey-manager/2019-10.key.pem",
+///                   "publicKeyPath": "/etc/survey-manager/2019-10.pub.pem" },
+///   "verificationKeys": [
+///     { "kid": "2019-07", "algorithm": "RS256", "publicKeyPath": "/etc/survey-manager/2019-07.pub.pem" }
+///   ]
+/// }
+/// ```
+///
+/// To rotate keys without downtime, add the new key to `verificationKeys` on every instance first,
+/// then promote it to `signingKey` and keep the old one in `verificationKeys` until every token
+/// it signed has expired.
+///
+/// When tokens come from an external identity provider, configure `jwks`.  `issuer` and `audience`
+/// must then match what the provider puts in its tokens.  Keys can stay configured alongside it,
+/// tokens carrying one of their `kid`s, such as those from `/auth/login`, are verified locally and
+/// everything else against the provider's keys.
+///
+/// ```json
+/// {
+///   "issuer": "https://idp.libellis.com/",
+///   "audience": "survey-manager",
+///   "jwks": { "url": "https://idp.libellis.com/.well-known/jwks.json", "usernameClaim": "preferred_username" }
+/// }
+/// ```
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenConfig {
+    pub issuer: String,
+    pub audience: String,
+    /// Lifetime of issued tokens in seconds.
+    #[serde(default = "default_ttl")]
+    pub ttl: i64,
+    /// Lifetime of refresh tokens in seconds.
+    #[serde(default = "default_refresh_ttl")]
+    pub refresh_ttl: i64,
+    /// Allowed clock skew in seconds when checking `exp` and `nbf`.
+    #[serde(default)]
+    pub leeway: u64,
+    /// Key new tokens are signed with.  It's also accepted for verification.  Without one this
+    /// service only verifies tokens and never issues them.
+    #[serde(default)]
+    pub signing_key: Option<KeyConfig>,
+    /// Other keys still accepted for verification, selected by the token's `kid` header.
+    #[serde(default)]
+    pub verification_keys: Vec<KeyConfig>,
+    /// Also accept tokens signed by an identity provider's published keys.
+    #[serde(default)]
+    pub jwks: Option<JwksConfig>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyConfig {
+    pub kid: String,
+    /// One of HS256, RS256 or ES256.
+    pub algorithm: String,
+    /// Shared secret, for HS256 keys.
+    pub secret: Option<String>,
+    /// PEM encoded private key, needed to sign with RS256 or ES256 keys.  ES256 keys must be PKCS#8.
+    pub private_key_path: Option<PathBuf>,
+    /// PEM encoded public key, needed to verify RS256 or ES256 keys.
+    pub public_key_path: Option<PathBuf>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JwksConfig {
+    /// Where the provider publishes its JWKS.
+    pub url: Option<String>,
+    /// A JWKS document on local disk, used instead of `url`.
+    pub path: Option<PathBuf>,
+    /// Claim holding the name surveys are owned by.  Providers differ on this, so it's configurable.
+    #[serde(default = "default_username_claim")]
+    pub username_claim: String,
+    /// Claim holding an array of site wide role names, such as `admin`.
+    #[serde(default = "default_roles_claim")]
+    pub roles_claim: String,
+    /// Seconds between routine refetches of the key set.
+    #[serde(default = "default_jwks_cache_ttl")]
+    pub cache_ttl: u64,
+    /// Minimum seconds between refetches triggered by an unknown `kid`.
+    #[serde(default = "default_jwks_min_refresh")]
+    pub min_refresh_interval: u64,
+}
+
+fn default_username_claim() -> String {
+    "sub".to_string()
+}
+
+fn default_roles_claim() -> String {
+    "roles".to_string()
+}
+
+fn default_jwks_cache_ttl() -> u64 {
+    3600
+}
+
+fn default_jwks_min_refresh() -> u64 {
+    60
+}
+
+// Matches the 30 minute lifetime tokens have always had.
+fn default_ttl() -> i64 {
+    1800
+}
+
+// 30 days, after which a user has to log in again.
+fn default_refresh_ttl() -> i64 {
+    2_592_000
+}
+
+impl TokenConfig {
+    /// Loads configuration from `TOKEN_CONFIG_FILE` if set, otherwise from `JWT_*` variables.
+    ///
+    /// The environment form describes a single key: `JWT_ALGORITHM` (default HS256), `JWT_KID`
+    /// (default "default"), `JWT_SECRET` or `JWT_PRIVATE_KEY_PATH` and `JWT_PUBLIC_KEY_PATH`, plus
+    /// `JWT_ISSUER`, `JWT_AUDIENCE` and optionally `JWT_TTL` and `JWT_REFRESH_TTL`.  Rotation needs the file form.
+    /// `JWT_JWKS_URL` or `JWT_JWKS_PATH`, with `JWT_USERNAME_CLAIM` and `JWT_ROLES_CLAIM`, switch to
+    /// provider tokens.
+    pub fn from_env() -> Result<TokenConfig, TokenConfigError> {
+        if let Ok(path) = std::env::var("TOKEN_CONFIG_FILE") {
+            return TokenConfig::from_file(path);
+        }
+
+        let secret = std::env::var("JWT_SECRET").ok();
+        let private_key_path = std::env::var("JWT_PRIVATE_KEY_PATH").ok().map(PathBuf::from);
+        let signing_key = if secret.is_some() || private_key_path.is_some() {
+            Some(KeyConfig {
+                kid: std::env::var("JWT_KID").unwrap_or("default".to_string()),
+                algorithm: std::env::var("JWT_ALGORITHM").unwrap_or("HS256".to_string()),
+                secret,
+                private_key_path,
+                public_key_path: std::env::var("JWT_PUBLIC_KEY_PATH").ok().map(PathBuf::from),
+            })
+        } else {
+            None
+        };
+
+        let jwks_url = std::env::var("JWT_JWKS_URL").ok();
+        let jwks_path = std::env::var("JWT_JWKS_PATH").ok().map(PathBuf::from);
+        let jwks = if jwks_url.is_some() || jwks_path.is_some() {
+            Some(JwksConfig {
+                url: jwks_url,
+                path: jwks_path,
+                username_claim: std::env::var("JWT_USERNAME_CLAIM").unwrap_or(default_username_claim()),
+                roles_claim: std::env::var("JWT_ROLES_CLAIM").unwrap_or(default_roles_claim()),
+                cache_ttl: default_jwks_cache_ttl(),
+                min_refresh_interval: default_jwks_min_refresh(),
+            })
+        } else {
+            None
+        };
+
+        Ok(TokenConfig {
+            issuer: required_var("JWT_ISSUER")?,
+            audience: required_var("JWT_AUDIENCE")?,
+            ttl: seconds_var("JWT_TTL")?.unwrap_or(default_ttl()),
+            refresh_ttl: seconds_var("JWT_REFRESH_TTL")?.unwrap_or(default_refresh_ttl()),
+            leeway: 0,
+            signing_key,
+            verification_keys: vec![],
+            jwks,
+        })
+    }
+
+    pub fn from_file(path: impl Into<PathBuf>) -> Result<TokenConfig, TokenConfigError> {
+        let path = path.into();
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| TokenConfigError::UnreadableFile { path, source: e })?;
+
+        serde_json::from_str(&contents)
+            .map_err(|e| TokenConfigError::MalformedConfig { source: e })
+    }
+}
+
+fn required_var(name: &str) -> Result<String, TokenConfigError> {
+    std::env::var(name)
+        .map_err(|_| TokenConfigError::MissingVariable { name: name.to_string() })
+}
+
+// Unset is fine, there's a default.  Set to something that isn't a number is a typo that would
+// otherwise quietly hand out tokens with the default lifetime.
+fn seconds_var(name: &str) -> Result<Option<i64>, TokenConfigError> {
+    match std::env::var(name) {
+        Ok(value) => value.parse()
+            .map(Some)
+            .map_err(|_| TokenConfigError::InvalidSeconds { name: name.to_string(), value }),
+        Err(_) => Ok(None),
+    }
+}
+
+pub(crate) fn read_key_file(path: &PathBuf) -> Result<Vec<u8>, TokenConfigError> {
+    std::fs::read(path)
+        .map_err(|e| TokenConfigError::UnreadableFile { path: path.clone(), source: e })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_ttl_that_isnt_a_number_is_refused() {
+        std::env::set_var("JWT_ISSUER", "https://surveys.test");
+        std::env::set_var("JWT_AUDIENCE", "survey-manager");
+        std::env::set_var("JWT_TTL", "30m");
+
+        match TokenConfig::from_env() {
+            Err(TokenConfigError::InvalidSeconds { name, value }) => {
+                assert_eq!(name, "JWT_TTL");
+                assert_eq!(value, "30m");
+            },
+            _ => panic!("a malformed JWT_TTL should not fall back to the default"),
+        }
+    }
+}
@@ -0,0 +1,386 @@
+use jsonwebtoken::{decode, Algorithm, DecodingKey, TokenData, Validation};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use crate::app_services::token::{Payload, TokenRejection};
+
+/// Somewhere a JWKS document can be fetched from.  The service only needs the raw json, so an
+/// implementation is free to read it from disk, an http endpoint or anywhere else.
+pub trait JwksSource {
+    fn fetch(&self) -> Result<String, Box<dyn std::error::Error + Send + Sync>>;
+}
+
+/// Reads a JWKS document from a local file.  Mostly useful for tests and for air gapped installs
+/// where the identity provider's keys are copied over by hand.
+pub struct FileJwksSource {
+    path: PathBuf,
+}
+
+impl FileJwksSource {
+    pub fn new(path: impl Into<PathBuf>) -> FileJwksSource {
+        FileJwksSource {
+            path: path.into(),
+        }
+    }
+}
+
+impl JwksSource for FileJwksSource {
+    fn fetch(&self) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(std::fs::read_to_string(&self.path)?)
+    }
+}
+
+#[derive(Deserialize)]
+struct Jwks {
+    keys: Vec<Jwk>,
+}
+
+#[derive(Deserialize)]
+struct Jwk {
+    kid: Option<String>,
+    kty: String,
+    alg: Option<String>,
+    #[serde(rename = "use")]
+    key_use: Option<String>,
+    n: Option<String>,
+    e: Option<String>,
+}
+
+struct CachedKey {
+    algorithm: Option<Algorithm>,
+    key: DecodingKey<'static>,
+}
+
+struct CachedKeys {
+    keys: HashMap<String, CachedKey>,
+    fetched_at: Option<Instant>,
+}
+
+/// Verifies tokens issued by an external identity provider against its published JWKS.
+///
+/// Keys are cached and refetched every `cache_ttl`.  A token signed by a key we haven't seen yet
+/// triggers an early refetch, since that's what a rotation at the provider looks like, but never
+/// more than once per `min_refresh_interval` so a flood of bogus `kid`s can't hammer the provider.
+/// If a refetch fails the keys we already have stay in use.
+///
+/// Only RSA keys are supported for now.  Other key types in the document are skipped.
+pub struct JwksVerifier {
+    source: Box<dyn JwksSource + Send + Sync>,
+    cache_ttl: Duration,
+    min_refresh_interval: Duration,
+    username_claim: String,
+    roles_claim: String,
+    cache: RwLock<CachedKeys>,
+}
+
+impl JwksVerifier {
+    pub fn new(
+        source: Box<dyn JwksSource + Send + Sync>,
+        cache_ttl: Duration,
+        min_refresh_interval: Duration,
+        username_claim: String,
+        roles_claim: String,
+    ) -> JwksVerifier {
+        JwksVerifier {
+            source,
+            cache_ttl,
+            min_refresh_interval,
+            username_claim,
+            roles_claim,
+            cache: RwLock::new(CachedKeys { keys: HashMap::new(), fetched_at: None }),
+        }
+    }
+
+    /// Checks the signature, `exp`, `nbf`, `iss` and `aud` of `token`, then maps its claims onto a
+    /// `Payload`.  The configured username claim becomes `Payload.username` and `iss|sub` becomes
+    /// `Payload.user_id`.  Site wide roles come from the configured roles claim, if present.
+    ///
+    /// `sub` is only unique within its issuer, and refresh tokens, logouts and responses are keyed
+    /// by user id, so it's prefixed to keep a provider subject from passing for a local account.
+    pub fn verify(&self, token: &str, issuer: &str, audience: &str, leeway: u64) -> Result<Payload, TokenRejection> {
+        let header = jsonwebtoken::decode_header(token)?;
+        let header_alg = header.alg;
+        let kid = header.kid.ok_or(TokenRejection::UnknownKey)?;
+
+        let token_data = self.with_key(&kid, |cached| {
+            // A key that names its algorithm only verifies that algorithm.  Otherwise trust the header
+            // as far as it stays within the RSA family, which is all we load.
+            let algorithm = cached.algorithm.unwrap_or(header_alg);
+            if !is_rsa(algorithm) || algorithm != header_alg {
+                return Err(TokenRejection::BadSignature);
+            }
+
+            let mut validation = Validation::new(algorithm);
+            validation.leeway = leeway;
+            validation.iss = Some(issuer.to_string());
+            validation.set_audience(&[audience]);
+
+            Ok(decode::<Map<String, Value>>(token, &cached.key, &validation)?)
+        })?;
+
+        self.payload_from(token_data, audience, leeway)
+    }
+
+    fn payload_from(&self, token_data: TokenData<Map<String, Value>>, audience: &str, leeway: u64) -> Result<Payload, TokenRejection> {
+        let claims = token_data.claims;
+
+        // `nbf` is optional for provider tokens, so it's only checked when present.
+        if let Some(nbf) = claims.get("nbf").and_then(Value::as_i64) {
+            if nbf > chrono::Utc::now().timestamp() + leeway as i64 {
+                return Err(TokenRejection::NotYetValid);
+            }
+        }
+
+        let string_claim = |name: &str| {
+            claims.get(name)
+                .and_then(Value::as_str)
+                .map(|s| s.to_string())
+                .ok_or(TokenRejection::Malformed)
+        };
+
+        let issuer = string_claim("iss")?;
+
+        Ok(Payload {
+            username: string_claim(&self.username_claim)?,
+            user_id: format!("{}|{}", issuer, string_claim("sub")?),
+            exp: claims.get("exp").and_then(Value::as_i64).ok_or(TokenRejection::Malformed)?,
+            iss: Some(issuer),
+            // The provider may list several audiences.  We've checked ours is among them.
+            aud: Some(audience.to_string()),
+            jti: claims.get("jti").and_then(Value::as_str).map(|s| s.to_string()),
+            // Provider tokens don't know about our organizations.
+            org: None,
+            // Anything in the array that isn't a string is ignored rather than failing the token.
+            roles: claims.get(&self.roles_claim)
+                .and_then(Value::as_array)
+                .map(|roles| roles.iter().filter_map(Value::as_str).map(|r| r.to_string()).collect())
+                .unwrap_or_default(),
+        })
+    }
+
+    fn with_key<F, T>(&self, kid: &str, f: F) -> Result<T, TokenRejection>
+        where F: FnOnce(&CachedKey) -> Result<T, TokenRejection>
+    {
+        let stale = self.needs_refresh(&self.cache.read().unwrap(), kid);
+        if stale {
+            self.refresh(kid);
+        }
+
+        let cache = self.cache.read().unwrap();
+        let cached = cache.keys.get(kid).ok_or(TokenRejection::UnknownKey)?;
+        f(cached)
+    }
+
+    fn needs_refresh(&self, cache: &CachedKeys, kid: &str) -> bool {
+        match cache.fetched_at {
+            None => true,
+            Some(at) => at.elapsed() > self.cache_ttl
+                || (!cache.keys.contains_key(kid) && at.elapsed() > self.min_refresh_interval),
+        }
+    }
+
+    // The fetch happens without holding the lock, so a slow provider only holds up the requests
+    // that actually need new keys.  Requests that arrive meanwhile see the attempt recorded and
+    // carry on with the keys already cached.
+    fn refresh(&self, kid: &str) {
+        {
+            let mut cache = self.cache.write().unwrap();
+            // Someone else may have refreshed while we waited for the lock.
+            if !self.needs_refresh(&cache, kid) {
+                return;
+            }
+            // Record the attempt even if it fails, so a provider outage is retried at the normal pace.
+            cache.fetched_at = Some(Instant::now());
+        }
+
+        let keys = match self.source.fetch() {
+            Ok(document) => parse_jwks(&document),
+            Err(_) => None,
+        };
+
+        if let Some(keys) = keys {
+            self.cache.write().unwrap().keys = keys;
+        }
+    }
+}
+
+fn parse_jwks(document: &str) -> Option<HashMap<String, CachedKey>> {
+    let jwks: Jwks = serde_json::from_str(document).ok()?;
+
+    let keys = jwks.keys
+        .into_iter()
+        .filter(|jwk| jwk.kty == "RSA" && jwk.key_use.as_ref().is_none_or(|u| u == "sig"))
+        .filter_map(|jwk| {
+            let algorithm = match &jwk.alg {
+                Some(alg) => Some(alg.parse::<Algorithm>().ok().filter(|a| is_rsa(*a))?),
+                None => None,
+            };
+            let key = DecodingKey::from_rsa_components(jwk.n.as_ref()?, jwk.e.as_ref()?).into_static();
+            Some((jwk.kid?, CachedKey { algorithm, key }))
+        })
+        .collect();
+
+    Some(keys)
+}
+
+fn is_rsa(algorithm: Algorithm) -> bool {
+    matches!(algorithm, Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const ISSUER: &str = "https://idp.test/";
+    const AUDIENCE: &str = "survey-manager";
+    const OLD_KID: &str = "provider-2019-07";
+    const NEW_KID: &str = "provider-2019-10";
+
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    fn fixture(name: &str) -> String {
+        let path = format!("{}/tests/fixtures/jwks/{}", env!("CARGO_MANIFEST_DIR"), name);
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    /// A JWKS document on disk that a test can republish, the way a provider rotates its keys.
+    struct Provider {
+        path: PathBuf,
+    }
+
+    impl Provider {
+        fn publishing(kids: &[&str]) -> Provider {
+            let path = std::env::temp_dir().join(format!(
+                "jwks-test-{}-{}.json",
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::SeqCst),
+            ));
+            let provider = Provider { path };
+            provider.publish(kids);
+            provider
+        }
+
+        fn publish(&self, kids: &[&str]) {
+            let keys: Vec<_> = kids.iter()
+                .map(|kid| fixture(&format!("{}.jwk.json", kid)))
+                .collect();
+            std::fs::write(&self.path, format!("{{\"keys\": [{}]}}", keys.join(","))).unwrap();
+        }
+
+        fn verifier(&self, min_refresh_interval: Duration) -> JwksVerifier {
+            JwksVerifier::new(
+                Box::new(FileJwksSource::new(self.path.clone())),
+                Duration::from_secs(3600),
+                min_refresh_interval,
+                "preferred_username".to_string(),
+                "roles".to_string(),
+            )
+        }
+    }
+
+    impl Drop for Provider {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    fn token_signed_by(kid: &str) -> String {
+        token_for_subject(kid, "provider-user-id")
+    }
+
+    fn token_for_subject(kid: &str, sub: &str) -> String {
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some(kid.to_string());
+        let claims = serde_json::json!({
+            "sub": sub,
+            "preferred_username": "test_user",
+            "iss": ISSUER,
+            "aud": AUDIENCE,
+            "exp": chrono::Utc::now().timestamp() + 60,
+            "roles": ["admin"],
+        });
+        let key = EncodingKey::from_rsa_pem(fixture(&format!("{}.key.pem", kid)).as_bytes()).unwrap();
+        encode(&header, &claims, &key).unwrap()
+    }
+
+    fn verify(verifier: &JwksVerifier, token: &str) -> Result<Payload, TokenRejection> {
+        verifier.verify(token, ISSUER, AUDIENCE, 0)
+    }
+
+    #[test]
+    fn tokens_signed_by_a_published_key_verify() {
+        let provider = Provider::publishing(&[OLD_KID]);
+        let verifier = provider.verifier(Duration::from_secs(60));
+
+        let payload = verify(&verifier, &token_signed_by(OLD_KID)).unwrap();
+
+        assert_eq!(payload.username, "test_user");
+        assert_eq!(payload.user_id, "https://idp.test/|provider-user-id");
+        assert!(payload.is_admin());
+    }
+
+    #[test]
+    fn provider_subjects_dont_collide_with_local_user_ids() {
+        let provider = Provider::publishing(&[OLD_KID]);
+        let verifier = provider.verifier(Duration::from_secs(60));
+        // Local accounts are keyed by uuid, a provider is free to hand out the same string as a `sub`.
+        let local_user_id = uuid::Uuid::new_v4().to_string();
+
+        let payload = verify(&verifier, &token_for_subject(OLD_KID, &local_user_id)).unwrap();
+
+        assert_ne!(payload.user_id, local_user_id);
+        assert_eq!(payload.user_id, format!("{}|{}", ISSUER, local_user_id));
+    }
+
+    #[test]
+    fn an_unknown_kid_refetches_the_key_set() {
+        let provider = Provider::publishing(&[OLD_KID]);
+        let verifier = provider.verifier(Duration::from_secs(0));
+        verify(&verifier, &token_signed_by(OLD_KID)).unwrap();
+
+        provider.publish(&[OLD_KID, NEW_KID]);
+
+        assert!(verify(&verifier, &token_signed_by(NEW_KID)).is_ok());
+    }
+
+    #[test]
+    fn unknown_kids_dont_refetch_more_often_than_allowed() {
+        let provider = Provider::publishing(&[OLD_KID]);
+        let verifier = provider.verifier(Duration::from_secs(3600));
+        verify(&verifier, &token_signed_by(OLD_KID)).unwrap();
+
+        provider.publish(&[OLD_KID, NEW_KID]);
+
+        assert_eq!(verify(&verifier, &token_signed_by(NEW_KID)).unwrap_err(), TokenRejection::UnknownKey);
+    }
+
+    #[test]
+    fn keys_rotated_out_by_the_provider_stop_verifying() {
+        let provider = Provider::publishing(&[OLD_KID]);
+        let verifier = provider.verifier(Duration::from_secs(0));
+        let old_token = token_signed_by(OLD_KID);
+        verify(&verifier, &old_token).unwrap();
+
+        provider.publish(&[NEW_KID]);
+
+        assert!(verify(&verifier, &token_signed_by(NEW_KID)).is_ok());
+        assert_eq!(verify(&verifier, &old_token).unwrap_err(), TokenRejection::UnknownKey);
+    }
+
+    #[test]
+    fn cached_keys_survive_a_failed_refetch() {
+        let provider = Provider::publishing(&[OLD_KID]);
+        let verifier = provider.verifier(Duration::from_secs(0));
+        let old_token = token_signed_by(OLD_KID);
+        verify(&verifier, &old_token).unwrap();
+
+        std::fs::write(&provider.path, "not a key set").unwrap();
+
+        assert!(verify(&verifier, &token_signed_by(NEW_KID)).is_err());
+        assert!(verify(&verifier, &old_token).is_ok());
+    }
+}
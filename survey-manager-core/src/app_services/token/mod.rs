@@ -0,0 +1,49 @@
+pub mod config;
+pub use config::*;
+
+pub mod service;
+pub use service::*;
+
+pub mod jwks;
+pub use jwks::*;
+
+pub mod rejection;
+pub use rejection::*;
+
+/// Claims carried by every access token this service issues or accepts.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Payload {
+    pub username: String,
+    pub user_id: String,
+    pub exp: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+    /// Unique id of the token, so it can be revoked before it expires.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    /// The organization the user logged in to, if any.  Org owned surveys are only reachable
+    /// through this claim.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org: Option<OrganizationClaim>,
+    /// Site wide roles, such as `admin`.  Nothing here grants access to a particular survey.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+}
+
+/// The role that unlocks moderation.  See `policy::authorize_moderation`.
+pub const ADMIN_ROLE: &str = "admin";
+
+impl Payload {
+    pub fn is_admin(&self) -> bool {
+        self.roles.iter().any(|r| r == ADMIN_ROLE)
+    }
+}
+
+/// Which organization a token acts for, and the holder's role in it when the token was issued.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OrganizationClaim {
+    pub id: String,
+    pub role: String,
+}
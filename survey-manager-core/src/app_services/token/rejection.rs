@@ -0,0 +1,34 @@
+use jsonwebtoken::errors::{Error as JwtError, ErrorKind};
+
+/// Why an access token was refused.  Kept apart from `crate::Error` so callers can tell a client
+/// whether refreshing will help, an expired token, or whether the token will never work.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TokenRejection {
+    /// Not a JWT at all, or missing a claim every token needs.
+    Malformed,
+    /// Signed by a key that's neither ours nor the identity provider's, or names no key at all.
+    UnknownKey,
+    /// The signature doesn't match the key the token names, or uses an algorithm that key doesn't.
+    BadSignature,
+    /// `iss` isn't the issuer this service is configured to trust.
+    WrongIssuer,
+    /// `aud` doesn't include this service.
+    WrongAudience,
+    /// `exp` has passed.
+    Expired,
+    /// `nbf` is still to come.
+    NotYetValid,
+}
+
+impl From<JwtError> for TokenRejection {
+    fn from(err: JwtError) -> Self {
+        match err.kind() {
+            ErrorKind::InvalidSignature | ErrorKind::InvalidAlgorithm => TokenRejection::BadSignature,
+            ErrorKind::ExpiredSignature => TokenRejection::Expired,
+            ErrorKind::ImmatureSignature => TokenRejection::NotYetValid,
+            ErrorKind::InvalidIssuer => TokenRejection::WrongIssuer,
+            ErrorKind::InvalidAudience => TokenRejection::WrongAudience,
+            _ => TokenRejection::Malformed,
+        }
+    }
+}
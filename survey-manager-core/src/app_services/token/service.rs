@@ -0,0 +1,403 @@
+use jsonwebtoken::{encode, decode, decode_header, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use chrono::Utc;
+use uuid::Uuid;
+use std::collections::HashMap;
+use std::time::Duration;
+use crate::Error;
+use crate::app_services::token::{
+    Payload, OrganizationClaim, TokenConfig, TokenConfigError, TokenRejection, KeyConfig,
+    read_key_file, JwksSource, FileJwksSource, JwksVerifier,
+};
+
+/// Issues and verifies access tokens.  Built once at startup from a `TokenConfig`.
+///
+/// Tokens are signed with a single active key and carry its id in the `kid` header.  Verification
+/// accepts any configured key, picked by that `kid`, which is what allows keys to be rotated
+/// without invalidating tokens already handed out.  When a JWKS is configured, tokens whose `kid`
+/// isn't one of ours are verified against the identity provider's keys, so sessions from
+/// `/auth/login` and from the provider both work.
+pub struct TokenService {
+    issuer: String,
+    audience: String,
+    ttl: i64,
+    refresh_ttl: i64,
+    leeway: u64,
+    signing_key: Option<SigningKey>,
+    verification_keys: HashMap<String, VerificationKey>,
+    jwks: Option<JwksVerifier>,
+}
+
+struct SigningKey {
+    kid: String,
+    algorithm: Algorithm,
+    key: EncodingKey,
+}
+
+struct VerificationKey {
+    algorithm: Algorithm,
+    key: DecodingKey<'static>,
+}
+
+impl TokenService {
+    /// Builds the service from `config`.  A JWKS `url` needs an http client, which lives outside
+    /// this crate, so use `with_jwks_source` for those.
+    pub fn new(config: TokenConfig) -> Result<TokenService, TokenConfigError> {
+        let source: Option<Box<dyn JwksSource + Send + Sync>> = match &config.jwks {
+            Some(jwks) => match (&jwks.path, &jwks.url) {
+                (Some(path), _) => Some(Box::new(FileJwksSource::new(path.clone()))),
+                (None, Some(url)) => return Err(TokenConfigError::JwksSourceRequired { url: url.clone() }),
+                (None, None) => None,
+            },
+            None => None,
+        };
+
+        TokenService::build(config, source)
+    }
+
+    /// Builds a service that verifies against the JWKS served by `source`.  The `jwks` section of
+    /// `config` still supplies the username claim and refresh intervals.
+    pub fn with_jwks_source(
+        config: TokenConfig,
+        source: Box<dyn JwksSource + Send + Sync>,
+    ) -> Result<TokenService, TokenConfigError> {
+        TokenService::build(config, Some(source))
+    }
+
+    fn build(
+        config: TokenConfig,
+        source: Option<Box<dyn JwksSource + Send + Sync>>,
+    ) -> Result<TokenService, TokenConfigError> {
+        let signing_key = match &config.signing_key {
+            Some(key_config) => {
+                let (algorithm, key) = load_signing_key(key_config)?;
+                Some(SigningKey { kid: key_config.kid.clone(), algorithm, key })
+            },
+            None => None,
+        };
+
+        let mut verification_keys = HashMap::new();
+        for key_config in config.signing_key.iter().chain(config.verification_keys.iter()) {
+            verification_keys.insert(key_config.kid.clone(), load_verification_key(key_config)?);
+        }
+
+        let jwks = match source {
+            Some(source) => {
+                let (username_claim, roles_claim, cache_ttl, min_refresh_interval) = match &config.jwks {
+                    Some(jwks) => (jwks.username_claim.clone(), jwks.roles_claim.clone(), jwks.cache_ttl, jwks.min_refresh_interval),
+                    None => ("sub".to_string(), "roles".to_string(), 3600, 60),
+                };
+                Some(JwksVerifier::new(
+                    source,
+                    Duration::from_secs(cache_ttl),
+                    Duration::from_secs(min_refresh_interval),
+                    username_claim,
+                    roles_claim,
+                ))
+            },
+            None => None,
+        };
+
+        if verification_keys.is_empty() && jwks.is_none() {
+            return Err(TokenConfigError::NoKeys);
+        }
+
+        Ok(TokenService {
+            issuer: config.issuer,
+            audience: config.audience,
+            ttl: config.ttl,
+            refresh_ttl: config.refresh_ttl,
+            leeway: config.leeway,
+            signing_key,
+            verification_keys,
+            jwks,
+        })
+    }
+
+    /// Convenience for `TokenService::new(TokenConfig::from_env()?)`.
+    pub fn from_env() -> Result<TokenService, TokenConfigError> {
+        TokenService::new(TokenConfig::from_env()?)
+    }
+
+    /// Signs a new token for the user.  Fails when no signing key is configured, which is the
+    /// normal setup when an identity provider issues tokens instead.
+    pub fn create_token(&self, username: String, user_id: String) -> Result<String, Error> {
+        self.create_session_token(username, user_id, None, vec![])
+    }
+
+    /// Like `create_token`, but acting for an organization and carrying site wide `roles`.  The
+    /// caller must already have checked the user belongs to the organization with that role, and
+    /// holds the roles.
+    pub fn create_session_token(&self, username: String, user_id: String, org: Option<OrganizationClaim>, roles: Vec<String>) -> Result<String, Error> {
+        let signing_key = self.signing_key.as_ref()
+            .ok_or(Error::UnknownFailure)?;
+
+        let new_payload = Payload {
+            username,
+            user_id,
+            exp: Utc::now().timestamp() + self.ttl,
+            iss: Some(self.issuer.clone()),
+            aud: Some(self.audience.clone()),
+            jti: Some(Uuid::new_v4().to_string()),
+            org,
+            roles,
+        };
+
+        let mut header = Header::new(signing_key.algorithm);
+        header.kid = Some(signing_key.kid.clone());
+
+        // Keys are checked when the service is built, so this only fails if something is badly wrong.
+        encode(&header, &new_payload, &signing_key.key)
+            .map_err(|_| Error::UnknownFailure)
+    }
+
+    /// Lifetime of access tokens in seconds.
+    pub fn ttl(&self) -> i64 {
+        self.ttl
+    }
+
+    /// Lifetime of refresh tokens in seconds.
+    pub fn refresh_ttl(&self) -> i64 {
+        self.refresh_ttl
+    }
+
+    /// Verifies the signature, expiry, issuer and audience of `token` and returns its claims, or
+    /// the first check it failed.
+    pub fn decode_payload(&self, token: &str) -> Result<Payload, TokenRejection> {
+        let header = decode_header(token)?;
+
+        // Tokens from before keys had ids are checked against the current signing key.
+        let kid = match (header.kid, &self.signing_key) {
+            (Some(kid), _) => Some(kid),
+            (None, Some(signing_key)) => Some(signing_key.kid.clone()),
+            (None, None) => None,
+        };
+
+        // Our own keys win a `kid` clash with the provider, since we know exactly what they are.
+        let verification_key = match kid.and_then(|kid| self.verification_keys.get(&kid)) {
+            Some(verification_key) => verification_key,
+            None => return match &self.jwks {
+                Some(jwks) => jwks.verify(token, &self.issuer, &self.audience, self.leeway),
+                None => Err(TokenRejection::UnknownKey),
+            },
+        };
+
+        let mut validation = Validation::new(verification_key.algorithm);
+        validation.leeway = self.leeway;
+        validation.iss = Some(self.issuer.clone());
+        validation.set_audience(&[&self.audience]);
+
+        let token_data = decode::<Payload>(token, &verification_key.key, &validation)?;
+        Ok(token_data.claims)
+    }
+}
+
+fn parse_algorithm(key_config: &KeyConfig) -> Result<Algorithm, TokenConfigError> {
+    match key_config.algorithm.as_ref() {
+        "HS256" => Ok(Algorithm::HS256),
+        "RS256" => Ok(Algorithm::RS256),
+        "ES256" => Ok(Algorithm::ES256),
+        other => Err(TokenConfigError::UnsupportedAlgorithm {
+            kid: key_config.kid.clone(),
+            algorithm: other.to_string(),
+        }),
+    }
+}
+
+fn load_signing_key(key_config: &KeyConfig) -> Result<(Algorithm, EncodingKey), TokenConfigError> {
+    let algorithm = parse_algorithm(key_config)?;
+    let invalid = |e| TokenConfigError::InvalidKey { kid: key_config.kid.clone(), source: e };
+
+    let key = match algorithm {
+        Algorithm::HS256 => EncodingKey::from_secret(secret(key_config)?.as_bytes()),
+        Algorithm::RS256 => EncodingKey::from_rsa_pem(&private_pem(key_config)?).map_err(invalid)?,
+        Algorithm::ES256 => EncodingKey::from_ec_pem(&private_pem(key_config)?).map_err(invalid)?,
+        _ => unreachable!("parse_algorithm only accepts HS256, RS256 and ES256"),
+    };
+
+    Ok((algorithm, key))
+}
+
+fn load_verification_key(key_config: &KeyConfig) -> Result<VerificationKey, TokenConfigError> {
+    let algorithm = parse_algorithm(key_config)?;
+    let invalid = |e| TokenConfigError::InvalidKey { kid: key_config.kid.clone(), source: e };
+
+    let key = match algorithm {
+        Algorithm::HS256 => DecodingKey::from_secret(secret(key_config)?.as_bytes()).into_static(),
+        Algorithm::RS256 => DecodingKey::from_rsa_pem(&public_pem(key_config)?).map_err(invalid)?.into_static(),
+        Algorithm::ES256 => DecodingKey::from_ec_pem(&public_pem(key_config)?).map_err(invalid)?.into_static(),
+        _ => unreachable!("parse_algorithm only accepts HS256, RS256 and ES256"),
+    };
+
+    Ok(VerificationKey { algorithm, key })
+}
+
+fn secret(key_config: &KeyConfig) -> Result<&String, TokenConfigError> {
+    key_config.secret.as_ref()
+        .ok_or(TokenConfigError::MissingKeyMaterial { kid: key_config.kid.clone(), what: "secret".to_string() })
+}
+
+fn private_pem(key_config: &KeyConfig) -> Result<Vec<u8>, TokenConfigError> {
+    let path = key_config.private_key_path.as_ref()
+        .ok_or(TokenConfigError::MissingKeyMaterial { kid: key_config.kid.clone(), what: "privateKeyPath".to_string() })?;
+    read_key_file(path)
+}
+
+fn public_pem(key_config: &KeyConfig) -> Result<Vec<u8>, TokenConfigError> {
+    let path = key_config.public_key_path.as_ref()
+        .ok_or(TokenConfigError::MissingKeyMaterial { kid: key_config.kid.clone(), what: "publicKeyPath".to_string() })?;
+    read_key_file(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app_services::token::JwksConfig;
+
+    fn hs256_key(kid: &str) -> KeyConfig {
+        KeyConfig {
+            kid: kid.to_string(),
+            algorithm: "HS256".to_string(),
+            secret: Some(format!("secret for {}", kid)),
+            private_key_path: None,
+            public_key_path: None,
+        }
+    }
+
+    fn config(signing_kid: &str, verification_kids: &[&str]) -> TokenConfig {
+        TokenConfig {
+            issuer: "https://surveys.test".to_string(),
+            audience: "survey-manager".to_string(),
+            ttl: 60,
+            refresh_ttl: 600,
+            leeway: 0,
+            signing_key: Some(hs256_key(signing_kid)),
+            verification_keys: verification_kids.iter().map(|kid| hs256_key(kid)).collect(),
+            jwks: None,
+        }
+    }
+
+    fn issue(service: &TokenService) -> String {
+        service.create_token("test_user".to_string(), "test_user_id".to_string()).unwrap()
+    }
+
+    #[test]
+    fn tokens_are_signed_with_the_active_key() {
+        let service = TokenService::new(config("2019-10", &["2019-07"])).unwrap();
+
+        let header = decode_header(&issue(&service)).unwrap();
+
+        assert_eq!(header.kid, Some("2019-10".to_string()));
+    }
+
+    #[test]
+    fn tokens_signed_by_a_rotated_out_key_still_verify() {
+        let before = TokenService::new(config("2019-07", &[])).unwrap();
+        let after = TokenService::new(config("2019-10", &["2019-07"])).unwrap();
+        let old_token = issue(&before);
+
+        let payload = after.decode_payload(&old_token).unwrap();
+
+        assert_eq!(payload.username, "test_user");
+        assert!(after.decode_payload(&issue(&after)).is_ok());
+    }
+
+    #[test]
+    fn tokens_signed_by_a_retired_key_are_rejected() {
+        let before = TokenService::new(config("2019-07", &[])).unwrap();
+        let after = TokenService::new(config("2019-10", &[])).unwrap();
+
+        assert_eq!(after.decode_payload(&issue(&before)).unwrap_err(), TokenRejection::UnknownKey);
+    }
+
+    #[test]
+    fn a_known_kid_with_the_wrong_key_is_rejected() {
+        let mut forged = config("2019-10", &[]);
+        forged.signing_key.as_mut().unwrap().secret = Some("guessed secret".to_string());
+        let forger = TokenService::new(forged).unwrap();
+        let service = TokenService::new(config("2019-10", &[])).unwrap();
+
+        assert_eq!(service.decode_payload(&issue(&forger)).unwrap_err(), TokenRejection::BadSignature);
+    }
+
+    #[test]
+    fn tokens_for_another_issuer_or_audience_are_rejected() {
+        let service = TokenService::new(config("2019-10", &[])).unwrap();
+
+        let mut other_issuer = config("2019-10", &[]);
+        other_issuer.issuer = "https://elsewhere.test".to_string();
+        let mut other_audience = config("2019-10", &[]);
+        other_audience.audience = "some-other-service".to_string();
+
+        let from_other_issuer = issue(&TokenService::new(other_issuer).unwrap());
+        let for_other_audience = issue(&TokenService::new(other_audience).unwrap());
+
+        assert_eq!(service.decode_payload(&from_other_issuer).unwrap_err(), TokenRejection::WrongIssuer);
+        assert_eq!(service.decode_payload(&for_other_audience).unwrap_err(), TokenRejection::WrongAudience);
+    }
+
+    #[test]
+    fn expired_tokens_are_rejected_as_expired() {
+        let mut already_expired = config("2019-10", &[]);
+        already_expired.ttl = -60;
+        let service = TokenService::new(already_expired).unwrap();
+
+        assert_eq!(service.decode_payload(&issue(&service)).unwrap_err(), TokenRejection::Expired);
+    }
+
+    #[test]
+    fn tokens_that_arent_jwts_are_rejected_as_malformed() {
+        let service = TokenService::new(config("2019-10", &[])).unwrap();
+
+        for garbage in ["", "not-a-token", "a.b.c"] {
+            assert_eq!(service.decode_payload(garbage).unwrap_err(), TokenRejection::Malformed, "{}", garbage);
+        }
+    }
+
+    #[test]
+    fn a_service_without_keys_is_refused() {
+        let mut no_keys = config("unused", &[]);
+        no_keys.signing_key = None;
+
+        match TokenService::new(no_keys) {
+            Err(TokenConfigError::NoKeys) => (),
+            _ => panic!("a service that can't verify anything should not build"),
+        }
+    }
+
+    #[test]
+    fn with_a_jwks_our_own_tokens_still_verify() {
+        let fixtures = format!("{}/tests/fixtures/jwks", env!("CARGO_MANIFEST_DIR"));
+        let jwks_path = std::env::temp_dir().join(format!("jwks-service-test-{}.json", std::process::id()));
+        let jwk = std::fs::read_to_string(format!("{}/provider-2019-10.jwk.json", fixtures)).unwrap();
+        std::fs::write(&jwks_path, format!("{{\"keys\": [{}]}}", jwk)).unwrap();
+
+        let mut with_jwks = config("2019-10", &[]);
+        with_jwks.jwks = Some(JwksConfig {
+            url: None,
+            path: Some(jwks_path.clone()),
+            username_claim: "sub".to_string(),
+            roles_claim: "roles".to_string(),
+            cache_ttl: 3600,
+            min_refresh_interval: 60,
+        });
+        let service = TokenService::new(with_jwks).unwrap();
+
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some("provider-2019-10".to_string());
+        let claims = serde_json::json!({
+            "sub": "provider_user",
+            "iss": "https://surveys.test",
+            "aud": "survey-manager",
+            "exp": Utc::now().timestamp() + 60,
+        });
+        let private_pem = std::fs::read(format!("{}/provider-2019-10.key.pem", fixtures)).unwrap();
+        let provider_token = encode(&header, &claims, &EncodingKey::from_rsa_pem(&private_pem).unwrap()).unwrap();
+
+        let ours = service.decode_payload(&issue(&service));
+        let theirs = service.decode_payload(&provider_token);
+        let _ = std::fs::remove_file(&jwks_path);
+
+        assert_eq!(ours.unwrap().username, "test_user");
+        assert_eq!(theirs.unwrap().username, "provider_user");
+    }
+}
@@ -1,2 +1,11 @@
 pub mod survey_dto;
-pub use survey_dto::*;
\ No newline at end of file
+pub use survey_dto::*;
+
+pub mod survey_definition;
+pub use survey_definition::*;
+
+pub mod user_dto;
+pub use user_dto::*;
+
+pub mod organization_dto;
+pub use organization_dto::*;
@@ -0,0 +1,41 @@
+use serde::{Serialize, Deserialize};
+use std::convert::From;
+use domain_patterns::models::{Entity, AggregateRoot};
+use crate::organization::{Organization, Member};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrganizationDTO {
+    pub id: String,
+    pub version: u64,
+    pub name: String,
+    pub members: Vec<MemberDTO>,
+    pub created_on: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MemberDTO {
+    pub username: String,
+    pub role: String,
+}
+
+impl From<&Member> for MemberDTO {
+    fn from(m: &Member) -> Self {
+        MemberDTO {
+            username: m.username().to_string(),
+            role: m.role().to_string(),
+        }
+    }
+}
+
+impl From<&Organization> for OrganizationDTO {
+    fn from(o: &Organization) -> Self {
+        OrganizationDTO {
+            id: o.id().to_string(),
+            version: o.version(),
+            name: o.name().to_string(),
+            members: o.members().iter().map(MemberDTO::from).collect(),
+            created_on: *o.created_on(),
+        }
+    }
+}
@@ -0,0 +1,250 @@
+//! Portable survey definition documents.
+//!
+//! A `SurveyDefinition` is everything needed to recreate a survey in another environment, and
+//! nothing that is tied to the environment it came from.  It is derived from `SurveyDTO` but drops
+//! ids, author, version and creation time, so the same document can be imported any number of
+//! times and checked into source control without churn.
+//!
+//! Documents can be written as JSON or YAML and always carry a `schemaVersion` so the format can
+//! evolve.  The JSON schema for version 1 lives in `schema/survey-definition.v1.json`.
+//!
+//! ```yaml
+//! schemaVersion: 1
+//! title: Favorite albums of 2019
+//! description: Rank the albums that defined the year for you.
+//! category: music
+//! questions:
+//!   - type: ranked
+//!     title: Best album overall
+//!     choices:
+//!       - contentType: text
+//!         title: Father of the Bride
+//! ```
+use serde::{Serialize, Deserialize};
+use std::str::FromStr;
+use crate::dtos::{SurveyDTO, QuestionDTO, ChoiceDTO};
+use crate::app_services::commands::{CreateSurveyCommand, CreateQuestionCommand, CreateChoiceCommand};
+use crate::errors::{Error, Result};
+
+/// The definition schema version this build reads and writes.
+pub const SURVEY_DEFINITION_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SurveyDefinition {
+    pub schema_version: u32,
+    pub title: String,
+    pub description: String,
+    pub category: String,
+    pub questions: Vec<QuestionDefinition>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct QuestionDefinition {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub title: String,
+    pub choices: Vec<ChoiceDefinition>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ChoiceDefinition {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    pub content_type: String,
+    pub title: String,
+}
+
+/// Serialization formats a `SurveyDefinition` can be read from and written to.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum DefinitionFormat {
+    #[default]
+    Json,
+    Yaml,
+}
+
+impl DefinitionFormat {
+    /// Media type to advertise when serving a document in this format.
+    pub fn media_type(&self) -> &'static str {
+        match self {
+            DefinitionFormat::Json => "application/json",
+            DefinitionFormat::Yaml => "application/x-yaml",
+        }
+    }
+}
+
+impl FromStr for DefinitionFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_ref() {
+            "json" => Ok(DefinitionFormat::Json),
+            "yaml" | "yml" => Ok(DefinitionFormat::Yaml),
+            other => Err(Error::InvalidDocument {
+                msg: format!("unsupported definition format '{}', expected json or yaml", other),
+            }),
+        }
+    }
+}
+
+impl SurveyDefinition {
+    /// Parses a document in the given format.  Only structural problems are reported here, the
+    /// values themselves are validated by `Survey::new` when the definition is imported.
+    pub fn parse(document: &str, format: DefinitionFormat) -> Result<SurveyDefinition> {
+        let definition: SurveyDefinition = match format {
+            DefinitionFormat::Json => serde_json::from_str(document)
+                .map_err(|e| Error::InvalidDocument { msg: e.to_string() })?,
+            DefinitionFormat::Yaml => serde_yaml::from_str(document)
+                .map_err(|e| Error::InvalidDocument { msg: e.to_string() })?,
+        };
+
+        if definition.schema_version != SURVEY_DEFINITION_VERSION {
+            return Err(Error::InvalidDocument {
+                msg: format!(
+                    "unsupported schemaVersion {}, this service reads version {}",
+                    definition.schema_version,
+                    SURVEY_DEFINITION_VERSION,
+                ),
+            });
+        }
+
+        Ok(definition)
+    }
+
+    /// Turns the definition into a regular create command owned by `author`.
+    pub fn into_create_command(self, author: String) -> CreateSurveyCommand {
+        let questions = self.questions
+            .into_iter()
+            .map(|q| {
+                CreateQuestionCommand {
+                    question_type: q.kind,
+                    title: q.title,
+                    choices: q.choices
+                        .into_iter()
+                        .map(|c| {
+                            CreateChoiceCommand {
+                                content: c.content,
+                                content_type: c.content_type,
+                                title: c.title,
+                            }
+                        }).collect(),
+                }
+            }).collect();
+
+        CreateSurveyCommand {
+            author,
+            title: self.title,
+            description: self.description,
+            category: self.category,
+            questions,
+        }
+    }
+
+    pub fn to_document(&self, format: DefinitionFormat) -> Result<String> {
+        match format {
+            DefinitionFormat::Json => serde_json::to_string_pretty(self)
+                .map_err(|e| Error::InvalidDocument { msg: e.to_string() }),
+            DefinitionFormat::Yaml => serde_yaml::to_string(self)
+                .map_err(|e| Error::InvalidDocument { msg: e.to_string() }),
+        }
+    }
+}
+
+impl From<SurveyDTO> for SurveyDefinition {
+    fn from(dto: SurveyDTO) -> Self {
+        SurveyDefinition {
+            schema_version: SURVEY_DEFINITION_VERSION,
+            title: dto.title,
+            description: dto.description,
+            category: dto.category,
+            questions: dto.questions.into_iter().map(QuestionDefinition::from).collect(),
+        }
+    }
+}
+
+impl From<QuestionDTO> for QuestionDefinition {
+    fn from(dto: QuestionDTO) -> Self {
+        QuestionDefinition {
+            kind: dto.kind,
+            title: dto.title,
+            choices: dto.choices.into_iter().map(ChoiceDefinition::from).collect(),
+        }
+    }
+}
+
+impl From<ChoiceDTO> for ChoiceDefinition {
+    fn from(dto: ChoiceDTO) -> Self {
+        ChoiceDefinition {
+            content: dto.content,
+            content_type: dto.content_type,
+            title: dto.title,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::survey::Survey;
+
+    const YAML: &str = "\
+schemaVersion: 1
+title: Favorite albums of 2019
+description: Rank the albums that defined the year for you.
+category: music
+questions:
+  - type: ranked
+    title: Best album overall
+    choices:
+      - contentType: text
+        title: Father of the Bride
+      - content: https://www.youtube.com/watch?v=5qap5aO4i9A
+        contentType: youtube
+        title: \"Norman F***ing Rockwell!\"
+  - type: multiple_choice
+    title: \"Which formats did you buy?\\nPick all that apply\"
+    choices:
+      - contentType: text
+        title: Vinyl records
+";
+
+    fn round_trip(definition: &SurveyDefinition, format: DefinitionFormat) -> SurveyDefinition {
+        let document = definition.to_document(format).unwrap();
+        SurveyDefinition::parse(&document, format).unwrap()
+    }
+
+    #[test]
+    fn yaml_documents_round_trip() {
+        let definition = SurveyDefinition::parse(YAML, DefinitionFormat::Yaml).unwrap();
+
+        assert_eq!(round_trip(&definition, DefinitionFormat::Yaml), definition);
+    }
+
+    #[test]
+    fn json_documents_round_trip() {
+        let definition = SurveyDefinition::parse(YAML, DefinitionFormat::Yaml).unwrap();
+        let json = definition.to_document(DefinitionFormat::Json).unwrap();
+        let from_json = SurveyDefinition::parse(&json, DefinitionFormat::Json).unwrap();
+
+        assert_eq!(from_json, definition);
+        assert_eq!(round_trip(&from_json, DefinitionFormat::Json), definition);
+    }
+
+    #[test]
+    fn exported_surveys_import_to_the_same_definition() {
+        let definition = SurveyDefinition::parse(YAML, DefinitionFormat::Yaml).unwrap();
+        let cmd = round_trip(&definition, DefinitionFormat::Json).into_create_command("importer".to_string());
+        let survey = Survey::new(&cmd).unwrap();
+
+        assert_eq!(SurveyDefinition::from(SurveyDTO::from(&survey)), definition);
+    }
+
+    #[test]
+    fn other_schema_versions_are_refused() {
+        let document = YAML.replace("schemaVersion: 1", "schemaVersion: 2");
+
+        assert!(SurveyDefinition::parse(&document, DefinitionFormat::Yaml).is_err());
+    }
+}
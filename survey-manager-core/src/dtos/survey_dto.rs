@@ -1,22 +1,28 @@
 use serde::{Serialize, Deserialize};
 use std::convert::From;
 use domain_patterns::models::{Entity, AggregateRoot};
-use crate::survey::{Choice, Survey, Question};
+use crate::survey::{Choice, Survey, Question, Collaborator};
+use crate::policy::{AccessControlled, Actor, survey_role};
+use crate::value_objects::{Role, ResponsePolicy, Publication};
+use std::convert::TryFrom;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct SurveyDTOs {
     pub surveys: Vec<ListViewSurveyDTO>
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct ListViewSurveyDTO {
     pub id: String,
     pub author: String,
     pub title: String,
     pub category: String,
+    /// The caller's role, for surveys listed because they were shared with the caller.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct SurveyDTO {
     pub id: String,
@@ -27,9 +33,69 @@ pub struct SurveyDTO {
     pub created_on: i64,
     pub category: String,
     pub questions: Vec<QuestionDTO>,
+    // Defaulted so surveys stored before sharing existed still load.
+    #[serde(default)]
+    pub collaborators: Vec<CollaboratorDTO>,
+    // Defaulted for the same reason, surveys stored before organizations existed are personal.
+    #[serde(default)]
+    pub organization_id: Option<String>,
+    // Surveys stored before response policies existed were open to anyone.
+    #[serde(default = "default_response_policy")]
+    pub response_policy: String,
+    // And surveys stored before publication existed were taking responses.
+    #[serde(default = "default_publication")]
+    pub publication: String,
 }
 
-#[derive(Serialize, Deserialize)]
+fn default_response_policy() -> String {
+    ResponsePolicy::default().to_string()
+}
+
+fn default_publication() -> String {
+    Publication::default().to_string()
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CollaboratorDTO {
+    pub username: String,
+    pub role: String,
+}
+
+impl From<&Collaborator> for CollaboratorDTO {
+    fn from(c: &Collaborator) -> Self {
+        CollaboratorDTO {
+            username: c.username().to_string(),
+            role: c.role().to_string(),
+        }
+    }
+}
+
+impl SurveyDTO {
+    /// Whether `get_survey_for_author` would hand this survey to `author`, acting for
+    /// `organization_id`.  Caches use this so a hit can't reach further than the repository would.
+    pub fn is_visible_to(&self, author: &String, organization_id: Option<&String>) -> bool {
+        &self.author == author
+            || (organization_id.is_some() && self.organization_id.as_ref() == organization_id)
+            || self.collaborators.iter().any(|c| &c.username == author)
+    }
+}
+
+impl AccessControlled for SurveyDTO {
+    fn role_of(&self, actor: &Actor) -> Option<Role> {
+        let shared_role = self.collaborators.iter()
+            .find(|c| c.username == actor.username)
+            .and_then(|c| Role::try_from(c.role.clone()).ok());
+
+        survey_role(
+            actor,
+            &self.author,
+            self.organization_id.as_deref(),
+            shared_role,
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct QuestionDTO {
     pub id: String,
     #[serde(rename = "type")]
@@ -38,7 +104,7 @@ pub struct QuestionDTO {
     pub choices: Vec<ChoiceDTO>
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ChoiceDTO {
     pub id: String,
@@ -63,6 +129,10 @@ impl From<Survey> for SurveyDTO {
             created_on: s.created_on().clone(),
             category: s.category().to_string(),
             questions,
+            collaborators: s.collaborators().iter().map(CollaboratorDTO::from).collect(),
+            organization_id: s.organization_id().as_ref().map(|id| id.to_string()),
+            response_policy: s.response_policy().to_string(),
+            publication: s.publication().to_string(),
         }
     }
 }
@@ -117,6 +187,10 @@ impl From<&Survey> for SurveyDTO {
             created_on: s.created_on().clone(),
             category: s.category().to_string(),
             questions,
+            collaborators: s.collaborators().iter().map(CollaboratorDTO::from).collect(),
+            organization_id: s.organization_id().as_ref().map(|id| id.to_string()),
+            response_policy: s.response_policy().to_string(),
+            publication: s.publication().to_string(),
         }
     }
 }
@@ -0,0 +1,44 @@
+use serde::{Serialize, Deserialize};
+use std::convert::From;
+use domain_patterns::models::{Entity, AggregateRoot};
+use crate::user::User;
+
+/// Everything stored about a user.  This carries the password hash, so it's for repositories only
+/// and must never be handed to a client.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserDTO {
+    pub id: String,
+    pub version: u64,
+    pub username: String,
+    pub password_hash: String,
+    pub created_on: i64,
+    #[serde(default)]
+    pub admin: bool,
+    #[serde(default)]
+    pub locked: bool,
+}
+
+impl From<&User> for UserDTO {
+    fn from(u: &User) -> Self {
+        UserDTO {
+            id: u.id().to_string(),
+            version: u.version(),
+            username: u.username().to_string(),
+            password_hash: u.password_hash().to_string(),
+            created_on: *u.created_on(),
+            admin: *u.admin(),
+            locked: *u.locked(),
+        }
+    }
+}
+
+/// Returned from login and refresh.  `expires_in` is the access token's lifetime in seconds.  Field
+/// names follow OAuth 2.0 token responses so off the shelf clients can read them.
+#[derive(Serialize, Deserialize)]
+pub struct TokenPairDTO {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+}
@@ -10,12 +10,38 @@ pub enum Error {
         source: crate::value_objects::ValidationError,
     },
 
+    /// InvalidFields carries every validation failure found in a document, rather than just the first.
+    #[snafu(display("{}", join_field_errors(errors)))]
+    InvalidFields {
+        errors: Vec<FieldError>,
+    },
+
+    /// InvalidDocument conveys that a supplied document could not be parsed at all.
+    #[snafu(display("invalid document: {}", msg))]
+    InvalidDocument {
+        msg: String,
+    },
+
     /// Represents a resource that has not been found.
     #[snafu(display("resource '{}' was not found", resource))]
     ResourceNotFound {
         resource: String,
     },
 
+    /// AlreadyExists conveys that a resource with the same unique key is already stored.
+    #[snafu(display("{} already exists", resource))]
+    AlreadyExists {
+        resource: String,
+    },
+
+    /// StaleVersion conveys that something was based on a version of a survey that has since
+    /// changed, so it has to be redone against the current one.
+    #[snafu(display("survey version {} was answered, but the survey has changed and is now at version {}; reload it and answer again", submitted, current))]
+    StaleVersion {
+        submitted: u64,
+        current: u64,
+    },
+
     /// NotAuthorized conveys that the caller is not authorized to commit the action.
     #[snafu(display("not authorized"))]
     NotAuthorized,
@@ -43,3 +69,67 @@ impl From<crate::value_objects::ValidationError> for Error {
         }
     }
 }
+
+/// A validation failure tied to the location in the input that caused it.  `path` is a JSON pointer
+/// into the submitted document, e.g. `/questions/2/choices/0/title`.
+#[derive(Debug)]
+pub struct FieldError {
+    pub path: String,
+    pub error: crate::value_objects::ValidationError,
+}
+
+impl std::fmt::Display for FieldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.error)
+    }
+}
+
+/// Accumulates validation failures so a caller can be told about all of them at once, rather than
+/// fixing one mistake per round trip.
+#[derive(Default)]
+pub struct FieldErrors {
+    errors: Vec<FieldError>,
+}
+
+impl FieldErrors {
+    pub fn new() -> FieldErrors {
+        FieldErrors::default()
+    }
+
+    /// Records a validation failure at `path` and returns `Ok(None)` so the caller can keep going.
+    /// Any other kind of error isn't something more input would fix, so it's passed straight back.
+    pub fn check<T>(&mut self, path: impl Into<String>, result: Result<T>) -> Result<Option<T>> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(Error::ValidationError { source }) => {
+                self.errors.push(FieldError { path: path.into(), error: source });
+                Ok(None)
+            },
+            Err(Error::InvalidFields { errors }) => {
+                self.errors.extend(errors);
+                Ok(None)
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Ok if nothing failed, otherwise every recorded failure as `Error::InvalidFields`.
+    pub fn into_result(self) -> Result<()> {
+        if self.errors.is_empty() {
+            return Ok(());
+        }
+
+        Err(Error::InvalidFields { errors: self.errors })
+    }
+}
+
+fn join_field_errors(errors: &[FieldError]) -> String {
+    errors.iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
@@ -12,7 +12,10 @@ pub mod errors;
 pub use errors::Error;
 
 pub mod app_services;
+pub mod policy;
 pub mod survey;
+pub mod user;
+pub mod organization;
 pub mod dtos;
 pub mod value_objects;
 
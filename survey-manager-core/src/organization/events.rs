@@ -0,0 +1,32 @@
+use serde::Serialize;
+use domain_patterns::event::DomainEvent;
+use domain_patterns::message::Message;
+use domain_patterns::models::{Entity, AggregateRoot};
+use uuid::Uuid;
+use crate::organization::Organization;
+
+#[derive(DomainEvent, Serialize)]
+pub struct OrganizationCreatedEvent {
+    pub id: String,
+    pub aggregate_id: String,
+    pub version: u64,
+    pub occurred: i64,
+    pub name: String,
+}
+
+impl From<&Organization> for OrganizationCreatedEvent {
+    fn from(org: &Organization) -> Self {
+        OrganizationCreatedEvent {
+            id: Uuid::new_v4().to_string(),
+            aggregate_id: org.id(),
+            version: org.version(),
+            occurred: org.created_on,
+            name: org.name.to_string(),
+        }
+    }
+}
+
+#[derive(DomainEvents)]
+pub enum OrganizationEvents {
+    OrganizationCreatedEvent(OrganizationCreatedEvent),
+}
@@ -0,0 +1,32 @@
+use crate::value_objects::{Author, OrgRole};
+use crate::dtos::MemberDTO;
+use crate::errors::{Error, Result};
+use std::convert::TryFrom;
+
+/// A user who belongs to an organization, and their standing in it.
+#[derive(Clone)]
+pub struct Member {
+    pub(super) username: Author,
+    pub(super) role: OrgRole,
+}
+
+impl Member {
+    pub fn username(&self) -> &Author {
+        &self.username
+    }
+
+    pub fn role(&self) -> OrgRole {
+        self.role
+    }
+}
+
+impl TryFrom<MemberDTO> for Member {
+    type Error = Error;
+
+    fn try_from(dto: MemberDTO) -> Result<Self> {
+        Ok(Member {
+            username: Author::try_from(dto.username)?,
+            role: OrgRole::try_from(dto.role)?,
+        })
+    }
+}
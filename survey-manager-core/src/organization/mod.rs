@@ -0,0 +1,317 @@
+pub mod events;
+pub use events::*;
+
+pub mod member;
+pub use member::*;
+
+use crate::value_objects::{Author, OrganizationName, OrgRole, ValidationError};
+use uuid::Uuid;
+use domain_patterns::models::AggregateRoot;
+use chrono::Utc;
+use crate::app_services::organizations::CreateOrganizationCommand;
+use crate::errors::{Result, FieldErrors};
+use crate::errors::Error;
+use crate::errors::Error::{NotAuthorized, ResourceNotFound};
+use crate::dtos::OrganizationDTO;
+use crate::survey::parse_id;
+use std::convert::TryFrom;
+
+/// A workspace that owns surveys on behalf of its members, so a survey outlives whoever wrote it.
+/// There is always at least one owner.
+#[derive(Entity, Clone)]
+pub struct Organization {
+    id: Uuid,
+    version: u64,
+    name: OrganizationName,
+    members: Vec<Member>,
+    created_on: i64,
+}
+
+impl AggregateRoot for Organization {
+    type Events = OrganizationEvents;
+
+    type Error = Error;
+
+    fn version(&self) -> u64 {
+        self.version
+    }
+}
+
+impl Organization {
+    /// Creates the organization with its creator as the only owner.
+    pub fn new(cmd: &CreateOrganizationCommand) -> Result<Organization> {
+        let mut errors = FieldErrors::new();
+
+        let name = errors.check("/name", OrganizationName::try_from(cmd.name.clone()))?;
+        let creator = errors.check("/creator", Author::try_from(cmd.creator.clone()))?;
+
+        errors.into_result()?;
+
+        // Safe to unwrap, both were checked above.
+        Ok(Organization {
+            id: Uuid::new_v4(),
+            version: 0,
+            name: name.unwrap(),
+            members: vec![Member { username: creator.unwrap(), role: OrgRole::Owner }],
+            created_on: Utc::now().timestamp(),
+        })
+    }
+
+    /// The role `username` holds here, or `None` if they aren't a member.
+    pub fn role_of(&self, username: &str) -> Option<OrgRole> {
+        self.members.iter()
+            .find(|m| m.username.to_string() == username)
+            .map(|m| m.role)
+    }
+
+    /// Adds `username` with `role`, or changes the role of an existing member.  Only owners and
+    /// admins manage membership, and only owners can make someone else an owner.
+    pub fn add_member(&mut self, requested_by: &str, username: &String, role: &String) -> Result<()> {
+        let requester_role = self.manager_role(requested_by)?;
+
+        let mut errors = FieldErrors::new();
+        let username = errors.check("/username", Author::try_from(username.clone()))?;
+        let role = errors.check("/role", OrgRole::try_from(role.clone()))?;
+        errors.into_result()?;
+
+        // Safe to unwrap, both were checked above.
+        let (username, role) = (username.unwrap(), role.unwrap());
+        if role == OrgRole::Owner && requester_role != OrgRole::Owner {
+            return Err(NotAuthorized);
+        }
+        // An admin demoting an owner would be a way around the rule above.
+        if self.role_of(&username.to_string()) == Some(OrgRole::Owner) && requester_role != OrgRole::Owner {
+            return Err(NotAuthorized);
+        }
+
+        self.apply_atomically(|staged| {
+            staged.members.retain(|m| m.username.to_string() != username.to_string());
+            staged.members.push(Member { username, role });
+            staged.ensure_owner()
+        })
+    }
+
+    /// Removes `username`.  Members may always leave on their own; removing anyone else takes an
+    /// owner or admin, and only an owner can remove another owner.
+    pub fn remove_member(&mut self, requested_by: &str, username: &String) -> Result<()> {
+        let target_role = self.role_of(username)
+            .ok_or(ResourceNotFound { resource: format!("member {}", username) })?;
+
+        if requested_by != username {
+            let requester_role = self.manager_role(requested_by)?;
+            if target_role == OrgRole::Owner && requester_role != OrgRole::Owner {
+                return Err(NotAuthorized);
+            }
+        }
+
+        self.apply_atomically(|staged| {
+            staged.members.retain(|m| &m.username.to_string() != username);
+            staged.ensure_owner()
+        })
+    }
+
+    fn manager_role(&self, username: &str) -> Result<OrgRole> {
+        match self.role_of(username) {
+            Some(role) if role.can_manage() => Ok(role),
+            _ => Err(NotAuthorized),
+        }
+    }
+
+    fn ensure_owner(&self) -> Result<()> {
+        if self.members.iter().any(|m| m.role == OrgRole::Owner) {
+            return Ok(());
+        }
+
+        Err(ValidationError::MembershipValidationError { msg: "an organization must keep at least one owner".to_string() }.into())
+    }
+
+    // Same staging approach as `Survey`, so a rejected change never leaves a half applied membership.
+    fn apply_atomically<F>(&mut self, change: F) -> Result<()>
+        where F: FnOnce(&mut Organization) -> Result<()>
+    {
+        let mut staged = self.clone();
+        change(&mut staged)?;
+
+        staged.version = self.next_version();
+        *self = staged;
+        Ok(())
+    }
+}
+
+impl TryFrom<OrganizationDTO> for Organization {
+    type Error = Error;
+
+    fn try_from(dto: OrganizationDTO) -> Result<Self> {
+        Ok(Organization {
+            id: parse_id(&dto.id)?,
+            version: dto.version,
+            name: OrganizationName::try_from(dto.name)?,
+            members: dto.members.into_iter()
+                .map(Member::try_from)
+                .collect::<Result<Vec<_>>>()?,
+            created_on: dto.created_on,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // "owner" created the organization; "admin" and "member" were added by them.
+    fn create_test_organization() -> Organization {
+        let mut org = Organization::new(&CreateOrganizationCommand {
+            name: "Test organization".to_string(),
+            creator: "owner".to_string(),
+        }).unwrap();
+        org.add_member("owner", &"admin".to_string(), &"admin".to_string()).unwrap();
+        org.add_member("owner", &"member".to_string(), &"member".to_string()).unwrap();
+        org
+    }
+
+    fn is_not_authorized(result: Result<()>) -> bool {
+        matches!(result, Err(NotAuthorized))
+    }
+
+    #[test]
+    fn the_creator_is_the_only_owner() {
+        let org = Organization::new(&CreateOrganizationCommand {
+            name: "Test organization".to_string(),
+            creator: "owner".to_string(),
+        }).unwrap();
+
+        assert_eq!(org.role_of("owner"), Some(OrgRole::Owner));
+        assert_eq!(org.members.len(), 1);
+    }
+
+    #[test]
+    fn new_reports_every_invalid_field_at_once() {
+        let result = Organization::new(&CreateOrganizationCommand {
+            name: " ".to_string(),
+            creator: "me".to_string(),
+        });
+
+        match result {
+            Err(Error::InvalidFields { errors }) => {
+                let paths: Vec<_> = errors.into_iter().map(|e| e.path).collect();
+                assert_eq!(paths, vec!["/name", "/creator"]);
+            },
+            _ => panic!("expected invalid fields"),
+        }
+    }
+
+    #[test]
+    fn who_may_add_members_and_with_which_role() {
+        // (requested by, role granted, allowed)
+        let cases = vec![
+            ("owner", "owner", true),
+            ("owner", "admin", true),
+            ("owner", "member", true),
+            ("admin", "owner", false),
+            ("admin", "admin", true),
+            ("admin", "member", true),
+            ("member", "member", false),
+            ("outsider", "member", false),
+        ];
+
+        for (requested_by, role, allowed) in cases {
+            let mut org = create_test_organization();
+            let version = org.version();
+            let result = org.add_member(requested_by, &"newcomer".to_string(), &role.to_string());
+
+            if allowed {
+                assert!(result.is_ok(), "{} granting {}", requested_by, role);
+                assert_eq!(org.role_of("newcomer"), OrgRole::try_from(role.to_string()).ok());
+                assert_eq!(org.version(), version + 1);
+            } else {
+                assert!(is_not_authorized(result), "{} granting {}", requested_by, role);
+                assert_eq!(org.role_of("newcomer"), None);
+                assert_eq!(org.version(), version);
+            }
+        }
+    }
+
+    #[test]
+    fn adding_an_existing_member_changes_their_role() {
+        let mut org = create_test_organization();
+
+        org.add_member("admin", &"member".to_string(), &"admin".to_string()).unwrap();
+
+        assert_eq!(org.role_of("member"), Some(OrgRole::Admin));
+        assert_eq!(org.members.iter().filter(|m| m.username.to_string() == "member").count(), 1);
+    }
+
+    #[test]
+    fn only_owners_change_an_owners_role() {
+        let mut org = create_test_organization();
+
+        assert!(is_not_authorized(org.add_member("admin", &"owner".to_string(), &"member".to_string())));
+        assert_eq!(org.role_of("owner"), Some(OrgRole::Owner));
+    }
+
+    #[test]
+    fn the_last_owner_cant_step_down() {
+        let mut org = create_test_organization();
+
+        assert!(org.add_member("owner", &"owner".to_string(), &"admin".to_string()).is_err());
+        assert_eq!(org.role_of("owner"), Some(OrgRole::Owner));
+
+        // Once there is a second owner, either may step down.
+        org.add_member("owner", &"admin".to_string(), &"owner".to_string()).unwrap();
+        org.add_member("owner", &"owner".to_string(), &"admin".to_string()).unwrap();
+        assert_eq!(org.role_of("owner"), Some(OrgRole::Admin));
+    }
+
+    #[test]
+    fn invalid_roles_are_rejected() {
+        let mut org = create_test_organization();
+
+        assert!(org.add_member("owner", &"newcomer".to_string(), &"superuser".to_string()).is_err());
+        assert_eq!(org.role_of("newcomer"), None);
+    }
+
+    #[test]
+    fn who_may_remove_whom() {
+        // (requested by, removed, allowed)
+        let cases = vec![
+            ("admin", "member", true),
+            ("owner", "admin", true),
+            ("member", "member", true),
+            ("admin", "admin", true),
+            ("member", "admin", false),
+            ("admin", "owner", false),
+            ("outsider", "member", false),
+        ];
+
+        for (requested_by, removed, allowed) in cases {
+            let mut org = create_test_organization();
+            let result = org.remove_member(requested_by, &removed.to_string());
+
+            if allowed {
+                assert!(result.is_ok(), "{} removing {}", requested_by, removed);
+                assert_eq!(org.role_of(removed), None);
+            } else {
+                assert!(is_not_authorized(result), "{} removing {}", requested_by, removed);
+                assert!(org.role_of(removed).is_some());
+            }
+        }
+    }
+
+    #[test]
+    fn the_last_owner_cant_leave() {
+        let mut org = create_test_organization();
+
+        assert!(org.remove_member("owner", &"owner".to_string()).is_err());
+        assert_eq!(org.role_of("owner"), Some(OrgRole::Owner));
+    }
+
+    #[test]
+    fn removing_someone_who_isnt_a_member_is_not_found() {
+        let mut org = create_test_organization();
+
+        match org.remove_member("owner", &"stranger".to_string()) {
+            Err(ResourceNotFound { .. }) => (),
+            _ => panic!("expected not found"),
+        }
+    }
+}
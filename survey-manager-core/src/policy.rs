@@ -0,0 +1,295 @@
+//! Who may do what to a survey.
+//!
+//! Every command and query handler asks `authorize` before touching a survey, rather than comparing
+//! usernames itself.  What each role allows lives in `Role::grants`, so changing the rules is a
+//! change to one match.
+//!
+//! A caller's role on a survey comes from one of three places.  The author owns a personal survey.
+//! For a survey owned by an organization, the organization claim in the caller's token decides,
+//! and the author has no special standing.  On top of either, a survey can be shared directly.
+//! When more than one applies the strongest role wins.
+use std::convert::TryFrom;
+use crate::value_objects::{Role, OrgRole};
+use crate::app_services::token::{OrganizationClaim, ADMIN_ROLE};
+use crate::errors::{Error, Result};
+
+/// Things a caller can try to do with a survey.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Permission {
+    /// Read the survey and its questions, including exporting it.
+    View,
+    /// Change the title, description, category, questions or choices.
+    Edit,
+    /// Remove the survey entirely.
+    Delete,
+    /// Grant or revoke other users' access.
+    Share,
+    /// Read submitted responses and aggregate results.
+    ViewResults,
+    /// Hand the survey to another user or an organization.
+    Transfer,
+}
+
+impl Role {
+    // Each role can do everything the ones below it can.
+    fn rank(&self) -> u8 {
+        match self {
+            Role::Owner => 3,
+            Role::Editor => 2,
+            Role::Analyst => 1,
+            Role::Viewer => 0,
+        }
+    }
+
+    pub fn grants(&self, permission: Permission) -> bool {
+        match self {
+            Role::Owner => true,
+            Role::Editor => matches!(permission, Permission::View | Permission::Edit | Permission::ViewResults),
+            Role::Analyst => matches!(permission, Permission::View | Permission::ViewResults),
+            Role::Viewer => permission == Permission::View,
+        }
+    }
+}
+
+impl OrgRole {
+    /// The role members get on surveys their organization owns.
+    pub fn survey_role(&self) -> Role {
+        match self {
+            OrgRole::Owner | OrgRole::Admin => Role::Owner,
+            OrgRole::Member => Role::Editor,
+        }
+    }
+
+    /// Whether this member may add and remove other members and transfer surveys in.
+    pub fn can_manage(&self) -> bool {
+        *self != OrgRole::Member
+    }
+}
+
+/// Whoever is making a request, as their token describes them.
+pub struct Actor<'a> {
+    pub username: &'a str,
+    pub org: Option<&'a OrganizationClaim>,
+}
+
+impl<'a> Actor<'a> {
+    pub fn new(username: &'a str, org: &'a Option<OrganizationClaim>) -> Actor<'a> {
+        Actor {
+            username,
+            org: org.as_ref(),
+        }
+    }
+
+    /// The actor's role in `organization_id`, if their token is for that organization.
+    pub fn org_role(&self, organization_id: &str) -> Option<OrgRole> {
+        self.org
+            .filter(|org| org.id == organization_id)
+            .and_then(|org| OrgRole::try_from(org.role.clone()).ok())
+    }
+}
+
+/// Anything that knows which role an actor holds on it.
+pub trait AccessControlled {
+    /// The caller's role, or `None` if they have no access at all.
+    fn role_of(&self, actor: &Actor) -> Option<Role>;
+}
+
+/// Ok if `actor` holds a role on `resource` that grants `permission`.
+pub fn authorize<T: AccessControlled>(resource: &T, actor: &Actor, permission: Permission) -> Result<()> {
+    match resource.role_of(actor) {
+        Some(role) if role.grants(permission) => Ok(()),
+        _ => Err(Error::NotAuthorized),
+    }
+}
+
+/// Works out a survey role from the three sources described at the top of this module.  Shared by
+/// the aggregate and its DTO so the read and write sides can't disagree.
+pub(crate) fn survey_role(
+    actor: &Actor,
+    author: &str,
+    organization_id: Option<&str>,
+    shared_role: Option<Role>,
+) -> Option<Role> {
+    let owned_role = match organization_id {
+        Some(org_id) => actor.org_role(org_id).map(|r| r.survey_role()),
+        None if author == actor.username => Some(Role::Owner),
+        None => None,
+    };
+
+    match (owned_role, shared_role) {
+        (Some(a), Some(b)) => Some(if a.rank() >= b.rank() { a } else { b }),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Things only site administrators can do.  None of these go through survey roles: an admin gets
+/// no access to a survey from `authorize`, only through the moderation handler, which checks here
+/// and writes every use to the audit log.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ModerationPermission {
+    /// Read any survey, whoever owns it.
+    ViewAnySurvey,
+    /// Remove any survey, whoever owns it.
+    DeleteAnySurvey,
+    /// Force-unpublish any survey, or lift that again.
+    WithdrawAnySurvey,
+    /// Lock or unlock an author's account.
+    LockAuthor,
+    /// Read the audit log.
+    ReadAuditLog,
+    /// Read operational counters, like cache hit rates.
+    ReadMetrics,
+}
+
+/// Ok if `roles`, taken from the caller's token, allow `permission`.  Today the admin role allows
+/// all of them.
+pub fn authorize_moderation(roles: &[String], permission: ModerationPermission) -> Result<()> {
+    let is_admin = roles.iter().any(|r| r == ADMIN_ROLE);
+    match permission {
+        ModerationPermission::ViewAnySurvey
+        | ModerationPermission::DeleteAnySurvey
+        | ModerationPermission::WithdrawAnySurvey
+        | ModerationPermission::LockAuthor
+        | ModerationPermission::ReadAuditLog
+        | ModerationPermission::ReadMetrics if is_admin => Ok(()),
+        _ => Err(Error::NotAuthorized),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Permission::*;
+
+    const ALL_PERMISSIONS: [Permission; 6] = [View, Edit, Delete, Share, ViewResults, Transfer];
+
+    const ALL_MODERATION: [ModerationPermission; 6] = [
+        ModerationPermission::ViewAnySurvey,
+        ModerationPermission::DeleteAnySurvey,
+        ModerationPermission::WithdrawAnySurvey,
+        ModerationPermission::LockAuthor,
+        ModerationPermission::ReadAuditLog,
+        ModerationPermission::ReadMetrics,
+    ];
+
+    fn claim(id: &str, role: &str) -> Option<OrganizationClaim> {
+        Some(OrganizationClaim { id: id.to_string(), role: role.to_string() })
+    }
+
+    #[test]
+    fn each_role_grants_exactly_its_permissions() {
+        let granted = vec![
+            (Role::Owner, vec![View, Edit, Delete, Share, ViewResults, Transfer]),
+            (Role::Editor, vec![View, Edit, ViewResults]),
+            (Role::Analyst, vec![View, ViewResults]),
+            (Role::Viewer, vec![View]),
+        ];
+
+        for (role, expected) in granted {
+            for permission in ALL_PERMISSIONS.iter() {
+                assert_eq!(
+                    role.grants(*permission),
+                    expected.contains(permission),
+                    "{} and {:?}", role, permission,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn organization_roles_map_to_survey_roles() {
+        let cases = vec![
+            (OrgRole::Owner, Role::Owner, true),
+            (OrgRole::Admin, Role::Owner, true),
+            (OrgRole::Member, Role::Editor, false),
+        ];
+
+        for (org_role, survey_role, can_manage) in cases {
+            assert_eq!(org_role.survey_role(), survey_role, "{}", org_role);
+            assert_eq!(org_role.can_manage(), can_manage, "{}", org_role);
+        }
+    }
+
+    #[test]
+    fn survey_role_combines_ownership_organization_and_sharing() {
+        let none = None;
+        let member_of_acme = claim("acme", "member");
+        let admin_of_acme = claim("acme", "admin");
+        let admin_of_other = claim("other", "admin");
+        let bogus_role = claim("acme", "superuser");
+
+        // (caller, caller's org claim, survey author, owning organization, shared role, expected)
+        let cases = vec![
+            // Personal surveys belong to their author.
+            ("alice", &none, "alice", None, None, Some(Role::Owner)),
+            ("bob", &none, "alice", None, None, None),
+            ("bob", &none, "alice", None, Some(Role::Viewer), Some(Role::Viewer)),
+            // Sharing never takes anything away from the owner.
+            ("alice", &none, "alice", None, Some(Role::Viewer), Some(Role::Owner)),
+            // Organization surveys go by the claim, and the author has no special standing.
+            ("alice", &none, "alice", Some("acme"), None, None),
+            ("bob", &member_of_acme, "alice", Some("acme"), None, Some(Role::Editor)),
+            ("bob", &admin_of_acme, "alice", Some("acme"), None, Some(Role::Owner)),
+            // The strongest of the organization role and a direct share wins.
+            ("bob", &member_of_acme, "alice", Some("acme"), Some(Role::Owner), Some(Role::Owner)),
+            ("bob", &member_of_acme, "alice", Some("acme"), Some(Role::Analyst), Some(Role::Editor)),
+            ("bob", &admin_of_acme, "alice", Some("acme"), Some(Role::Viewer), Some(Role::Owner)),
+            // A claim for another organization counts for nothing, shares still apply.
+            ("bob", &admin_of_other, "alice", Some("acme"), None, None),
+            ("bob", &admin_of_other, "alice", Some("acme"), Some(Role::Viewer), Some(Role::Viewer)),
+            // So does a claim naming a role we don't know.
+            ("bob", &bogus_role, "alice", Some("acme"), None, None),
+        ];
+
+        for (username, org, author, organization_id, shared, expected) in cases {
+            let actor = Actor::new(username, org);
+            assert_eq!(
+                survey_role(&actor, author, organization_id, shared),
+                expected,
+                "{} with {:?} on {}'s survey owned by {:?}, shared {:?}", username, org, author, organization_id, shared,
+            );
+        }
+    }
+
+    struct TestSurvey(Option<Role>);
+
+    impl AccessControlled for TestSurvey {
+        fn role_of(&self, _actor: &Actor) -> Option<Role> {
+            self.0
+        }
+    }
+
+    #[test]
+    fn authorize_follows_the_callers_role() {
+        let no_org = None;
+        let actor = Actor::new("bob", &no_org);
+
+        for role in [Some(Role::Owner), Some(Role::Editor), Some(Role::Analyst), Some(Role::Viewer), None] {
+            for permission in ALL_PERMISSIONS.iter() {
+                let allowed = role.is_some_and(|r| r.grants(*permission));
+                match authorize(&TestSurvey(role), &actor, *permission) {
+                    Ok(()) => assert!(allowed, "{:?} and {:?}", role, permission),
+                    Err(Error::NotAuthorized) => assert!(!allowed, "{:?} and {:?}", role, permission),
+                    Err(e) => panic!("unexpected error {}", e),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn only_admins_moderate() {
+        let cases = vec![
+            (vec![ADMIN_ROLE.to_string()], true),
+            (vec!["moderator".to_string(), ADMIN_ROLE.to_string()], true),
+            (vec![], false),
+            (vec!["moderator".to_string()], false),
+            (vec!["Admin".to_string()], false),
+        ];
+
+        for (roles, allowed) in cases {
+            for permission in ALL_MODERATION.iter() {
+                assert_eq!(authorize_moderation(&roles, *permission).is_ok(), allowed, "{:?} and {:?}", roles, permission);
+            }
+        }
+    }
+}
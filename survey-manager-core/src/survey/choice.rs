@@ -1,12 +1,13 @@
 use uuid::Uuid;
 use crate::value_objects::title::Title;
 use crate::value_objects::content_type::ContentType;
-use std::str::FromStr;
 use std::convert::TryFrom;
 use crate::survey::Content::Youtube;
+use crate::survey::parse_id;
 use crate::dtos::ChoiceDTO;
+use crate::errors::{Error, Result};
 
-#[derive(Entity)]
+#[derive(Entity, Clone)]
 pub struct Choice {
     pub(super) id: Uuid,
     pub(super) content: Option<Content>,
@@ -35,8 +36,10 @@ impl std::fmt::Display for Content {
     }
 }
 
-impl From<ChoiceDTO> for Choice {
-    fn from(dto: ChoiceDTO) -> Self {
+impl TryFrom<ChoiceDTO> for Choice {
+    type Error = Error;
+
+    fn try_from(dto: ChoiceDTO) -> Result<Self> {
         let content = if let Some(c) = dto.content {
             // Todo: This is a placehoder.  fix once we figure out streaming content.
             Some(Youtube(c))
@@ -44,11 +47,11 @@ impl From<ChoiceDTO> for Choice {
             None
         };
 
-        Choice {
-            id: Uuid::from_str(&dto.id).unwrap().clone(),
+        Ok(Choice {
+            id: parse_id(&dto.id)?,
             content,
-            content_type: ContentType::try_from(dto.content_type).unwrap(),
-            title: Title::try_from(dto.title).unwrap(),
-        }
+            content_type: ContentType::try_from(dto.content_type)?,
+            title: Title::try_from(dto.title)?,
+        })
     }
 }
@@ -0,0 +1,32 @@
+use crate::value_objects::{Author, Role};
+use crate::dtos::CollaboratorDTO;
+use crate::errors::{Error, Result};
+use std::convert::TryFrom;
+
+/// A user the survey has been shared with, and the role they were given.
+#[derive(Clone)]
+pub struct Collaborator {
+    pub(super) username: Author,
+    pub(super) role: Role,
+}
+
+impl Collaborator {
+    pub fn username(&self) -> &Author {
+        &self.username
+    }
+
+    pub fn role(&self) -> Role {
+        self.role
+    }
+}
+
+impl TryFrom<CollaboratorDTO> for Collaborator {
+    type Error = Error;
+
+    fn try_from(dto: CollaboratorDTO) -> Result<Self> {
+        Ok(Collaborator {
+            username: Author::try_from(dto.username)?,
+            role: Role::try_from(dto.role)?,
+        })
+    }
+}
@@ -7,19 +7,41 @@ pub use question::*;
 pub mod events;
 pub use events::*;
 
-use crate::value_objects::{Title, QuestionType, ContentType, Author, Description, Category};
+pub mod collaborator;
+pub use collaborator::*;
+
+use crate::value_objects::{Title, QuestionType, ContentType, Author, Description, Category, Role, ResponsePolicy, Publication, ValidationError};
+use crate::policy::{AccessControlled, Actor, survey_role};
 use uuid::Uuid;
 use domain_patterns::models::{Entity, AggregateRoot};
 use chrono::Utc;
 use crate::app_services::commands::*;
-use crate::errors::Result;
+use crate::errors::{Result, FieldErrors};
 use crate::errors::Error;
 use crate::errors::Error::ResourceNotFound;
 use crate::dtos::SurveyDTO;
 use std::str::FromStr;
 use std::convert::{TryFrom, TryInto};
 
-#[derive(Entity)]
+/// How the document a command was read from spells multi-word field names.  Error pointers use
+/// the same spelling so they name the field the caller actually sent: the create body is
+/// snake_case, while patches and survey definitions are camelCase.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FieldCase {
+    Snake,
+    Camel,
+}
+
+impl FieldCase {
+    fn content_type(self) -> &'static str {
+        match self {
+            FieldCase::Snake => "content_type",
+            FieldCase::Camel => "contentType",
+        }
+    }
+}
+
+#[derive(Entity, Clone)]
 pub struct Survey {
     id: Uuid,
     version: u64,
@@ -29,6 +51,15 @@ pub struct Survey {
     created_on: i64,
     category: Category,
     questions: Vec<Question>,
+    // Everyone the survey is shared with.  The author of a personal survey is its owner and never
+    // appears here.
+    collaborators: Vec<Collaborator>,
+    // Set when an organization owns the survey.  The author is then only a record of who wrote it.
+    organization_id: Option<Uuid>,
+    // Who may answer, and so how duplicate responses are recognized.
+    response_policy: ResponsePolicy,
+    // Whether it's taking responses at all.
+    publication: Publication,
 }
 
 impl AggregateRoot for Survey {
@@ -43,88 +74,270 @@ impl AggregateRoot for Survey {
 
 impl Survey {
     // TODO: This should probably consume the command.
+    /// Validates every field of the command before building the survey, so a caller with several
+    /// mistakes hears about all of them in one `Error::InvalidFields`.  Paths in that error follow
+    /// the survey's json representation, e.g. `/questions/2/choices/0/title`, with multi-word
+    /// fields spelled as in the create body (`content_type`).
     pub fn new(
         new_survey: &CreateSurveyCommand,
     ) -> Result<Survey> {
+        Self::new_from_document(new_survey, FieldCase::Snake)
+    }
+
+    /// Same as `new`, for a command read from a document that spells multi-word fields in `case`,
+    /// such as an imported definition.
+    pub fn new_from_document(
+        new_survey: &CreateSurveyCommand,
+        case: FieldCase,
+    ) -> Result<Survey> {
+        let mut errors = FieldErrors::new();
+
+        let author = errors.check("/author", Author::try_from(new_survey.author.clone()))?;
+        let title = errors.check("/title", Title::try_from(new_survey.title.clone()))?;
+        let description = errors.check("/description", Description::try_from(new_survey.description.clone()))?;
+        let category = errors.check("/category", Category::try_from(new_survey.category.clone()))?;
+        let questions = Self::create_questions(new_survey.questions.clone(), case, &mut errors)?;
+
+        errors.into_result()?;
+
+        // Safe to unwrap.  Any field that failed to validate was recorded above and returned.
         Ok(Survey {
             id: Uuid::new_v4(),
             version: 0,
-            author: Author::try_from(new_survey.author.clone())?,
-            title: Title::try_from(new_survey.title.clone())?,
-            description: Description::try_from(new_survey.description.clone())?,
+            author: author.unwrap(),
+            title: title.unwrap(),
+            description: description.unwrap(),
             created_on: Utc::now().timestamp(),
-            category: Category::try_from(new_survey.category.clone())?,
-            questions: Self::create_questions(new_survey.questions.clone())?
+            category: category.unwrap(),
+            questions,
+            collaborators: vec![],
+            organization_id: None,
+            response_policy: ResponsePolicy::default(),
+            publication: Publication::default(),
         })
     }
 
     // CONSTRUCTORS FOR CHILD ENTITIES
-    fn create_questions(new_questions: Vec<CreateQuestionCommand>) -> Result<Vec<Question>> {
-        let q_results = new_questions
-            .into_iter()
-            .map(|q| { Self::create_question(q) });
-
+    // These record validation failures in `errors` rather than returning early, and only hand back
+    // the children that validated.  Callers must check `errors` before using the result.
+    fn create_questions(new_questions: Vec<CreateQuestionCommand>, case: FieldCase, errors: &mut FieldErrors) -> Result<Vec<Question>> {
         let mut questions: Vec<_> = vec![];
 
-        for q_result in q_results {
-            questions.push(q_result?)
+        for (idx, new_question) in new_questions.into_iter().enumerate() {
+            let path = format!("/questions/{}", idx);
+            if let Some(q) = Self::create_question(new_question, &path, case, errors)? {
+                questions.push(q);
+            }
         }
 
         Ok(questions)
     }
 
-    fn create_question(new_question: CreateQuestionCommand) -> Result<Question> {
-        Ok(Question {
-            id: Uuid::new_v4(),
-            kind: QuestionType::try_from(new_question.question_type)?,
-            title: Title::try_from(new_question.title)?,
-            choices: Self::create_choices(new_question.choices)?,
-        })
+    fn create_question(new_question: CreateQuestionCommand, path: &str, case: FieldCase, errors: &mut FieldErrors) -> Result<Option<Question>> {
+        let kind = errors.check(format!("{}/type", path), QuestionType::try_from(new_question.question_type))?;
+        let title = errors.check(format!("{}/title", path), Title::try_from(new_question.title))?;
+        let choices = Self::create_choices(new_question.choices, path, case, errors)?;
+
+        match (kind, title) {
+            (Some(kind), Some(title)) => Ok(Some(Question {
+                id: Uuid::new_v4(),
+                kind,
+                title,
+                choices,
+            })),
+            _ => Ok(None),
+        }
     }
 
-    fn create_choices(new_choices: Vec<CreateChoiceCommand>) -> Result<Vec<Choice>> {
-        let c_results = new_choices
-            .into_iter()
-            .map(|c| { Self::create_choice(c) });
-
+    fn create_choices(new_choices: Vec<CreateChoiceCommand>, question_path: &str, case: FieldCase, errors: &mut FieldErrors) -> Result<Vec<Choice>> {
         let mut choices: Vec<_> = vec![];
 
-        for c_result in c_results {
-            choices.push(c_result?)
+        for (idx, new_choice) in new_choices.into_iter().enumerate() {
+            let path = format!("{}/choices/{}", question_path, idx);
+            if let Some(c) = Self::create_choice(new_choice, &path, case, errors)? {
+                choices.push(c);
+            }
         }
 
         Ok(choices)
     }
 
-    fn create_choice(new_choice: CreateChoiceCommand) -> Result<Choice> {
-        Ok(Choice {
-            id: Uuid::new_v4(),
-            // TODO: Update for content translation once we understand embed strings.
-            content: None,
-            content_type: ContentType::try_from(new_choice.content_type)?,
-            title: Title::try_from(new_choice.title)?,
+    fn create_choice(new_choice: CreateChoiceCommand, path: &str, case: FieldCase, errors: &mut FieldErrors) -> Result<Option<Choice>> {
+        let content_type = errors.check(format!("{}/{}", path, case.content_type()), ContentType::try_from(new_choice.content_type))?;
+        let title = errors.check(format!("{}/title", path), Title::try_from(new_choice.title))?;
+
+        match (content_type, title) {
+            (Some(content_type), Some(title)) => Ok(Some(Choice {
+                id: Uuid::new_v4(),
+                // Same placeholder translation as `change_choice_content` until embed strings are
+                // understood.  Dropping it would lose content on import.
+                content: new_choice.content.map(Content::Youtube),
+                content_type,
+                title,
+            })),
+            _ => Ok(None),
+        }
+    }
+
+    /// Gives `username` the `role` on this survey, replacing any role they already had.  Ownership
+    /// can't be handed out this way, and the owner can't be given a lesser role.
+    pub fn share(&mut self, username: &String, role: &String) -> Result<()> {
+        self.apply_atomically(|staged| {
+            let mut errors = FieldErrors::new();
+
+            let username = errors.check("/username", Author::try_from(username.clone()))?;
+            let role = errors.check("/role", Role::try_from(role.clone()))?;
+
+            if role == Some(Role::Owner) {
+                errors.check("/role", sharing_error::<()>("ownership can't be shared, only transferred"))?;
+            }
+            if let Some(username) = &username {
+                if staged.organization_id.is_none() && username.to_string() == staged.author.to_string() {
+                    errors.check("/username", sharing_error::<()>("the owner already has full access"))?;
+                }
+            }
+
+            errors.into_result()?;
+
+            // Safe to unwrap, both were checked above.
+            let (username, role) = (username.unwrap(), role.unwrap());
+            staged.collaborators.retain(|c| c.username.to_string() != username.to_string());
+            staged.collaborators.push(Collaborator { username, role });
+            Ok(())
         })
     }
 
-    pub fn belongs_to(&self, author: &String) -> bool {
-        &self.author.to_string() == author
+    /// Takes away whatever access `username` was given.
+    pub fn revoke_access(&mut self, username: &String) -> Result<()> {
+        self.apply_atomically(|staged| {
+            let before = staged.collaborators.len();
+            staged.collaborators.retain(|c| &c.username.to_string() != username);
+
+            if staged.collaborators.len() == before {
+                return Err(ResourceNotFound { resource: format!("collaborator {}", username) });
+            }
+            Ok(())
+        })
     }
 
-    pub fn try_update(&mut self, changeset: UpdateSurveyCommand) -> Result<()> {
-        if let Some(new_title) = &changeset.title {
-            self.change_title(new_title)?;
+    /// Makes `username` the personal owner of the survey, taking it out of any organization.  Any
+    /// role they were given directly is dropped since they now have full access.
+    pub fn transfer_to_user(&mut self, username: &String) -> Result<()> {
+        let new_owner = Author::try_from(username.clone())?;
+        if self.organization_id.is_none() && new_owner.to_string() == self.author.to_string() {
+            return transfer_error("the survey already belongs to that user");
+        }
+
+        self.apply_atomically(|staged| {
+            staged.collaborators.retain(|c| c.username.to_string() != new_owner.to_string());
+            staged.author = new_owner;
+            staged.organization_id = None;
+            Ok(())
+        })
+    }
+
+    /// Hands the survey to an organization.  Collaborators keep the access they were given.
+    pub fn transfer_to_organization(&mut self, organization_id: &String) -> Result<()> {
+        let organization_id = parse_id(organization_id)?;
+        if self.organization_id == Some(organization_id) {
+            return transfer_error("the survey already belongs to that organization");
+        }
+
+        self.apply_atomically(|staged| {
+            staged.organization_id = Some(organization_id);
+            Ok(())
+        })
+    }
+
+    /// Changes who may answer the survey.  Responses already recorded are kept as they are.
+    pub fn change_response_policy(&mut self, policy: &String) -> Result<()> {
+        let policy = ResponsePolicy::try_from(policy.clone())?;
+
+        self.apply_atomically(|staged| {
+            staged.response_policy = policy;
+            Ok(())
+        })
+    }
+
+    /// Opens the survey for responses.  A survey a moderator withdrew stays closed until they
+    /// reinstate it.
+    pub fn publish(&mut self) -> Result<()> {
+        match self.publication {
+            Publication::Unpublished => self.change_publication(Publication::Published),
+            Publication::Published => publication_error("the survey is already published"),
+            Publication::Withdrawn => publication_error("the survey was withdrawn by a moderator"),
         }
-        if let Some(new_category) = &changeset.category {
-            self.change_category(new_category)?;
+    }
+
+    /// Stops the survey taking responses.  Responses already recorded are kept.
+    pub fn unpublish(&mut self) -> Result<()> {
+        match self.publication {
+            Publication::Published => self.change_publication(Publication::Unpublished),
+            _ => publication_error("the survey isn't published"),
         }
-        if let Some(new_desc) = &changeset.description {
-            self.change_description(new_desc)?;
+    }
+
+    /// Takes the survey down on a moderator's say so, whatever its owners had it set to.
+    pub fn withdraw(&mut self) -> Result<()> {
+        match self.publication {
+            Publication::Withdrawn => publication_error("the survey is already withdrawn"),
+            _ => self.change_publication(Publication::Withdrawn),
         }
-        if let Some(q_changesets) = changeset.questions {
-            self.try_update_questions(q_changesets)?;
+    }
+
+    /// Lifts a moderator's withdrawal.  The survey comes back unpublished, it's up to its owners
+    /// to open it again.
+    pub fn reinstate(&mut self) -> Result<()> {
+        match self.publication {
+            Publication::Withdrawn => self.change_publication(Publication::Unpublished),
+            _ => publication_error("the survey isn't withdrawn"),
         }
-        // got to here so we succeeded and should version up.
-        self.version = self.next_version();
+    }
+
+    fn change_publication(&mut self, publication: Publication) -> Result<()> {
+        self.apply_atomically(|staged| {
+            staged.publication = publication;
+            Ok(())
+        })
+    }
+
+    /// Applies the changeset, collecting every validation failure into one `Error::InvalidFields`.
+    /// Paths point into the changeset, so `/questions/1/title` is the second entry of `questions`.
+    ///
+    /// The update is all or nothing.  If any part of the changeset fails, the survey is left exactly
+    /// as it was before the call.
+    pub fn try_update(&mut self, changeset: UpdateSurveyCommand) -> Result<()> {
+        self.apply_atomically(|staged| {
+            let mut errors = FieldErrors::new();
+
+            if let Some(new_title) = &changeset.title {
+                errors.check("/title", staged.change_title(new_title))?;
+            }
+            if let Some(new_category) = &changeset.category {
+                errors.check("/category", staged.change_category(new_category))?;
+            }
+            if let Some(new_desc) = &changeset.description {
+                errors.check("/description", staged.change_description(new_desc))?;
+            }
+            if let Some(q_changesets) = changeset.questions {
+                staged.try_update_questions(q_changesets, &mut errors)?;
+            }
+
+            errors.into_result()
+        })
+    }
+
+    // Every mutation of the survey, its questions or its choices should go through here.  Changes
+    // are made against a staged copy and only swapped in, with the version bumped, once the whole
+    // change has succeeded.  Anything that fails part way through simply drops the copy.
+    fn apply_atomically<F>(&mut self, change: F) -> Result<()>
+        where F: FnOnce(&mut Survey) -> Result<()>
+    {
+        let mut staged = self.clone();
+        change(&mut staged)?;
+
+        staged.version = self.next_version();
+        *self = staged;
         Ok(())
     }
 
@@ -143,30 +356,34 @@ impl Survey {
         Ok(())
     }
 
-    fn try_update_questions(&mut self, changesets: Vec<PatchQuestion>) -> Result<()> {
-        for changeset in changesets {
+    fn try_update_questions(&mut self, changesets: Vec<PatchQuestion>, errors: &mut FieldErrors) -> Result<()> {
+        for (idx, changeset) in changesets.into_iter().enumerate() {
+            let path = format!("/questions/{}", idx);
             if let Some(id) = &changeset.id {
                 // If there is an id, then we update the question at that id.
-                self.try_update_question(id.clone(), changeset)?;
+                self.try_update_question(id.clone(), changeset, &path, errors)?;
             } else  {
                 // Else the user wants to add a new question, so we add it.
-                let new_question = Self::create_question(changeset.try_into()?)?;
-                self.questions.push(new_question);
+                if let Some(new_question) = errors.check(path.clone(), changeset.try_into())? {
+                    if let Some(q) = Self::create_question(new_question, &path, FieldCase::Camel, errors)? {
+                        self.questions.push(q);
+                    }
+                }
             }
         }
 
         Ok(())
     }
 
-    fn try_update_question(&mut self, id: String, changeset: PatchQuestion) -> Result<()> {
+    fn try_update_question(&mut self, id: String, changeset: PatchQuestion, path: &str, errors: &mut FieldErrors) -> Result<()> {
         if let Some(new_title) = &changeset.title {
-            self.change_question_title(&id, new_title)?;
+            errors.check(format!("{}/title", path), self.change_question_title(&id, new_title))?;
         }
         if let Some(new_type) = &changeset.question_type {
-            self.change_question_type(&id, new_type)?;
+            errors.check(format!("{}/type", path), self.change_question_type(&id, new_type))?;
         }
         if let Some(changesets) = changeset.choices {
-            self.try_update_choices(&id, changesets)?;
+            self.try_update_choices(&id, changesets, path, errors)?;
         }
 
         Ok(())
@@ -213,29 +430,31 @@ impl Survey {
         Ok(())
     }
 
-    fn try_update_choices(&mut self, question_id: &String, changesets: Vec<PatchChoice>) -> Result<()> {
-        for changeset in changesets {
-            self.try_update_choice(question_id, changeset)?;
+    fn try_update_choices(&mut self, question_id: &String, changesets: Vec<PatchChoice>, question_path: &str, errors: &mut FieldErrors) -> Result<()> {
+        for (idx, changeset) in changesets.into_iter().enumerate() {
+            let path = format!("{}/choices/{}", question_path, idx);
+            self.try_update_choice(question_id, changeset, &path, errors)?;
         }
 
         Ok(())
     }
 
-    fn try_update_choice(&mut self, question_id: &String, changeset: PatchChoice) -> Result<()> {
+    fn try_update_choice(&mut self, question_id: &String, changeset: PatchChoice, path: &str, errors: &mut FieldErrors) -> Result<()> {
         if let Some(id) = &changeset.id {
             if let Some(new_title) = &changeset.title {
-                self.change_choice_title(id, new_title)?;
+                errors.check(format!("{}/title", path), self.change_choice_title(id, new_title))?;
             }
             if let Some(new_type) = &changeset.content_type {
-                self.change_choice_content_type(id, new_type)?;
+                errors.check(format!("{}/{}", path, FieldCase::Camel.content_type()), self.change_choice_content_type(id, new_type))?;
             }
             if let Some(new_content) = &changeset.content {
-                self.change_choice_content(id, new_content)?;
+                errors.check(format!("{}/content", path), self.change_choice_content(id, new_content))?;
             }
-        } else {
-            let new_choice = Self::create_choice(changeset.try_into()?)?;
-            if let Some(q) = self.questions.iter_mut().find(|q| &q.id.to_string() == question_id) {
-                q.choices.push(new_choice);
+        } else if let Some(new_choice) = errors.check(path, changeset.try_into())? {
+            if let Some(c) = Self::create_choice(new_choice, path, FieldCase::Camel, errors)? {
+                if let Some(q) = self.questions.iter_mut().find(|q| &q.id.to_string() == question_id) {
+                    q.choices.push(c);
+                }
             }
         }
 
@@ -316,21 +535,334 @@ impl Survey {
 //    }
 }
 
-impl From<SurveyDTO> for Survey {
-    fn from(dto: SurveyDTO) -> Self {
-        let questions: Vec<Question> = dto.questions.into_iter()
+// Conversions from DTOs are fallible because the DTO usually comes out of storage, and a row written
+// by an older build (or mangled by hand) may no longer pass today's validation rules.
+impl TryFrom<SurveyDTO> for Survey {
+    type Error = Error;
+
+    fn try_from(dto: SurveyDTO) -> Result<Self> {
+        let questions = dto.questions.into_iter()
             .map(|q| {
-                Question::from(q)
-            }).collect();
-        Survey {
-            id: Uuid::from_str(&dto.id).unwrap().clone(),
+                Question::try_from(q)
+            }).collect::<Result<Vec<_>>>()?;
+
+        Ok(Survey {
+            id: parse_id(&dto.id)?,
             version: dto.version,
-            author: Author::try_from(dto.author).unwrap(),
-            title: Title::try_from(dto.title).unwrap(),
-            description: Description::try_from(dto.description).unwrap(),
+            author: Author::try_from(dto.author)?,
+            title: Title::try_from(dto.title)?,
+            description: Description::try_from(dto.description)?,
             created_on: dto.created_on,
-            category: Category::try_from(dto.category).unwrap(),
+            category: Category::try_from(dto.category)?,
             questions,
+            collaborators: dto.collaborators.into_iter()
+                .map(Collaborator::try_from)
+                .collect::<Result<Vec<_>>>()?,
+            organization_id: dto.organization_id.as_ref()
+                .map(|id| parse_id(id))
+                .transpose()?,
+            response_policy: ResponsePolicy::try_from(dto.response_policy)?,
+            publication: Publication::try_from(dto.publication)?,
+        })
+    }
+}
+
+fn sharing_error<T>(msg: &str) -> Result<T> {
+    Err(ValidationError::SharingValidationError { msg: msg.to_string() }.into())
+}
+
+fn publication_error<T>(msg: &str) -> Result<T> {
+    Err(ValidationError::PublicationValidationError { msg: msg.to_string() }.into())
+}
+
+fn transfer_error<T>(msg: &str) -> Result<T> {
+    Err(ValidationError::TransferValidationError { msg: msg.to_string() }.into())
+}
+
+impl AccessControlled for Survey {
+    fn role_of(&self, actor: &Actor) -> Option<Role> {
+        let shared_role = self.collaborators.iter()
+            .find(|c| c.username.to_string() == actor.username)
+            .map(|c| c.role);
+
+        let organization_id = self.organization_id.map(|id| id.to_string());
+        survey_role(
+            actor,
+            &self.author.to_string(),
+            organization_id.as_deref(),
+            shared_role,
+        )
+    }
+}
+
+pub(crate) fn parse_id(id: &str) -> Result<Uuid> {
+    Uuid::from_str(id)
+        .map_err(|e| ValidationError::IdValidationError { msg: e.to_string() }.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn create_test_survey() -> Survey {
+        let choice = CreateChoiceCommand {
+            content: None,
+            content_type: "youtube".to_string(),
+            title: "test choice title".to_string()
+        };
+
+        let question = CreateQuestionCommand {
+            question_type: "ranked".to_string(),
+            title: "test question title".to_string(),
+            choices: vec![choice]
+        };
+
+        let create_survey_command = CreateSurveyCommand {
+            author: "test_author".to_string(),
+            title: "test survey title".to_string(),
+            description: "test survey description".to_string(),
+            category: "music".to_string(),
+            questions: vec![question]
+        };
+
+        Survey::new(&create_survey_command).unwrap()
+    }
+
+    // Serialized form of the whole aggregate, so comparisons cover every field down to the choices.
+    fn snapshot(survey: &Survey) -> String {
+        serde_json::to_string(&SurveyDTO::from(survey)).unwrap()
+    }
+
+    fn patch_question_title(id: Option<String>, title: String) -> PatchQuestion {
+        PatchQuestion {
+            id,
+            question_type: None,
+            title: Some(title),
+            choices: None,
+        }
+    }
+
+    fn new_question(title: String) -> PatchQuestion {
+        PatchQuestion {
+            id: None,
+            question_type: Some("multiple_choice".to_string()),
+            title: Some(title.clone()),
+            choices: Some(vec![
+                PatchChoice {
+                    id: None,
+                    content: Some(None),
+                    content_type: Some("text".to_string()),
+                    title: Some(title),
+                },
+            ]),
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn failed_update_leaves_survey_unchanged(
+            new_title in proptest::option::of("[a-z ]{8,64}"),
+            new_description in proptest::option::of("[a-z ]{20,64}"),
+            good_question_title in "[a-z ]{8,64}",
+            bad_title in "[a-z ]{0,7}",
+            bad_position in 0usize..3,
+        ) {
+            let mut survey = create_test_survey();
+            let before = snapshot(&survey);
+            let q_id = survey.questions()[0].id();
+
+            // Valid changes mixed in around a single invalid one, which can land anywhere in the list.
+            let mut questions = vec![
+                patch_question_title(Some(q_id.clone()), good_question_title.clone()),
+                new_question(good_question_title),
+            ];
+            questions.insert(bad_position, patch_question_title(Some(q_id), bad_title));
+
+            let changeset = UpdateSurveyCommand {
+                id: survey.id(),
+                author: "test_author".to_string(),
+                organization: None,
+                title: new_title,
+                description: new_description,
+                category: Some("technology".to_string()),
+                questions: Some(questions),
+            };
+
+            prop_assert!(survey.try_update(changeset).is_err());
+            prop_assert_eq!(snapshot(&survey), before);
+        }
+
+        #[test]
+        fn update_of_missing_question_leaves_survey_unchanged(
+            new_title in "[a-z ]{8,64}",
+            missing_id in "[a-f0-9]{32}",
+        ) {
+            let mut survey = create_test_survey();
+            let before = snapshot(&survey);
+
+            let changeset = UpdateSurveyCommand {
+                id: survey.id(),
+                author: "test_author".to_string(),
+                organization: None,
+                title: Some(new_title.clone()),
+                description: None,
+                category: None,
+                questions: Some(vec![patch_question_title(Some(missing_id), new_title)]),
+            };
+
+            prop_assert!(survey.try_update(changeset).is_err());
+            prop_assert_eq!(snapshot(&survey), before);
+        }
+
+        #[test]
+        fn successful_update_applies_and_versions_up(
+            new_title in "[a-z ]{8,64}",
+            question_title in "[a-z ]{8,64}",
+        ) {
+            let mut survey = create_test_survey();
+            let version = survey.version();
+            let q_id = survey.questions()[0].id();
+
+            let changeset = UpdateSurveyCommand {
+                id: survey.id(),
+                author: "test_author".to_string(),
+                organization: None,
+                title: Some(new_title.clone()),
+                description: None,
+                category: None,
+                questions: Some(vec![patch_question_title(Some(q_id), question_title.clone())]),
+            };
+
+            prop_assert!(survey.try_update(changeset).is_ok());
+            prop_assert_eq!(survey.title().to_string(), new_title);
+            prop_assert_eq!(survey.questions()[0].title().to_string(), question_title);
+            prop_assert_eq!(survey.version(), version + 1);
+        }
+    }
+
+    fn invalid_paths(result: Result<()>) -> Vec<String> {
+        match result {
+            Err(Error::InvalidFields { errors }) => errors.into_iter().map(|e| e.path).collect(),
+            Err(e) => panic!("expected invalid fields, got {}", e),
+            Ok(()) => panic!("expected invalid fields, got success"),
         }
     }
+
+    fn create_command_with_mistakes() -> CreateSurveyCommand {
+        CreateSurveyCommand {
+            author: "test_author".to_string(),
+            title: "short".to_string(),
+            description: "test survey description".to_string(),
+            category: "music".to_string(),
+            questions: vec![
+                CreateQuestionCommand {
+                    question_type: "essay".to_string(),
+                    title: "test question title".to_string(),
+                    choices: vec![
+                        CreateChoiceCommand {
+                            content: None,
+                            content_type: "hologram".to_string(),
+                            title: "test choice title".to_string(),
+                        },
+                        CreateChoiceCommand {
+                            content: None,
+                            content_type: "text".to_string(),
+                            title: "tiny".to_string(),
+                        },
+                    ],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn create_reports_every_invalid_field_at_once() {
+        let paths = invalid_paths(Survey::new(&create_command_with_mistakes()).map(|_| ()));
+
+        assert_eq!(paths, vec![
+            "/title",
+            "/questions/0/type",
+            "/questions/0/choices/0/content_type",
+            "/questions/0/choices/1/title",
+        ]);
+    }
+
+    #[test]
+    fn create_from_a_camel_case_document_points_at_camel_case_fields() {
+        let result = Survey::new_from_document(&create_command_with_mistakes(), FieldCase::Camel);
+        let paths = invalid_paths(result.map(|_| ()));
+
+        assert!(paths.contains(&"/questions/0/choices/0/contentType".to_string()));
+    }
+
+    #[test]
+    fn update_reports_every_invalid_field_at_once() {
+        let mut survey = create_test_survey();
+        let before = snapshot(&survey);
+        let question = &survey.questions()[0];
+        let q_id = question.id();
+        let c_id = question.choices()[0].id();
+
+        let changeset = UpdateSurveyCommand {
+            id: survey.id(),
+            author: "test_author".to_string(),
+            organization: None,
+            title: Some("short".to_string()),
+            description: None,
+            category: None,
+            questions: Some(vec![
+                PatchQuestion {
+                    id: Some(q_id),
+                    question_type: None,
+                    title: None,
+                    choices: Some(vec![
+                        PatchChoice {
+                            id: Some(c_id),
+                            content: None,
+                            content_type: Some("hologram".to_string()),
+                            title: None,
+                        },
+                        PatchChoice {
+                            id: None,
+                            content: Some(None),
+                            content_type: Some("hologram".to_string()),
+                            title: Some("tiny".to_string()),
+                        },
+                    ]),
+                },
+                PatchQuestion {
+                    id: None,
+                    question_type: Some("essay".to_string()),
+                    title: Some("test question title".to_string()),
+                    choices: Some(vec![]),
+                },
+            ]),
+        };
+
+        assert_eq!(invalid_paths(survey.try_update(changeset)), vec![
+            "/title",
+            "/questions/0/choices/0/contentType",
+            "/questions/0/choices/1/contentType",
+            "/questions/0/choices/1/title",
+            "/questions/1/type",
+        ]);
+        assert_eq!(snapshot(&survey), before);
+    }
+
+    #[test]
+    fn a_withdrawn_survey_stays_closed_until_reinstated() {
+        let mut survey = create_test_survey();
+        survey.unpublish().unwrap();
+        survey.publish().unwrap();
+
+        survey.withdraw().unwrap();
+        assert!(survey.publish().is_err());
+        assert!(survey.unpublish().is_err());
+
+        survey.reinstate().unwrap();
+        assert_eq!(survey.publication(), &Publication::Unpublished);
+        survey.publish().unwrap();
+        assert!(survey.publication().accepts_responses());
+    }
 }
@@ -1,11 +1,11 @@
 use uuid::Uuid;
 use crate::value_objects::{QuestionType, Title};
-use crate::survey::Choice;
+use crate::survey::{Choice, parse_id};
 use crate::dtos::QuestionDTO;
-use std::str::FromStr;
+use crate::errors::{Error, Result};
 use std::convert::TryFrom;
 
-#[derive(Entity)]
+#[derive(Entity, Clone)]
 pub struct Question {
     pub(super) id: Uuid,
     pub(super) kind: QuestionType,
@@ -13,18 +13,20 @@ pub struct Question {
     pub(super) choices: Vec<Choice>
 }
 
-impl From<QuestionDTO> for Question {
-    fn from(dto: QuestionDTO) -> Self {
-        let choices: Vec<Choice> = dto.choices.into_iter()
+impl TryFrom<QuestionDTO> for Question {
+    type Error = Error;
+
+    fn try_from(dto: QuestionDTO) -> Result<Self> {
+        let choices = dto.choices.into_iter()
             .map(|c| {
-                Choice::from(c)
-            }).collect();
-        Question {
-            id: Uuid::from_str(&dto.id).unwrap().clone(),
-            kind: QuestionType::try_from(dto.kind).unwrap(),
-            title: Title::try_from(dto.title).unwrap(),
+                Choice::try_from(c)
+            }).collect::<Result<Vec<_>>>()?;
+
+        Ok(Question {
+            id: parse_id(&dto.id)?,
+            kind: QuestionType::try_from(dto.kind)?,
+            title: Title::try_from(dto.title)?,
             choices,
-        }
+        })
     }
 }
-
@@ -0,0 +1,32 @@
+use serde::Serialize;
+use domain_patterns::event::DomainEvent;
+use domain_patterns::message::Message;
+use domain_patterns::models::{Entity, AggregateRoot};
+use uuid::Uuid;
+use crate::user::User;
+
+#[derive(DomainEvent, Serialize)]
+pub struct UserRegisteredEvent {
+    pub id: String,
+    pub aggregate_id: String,
+    pub version: u64,
+    pub occurred: i64,
+    pub username: String,
+}
+
+impl From<&User> for UserRegisteredEvent {
+    fn from(user: &User) -> Self {
+        UserRegisteredEvent {
+            id: Uuid::new_v4().to_string(),
+            aggregate_id: user.id(),
+            version: user.version(),
+            occurred: user.created_on,
+            username: user.username.to_string(),
+        }
+    }
+}
+
+#[derive(DomainEvents)]
+pub enum UserEvents {
+    UserRegisteredEvent(UserRegisteredEvent),
+}
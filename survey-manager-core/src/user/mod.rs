@@ -0,0 +1,143 @@
+pub mod events;
+pub use events::*;
+
+use crate::value_objects::{Username, ValidationError};
+use uuid::Uuid;
+use domain_patterns::models::AggregateRoot;
+use chrono::Utc;
+use crate::app_services::accounts::RegisterUserCommand;
+use crate::app_services::token::ADMIN_ROLE;
+use crate::errors::{Result, FieldErrors};
+use crate::errors::Error;
+use crate::dtos::UserDTO;
+use crate::survey::parse_id;
+use std::convert::TryFrom;
+
+const MIN_PASSWORD_LENGTH: usize = 10;
+const MAX_PASSWORD_LENGTH: usize = 128;
+
+/// Someone who can log in and own surveys.  Passwords are only ever held as argon2 hashes.
+///
+/// There's no way to become an admin through the service itself.  Operators grant it directly in
+/// storage, which keeps a compromised admin from minting more of them.
+#[derive(Entity, Clone)]
+pub struct User {
+    id: Uuid,
+    version: u64,
+    username: Username,
+    password_hash: String,
+    created_on: i64,
+    admin: bool,
+    // A locked user can't log in or refresh.  Set by moderators.
+    locked: bool,
+}
+
+impl AggregateRoot for User {
+    type Events = UserEvents;
+
+    type Error = Error;
+
+    fn version(&self) -> u64 {
+        self.version
+    }
+}
+
+impl User {
+    /// Validates the registration and hashes the password.  Like `Survey::new`, every problem is
+    /// reported at once in an `Error::InvalidFields`.
+    pub fn register(cmd: &RegisterUserCommand) -> Result<User> {
+        let mut errors = FieldErrors::new();
+
+        let username = errors.check("/username", Username::try_from(cmd.username.clone()))?;
+        errors.check("/password", validate_password(&cmd.password))?;
+
+        errors.into_result()?;
+
+        Ok(User {
+            id: Uuid::new_v4(),
+            version: 0,
+            // Safe to unwrap, a username that failed to validate was returned above.
+            username: username.unwrap(),
+            password_hash: hash_password(&cmd.password)?,
+            created_on: Utc::now().timestamp(),
+            admin: false,
+            locked: false,
+        })
+    }
+
+    /// True if `password` matches the stored hash.  A hash we can't parse never matches.
+    pub fn verify_password(&self, password: &str) -> bool {
+        argon2::verify_encoded(&self.password_hash, password.as_bytes())
+            .unwrap_or(false)
+    }
+
+    /// Site wide roles to put in this user's tokens.
+    pub fn roles(&self) -> Vec<String> {
+        if self.admin {
+            vec![ADMIN_ROLE.to_string()]
+        } else {
+            vec![]
+        }
+    }
+
+    pub fn lock(&mut self) -> Result<()> {
+        if self.locked {
+            return Err(ValidationError::LockValidationError { msg: "the account is already locked".to_string() }.into());
+        }
+
+        self.locked = true;
+        self.version = self.next_version();
+        Ok(())
+    }
+
+    pub fn unlock(&mut self) -> Result<()> {
+        if !self.locked {
+            return Err(ValidationError::LockValidationError { msg: "the account isn't locked".to_string() }.into());
+        }
+
+        self.locked = false;
+        self.version = self.next_version();
+        Ok(())
+    }
+}
+
+fn validate_password(password: &str) -> Result<()> {
+    let len = password.chars().count();
+    if !(MIN_PASSWORD_LENGTH..=MAX_PASSWORD_LENGTH).contains(&len) {
+        return Err(
+            ValidationError::PasswordValidationError {
+                msg: format!("length must be between {} and {}", MIN_PASSWORD_LENGTH, MAX_PASSWORD_LENGTH),
+            }.into()
+        );
+    }
+
+    Ok(())
+}
+
+fn hash_password(password: &str) -> Result<String> {
+    // A fresh v4 uuid is 16 random bytes, which is exactly what argon2 wants for a salt.
+    let salt = Uuid::new_v4();
+    let config = argon2::Config {
+        variant: argon2::Variant::Argon2id,
+        ..argon2::Config::default()
+    };
+
+    argon2::hash_encoded(password.as_bytes(), salt.as_bytes(), &config)
+        .map_err(|_| Error::UnknownFailure)
+}
+
+impl TryFrom<UserDTO> for User {
+    type Error = Error;
+
+    fn try_from(dto: UserDTO) -> Result<Self> {
+        Ok(User {
+            id: parse_id(&dto.id)?,
+            version: dto.version,
+            username: Username::try_from(dto.username)?,
+            password_hash: dto.password_hash,
+            created_on: dto.created_on,
+            admin: dto.admin,
+            locked: dto.locked,
+        })
+    }
+}
@@ -0,0 +1,51 @@
+use domain_patterns::models::ValueObject;
+use crate::value_objects::ValidationError;
+use crate::errors::{Error, Result};
+
+/// An address invitations are mailed to.  Only the shape is checked, whether mail actually arrives
+/// is up to the mailer.
+#[derive(ValueSetup)]
+pub struct Email {
+    value: String,
+}
+
+impl ValueObject<String> for Email {
+    type ValueError = Error;
+
+    fn validate(value: &String) -> Result<()> {
+        // The longest address SMTP will carry.
+        let max = 254;
+
+        if value.len() > max {
+            return Err(
+                ValidationError::EmailValidationError {
+                    msg: format!("length must be at most {}", max),
+                }.into()
+            );
+        }
+
+        let mut parts = value.splitn(2, '@');
+        let valid = match (parts.next(), parts.next()) {
+            (Some(local), Some(domain)) => !local.is_empty()
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !domain.contains('@'),
+            _ => false,
+        };
+
+        if !valid || value.chars().any(|c| c.is_whitespace() || c.is_control()) {
+            return Err(
+                ValidationError::EmailValidationError {
+                    msg: "expected an address like name@example.com".to_string(),
+                }.into()
+            );
+        }
+
+        Ok(())
+    }
+
+    fn value(&self) -> String {
+        self.value.clone()
+    }
+}
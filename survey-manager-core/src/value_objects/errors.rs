@@ -7,6 +7,18 @@ pub enum ValidationError {
     AuthorsValidationError {
         msg: String,
     },
+    #[snafu(display("Username failed to validate. {}", msg))]
+    UsernameValidationError {
+        msg: String,
+    },
+    #[snafu(display("Password failed to validate. {}", msg))]
+    PasswordValidationError {
+        msg: String,
+    },
+    #[snafu(display("Email address failed to validate. {}", msg))]
+    EmailValidationError {
+        msg: String,
+    },
     #[snafu(display("Title failed to validate. {}", msg))]
     TitleValidationError {
         msg: String,
@@ -16,6 +28,11 @@ pub enum ValidationError {
         msg: String,
     },
 
+    #[snafu(display("Answer failed to validate. {}", msg))]
+    AnswerValidationError {
+        msg: String,
+    },
+
     #[snafu(display("Not a valid category."))]
     CategoryValidationError,
 
@@ -28,6 +45,70 @@ pub enum ValidationError {
     #[snafu(display("Not a valid question type."))]
     QuestionTypeValidationError,
 
+    #[snafu(display("Not a valid role, expected owner, editor, viewer or analyst."))]
+    RoleValidationError,
+
+    #[snafu(display("Not a valid organization role, expected owner, admin or member."))]
+    OrgRoleValidationError,
+
+    #[snafu(display("Not a valid response policy, expected anyone, link or authenticated."))]
+    ResponsePolicyValidationError,
+
+    #[snafu(display("Respondent can't answer this survey. {}", msg))]
+    RespondentValidationError {
+        msg: String,
+    },
+
+    #[snafu(display("Survey publication can't be changed that way. {}", msg))]
+    PublicationValidationError {
+        msg: String,
+    },
+
+    #[snafu(display("Response can't be resumed. {}", msg))]
+    ResumeValidationError {
+        msg: String,
+    },
+
+    #[snafu(display("Organization name failed to validate. {}", msg))]
+    OrganizationNameValidationError {
+        msg: String,
+    },
+
+    #[snafu(display("Organization membership can't be changed that way. {}", msg))]
+    MembershipValidationError {
+        msg: String,
+    },
+
+    #[snafu(display("Account lock can't be changed. {}", msg))]
+    LockValidationError {
+        msg: String,
+    },
+
+    #[snafu(display("A reason is required for moderation. {}", msg))]
+    ReasonValidationError {
+        msg: String,
+    },
+
+    #[snafu(display("Survey ownership can't be transferred that way. {}", msg))]
+    TransferValidationError {
+        msg: String,
+    },
+
+    #[snafu(display("Invitation link is not valid. {}", msg))]
+    InvitationValidationError {
+        msg: String,
+    },
+
+    #[snafu(display("Survey can't be shared that way. {}", msg))]
+    SharingValidationError {
+        msg: String,
+    },
+
+    #[snafu(display("Not a valid id. {}", msg))]
+    IdValidationError {
+        msg: String,
+    },
+
     // TODO: Does this really go here?
     #[snafu(display("Adding a question requires that you supply at least one choice for the question."))]
     MissingChoicesError,
@@ -18,3 +18,24 @@ pub use content_type::*;
 
 pub mod question_type;
 pub use question_type::*;
+
+pub mod username;
+pub use username::*;
+
+pub mod role;
+pub use role::*;
+
+pub mod organization_name;
+pub use organization_name::*;
+
+pub mod org_role;
+pub use org_role::*;
+
+pub mod response_policy;
+pub use response_policy::*;
+
+pub mod publication;
+pub use publication::*;
+
+pub mod email;
+pub use email::*;
@@ -0,0 +1,59 @@
+use domain_patterns::models::ValueObject;
+use std::convert::TryFrom;
+use crate::value_objects::ValidationError;
+use crate::errors::{Error, Result};
+
+/// A member's standing within an organization.  Owners and admins manage the organization and
+/// everything it owns, members work on its surveys.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OrgRole {
+    Owner,
+    Admin,
+    Member,
+}
+
+impl std::fmt::Display for OrgRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.value())
+    }
+}
+
+impl TryFrom<String> for OrgRole {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        Self::validate(&value)?;
+
+        let role = match value.as_ref() {
+            "owner" => OrgRole::Owner,
+            "admin" => OrgRole::Admin,
+            "member" => OrgRole::Member,
+            _ => OrgRole::Member,
+        };
+
+        Ok(role)
+    }
+}
+
+impl ValueObject<String> for OrgRole {
+    type ValueError = Error;
+
+    fn validate(value: &String) -> Result<()> {
+        match value.as_ref() {
+            "owner" => Ok(()),
+            "admin" => Ok(()),
+            "member" => Ok(()),
+            _ => Err(
+                ValidationError::OrgRoleValidationError.into()
+            ),
+        }
+    }
+
+    fn value(&self) -> String {
+        match self {
+            OrgRole::Owner => "owner".to_string(),
+            OrgRole::Admin => "admin".to_string(),
+            OrgRole::Member => "member".to_string(),
+        }
+    }
+}
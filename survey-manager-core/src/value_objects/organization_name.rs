@@ -0,0 +1,32 @@
+use domain_patterns::models::ValueObject;
+use crate::errors::{Error, Result};
+use crate::value_objects::ValidationError;
+
+#[derive(ValueSetup)]
+pub struct OrganizationName {
+    value: String,
+}
+
+impl ValueObject<String> for OrganizationName {
+    type ValueError = Error;
+
+    fn validate(value: &String) -> Result<()> {
+        let min = 2;
+        let max = 64;
+        let len = value.trim().len();
+
+        if len < min || len > max {
+            return Err(
+                ValidationError::OrganizationNameValidationError {
+                    msg: format!("Length must be between {} and {}.", min, max),
+                }.into()
+            );
+        }
+
+        Ok(())
+    }
+
+    fn value(&self) -> String {
+        self.value.clone()
+    }
+}
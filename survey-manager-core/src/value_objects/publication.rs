@@ -0,0 +1,73 @@
+use domain_patterns::models::ValueObject;
+use std::convert::TryFrom;
+use crate::value_objects::ValidationError;
+use crate::errors::{Error, Result};
+
+/// Whether a survey is taking responses.
+///
+/// * `Published` - open for responses.
+/// * `Unpublished` - closed by its owners, who can open it again whenever they like.
+/// * `Withdrawn` - taken down by a moderator.  Only a moderator can reinstate it, which leaves it
+///   unpublished for its owners to decide on.
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum Publication {
+    // Surveys have always taken responses as soon as they were created, so that stays the default.
+    #[default]
+    Published,
+    Unpublished,
+    Withdrawn,
+}
+
+impl Publication {
+    pub fn accepts_responses(&self) -> bool {
+        *self == Publication::Published
+    }
+}
+
+impl std::fmt::Display for Publication {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.value())
+    }
+}
+
+impl TryFrom<String> for Publication {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        Self::validate(&value)?;
+
+        let publication = match value.as_ref() {
+            "published" => Publication::Published,
+            "unpublished" => Publication::Unpublished,
+            "withdrawn" => Publication::Withdrawn,
+            _ => Publication::Published,
+        };
+
+        Ok(publication)
+    }
+}
+
+impl ValueObject<String> for Publication {
+    type ValueError = Error;
+
+    fn validate(value: &String) -> Result<()> {
+        match value.as_ref() {
+            "published" => Ok(()),
+            "unpublished" => Ok(()),
+            "withdrawn" => Ok(()),
+            _ => Err(
+                ValidationError::PublicationValidationError {
+                    msg: "expected published, unpublished or withdrawn".to_string(),
+                }.into()
+            ),
+        }
+    }
+
+    fn value(&self) -> String {
+        match self {
+            Publication::Published => "published".to_string(),
+            Publication::Unpublished => "unpublished".to_string(),
+            Publication::Withdrawn => "withdrawn".to_string(),
+        }
+    }
+}
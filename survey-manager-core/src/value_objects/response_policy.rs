@@ -0,0 +1,95 @@
+use domain_patterns::models::ValueObject;
+use sha2::{Sha256, Digest};
+use std::convert::TryFrom;
+use crate::value_objects::ValidationError;
+use crate::errors::{Error, Result};
+
+/// Who may answer a survey.  Each mode also decides how a second response from the same person is
+/// recognized, so the two can never be set in a combination that leaves duplicates unchecked.
+///
+/// * `Anyone` - no login or link needed.  Duplicates are recognized by ip address and cookie.
+/// * `LinkHolders` - only someone holding a signed response link.  Each link answers once.
+/// * `Authenticated` - only logged in users.  Each user answers once.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ResponsePolicy {
+    /// Surveys have always been open to anyone, so that stays the default.
+    #[default]
+    Anyone,
+    LinkHolders,
+    Authenticated,
+}
+
+/// What a submission knows about whoever sent it.  Link tokens must already have had their
+/// signature checked by the caller, the policy only looks at whether one is there.
+#[derive(Clone, Debug, Default)]
+pub struct Respondent {
+    pub user_id: Option<String>,
+    pub link_token: Option<String>,
+    pub ip_address: Option<String>,
+    pub cookie: Option<String>,
+}
+
+impl ResponsePolicy {
+    /// Checks `respondent` may answer under this policy and hands back the key their response
+    /// should be recorded under.  A submission handler must refuse a response when one is already
+    /// stored for the survey under the same key.
+    pub fn admit(&self, respondent: &Respondent) -> Result<String> {
+        match self {
+            ResponsePolicy::Authenticated => {
+                let user_id = respondent.user_id.as_ref().ok_or(Error::NotAuthorized)?;
+                Ok(format!("user:{}", user_id))
+            },
+            ResponsePolicy::LinkHolders => {
+                let link_token = respondent.link_token.as_ref().ok_or(Error::NotAuthorized)?;
+                // Hashed like refresh tokens, so stored keys can't be replayed as links.
+                Ok(format!("link:{:x}", Sha256::digest(link_token.as_bytes())))
+            },
+            ResponsePolicy::Anyone => {
+                match (&respondent.ip_address, &respondent.cookie) {
+                    (Some(ip_address), Some(cookie)) => {
+                        let fingerprint = Sha256::digest(format!("{}\n{}", ip_address, cookie).as_bytes());
+                        Ok(format!("fingerprint:{:x}", fingerprint))
+                    },
+                    _ => Err(ValidationError::RespondentValidationError {
+                        msg: "anonymous responses need both an ip address and a cookie".to_string(),
+                    }.into()),
+                }
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for ResponsePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.value())
+    }
+}
+
+impl TryFrom<String> for ResponsePolicy {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        match value.as_ref() {
+            "anyone" => Ok(ResponsePolicy::Anyone),
+            "link" => Ok(ResponsePolicy::LinkHolders),
+            "authenticated" => Ok(ResponsePolicy::Authenticated),
+            _ => Err(ValidationError::ResponsePolicyValidationError.into()),
+        }
+    }
+}
+
+impl ValueObject<String> for ResponsePolicy {
+    type ValueError = Error;
+
+    fn validate(value: &String) -> Result<()> {
+        ResponsePolicy::try_from(value.clone()).map(|_| ())
+    }
+
+    fn value(&self) -> String {
+        match self {
+            ResponsePolicy::Anyone => "anyone".to_string(),
+            ResponsePolicy::LinkHolders => "link".to_string(),
+            ResponsePolicy::Authenticated => "authenticated".to_string(),
+        }
+    }
+}
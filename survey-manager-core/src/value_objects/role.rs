@@ -0,0 +1,63 @@
+use domain_patterns::models::ValueObject;
+use std::convert::TryFrom;
+use crate::value_objects::ValidationError;
+use crate::errors::{Error, Result};
+
+/// How much a user may do with a survey.  The author is always the owner, the other roles are
+/// granted by sharing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Role {
+    Owner,
+    Editor,
+    Viewer,
+    Analyst,
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.value())
+    }
+}
+
+impl TryFrom<String> for Role {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        Self::validate(&value)?;
+
+        let role = match value.as_ref() {
+            "owner" => Role::Owner,
+            "editor" => Role::Editor,
+            "viewer" => Role::Viewer,
+            "analyst" => Role::Analyst,
+            _ => Role::Viewer,
+        };
+
+        Ok(role)
+    }
+}
+
+impl ValueObject<String> for Role {
+    type ValueError = Error;
+
+    fn validate(value: &String) -> Result<()> {
+        match value.as_ref() {
+            "owner" => Ok(()),
+            "editor" => Ok(()),
+            "viewer" => Ok(()),
+            "analyst" => Ok(()),
+            _ => Err(
+                ValidationError::RoleValidationError.into()
+            ),
+        }
+    }
+
+    fn value(&self) -> String {
+        match self {
+            Role::Owner => "owner".to_string(),
+            Role::Editor => "editor".to_string(),
+            Role::Viewer => "viewer".to_string(),
+            Role::Analyst => "analyst".to_string(),
+        }
+    }
+}
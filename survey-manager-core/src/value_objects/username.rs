@@ -0,0 +1,42 @@
+use domain_patterns::models::ValueObject;
+use crate::value_objects::ValidationError;
+use crate::errors::{Error, Result};
+
+/// The name a user logs in with.  It doubles as the author on every survey they create, so it has
+/// to satisfy `Author` as well.
+#[derive(ValueSetup)]
+pub struct Username {
+    value: String,
+}
+
+impl ValueObject<String> for Username {
+    type ValueError = Error;
+
+    fn validate(value: &String) -> Result<()> {
+        let min = 3;
+        let max = 32;
+        let len = value.len();
+
+        if len < min || len > max {
+            return Err(
+                ValidationError::UsernameValidationError {
+                    msg: format!("length must be between {} and {}", min, max),
+                }.into()
+            );
+        }
+
+        if !value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.') {
+            return Err(
+                ValidationError::UsernameValidationError {
+                    msg: "only letters, digits, '_', '-' and '.' are allowed".to_string(),
+                }.into()
+            );
+        }
+
+        Ok(())
+    }
+
+    fn value(&self) -> String {
+        self.value.clone()
+    }
+}
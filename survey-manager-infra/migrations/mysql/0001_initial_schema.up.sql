@@ -0,0 +1,12 @@
+-- The schema as it stood before migrations.  Databases created from the old init/schema.sql have
+-- exactly this, and are marked as being at this version instead of having it run again.
+
+CREATE TABLE survey (
+	id VARCHAR(64) PRIMARY KEY,
+	version BIGINT UNSIGNED NOT NULL,
+	author VARCHAR(64) NOT NULL,
+	title VARCHAR(128) NOT NULL,
+	category VARCHAR(64) NOT NULL,
+	created_on BIGINT NOT NULL,
+	survey_data JSON NOT NULL
+);
@@ -0,0 +1,17 @@
+-- Responses to surveys.  seq keeps them in the order they were stored, so exports can page
+-- through them with a stable cursor.
+CREATE TABLE survey_response (
+	seq BIGINT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+	id VARCHAR(64) NOT NULL,
+	survey_id VARCHAR(64) NOT NULL,
+	-- The survey version answered.  Responses stored before versions were recorded have none.
+	survey_version BIGINT UNSIGNED NULL,
+	-- Key from the survey's response policy, one response per key.  Responses stored before
+	-- policies were enforced have none, and any number of those can share the index.
+	respondent VARCHAR(128) NULL,
+	answers JSON NOT NULL,
+	submitted_on BIGINT NOT NULL,
+	UNIQUE INDEX survey_response_id (id),
+	UNIQUE INDEX survey_response_respondent (survey_id, respondent),
+	INDEX survey_response_survey (survey_id, seq)
+);
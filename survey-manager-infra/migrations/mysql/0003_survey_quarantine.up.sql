@@ -0,0 +1,7 @@
+-- Rows moved aside by `survey-doctor --quarantine` because they no longer load.
+CREATE TABLE survey_quarantine (
+	id VARCHAR(64) PRIMARY KEY,
+	survey_data LONGTEXT NOT NULL,
+	reason TEXT NOT NULL,
+	quarantined_on BIGINT NOT NULL
+);
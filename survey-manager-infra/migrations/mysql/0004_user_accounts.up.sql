@@ -0,0 +1,10 @@
+CREATE TABLE user_account (
+	id VARCHAR(64) PRIMARY KEY,
+	version BIGINT UNSIGNED NOT NULL,
+	username VARCHAR(32) NOT NULL UNIQUE,
+	password_hash VARCHAR(255) NOT NULL,
+	created_on BIGINT NOT NULL,
+	-- Granted by hand, e.g. UPDATE user_account SET admin = TRUE WHERE username = '...'.
+	admin BOOLEAN NOT NULL DEFAULT FALSE,
+	locked BOOLEAN NOT NULL DEFAULT FALSE
+);
@@ -0,0 +1,13 @@
+-- Only a sha256 of each refresh token is kept.  Tokens from one login share a family_id so they
+-- can be revoked together.
+CREATE TABLE refresh_token (
+	token_hash CHAR(64) PRIMARY KEY,
+	family_id VARCHAR(64) NOT NULL,
+	user_id VARCHAR(64) NOT NULL,
+	username VARCHAR(32) NOT NULL,
+	organization_id VARCHAR(64) NULL,
+	expires_at BIGINT NOT NULL,
+	used BOOLEAN NOT NULL DEFAULT FALSE,
+	revoked BOOLEAN NOT NULL DEFAULT FALSE,
+	INDEX refresh_token_family (family_id)
+);
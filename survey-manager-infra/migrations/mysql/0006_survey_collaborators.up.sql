@@ -0,0 +1,9 @@
+-- Copied out of survey_data on every write so surveys shared with someone can be found by username.
+CREATE TABLE survey_collaborator (
+	survey_id VARCHAR(64) NOT NULL,
+	username VARCHAR(64) NOT NULL,
+	role VARCHAR(16) NOT NULL,
+	PRIMARY KEY (survey_id, username),
+	INDEX survey_collaborator_username (username),
+	FOREIGN KEY (survey_id) REFERENCES survey (id) ON DELETE CASCADE
+);
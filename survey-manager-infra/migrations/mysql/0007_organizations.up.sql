@@ -0,0 +1,8 @@
+-- Members and their roles live in organization_data.  Surveys point here through organization_id.
+CREATE TABLE organization (
+	id VARCHAR(64) PRIMARY KEY,
+	version BIGINT UNSIGNED NOT NULL,
+	name VARCHAR(64) NOT NULL,
+	created_on BIGINT NOT NULL,
+	organization_data JSON NOT NULL
+);
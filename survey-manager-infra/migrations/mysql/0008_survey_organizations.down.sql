@@ -0,0 +1,3 @@
+ALTER TABLE survey
+	DROP INDEX survey_organization,
+	DROP COLUMN organization_id;
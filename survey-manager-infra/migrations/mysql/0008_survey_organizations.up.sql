@@ -0,0 +1,4 @@
+-- Set when an organization owns the survey, copied out of survey_data like the other columns.
+ALTER TABLE survey
+	ADD COLUMN organization_id VARCHAR(64) NULL AFTER created_on,
+	ADD INDEX survey_organization (organization_id);
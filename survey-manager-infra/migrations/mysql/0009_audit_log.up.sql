@@ -0,0 +1,18 @@
+-- Every administrative action, written before the action is taken.  seq keeps entries from the same
+-- second in order.  The triggers make the table append only for everyone, including the service.
+CREATE TABLE audit_log (
+	seq BIGINT UNSIGNED AUTO_INCREMENT PRIMARY KEY,
+	id VARCHAR(64) NOT NULL UNIQUE,
+	actor VARCHAR(64) NOT NULL,
+	action VARCHAR(32) NOT NULL,
+	target VARCHAR(64) NOT NULL,
+	reason TEXT NOT NULL,
+	occurred BIGINT NOT NULL,
+	INDEX audit_log_target (target, occurred)
+);
+
+CREATE TRIGGER audit_log_no_update BEFORE UPDATE ON audit_log
+	FOR EACH ROW SIGNAL SQLSTATE '45000' SET MESSAGE_TEXT = 'audit_log is append only';
+
+CREATE TRIGGER audit_log_no_delete BEFORE DELETE ON audit_log
+	FOR EACH ROW SIGNAL SQLSTATE '45000' SET MESSAGE_TEXT = 'audit_log is append only';
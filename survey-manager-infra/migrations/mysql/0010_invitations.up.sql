@@ -0,0 +1,10 @@
+-- One row per invited recipient.  Links aren't stored, they're rebuilt from id and expires_at.
+CREATE TABLE invitation (
+	id VARCHAR(64) PRIMARY KEY,
+	survey_id VARCHAR(64) NOT NULL,
+	recipient VARCHAR(254) NOT NULL,
+	status VARCHAR(16) NOT NULL,
+	created_at BIGINT NOT NULL,
+	expires_at BIGINT NOT NULL,
+	INDEX invitation_survey (survey_id, status)
+);
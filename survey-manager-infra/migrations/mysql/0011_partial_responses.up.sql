@@ -0,0 +1,15 @@
+-- Responses saved part way through.  Only a hash of the resume token is kept.  Rows stay once
+-- they're submitted or abandoned, the funnel statistics count them.
+CREATE TABLE survey_partial_response (
+	seq BIGINT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+	token_hash CHAR(64) NOT NULL,
+	survey_id VARCHAR(64) NOT NULL,
+	survey_version BIGINT UNSIGNED NOT NULL,
+	respondent VARCHAR(128) NOT NULL,
+	answers JSON NOT NULL,
+	started_on BIGINT NOT NULL,
+	expires_at BIGINT NOT NULL,
+	response_id VARCHAR(64) NULL,
+	UNIQUE INDEX survey_partial_response_token (token_hash),
+	INDEX survey_partial_response_survey (survey_id, seq)
+);
@@ -0,0 +1,8 @@
+-- The definition of every survey version that was answered, so results can be read against the
+-- survey as the respondent saw it.
+CREATE TABLE survey_snapshot (
+	survey_id VARCHAR(64) NOT NULL,
+	version BIGINT UNSIGNED NOT NULL,
+	definition JSON NOT NULL,
+	PRIMARY KEY (survey_id, version)
+);
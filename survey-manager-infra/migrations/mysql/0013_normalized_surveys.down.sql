@@ -0,0 +1,4 @@
+DROP TABLE normalized_survey_collaborator;
+DROP TABLE normalized_choice;
+DROP TABLE normalized_question;
+DROP TABLE normalized_survey;
@@ -0,0 +1,52 @@
+-- Surveys for SURVEY_BACKEND=mysql-normalized, a row per survey, question, choice and collaborator
+-- instead of a survey_data blob, so questions and choices can be queried and referenced by key.
+-- Positions keep questions and choices in the order the survey has them.
+
+CREATE TABLE normalized_survey (
+	id VARCHAR(64) PRIMARY KEY,
+	version BIGINT UNSIGNED NOT NULL,
+	author VARCHAR(64) NOT NULL,
+	title VARCHAR(128) NOT NULL,
+	description TEXT NOT NULL,
+	category VARCHAR(64) NOT NULL,
+	created_on BIGINT NOT NULL,
+	organization_id VARCHAR(64) NULL,
+	response_policy VARCHAR(32) NOT NULL,
+	publication VARCHAR(16) NOT NULL,
+	INDEX normalized_survey_author (author),
+	INDEX normalized_survey_organization (organization_id)
+);
+
+CREATE TABLE normalized_question (
+	id VARCHAR(64) PRIMARY KEY,
+	survey_id VARCHAR(64) NOT NULL,
+	position INT UNSIGNED NOT NULL,
+	kind VARCHAR(32) NOT NULL,
+	title VARCHAR(128) NOT NULL,
+	INDEX normalized_question_survey (survey_id, position),
+	INDEX normalized_question_kind (kind),
+	FOREIGN KEY (survey_id) REFERENCES normalized_survey (id) ON DELETE CASCADE
+);
+
+CREATE TABLE normalized_choice (
+	id VARCHAR(64) PRIMARY KEY,
+	question_id VARCHAR(64) NOT NULL,
+	position INT UNSIGNED NOT NULL,
+	content TEXT NULL,
+	content_type VARCHAR(32) NOT NULL,
+	title VARCHAR(128) NOT NULL,
+	INDEX normalized_choice_question (question_id, position),
+	FOREIGN KEY (question_id) REFERENCES normalized_question (id) ON DELETE CASCADE
+);
+
+-- seq keeps collaborators in the order they were added.
+CREATE TABLE normalized_survey_collaborator (
+	survey_id VARCHAR(64) NOT NULL,
+	username VARCHAR(64) NOT NULL,
+	role VARCHAR(16) NOT NULL,
+	seq BIGINT UNSIGNED NOT NULL AUTO_INCREMENT,
+	PRIMARY KEY (survey_id, username),
+	INDEX normalized_survey_collaborator_seq (seq),
+	INDEX normalized_survey_collaborator_username (username),
+	FOREIGN KEY (survey_id) REFERENCES normalized_survey (id) ON DELETE CASCADE
+);
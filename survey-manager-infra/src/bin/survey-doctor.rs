@@ -0,0 +1,68 @@
+//! Reports stored surveys that fail to load, and optionally quarantines them.
+//!
+//! ```text
+//! DATABASE_URL=mysql://... survey-doctor              # report only
+//! DATABASE_URL=mysql://... survey-doctor --quarantine # move failing rows to survey_quarantine
+//! ```
+//!
+//! Only surveys stored by `SURVEY_BACKEND=mysql`, the default, can be checked.  Any other backend
+//! is refused with exit code 2.
+//!
+//! Exits non-zero when failing rows were found and left in place.  With `CACHE_URL` set, cached
+//! copies of quarantined surveys are evicted too, including the ones API nodes keep in memory.
+use survey_manager_infra::mysql_repos::SurveyIntegrityChecker;
+use survey_manager_infra::cache_repo_decorators::{CacheStore, RedisCacheStore, survey_eviction_keys, publish_invalidation};
+use std::process;
+
+fn main() {
+    let quarantine = std::env::args().skip(1).any(|arg| arg == "--quarantine");
+
+    let mut checker = SurveyIntegrityChecker::new().unwrap_or_else(|e| {
+        eprintln!("could not start checking surveys: {}", e);
+        process::exit(2);
+    });
+    let failures = match checker.find_unloadable() {
+        Ok(f) => f,
+        Err(e) => {
+            eprintln!("failed to scan surveys: {}", e);
+            process::exit(2);
+        },
+    };
+
+    if failures.is_empty() {
+        println!("all stored surveys load cleanly");
+        return;
+    }
+
+    for failure in &failures {
+        println!("{}\t{}", failure.id, failure.reason);
+    }
+    println!("{} survey(s) failed to load", failures.len());
+
+    if !quarantine {
+        process::exit(1);
+    }
+
+    let mut cache = std::env::var("CACHE_URL").ok().map(|_| RedisCacheStore::new().unwrap_or_else(|e| {
+        eprintln!("invalid cache configuration: {}", e);
+        process::exit(2);
+    }));
+    for failure in &failures {
+        let usernames = match checker.quarantine(failure) {
+            Ok(usernames) => usernames,
+            Err(e) => {
+                eprintln!("failed to quarantine {}: {}", failure.id, e);
+                process::exit(2);
+            },
+        };
+
+        if let Some(cache) = cache.as_mut() {
+            let keys = survey_eviction_keys(&failure.id, usernames.iter());
+            if let Err(e) = cache.delete(&keys).and_then(|_| publish_invalidation(cache, &keys)) {
+                eprintln!("quarantined {} but could not evict it from the cache: {}", failure.id, e);
+                process::exit(2);
+            }
+        }
+    }
+    println!("moved {} survey(s) to survey_quarantine", failures.len());
+}
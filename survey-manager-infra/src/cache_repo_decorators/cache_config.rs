@@ -0,0 +1,145 @@
+use std::time::Duration;
+use rand::Rng;
+
+/// The kinds of entry the survey caches hold.  Each gets its own lifetime, and its own counters in
+/// `cache_metrics`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeyClass {
+    /// A whole survey, under `survey_key`.
+    Survey,
+    /// A listing of surveys, under `author_listing_key`.
+    Listing,
+}
+
+/// How long cache entries live.  Read from the environment, with every setting optional:
+///
+/// * `CACHE_SURVEY_TTL` - seconds a cached survey is fresh for, default 3600.
+/// * `CACHE_LISTING_TTL` - seconds a cached listing is fresh for, default 300.
+/// * `CACHE_NEGATIVE_TTL` - seconds a "not found" is remembered for, default 30.
+/// * `CACHE_TTL_JITTER` - percentage lifetimes are randomly spread by either way, default 10.
+///   Entries filled together then don't all expire together.
+/// * `CACHE_STALE_TTL` - seconds an expired survey or listing may still be served while one caller
+///   reloads it, default 0 which turns stale-while-revalidate off.
+/// * `CACHE_LOCAL_CAPACITY` - how many surveys, and separately listings, each process keeps in
+///   memory in front of Redis, default 1000.  0 turns the in-process tier off.
+/// * `CACHE_LOCAL_TTL` - seconds an entry is kept in memory, default 5.  Invalidations normally
+///   evict entries sooner, this bounds how stale one can get if an invalidation is missed.
+/// * `CACHE_TIMEOUT_MS` - milliseconds to wait for a Redis connection or reply, default 500.
+/// * `CACHE_BREAKER_THRESHOLD` - consecutive failures after which Redis is bypassed, default 5.
+/// * `CACHE_BREAKER_COOLDOWN` - seconds Redis is bypassed for before it's tried again, default 10.
+/// * `CACHE_REVOCATION_FALLBACK` - what happens to a token when the revoked token list in Redis
+///   can't be checked, `reject` (the default) or `allow`.  See `RevocationFallback`.
+#[derive(Clone, Debug)]
+pub struct CacheConfig {
+    pub survey_ttl: Duration,
+    pub listing_ttl: Duration,
+    pub negative_ttl: Duration,
+    pub jitter_percent: u32,
+    pub stale_ttl: Duration,
+    pub local_capacity: usize,
+    pub local_ttl: Duration,
+    pub timeout: Duration,
+    pub breaker_threshold: u32,
+    pub breaker_cooldown: Duration,
+    pub revocation_fallback: RevocationFallback,
+}
+
+/// What to do with an access token when the revoked token list can't be checked, because Redis is
+/// down or the breaker is bypassing it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RevocationFallback {
+    /// Refuse the token.  A logged out session can never be used, but while Redis is unreachable
+    /// nobody can use any authenticated route.
+    Reject,
+    /// Accept the token.  Requests keep working through an outage, at the cost of honoring tokens
+    /// revoked by a logout until they expire, at most the token lifetime.
+    Allow,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            survey_ttl: Duration::from_secs(3600),
+            listing_ttl: Duration::from_secs(300),
+            negative_ttl: Duration::from_secs(30),
+            jitter_percent: 10,
+            stale_ttl: Duration::from_secs(0),
+            local_capacity: 1000,
+            local_ttl: Duration::from_secs(5),
+            timeout: Duration::from_millis(500),
+            breaker_threshold: 5,
+            breaker_cooldown: Duration::from_secs(10),
+            revocation_fallback: RevocationFallback::Reject,
+        }
+    }
+}
+
+lazy_static! {
+    static ref CACHE_CONFIG: CacheConfig = CacheConfig::from_env();
+}
+
+/// The configuration read from the environment on first use, shared by the whole process.
+pub fn cache_config() -> &'static CacheConfig {
+    &CACHE_CONFIG
+}
+
+impl CacheConfig {
+    /// Anything unset or unparseable keeps its default.
+    pub fn from_env() -> CacheConfig {
+        let defaults = CacheConfig::default();
+        let seconds = |name: &str, default: Duration| std::env::var(name).ok()
+            .and_then(|secs| secs.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(default);
+
+        CacheConfig {
+            survey_ttl: seconds("CACHE_SURVEY_TTL", defaults.survey_ttl),
+            listing_ttl: seconds("CACHE_LISTING_TTL", defaults.listing_ttl),
+            negative_ttl: seconds("CACHE_NEGATIVE_TTL", defaults.negative_ttl),
+            jitter_percent: std::env::var("CACHE_TTL_JITTER").ok()
+                .and_then(|percent| percent.parse().ok())
+                .map(|percent: u32| percent.min(100))
+                .unwrap_or(defaults.jitter_percent),
+            stale_ttl: seconds("CACHE_STALE_TTL", defaults.stale_ttl),
+            local_capacity: std::env::var("CACHE_LOCAL_CAPACITY").ok()
+                .and_then(|capacity| capacity.parse().ok())
+                .unwrap_or(defaults.local_capacity),
+            local_ttl: seconds("CACHE_LOCAL_TTL", defaults.local_ttl),
+            timeout: std::env::var("CACHE_TIMEOUT_MS").ok()
+                .and_then(|ms| ms.parse().ok())
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.timeout),
+            breaker_threshold: std::env::var("CACHE_BREAKER_THRESHOLD").ok()
+                .and_then(|threshold| threshold.parse().ok())
+                .unwrap_or(defaults.breaker_threshold),
+            breaker_cooldown: seconds("CACHE_BREAKER_COOLDOWN", defaults.breaker_cooldown),
+            revocation_fallback: match std::env::var("CACHE_REVOCATION_FALLBACK").ok().as_deref() {
+                Some("allow") => RevocationFallback::Allow,
+                Some("reject") => RevocationFallback::Reject,
+                _ => defaults.revocation_fallback,
+            },
+        }
+    }
+
+    /// How long a freshly filled entry of `class` counts as fresh, jittered.
+    pub fn fresh_ttl(&self, class: KeyClass) -> Duration {
+        match class {
+            KeyClass::Survey => self.jittered(self.survey_ttl),
+            KeyClass::Listing => self.jittered(self.listing_ttl),
+        }
+    }
+
+    /// How long a "not found" is remembered, jittered.
+    pub fn negative_ttl(&self) -> Duration {
+        self.jittered(self.negative_ttl)
+    }
+
+    fn jittered(&self, ttl: Duration) -> Duration {
+        let ms = ttl.as_millis() as u64;
+        let spread = ms * u64::from(self.jitter_percent) / 100;
+        if spread == 0 {
+            return ttl;
+        }
+        Duration::from_millis(ms - spread + rand::thread_rng().gen_range(0, 2 * spread + 1))
+    }
+}
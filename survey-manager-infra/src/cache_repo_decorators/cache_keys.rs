@@ -0,0 +1,56 @@
+//! Every key the survey caches use.  Keys are namespaced so they can share a Redis with other
+//! services, and carry a schema version so instances running different builds never read each
+//! other's entries.
+
+const NAMESPACE: &str = "survey-manager";
+
+/// Bump whenever the shape of a cached value changes, e.g. a field is added to `SurveyDTO`.
+/// Entries under the old version are simply never read again.
+pub const CACHE_SCHEMA_VERSION: u32 = 2;
+
+/// A whole survey, keyed by id.  Shared by everyone who can see it, so reads must check access on
+/// every hit.
+pub fn survey_key(id: &str) -> String {
+    format!("{}:v{}:survey:{}", NAMESPACE, CACHE_SCHEMA_VERSION, id)
+}
+
+/// The listing `get_surveys_by_author` returns for `author`, their own surveys and ones shared
+/// with them.
+pub fn author_listing_key(author: &str) -> String {
+    format!("{}:v{}:surveys-by-author:{}", NAMESPACE, CACHE_SCHEMA_VERSION, author)
+}
+
+/// A remembered "not found" for `author` asking for the survey `id` as themselves.  Kept per caller
+/// since the read repository can't tell a missing survey from one the caller may not see.
+pub fn survey_miss_key(id: &str, author: &str) -> String {
+    format!("{}:v{}:survey-miss:{}:{}", NAMESPACE, CACHE_SCHEMA_VERSION, id, author)
+}
+
+/// Everything to clear when the survey `id` changes or goes away.  `usernames` are everyone whose
+/// listing it appears in, its author and collaborators.
+pub fn survey_eviction_keys<'a, I>(id: &str, usernames: I) -> Vec<String>
+    where I: IntoIterator<Item = &'a String>
+{
+    let mut keys = vec![survey_key(id)];
+    push_reader_keys(&mut keys, id, usernames);
+    keys
+}
+
+/// Adds the keys `usernames` read the survey `id` through, their listings and any "not found"
+/// remembered for them, to `keys`, skipping ones already there.
+pub(crate) fn push_reader_keys<'a, I>(keys: &mut Vec<String>, id: &str, usernames: I)
+    where I: IntoIterator<Item = &'a String>
+{
+    for username in usernames {
+        for key in [author_listing_key(username), survey_miss_key(id, username)] {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+    }
+}
+
+/// The pub/sub channel invalidations are published on, so each node can evict its in-process copies.
+pub fn invalidation_channel() -> String {
+    format!("{}:v{}:invalidations", NAMESPACE, CACHE_SCHEMA_VERSION)
+}
@@ -0,0 +1,141 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use serde::Serialize;
+use crate::cache_repo_decorators::{KeyClass, BreakerState, cache_guard};
+
+/// What happened to a read that went through a cache decorator.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CacheOutcome {
+    /// Served from this process's memory, without going to Redis.
+    LocalHit,
+    /// Served a fresh cached entry.
+    Hit,
+    /// Served an expired entry because someone else was already reloading it.
+    StaleHit,
+    /// Served a remembered "not found".
+    NegativeHit,
+    /// Went to storage.
+    Miss,
+    /// Missed, but waited for a load already in flight instead of starting another.
+    Coalesced,
+}
+
+#[derive(Default)]
+struct Counters {
+    local_hits: AtomicU64,
+    hits: AtomicU64,
+    stale_hits: AtomicU64,
+    negative_hits: AtomicU64,
+    misses: AtomicU64,
+    coalesced: AtomicU64,
+}
+
+impl Counters {
+    fn counter(&self, outcome: CacheOutcome) -> &AtomicU64 {
+        match outcome {
+            CacheOutcome::LocalHit => &self.local_hits,
+            CacheOutcome::Hit => &self.hits,
+            CacheOutcome::StaleHit => &self.stale_hits,
+            CacheOutcome::NegativeHit => &self.negative_hits,
+            CacheOutcome::Miss => &self.misses,
+            CacheOutcome::Coalesced => &self.coalesced,
+        }
+    }
+
+    fn counts(&self) -> CacheCounts {
+        CacheCounts {
+            local_hits: self.local_hits.load(Ordering::Relaxed),
+            hits: self.hits.load(Ordering::Relaxed),
+            stale_hits: self.stale_hits.load(Ordering::Relaxed),
+            negative_hits: self.negative_hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            coalesced: self.coalesced.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Something that went wrong talking to the cache, see `GuardedCacheStore`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HealthEvent {
+    /// A call to the cache failed.
+    Failure,
+    /// A call was skipped because the circuit breaker was open.
+    Bypassed,
+    /// The circuit breaker opened.
+    Tripped,
+}
+
+#[derive(Default)]
+struct CacheCounters {
+    surveys: Counters,
+    listings: Counters,
+    failures: AtomicU64,
+    bypassed: AtomicU64,
+    breaker_trips: AtomicU64,
+}
+
+lazy_static! {
+    static ref COUNTERS: CacheCounters = CacheCounters::default();
+}
+
+/// Counts of each outcome for one key class since the process started.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheCounts {
+    pub local_hits: u64,
+    pub hits: u64,
+    pub stale_hits: u64,
+    pub negative_hits: u64,
+    pub misses: u64,
+    pub coalesced: u64,
+}
+
+/// How the cache itself has been doing since the process started.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheHealth {
+    pub failures: u64,
+    pub bypassed: u64,
+    pub breaker_trips: u64,
+    pub breaker: BreakerState,
+    /// Keys whose invalidation is waiting for the cache to come back.
+    pub pending_invalidations: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CacheMetrics {
+    pub surveys: CacheCounts,
+    pub listings: CacheCounts,
+    pub health: CacheHealth,
+}
+
+pub(crate) fn record(class: KeyClass, outcome: CacheOutcome) {
+    let counters = match class {
+        KeyClass::Survey => &COUNTERS.surveys,
+        KeyClass::Listing => &COUNTERS.listings,
+    };
+    counters.counter(outcome).fetch_add(1, Ordering::Relaxed);
+}
+
+pub(crate) fn record_health(event: HealthEvent) {
+    let counter = match event {
+        HealthEvent::Failure => &COUNTERS.failures,
+        HealthEvent::Bypassed => &COUNTERS.bypassed,
+        HealthEvent::Tripped => &COUNTERS.breaker_trips,
+    };
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Hit and miss counters for this process, across every cache decorator.
+pub fn cache_metrics() -> CacheMetrics {
+    CacheMetrics {
+        surveys: COUNTERS.surveys.counts(),
+        listings: COUNTERS.listings.counts(),
+        health: CacheHealth {
+            failures: COUNTERS.failures.load(Ordering::Relaxed),
+            bypassed: COUNTERS.bypassed.load(Ordering::Relaxed),
+            breaker_trips: COUNTERS.breaker_trips.load(Ordering::Relaxed),
+            breaker: cache_guard().state(),
+            pending_invalidations: cache_guard().pending(),
+        },
+    }
+}
@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use crate::utils::redis_pool::{Conn, redis_conn, check_redis_config};
+use crate::errors::Error;
+
+/// The few key/value operations the cache decorators need.  Keeping them behind a trait lets the
+/// decorators run against an in-process store in tests.
+pub trait CacheStore {
+    fn get(&mut self, key: &str) -> Result<Option<String>, Error>;
+
+    /// Stores `value` under `key` for `ttl`, replacing whatever was there.
+    fn set(&mut self, key: &str, value: &str, ttl: Duration) -> Result<(), Error>;
+
+    /// Stores `value` under `key` for `ttl` only if nothing is stored there yet, and returns
+    /// whether it was.
+    fn add(&mut self, key: &str, value: &str, ttl: Duration) -> Result<bool, Error>;
+
+    /// Replaces `current` under `key` with `value` for `ttl`, and returns whether it did.  Nothing
+    /// changes if `key` no longer holds exactly `current`.
+    fn swap(&mut self, key: &str, current: &str, value: &str, ttl: Duration) -> Result<bool, Error>;
+
+    fn delete(&mut self, keys: &[String]) -> Result<(), Error>;
+
+    /// Sends `message` to everyone subscribed to `channel`, on every node.
+    fn publish(&mut self, channel: &str, message: &str) -> Result<(), Error>;
+}
+
+// Lets a store be picked at runtime, e.g. Redis when it's configured and memory otherwise.
+impl<C: CacheStore + ?Sized> CacheStore for Box<C> {
+    fn get(&mut self, key: &str) -> Result<Option<String>, Error> {
+        (**self).get(key)
+    }
+
+    fn set(&mut self, key: &str, value: &str, ttl: Duration) -> Result<(), Error> {
+        (**self).set(key, value, ttl)
+    }
+
+    fn add(&mut self, key: &str, value: &str, ttl: Duration) -> Result<bool, Error> {
+        (**self).add(key, value, ttl)
+    }
+
+    fn swap(&mut self, key: &str, current: &str, value: &str, ttl: Duration) -> Result<bool, Error> {
+        (**self).swap(key, current, value, ttl)
+    }
+
+    fn delete(&mut self, keys: &[String]) -> Result<(), Error> {
+        (**self).delete(keys)
+    }
+
+    fn publish(&mut self, channel: &str, message: &str) -> Result<(), Error> {
+        (**self).publish(channel, message)
+    }
+}
+
+lazy_static! {
+    // The compare and the set have to happen as one step, or a write landing in between is lost.
+    static ref SWAP_SCRIPT: redis::Script = redis::Script::new(r"
+        if redis.call('GET', KEYS[1]) == ARGV[1] then
+            return redis.call('SET', KEYS[1], ARGV[2], 'PX', ARGV[3])
+        end
+        return false
+    ");
+}
+
+// Redis rejects a zero expiry, so anything shorter than a millisecond rounds up to one.
+fn millis(ttl: Duration) -> u64 {
+    std::cmp::max(ttl.as_millis() as u64, 1)
+}
+
+/// Redis, through the shared pool.  A connection is only taken when the first command runs, so
+/// building one never waits on Redis.
+pub struct RedisCacheStore {
+    conn: Option<Conn>,
+}
+
+impl RedisCacheStore {
+    /// Fails only when `CACHE_URL` is missing or malformed.  An unreachable Redis shows up as
+    /// errors from the commands instead.
+    pub fn new() -> Result<RedisCacheStore, Error> {
+        check_redis_config()?;
+        Ok(RedisCacheStore {
+            conn: None,
+        })
+    }
+
+    // Runs `command` on this store's connection, taking one first if needed.  A connection that
+    // failed a command is let go, since it may be broken.
+    fn run<T, F>(&mut self, command: F) -> Result<T, Error>
+        where F: FnOnce(&mut redis::Connection) -> redis::RedisResult<T>
+    {
+        let mut conn = match self.conn.take() {
+            Some(conn) => conn,
+            None => redis_conn()?,
+        };
+
+        let result = command(&mut conn);
+        if result.is_ok() {
+            self.conn = Some(conn);
+        }
+        Ok(result?)
+    }
+}
+
+impl CacheStore for RedisCacheStore {
+    fn get(&mut self, key: &str) -> Result<Option<String>, Error> {
+        self.run(|conn| redis::cmd("GET").arg(key).query(conn))
+    }
+
+    fn set(&mut self, key: &str, value: &str, ttl: Duration) -> Result<(), Error> {
+        self.run(|conn| redis::cmd("SET").arg(key).arg(value).arg("PX").arg(millis(ttl)).query::<()>(conn))
+    }
+
+    fn add(&mut self, key: &str, value: &str, ttl: Duration) -> Result<bool, Error> {
+        // SET NX replies OK when it stored the value and nil when the key was already there.
+        let stored: Option<String> = self.run(|conn| {
+            redis::cmd("SET").arg(key).arg(value).arg("PX").arg(millis(ttl)).arg("NX").query(conn)
+        })?;
+        Ok(stored.is_some())
+    }
+
+    fn swap(&mut self, key: &str, current: &str, value: &str, ttl: Duration) -> Result<bool, Error> {
+        let swapped: Option<String> = self.run(|conn| {
+            SWAP_SCRIPT.key(key).arg(current).arg(value).arg(millis(ttl)).invoke(conn)
+        })?;
+        Ok(swapped.is_some())
+    }
+
+    fn delete(&mut self, keys: &[String]) -> Result<(), Error> {
+        if keys.is_empty() {
+            return Ok(());
+        }
+
+        let mut cmd = redis::cmd("DEL");
+        for key in keys {
+            cmd.arg(key);
+        }
+        self.run(|conn| cmd.query::<()>(conn))
+    }
+
+    fn publish(&mut self, channel: &str, message: &str) -> Result<(), Error> {
+        self.run(|conn| redis::cmd("PUBLISH").arg(channel).arg(message).query::<()>(conn))
+    }
+}
+
+/// A `CacheStore` held in memory.  Clones share the same entries, so a read and a write decorator
+/// built from clones see each other's changes the way two connections to Redis would.  Published
+/// messages are handed straight to subscribers on the publishing thread.
+#[derive(Clone, Default)]
+pub struct InMemoryCacheStore {
+    entries: Arc<Mutex<HashMap<String, MemoryEntry>>>,
+    subscribers: Arc<Mutex<Vec<Subscriber>>>,
+}
+
+struct Subscriber {
+    channel: String,
+    deliver: Box<dyn Fn(&str) + Send>,
+}
+
+struct MemoryEntry {
+    value: String,
+    expires_at: Instant,
+}
+
+impl MemoryEntry {
+    fn new(value: &str, ttl: Duration) -> MemoryEntry {
+        MemoryEntry {
+            value: value.to_string(),
+            expires_at: Instant::now() + ttl,
+        }
+    }
+
+    fn is_live(&self) -> bool {
+        Instant::now() < self.expires_at
+    }
+}
+
+impl InMemoryCacheStore {
+    pub fn new() -> InMemoryCacheStore {
+        InMemoryCacheStore::default()
+    }
+
+    /// Calls `deliver` with every message published to `channel` from now on.
+    pub fn subscribe<F>(&self, channel: &str, deliver: F)
+        where F: Fn(&str) + Send + 'static
+    {
+        self.subscribers.lock().unwrap().push(Subscriber {
+            channel: channel.to_string(),
+            deliver: Box::new(deliver),
+        });
+    }
+
+    /// Every key currently stored and not yet expired, in no particular order.
+    pub fn keys(&self) -> Vec<String> {
+        self.entries.lock().unwrap().iter()
+            .filter(|(_, entry)| entry.is_live())
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    // Expired entries are dropped when they're next looked at, the way Redis expires keys lazily.
+    fn live<'a>(entries: &'a mut HashMap<String, MemoryEntry>, key: &str) -> Option<&'a MemoryEntry> {
+        if entries.get(key).is_some_and(|entry| !entry.is_live()) {
+            entries.remove(key);
+        }
+        entries.get(key)
+    }
+}
+
+impl CacheStore for InMemoryCacheStore {
+    fn get(&mut self, key: &str) -> Result<Option<String>, Error> {
+        let mut entries = self.entries.lock().unwrap();
+        Ok(InMemoryCacheStore::live(&mut entries, key).map(|entry| entry.value.clone()))
+    }
+
+    fn set(&mut self, key: &str, value: &str, ttl: Duration) -> Result<(), Error> {
+        self.entries.lock().unwrap().insert(key.to_string(), MemoryEntry::new(value, ttl));
+        Ok(())
+    }
+
+    fn add(&mut self, key: &str, value: &str, ttl: Duration) -> Result<bool, Error> {
+        let mut entries = self.entries.lock().unwrap();
+        if InMemoryCacheStore::live(&mut entries, key).is_some() {
+            return Ok(false);
+        }
+        entries.insert(key.to_string(), MemoryEntry::new(value, ttl));
+        Ok(true)
+    }
+
+    fn swap(&mut self, key: &str, current: &str, value: &str, ttl: Duration) -> Result<bool, Error> {
+        let mut entries = self.entries.lock().unwrap();
+        if InMemoryCacheStore::live(&mut entries, key).map(|entry| entry.value.as_str()) != Some(current) {
+            return Ok(false);
+        }
+        entries.insert(key.to_string(), MemoryEntry::new(value, ttl));
+        Ok(true)
+    }
+
+    fn delete(&mut self, keys: &[String]) -> Result<(), Error> {
+        let mut entries = self.entries.lock().unwrap();
+        for key in keys {
+            entries.remove(key);
+        }
+        Ok(())
+    }
+
+    fn publish(&mut self, channel: &str, message: &str) -> Result<(), Error> {
+        for subscriber in self.subscribers.lock().unwrap().iter().filter(|s| s.channel == channel) {
+            (subscriber.deliver)(message);
+        }
+        Ok(())
+    }
+}
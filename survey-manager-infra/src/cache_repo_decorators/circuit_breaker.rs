@@ -0,0 +1,265 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use serde::Serialize;
+use crate::cache_repo_decorators::{CacheStore, RedisCacheStore, cache_config, invalidation_channel, publish_invalidation};
+use crate::cache_repo_decorators::cache_metrics::{record_health, HealthEvent};
+use crate::errors::Error;
+
+lazy_static! {
+    // Shared by every guarded store in the process, so a failure seen by one request counts
+    // towards bypassing Redis for all of them.
+    static ref CACHE_GUARD: Arc<CacheGuard> = {
+        let config = cache_config();
+        Arc::new(CacheGuard::new(config.breaker_threshold, config.breaker_cooldown))
+    };
+}
+
+/// The guard shared by every `GuardedCacheStore::redis()`.
+pub fn cache_guard() -> Arc<CacheGuard> {
+    CACHE_GUARD.clone()
+}
+
+/// Where a `CircuitBreaker` is at.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BreakerState {
+    /// Calls go through.
+    Closed,
+    /// Calls are bypassed until the cooldown passes.
+    Open,
+    /// One call is going through to see whether the cache has recovered, everyone else is bypassed.
+    Probing,
+}
+
+/// Whether a call may go through, see `CircuitBreaker::admit`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Admission {
+    Normal,
+    /// The first call after the cooldown.  How it goes decides whether the breaker closes again.
+    Probe,
+    Rejected,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum State {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    Probing,
+}
+
+/// Opens after `threshold` consecutive failures, and then rejects calls for `cooldown` before
+/// letting a single probe through.  A successful probe closes it again, a failed one starts
+/// another cooldown.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: State,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: Duration) -> CircuitBreaker {
+        CircuitBreaker {
+            threshold: threshold.max(1),
+            cooldown,
+            state: State::Closed { failures: 0 },
+        }
+    }
+
+    pub fn state(&self) -> BreakerState {
+        match self.state {
+            State::Closed { .. } => BreakerState::Closed,
+            State::Open { .. } => BreakerState::Open,
+            State::Probing => BreakerState::Probing,
+        }
+    }
+
+    /// Call before each call, and hand the answer back to `record` once it's done.
+    pub fn admit(&mut self) -> Admission {
+        match self.state {
+            State::Closed { .. } => Admission::Normal,
+            State::Open { until } if Instant::now() >= until => {
+                self.state = State::Probing;
+                Admission::Probe
+            },
+            State::Open { .. } | State::Probing => Admission::Rejected,
+        }
+    }
+
+    /// Records how an admitted call went, and returns whether that opened the breaker.
+    pub fn record(&mut self, admission: Admission, ok: bool) -> bool {
+        match (admission, self.state) {
+            (Admission::Normal, State::Closed { failures }) => {
+                let failures = if ok { 0 } else { failures + 1 };
+                if failures >= self.threshold {
+                    self.open();
+                    return true;
+                }
+                self.state = State::Closed { failures };
+                false
+            },
+            (Admission::Probe, _) if ok => {
+                self.state = State::Closed { failures: 0 };
+                false
+            },
+            (Admission::Probe, _) => {
+                self.open();
+                true
+            },
+            // Calls admitted before the breaker opened still land afterwards, and change nothing.
+            _ => false,
+        }
+    }
+
+    fn open(&mut self) {
+        self.state = State::Open { until: Instant::now() + self.cooldown };
+    }
+}
+
+/// A `CircuitBreaker` in front of a cache, along with the invalidations that couldn't be made
+/// while it was failing.
+///
+/// A write whose invalidation is lost leaves a stale copy in the cache, and a stale copy can still
+/// show a survey to someone whose access was just revoked.  So the keys are remembered, and cleared
+/// before the cache is used for anything else.  Only writes made by this process are tracked.
+pub struct CacheGuard {
+    breaker: Mutex<CircuitBreaker>,
+    pending: Mutex<HashSet<String>>,
+}
+
+impl CacheGuard {
+    pub fn new(threshold: u32, cooldown: Duration) -> CacheGuard {
+        CacheGuard {
+            breaker: Mutex::new(CircuitBreaker::new(threshold, cooldown)),
+            pending: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn state(&self) -> BreakerState {
+        self.breaker.lock().unwrap().state()
+    }
+
+    /// How many keys are waiting to be invalidated.
+    pub fn pending(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+
+    fn remember<'a, I: IntoIterator<Item = &'a String>>(&self, keys: I) {
+        self.pending.lock().unwrap().extend(keys.into_iter().cloned());
+    }
+}
+
+/// Wraps a `CacheStore` so a failing cache is bypassed, see `CacheGuard`.  A call made while the
+/// breaker is open fails straight away with `CacheBypassed`, which the decorators treat like any
+/// other cache failure, i.e. they go to storage.
+pub struct GuardedCacheStore<C: CacheStore> {
+    inner: C,
+    guard: Arc<CacheGuard>,
+}
+
+impl GuardedCacheStore<RedisCacheStore> {
+    /// Redis behind the process-wide guard.  Fails only when `CACHE_URL` is missing or malformed.
+    pub fn redis() -> Result<GuardedCacheStore<RedisCacheStore>, Error> {
+        Ok(GuardedCacheStore::new(RedisCacheStore::new()?, cache_guard()))
+    }
+}
+
+impl<C: CacheStore> GuardedCacheStore<C> {
+    pub fn new(inner: C, guard: Arc<CacheGuard>) -> GuardedCacheStore<C> {
+        GuardedCacheStore {
+            inner,
+            guard,
+        }
+    }
+
+    // Runs `op` if the breaker allows it, after clearing any invalidations still owed.  `stale`
+    // names the keys left stale in the cache if `op` doesn't happen.
+    fn call<T, F>(&mut self, stale: &[String], op: F) -> Result<T, Error>
+        where F: FnOnce(&mut C) -> Result<T, Error>
+    {
+        let admission = self.guard.breaker.lock().unwrap().admit();
+        if admission == Admission::Rejected {
+            self.guard.remember(stale);
+            record_health(HealthEvent::Bypassed);
+            return Err(Error::CacheBypassed);
+        }
+
+        let result = self.replay_pending().and_then(|_| op(&mut self.inner));
+        let (opened, cooldown) = {
+            let mut breaker = self.guard.breaker.lock().unwrap();
+            (breaker.record(admission, result.is_ok()), breaker.cooldown)
+        };
+
+        match &result {
+            Ok(_) if admission == Admission::Probe => {
+                log::info!("survey cache recovered, no longer bypassing it");
+            },
+            Ok(_) => {},
+            Err(e) => {
+                self.guard.remember(stale);
+                record_health(HealthEvent::Failure);
+                log::warn!("survey cache call failed: {}", e);
+            },
+        }
+        if opened {
+            record_health(HealthEvent::Tripped);
+            log::warn!("survey cache is failing, bypassing it for {:?}", cooldown);
+        }
+
+        result
+    }
+
+    // Deletes and publishes every key still owed an invalidation.  Keys remembered while this
+    // runs are left for next time.
+    fn replay_pending(&mut self) -> Result<(), Error> {
+        let keys: Vec<String> = self.guard.pending.lock().unwrap().iter().cloned().collect();
+        if keys.is_empty() {
+            return Ok(());
+        }
+
+        self.inner.delete(&keys)?;
+        publish_invalidation(&mut self.inner, &keys)?;
+
+        let mut pending = self.guard.pending.lock().unwrap();
+        for key in &keys {
+            pending.remove(key);
+        }
+        Ok(())
+    }
+}
+
+impl<C: CacheStore> CacheStore for GuardedCacheStore<C> {
+    fn get(&mut self, key: &str) -> Result<Option<String>, Error> {
+        self.call(&[], |inner| inner.get(key))
+    }
+
+    // What was there before may now be out of date.
+    fn set(&mut self, key: &str, value: &str, ttl: Duration) -> Result<(), Error> {
+        self.call(&[key.to_string()], |inner| inner.set(key, value, ttl))
+    }
+
+    // Fills only happen when there's nothing to replace, so a lost one leaves nothing stale.
+    fn add(&mut self, key: &str, value: &str, ttl: Duration) -> Result<bool, Error> {
+        self.call(&[], |inner| inner.add(key, value, ttl))
+    }
+
+    // Likewise a lost refresh only leaves the copy that was going to be refreshed.
+    fn swap(&mut self, key: &str, current: &str, value: &str, ttl: Duration) -> Result<bool, Error> {
+        self.call(&[], |inner| inner.swap(key, current, value, ttl))
+    }
+
+    fn delete(&mut self, keys: &[String]) -> Result<(), Error> {
+        self.call(keys, |inner| inner.delete(keys))
+    }
+
+    // Other nodes keep the keys in memory until they hear about them.
+    fn publish(&mut self, channel: &str, message: &str) -> Result<(), Error> {
+        let stale: Vec<String> = if channel == invalidation_channel() {
+            serde_json::from_str(message).unwrap_or_default()
+        } else {
+            vec![]
+        };
+        self.call(&stale, |inner| inner.publish(channel, message))
+    }
+}
@@ -0,0 +1,83 @@
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use crate::cache_repo_decorators::{CacheStore, LocalCache, cache_config, local_cache, invalidation_channel};
+use crate::errors::Error;
+
+// How long the listener waits before reconnecting after losing Redis.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Evicts `keys` from this process's in-process tier straight away, and from every other node's by
+/// publishing them.  Call after changing or deleting the keys in the shared cache.
+pub fn publish_invalidation<C: CacheStore + ?Sized>(cache: &mut C, keys: &[String]) -> Result<(), Error> {
+    if keys.is_empty() {
+        return Ok(());
+    }
+
+    local_cache().evict(keys);
+    cache.publish(&invalidation_channel(), &serde_json::to_string(keys)?)
+}
+
+/// Applies a message published by `publish_invalidation` to `local`.  A message that can't be
+/// parsed might have named anything, so everything is dropped.
+pub fn apply_invalidation(local: &LocalCache, message: &str) {
+    match serde_json::from_str::<Vec<String>>(message) {
+        Ok(keys) => local.evict(&keys),
+        Err(_) => local.clear(),
+    }
+}
+
+/// Subscribes to invalidations from the Redis at `CACHE_URL` and applies them to `local` on a
+/// background thread.  Fails only when `CACHE_URL` is missing or malformed.
+///
+/// Invalidations sent while the connection is down are lost, so `local` is suspended until the
+/// listener is subscribed, and again whenever the connection drops.  Waits up to the cache timeout
+/// for the first subscription, so a node starting alongside a healthy Redis starts with its
+/// in-process tier working, and one starting while Redis is down starts anyway.
+pub fn spawn_invalidation_listener(local: LocalCache) -> Result<(), Error> {
+    let cache_url = std::env::var("CACHE_URL")
+        .map_err(|_| Error::CacheUnavailable { reason: "CACHE_URL must be set".to_string() })?;
+    let client = redis::Client::open(cache_url.as_str())?;
+    let (subscribed_tx, subscribed_rx) = mpsc::channel();
+
+    local.suspend();
+    thread::Builder::new()
+        .name("cache-invalidations".to_string())
+        .spawn(move || {
+            let mut subscribed = Some(subscribed_tx);
+            let mut live = false;
+            loop {
+                let result = listen(&client, &local, &mut subscribed, &mut live);
+                local.suspend();
+                // Only said once per outage, rather than on every attempt to reconnect.
+                if let (true, Err(e)) = (live, result) {
+                    log::warn!("lost cache invalidations, in-process cache suspended: {}", e);
+                }
+                live = false;
+                thread::sleep(RECONNECT_DELAY);
+            }
+        })
+        .expect("could not start the cache invalidation listener");
+
+    let _ = subscribed_rx.recv_timeout(cache_config().timeout);
+    Ok(())
+}
+
+// Only returns when the connection fails.
+fn listen(client: &redis::Client, local: &LocalCache, subscribed: &mut Option<mpsc::Sender<()>>, live: &mut bool) -> Result<(), Error> {
+    let mut conn = client.get_connection()?;
+    let mut pubsub = conn.as_pubsub();
+    pubsub.subscribe(invalidation_channel())?;
+
+    local.resume();
+    *live = true;
+    if let Some(subscribed) = subscribed.take() {
+        let _ = subscribed.send(());
+    }
+
+    loop {
+        let message = pubsub.get_message()?;
+        let payload: String = message.get_payload()?;
+        apply_invalidation(local, &payload);
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use survey_manager_core::dtos::{SurveyDTO, SurveyDTOs};
+use crate::cache_repo_decorators::{CacheConfig, cache_config};
+
+lazy_static! {
+    static ref LOCAL_CACHE: LocalCache = LocalCache::new(cache_config());
+}
+
+/// The in-process tier shared by every decorator in this process, sized by `cache_config`.
+pub fn local_cache() -> LocalCache {
+    LOCAL_CACHE.clone()
+}
+
+/// Surveys and listings kept in this process's memory, in front of Redis.  Each is bounded to
+/// `local_capacity` entries, dropping the least recently used, and entries expire after
+/// `local_ttl`.  Clones share the same entries.
+///
+/// Entries are keyed the same as in Redis, so invalidations name keys the same way for both.
+#[derive(Clone)]
+pub struct LocalCache {
+    tiers: Arc<Mutex<Tiers>>,
+}
+
+struct Tiers {
+    surveys: Lru<SurveyDTO>,
+    listings: Lru<SurveyDTOs>,
+    // Bumped on every eviction, so a fill can tell whether anything was evicted while it was
+    // loading and its value might already be out of date.
+    generation: u64,
+    // Set while invalidations can't be heard, see `suspend`.
+    suspended: bool,
+}
+
+impl LocalCache {
+    pub fn new(config: &CacheConfig) -> LocalCache {
+        LocalCache {
+            tiers: Arc::new(Mutex::new(Tiers {
+                surveys: Lru::new(config.local_capacity, config.local_ttl),
+                listings: Lru::new(config.local_capacity, config.local_ttl),
+                generation: 0,
+                suspended: false,
+            })),
+        }
+    }
+
+    /// False when configured with no capacity, in which case nothing is ever kept.
+    pub fn is_enabled(&self) -> bool {
+        self.tiers.lock().unwrap().surveys.capacity > 0
+    }
+
+    /// Take this before loading a value to fill in, and hand it back to the fill.
+    pub fn generation(&self) -> u64 {
+        self.tiers.lock().unwrap().generation
+    }
+
+    pub fn survey(&self, key: &str) -> Option<SurveyDTO> {
+        let mut tiers = self.tiers.lock().unwrap();
+        if tiers.suspended {
+            return None;
+        }
+        tiers.surveys.get(key)
+    }
+
+    pub fn listing(&self, key: &str) -> Option<SurveyDTOs> {
+        let mut tiers = self.tiers.lock().unwrap();
+        if tiers.suspended {
+            return None;
+        }
+        tiers.listings.get(key)
+    }
+
+    /// Keeps `survey` under `key`, unless anything was evicted since `generation` was taken.
+    pub fn fill_survey(&self, key: &str, survey: SurveyDTO, generation: u64) {
+        let mut tiers = self.tiers.lock().unwrap();
+        if tiers.generation == generation && !tiers.suspended {
+            tiers.surveys.put(key, survey);
+        }
+    }
+
+    /// Keeps `listing` under `key`, unless anything was evicted since `generation` was taken.
+    pub fn fill_listing(&self, key: &str, listing: SurveyDTOs, generation: u64) {
+        let mut tiers = self.tiers.lock().unwrap();
+        if tiers.generation == generation && !tiers.suspended {
+            tiers.listings.put(key, listing);
+        }
+    }
+
+    pub fn evict(&self, keys: &[String]) {
+        let mut tiers = self.tiers.lock().unwrap();
+        for key in keys {
+            tiers.surveys.remove(key);
+            tiers.listings.remove(key);
+        }
+        tiers.generation += 1;
+    }
+
+    /// Drops everything and keeps nothing more until `resume`.  For while invalidations from other
+    /// nodes can't be heard, when anything kept could go stale without us knowing.
+    pub fn suspend(&self) {
+        self.clear();
+        self.tiers.lock().unwrap().suspended = true;
+    }
+
+    /// Starts keeping entries again after `suspend`.
+    pub fn resume(&self) {
+        self.tiers.lock().unwrap().suspended = false;
+    }
+
+    /// Drops everything, e.g. when invalidations may have been missed.
+    pub fn clear(&self) {
+        let mut tiers = self.tiers.lock().unwrap();
+        tiers.surveys.clear();
+        tiers.listings.clear();
+        tiers.generation += 1;
+    }
+}
+
+// A bounded map that drops its least recently used entry to make room.  `order` maps the tick an
+// entry was last used at to its key, so the oldest is always first.
+struct Lru<V> {
+    capacity: usize,
+    ttl: Duration,
+    entries: HashMap<String, LruEntry<V>>,
+    order: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+struct LruEntry<V> {
+    value: V,
+    expires_at: Instant,
+    used_at: u64,
+}
+
+impl<V: Clone> Lru<V> {
+    fn new(capacity: usize, ttl: Duration) -> Lru<V> {
+        Lru {
+            capacity,
+            ttl,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+
+    fn get(&mut self, key: &str) -> Option<V> {
+        let expired = Instant::now() >= self.entries.get(key)?.expires_at;
+        if expired {
+            self.remove(key);
+            return None;
+        }
+
+        self.tick += 1;
+        let tick = self.tick;
+        let entry = self.entries.get_mut(key)?;
+        self.order.remove(&entry.used_at);
+        self.order.insert(tick, key.to_string());
+        entry.used_at = tick;
+        Some(entry.value.clone())
+    }
+
+    fn put(&mut self, key: &str, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        self.remove(key);
+        while self.entries.len() >= self.capacity {
+            let oldest = match self.order.keys().next() {
+                Some(tick) => *tick,
+                None => break,
+            };
+            if let Some(oldest_key) = self.order.remove(&oldest) {
+                self.entries.remove(&oldest_key);
+            }
+        }
+
+        self.tick += 1;
+        self.order.insert(self.tick, key.to_string());
+        self.entries.insert(key.to_string(), LruEntry {
+            value,
+            expires_at: Instant::now() + self.ttl,
+            used_at: self.tick,
+        });
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.order.remove(&entry.used_at);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+}
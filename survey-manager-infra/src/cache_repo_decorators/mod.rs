@@ -1,2 +1,29 @@
+pub mod cache_config;
+pub use cache_config::*;
+
+pub mod cache_keys;
+pub use cache_keys::*;
+
+pub mod cache_metrics;
+pub use cache_metrics::*;
+
+pub mod cache_store;
+pub use cache_store::*;
+
+pub mod circuit_breaker;
+pub use circuit_breaker::*;
+
+pub mod single_flight;
+pub use single_flight::*;
+
+pub mod local_cache;
+pub use local_cache::*;
+
+pub mod invalidation;
+pub use invalidation::*;
+
 pub mod redis_cache_decorators;
-pub use redis_cache_decorators::*;
\ No newline at end of file
+pub use redis_cache_decorators::*;
+
+pub mod tiered_cache_decorators;
+pub use tiered_cache_decorators::*;
@@ -1,133 +1,390 @@
+use std::time::Duration;
+use chrono::Utc;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
 use survey_manager_core::app_services::repository_contracts::SurveyDTOReadRepository;
-use crate::utils::redis_pool::{Conn, Pool, create_pool};
 use survey_manager_core::dtos::{SurveyDTO, SurveyDTOs};
 use domain_patterns::collections::Repository;
+use domain_patterns::models::Entity;
 use survey_manager_core::survey::Survey;
+use crate::cache_repo_decorators::{
+    CacheStore, GuardedCacheStore, RedisCacheStore, CacheConfig, KeyClass, CacheOutcome, cache_config,
+    survey_key, author_listing_key, survey_miss_key, push_reader_keys, publish_invalidation,
+};
+use crate::cache_repo_decorators::cache_metrics::record;
+use crate::cache_repo_decorators::single_flight::FLIGHTS;
+use crate::errors::Error;
 
-lazy_static! {
-    static ref REDIS_POOL: Pool = {
-        let cache_url = std::env::var("CACHE_URL").expect("CACHE_URL must be set");
-        create_pool(&cache_url)
+/// What's stored under every key.  `fresh_until` tells an expired entry apart from a fresh one while
+/// the stale window still keeps it around, and a `None` value is a remembered "not found".
+#[derive(Serialize, Deserialize)]
+pub struct CacheEntry<V> {
+    pub fresh_until: i64,
+    pub value: Option<V>,
+}
+
+impl<V> CacheEntry<V> {
+    fn is_fresh(&self) -> bool {
+        Utc::now().timestamp_millis() < self.fresh_until
+    }
+}
+
+// The json to store for `value` and how long the cache should keep it, fresh and then stale.
+fn encode_found<V: Serialize>(config: &CacheConfig, class: KeyClass, value: &V) -> (String, Duration) {
+    let fresh = config.fresh_ttl(class);
+    let entry = CacheEntry {
+        fresh_until: Utc::now().timestamp_millis() + fresh.as_millis() as i64,
+        value: Some(value),
     };
+    (serde_json::to_string(&entry).unwrap(), fresh + config.stale_ttl)
 }
 
-pub struct RedisSurveyReadCacheRepository<T>
-    where T: SurveyDTOReadRepository
+// A "not found" is never served stale, so it's kept only while fresh.
+fn encode_missing(config: &CacheConfig) -> (String, Duration) {
+    let fresh = config.negative_ttl();
+    let entry: CacheEntry<()> = CacheEntry {
+        fresh_until: Utc::now().timestamp_millis() + fresh.as_millis() as i64,
+        value: None,
+    };
+    (serde_json::to_string(&entry).unwrap(), fresh)
+}
+
+pub struct RedisSurveyReadCacheRepository<T, C = GuardedCacheStore<RedisCacheStore>>
+    where T: SurveyDTOReadRepository,
+          C: CacheStore,
 {
-    // A single connection to Mysql.  Handed down from a pool likely.
-    cache: Conn,
+    cache: C,
     repo: T,
+    config: CacheConfig,
 }
 
 impl<T> RedisSurveyReadCacheRepository<T>
     where T: SurveyDTOReadRepository
 {
-    pub fn new(repo: T) -> RedisSurveyReadCacheRepository<T> {
+    /// Redis behind the process-wide circuit breaker.  Fails only when `CACHE_URL` is missing or
+    /// malformed, an unreachable Redis is bypassed rather than failing construction.
+    pub fn new(repo: T) -> Result<RedisSurveyReadCacheRepository<T>, Error> {
+        Ok(RedisSurveyReadCacheRepository::with_cache(repo, GuardedCacheStore::redis()?, cache_config().clone()))
+    }
+}
+
+impl<T, C> RedisSurveyReadCacheRepository<T, C>
+    where T: SurveyDTOReadRepository,
+          C: CacheStore,
+{
+    pub fn with_cache(repo: T, cache: C, config: CacheConfig) -> RedisSurveyReadCacheRepository<T, C> {
         RedisSurveyReadCacheRepository {
-            cache: REDIS_POOL.clone().get().unwrap(),
+            cache,
             repo,
+            config,
         }
     }
+
+    // A cache that can't be reached is treated as a miss.  So is an entry we can't parse, which is
+    // evicted so the fill that follows the miss can replace it.  The raw json comes back too, so a
+    // refresh can check nothing replaced the entry in the meantime.
+    fn cached<V: DeserializeOwned>(&mut self, key: &str) -> Option<(String, CacheEntry<V>)> {
+        let json = self.cache.get(key).unwrap_or(None)?;
+        match serde_json::from_str(&json) {
+            Ok(entry) => Some((json, entry)),
+            Err(_) => {
+                let _ = self.cache.delete(&[key.to_string()]);
+                None
+            },
+        }
+    }
+
+    // Serves `key` from the cache when there's a fresh entry the caller may `see`, and loads it
+    // otherwise.  "Not found" is remembered under `negative_key`, which may be `key` itself, or not
+    // at all without one.
+    //
+    // Concurrent misses on the same key are coalesced, one caller loads and the rest wait and
+    // then read what it cached.  An expired entry still inside the stale window is reloaded by
+    // one caller while everyone else is served the stale copy.
+    fn read_through<V, S, F>(&mut self, class: KeyClass, key: &str, negative_key: Option<&str>, see: S, mut load: F) -> Result<Option<V>, T::Error>
+        where V: Serialize + DeserializeOwned,
+              S: Fn(&V) -> bool,
+              F: FnMut(&mut T) -> Result<Option<V>, T::Error>,
+    {
+        let mut waited = false;
+        loop {
+            let stale = match self.cached::<V>(key) {
+                Some((json, entry)) => {
+                    let fresh = entry.is_fresh();
+                    match entry.value {
+                        Some(value) if see(&value) => {
+                            if fresh {
+                                record(class, CacheOutcome::Hit);
+                                return Ok(Some(value));
+                            }
+                            Some((json, value))
+                        },
+                        None if fresh && negative_key == Some(key) => {
+                            record(class, CacheOutcome::NegativeHit);
+                            return Ok(None);
+                        },
+                        _ => None,
+                    }
+                },
+                None => None,
+            };
+
+            if let Some((json, value)) = stale {
+                let _flight = match FLIGHTS.lead(key) {
+                    Some(flight) => flight,
+                    None => {
+                        record(class, CacheOutcome::StaleHit);
+                        return Ok(Some(value));
+                    },
+                };
+
+                record(class, CacheOutcome::Miss);
+                let loaded = load(&mut self.repo)?;
+                if let Some(value) = &loaded {
+                    // Only replaces the stale copy we read, so a write that landed since wins.
+                    let (fresh_json, ttl) = encode_found(&self.config, class, value);
+                    let _ = self.cache.swap(key, &json, &fresh_json, ttl);
+                }
+                return Ok(loaded);
+            }
+
+            if let Some(negative_key) = negative_key.filter(|negative_key| *negative_key != key) {
+                if let Some((_, entry)) = self.cached::<V>(negative_key) {
+                    if entry.value.is_none() && entry.is_fresh() {
+                        record(class, CacheOutcome::NegativeHit);
+                        return Ok(None);
+                    }
+                }
+            }
+
+            // The load we waited on left nothing this caller can use, e.g. the leader wasn't
+            // allowed to see the survey, so go to storage rather than wait again.
+            if waited {
+                record(class, CacheOutcome::Miss);
+                return self.load_and_fill(class, key, negative_key, &mut load);
+            }
+
+            match FLIGHTS.lead(key) {
+                Some(_flight) => {
+                    record(class, CacheOutcome::Miss);
+                    return self.load_and_fill(class, key, negative_key, &mut load);
+                },
+                None => {
+                    record(class, CacheOutcome::Coalesced);
+                    FLIGHTS.wait(key);
+                    waited = true;
+                },
+            }
+        }
+    }
+
+    // Fills only when nothing is there yet.  A write that lands between our read from storage and
+    // this fill has already stored the newer copy, and this one must not replace it.  A failed
+    // fill only costs the next read a trip to storage, so it's ignored.
+    fn load_and_fill<V, F>(&mut self, class: KeyClass, key: &str, negative_key: Option<&str>, load: &mut F) -> Result<Option<V>, T::Error>
+        where V: Serialize,
+              F: FnMut(&mut T) -> Result<Option<V>, T::Error>,
+    {
+        let loaded = load(&mut self.repo)?;
+        match (&loaded, negative_key) {
+            (Some(value), _) => {
+                let (json, ttl) = encode_found(&self.config, class, value);
+                let _ = self.cache.add(key, &json, ttl);
+            },
+            (None, Some(negative_key)) => {
+                let (json, ttl) = encode_missing(&self.config);
+                let _ = self.cache.add(negative_key, &json, ttl);
+            },
+            (None, None) => {},
+        }
+        Ok(loaded)
+    }
 }
 
-impl<T> SurveyDTOReadRepository for RedisSurveyReadCacheRepository<T>
-    where T: SurveyDTOReadRepository
+impl<T, C> SurveyDTOReadRepository for RedisSurveyReadCacheRepository<T, C>
+    where T: SurveyDTOReadRepository,
+          C: CacheStore,
 {
     type Error = T::Error;
 
-    fn get_survey_for_author(&mut self, id: &String, author: &String) -> Result<Option<SurveyDTO>, Self::Error> {
-        let survey_result: Option<SurveyDTO> =
-        // First we try to get it from redis
-        if let Ok(survey_str) = redis::cmd("GET").arg(id).query::<String>(&mut *self.cache) {
-            // If we succeed we deserialize the json string into the `SurveyDTO` object.
-            Some(serde_json::from_str(&survey_str).unwrap())
-        } else {
-            // We didn't succeed so we attempt to grab it from the underlying repo we are wrapping (source of truth)
-            let s_result: Option<SurveyDTO> = self.repo.get_survey_for_author(id, author)?;
-
-            // If that was successful then we take the successful SurveyDTO, turn it into a string (json) and shove it into redis
-            // so our cache is in sync.
-            if let Some(survey) = &s_result {
-                redis::cmd("SET").arg(id).arg(serde_json::to_string(survey).unwrap()).execute(&mut *self.cache);
-            };
-
-            // Regardless of success from underlying repo we return the result that came from the underlying storage (which could be None).
-            s_result
+    fn get_survey_for_author(&mut self, id: &String, author: &String, organization_id: Option<&String>) -> Result<Option<SurveyDTO>, Self::Error> {
+        let key = survey_key(id);
+        // Misses for someone acting for an organization aren't remembered.  A transfer to the
+        // organization would have to clear them for every member, and a survey doesn't know who
+        // those are.
+        let miss_key = match organization_id {
+            Some(_) => None,
+            None => Some(survey_miss_key(id, author)),
         };
 
-        // Here we simply wrap it in an Ok so it becomes a Result type
-        Ok(survey_result)
+        // The cached copy is shared by everyone who can see the survey, so a hit only counts if the
+        // caller is one of them.  Anyone else goes to storage, which gets the final say.
+        self.read_through(
+            KeyClass::Survey,
+            &key,
+            miss_key.as_deref(),
+            |survey: &SurveyDTO| survey.is_visible_to(author, organization_id),
+            |repo| repo.get_survey_for_author(id, author, organization_id),
+        )
     }
 
     fn get_surveys_by_author(&mut self, author: &String) -> Result<Option<SurveyDTOs>, Self::Error> {
-        let key = format!("{}_surveys", author);
-        let survey_results: Option<SurveyDTOs> =
-            // First we try to get it from redis
-            if let Ok(surveys_str) = redis::cmd("GET").arg(&key).query::<String>(&mut *self.cache) {
-                // If we succeed we deserialize the json string into the `SurveyDTOs` object.
-                Some(serde_json::from_str(&surveys_str).unwrap())
-            } else {
-                // We didn't succeed so we attempt to grab it from the underlying repo we are wrapping (source of truth).
-                let s_results: Option<SurveyDTOs> = self.repo.get_surveys_by_author(author)?;
-
-                // If that was successful then we take the successful SurveyDTOs, turn them into a string (json) and shove them into redis
-                // so our cache is in sync.
-                if let Some(surveys) = &s_results {
-                    redis::cmd("SET").arg(&key).arg(serde_json::to_string(surveys).unwrap()).execute(&mut *self.cache);
-                };
+        let key = author_listing_key(author);
 
-                s_results
-            };
+        self.read_through(
+            KeyClass::Listing,
+            &key,
+            Some(&key),
+            |_: &SurveyDTOs| true,
+            |repo| repo.get_surveys_by_author(author),
+        )
+    }
 
-        // Here we simply wrap it in an Ok so it becomes a Result type
-        Ok(survey_results)
+    // passthrough.  The listing mixes the organization's surveys with ones shared with this member,
+    // so a cached copy would need clearing for every member on each write, and a survey doesn't
+    // know who its organization's members are.
+    fn get_surveys_by_organization(&mut self, organization_id: &String, member: &String) -> Result<Option<SurveyDTOs>, Self::Error> {
+        self.repo.get_surveys_by_organization(organization_id, member)
     }
 }
 
 // This wrapper is intended to write to the cache on writes, and otherwise is a pass through on all gets.
 // Gets are for write side of model, so we should always pass through to the real database on those gets.
-pub struct RedisSurveyWriteCacheRepository<T>
-    where T: Repository<Survey>
+//
+// Cache writes that fail are ignored here, the write has already reached storage.  A stale copy
+// left behind could still show a survey to someone whose access was just revoked, so the default
+// `GuardedCacheStore` remembers the keys and clears them before Redis is read again.
+pub struct RedisSurveyWriteCacheRepository<T, C = GuardedCacheStore<RedisCacheStore>>
+    where T: Repository<Survey>,
+          C: CacheStore,
 {
-    // A single connection to Mysql.  Handed down from a pool likely.
-    cache: Conn,
+    cache: C,
     repo: T,
+    config: CacheConfig,
+    // Cache writes waiting on a commit, when they're deferred.
+    pending: Option<Vec<PendingWrite>>,
+}
+
+// A cache write held back until the storage write it follows has committed.
+enum PendingWrite {
+    Set { key: String, value: String, ttl: Duration },
+    Delete(Vec<String>),
+    Publish(Vec<String>),
+}
+
+fn apply_write<C: CacheStore>(cache: &mut C, write: PendingWrite) {
+    let _ = match write {
+        PendingWrite::Set { key, value, ttl } => cache.set(&key, &value, ttl),
+        PendingWrite::Delete(keys) => cache.delete(&keys),
+        PendingWrite::Publish(keys) => publish_invalidation(cache, &keys),
+    };
 }
 
 impl<T> RedisSurveyWriteCacheRepository<T>
     where T: Repository<Survey>
 {
-    pub fn new(repo: T) -> RedisSurveyWriteCacheRepository<T> {
+    /// Redis behind the process-wide circuit breaker.  Fails only when `CACHE_URL` is missing or
+    /// malformed, an unreachable Redis is bypassed rather than failing construction.
+    pub fn new(repo: T) -> Result<RedisSurveyWriteCacheRepository<T>, Error> {
+        Ok(RedisSurveyWriteCacheRepository::with_cache(repo, GuardedCacheStore::redis()?, cache_config().clone()))
+    }
+}
+
+impl<T, C> RedisSurveyWriteCacheRepository<T, C>
+    where T: Repository<Survey>,
+          C: CacheStore,
+{
+    pub fn with_cache(repo: T, cache: C, config: CacheConfig) -> RedisSurveyWriteCacheRepository<T, C> {
         RedisSurveyWriteCacheRepository {
-            cache: REDIS_POOL.clone().get().unwrap(),
+            cache,
             repo,
+            config,
+            pending: None,
+        }
+    }
+
+    /// Holds every cache write back until `flush`, for a repository taking part in a unit of work.
+    /// A copy stored before the commit would outlive a rollback, and a listing evicted before it
+    /// could be filled again from the old rows.
+    pub fn deferred(mut self) -> RedisSurveyWriteCacheRepository<T, C> {
+        self.pending = Some(vec![]);
+        self
+    }
+
+    /// Makes the cache writes `deferred` held back.  Call once the unit of work has committed.
+    pub fn flush(&mut self) {
+        let cache = &mut self.cache;
+        if let Some(pending) = self.pending.as_mut() {
+            for write in pending.drain(..) {
+                apply_write(cache, write);
+            }
+        }
+    }
+
+    fn write(&mut self, write: PendingWrite) {
+        match self.pending.as_mut() {
+            Some(pending) => pending.push(write),
+            None => apply_write(&mut self.cache, write),
         }
     }
 
     // Resets cache of all surveys by author_surveys
     pub fn invalidate_surveys_cache(&mut self, author: String) {
-        let key = format!("{}_surveys", author);
-        redis::cmd("DEL")
-            .arg(&key)
-            .execute(&mut *self.cache);
+        let keys = vec![author_listing_key(&author)];
+        self.write(PendingWrite::Delete(keys.clone()));
+        self.write(PendingWrite::Publish(keys));
+    }
+
+    // A survey shows up in its author's listing and in the listing of everyone it's shared with,
+    // and any of them may have a "not found" remembered for it.  Both the stored copy and the new
+    // one count, so someone who just lost access, or a previous owner, stops seeing it too, and
+    // someone who just gained access sees it straight away.
+    //
+    // Every node's in-process copies of the survey and those listings are evicted too.  Callers
+    // have already replaced or deleted the survey itself.
+    fn invalidate_readers(&mut self, surveys: &[&Survey]) {
+        let mut keys: Vec<String> = vec![];
+        for survey in surveys {
+            let usernames: Vec<String> = std::iter::once(survey.author().to_string())
+                .chain(survey.collaborators().iter().map(|c| c.username().to_string()))
+                .collect();
+            push_reader_keys(&mut keys, &survey.id(), usernames.iter());
+        }
+
+        self.write(PendingWrite::Delete(keys.clone()));
+
+        let mut evicted = keys;
+        for survey in surveys {
+            let key = survey_key(&survey.id());
+            if !evicted.contains(&key) {
+                evicted.push(key);
+            }
+        }
+        self.write(PendingWrite::Publish(evicted));
+    }
+
+    fn store_survey(&mut self, survey: &Survey) {
+        let (value, ttl) = encode_found(&self.config, KeyClass::Survey, &SurveyDTO::from(survey));
+        self.write(PendingWrite::Set { key: survey_key(&survey.id()), value, ttl });
     }
 }
 
-impl<T> Repository<Survey> for RedisSurveyWriteCacheRepository<T>
-    where T: Repository<Survey>
+impl<T, C> Repository<Survey> for RedisSurveyWriteCacheRepository<T, C>
+    where T: Repository<Survey>,
+          C: CacheStore,
 {
     type Error = T::Error;
 
-    // Insert into underlying persistent storage, then set the survey into redis cache.
-    // Lastly invalidate the cache for aggregate surveys (all surveys).  That gets refreshed
-    // On read rather than on write.
+    // Insert into underlying persistent storage, then set the survey into the cache under its id.
+    // Lastly invalidate the listings it now appears in.  Those get refreshed on read rather than on
+    // write.
     fn insert(&mut self, entity: &Survey) -> Result<Option<String>, Self::Error> {
         let maybe_id = self.repo.insert(entity)?;
-        redis::cmd("SET")
-            .arg(entity.author().to_string())
-            .arg(serde_json::to_string(&SurveyDTO::from(entity)).unwrap())
-            .execute(&mut *self.cache);
-        self.invalidate_surveys_cache(entity.author().to_string());
+        // Nothing was stored for a duplicate id, so the cache is left alone too.
+        if maybe_id.is_some() {
+            self.store_survey(entity);
+            self.invalidate_readers(&[entity]);
+        }
         Ok(maybe_id)
     }
 
@@ -141,34 +398,41 @@ impl<T> Repository<Survey> for RedisSurveyWriteCacheRepository<T>
         self.repo.get_paged(page_num, page_size)
     }
 
-    // Update in underlying persistent storage, then update the survey in redis cache.
-    // Lastly invalidate the cache for aggregate surveys (all surveys).  That gets refreshed
-    // On read rather than on write.
+    // Update in underlying persistent storage, then replace the cached survey and invalidate every
+    // listing it appeared in before or appears in now.
     fn update(&mut self, entity: &Survey) -> Result<Option<String>, Self::Error> {
+        // Anyone whose access was just revoked needs their listing cleared too, and they're only
+        // on the stored copy.
+        let previous = self.repo.get(&entity.id())?;
         let maybe_id = self.repo.update(entity)?;
-        redis::cmd("SET")
-            .arg(entity.author().to_string())
-            .arg(serde_json::to_string(&SurveyDTO::from(entity)).unwrap())
-            .execute(&mut *self.cache);
-        self.invalidate_surveys_cache(entity.author().to_string());
+
+        if maybe_id.is_some() {
+            self.store_survey(entity);
+        } else {
+            // Removed since it was loaded, so any cached copy is stale.
+            self.write(PendingWrite::Delete(vec![survey_key(&entity.id())]));
+        }
+
+        match &previous {
+            Some(previous) => self.invalidate_readers(&[previous, entity]),
+            None => self.invalidate_readers(&[entity]),
+        }
         Ok(maybe_id)
     }
 
-    // Remove from underlying storage and remove survey from redis cache.
+    // Remove from underlying storage, then evict the survey and every listing it was in.  Evicting
+    // after the remove rather than before narrows the window in which a racing read can put the
+    // survey back.
     fn remove(&mut self, key: &String) -> Result<Option<String>, Self::Error> {
-        let s_id = self.repo.get(key)?;
-        // Invalidate caches before delete.
-        if let Some(s) = s_id {
-            self.invalidate_surveys_cache(s.author().to_string());
-            redis::cmd("DEL")
-                .arg(key)
-                .execute(&mut *self.cache);
-        } else {
+        let survey = match self.repo.get(key)? {
+            Some(s) => s,
             // No survey so we can just leave here without trying to delete.
-            return Ok(None);
-        }
+            None => return Ok(None),
+        };
 
         let maybe_id = self.repo.remove(key)?;
+        self.write(PendingWrite::Delete(vec![survey_key(key)]));
+        self.invalidate_readers(&[&survey]);
         Ok(maybe_id)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Condvar};
+use std::time::Duration;
+
+// A follower stops waiting after this long and loads for itself, so one stuck query can't hold up
+// every request for the same key.
+const MAX_WAIT: Duration = Duration::from_secs(5);
+
+lazy_static! {
+    /// Shared by every cache decorator in the process.  Decorators are built per request, so the
+    /// coalescing has to live outside them.
+    pub(crate) static ref FLIGHTS: SingleFlight = SingleFlight::new();
+}
+
+/// Coalesces concurrent loads of the same key.  The first caller to miss leads and loads from
+/// storage, and everyone else who misses meanwhile waits for it to land and then reads what it
+/// cached, rather than all going to storage at once.
+///
+/// Only callers in this process are coalesced.  Each API node still loads a given key once.
+pub struct SingleFlight {
+    flights: Mutex<HashMap<String, Arc<Flight>>>,
+}
+
+struct Flight {
+    landed: Mutex<bool>,
+    signal: Condvar,
+}
+
+/// Held by the caller leading the load of a key.  Dropping it, however the load ends, releases
+/// everyone waiting on that key.
+pub struct FlightGuard<'a> {
+    owner: &'a SingleFlight,
+    key: String,
+    flight: Arc<Flight>,
+}
+
+impl SingleFlight {
+    pub fn new() -> SingleFlight {
+        SingleFlight {
+            flights: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Makes the caller the leader for `key`, unless someone else already is.
+    pub fn lead(&self, key: &str) -> Option<FlightGuard<'_>> {
+        let mut flights = self.flights.lock().unwrap();
+        if flights.contains_key(key) {
+            return None;
+        }
+
+        let flight = Arc::new(Flight {
+            landed: Mutex::new(false),
+            signal: Condvar::new(),
+        });
+        flights.insert(key.to_string(), flight.clone());
+
+        Some(FlightGuard {
+            owner: self,
+            key: key.to_string(),
+            flight,
+        })
+    }
+
+    /// Blocks until the load of `key` in flight lands, if there is one.
+    pub fn wait(&self, key: &str) {
+        let flight = match self.flights.lock().unwrap().get(key) {
+            Some(flight) => flight.clone(),
+            None => return,
+        };
+
+        let mut landed = flight.landed.lock().unwrap();
+        while !*landed {
+            let (guard, timeout) = flight.signal.wait_timeout(landed, MAX_WAIT).unwrap();
+            landed = guard;
+            if timeout.timed_out() {
+                return;
+            }
+        }
+    }
+}
+
+impl Default for SingleFlight {
+    fn default() -> Self {
+        SingleFlight::new()
+    }
+}
+
+impl<'a> Drop for FlightGuard<'a> {
+    fn drop(&mut self) {
+        self.owner.flights.lock().unwrap().remove(&self.key);
+        *self.flight.landed.lock().unwrap() = true;
+        self.flight.signal.notify_all();
+    }
+}
@@ -0,0 +1,81 @@
+use survey_manager_core::app_services::repository_contracts::SurveyDTOReadRepository;
+use survey_manager_core::dtos::{SurveyDTO, SurveyDTOs};
+use crate::cache_repo_decorators::{LocalCache, KeyClass, CacheOutcome, local_cache, survey_key, author_listing_key};
+use crate::cache_repo_decorators::cache_metrics::record;
+
+/// Keeps recently read surveys and listings in this process's memory, in front of another read
+/// repository, normally `RedisSurveyReadCacheRepository`.  Entries are evicted when a write on any
+/// node publishes an invalidation, see `spawn_invalidation_listener`, and otherwise expire after
+/// `CACHE_LOCAL_TTL`.
+///
+/// Only found values are kept.  "Not found" is left to the tier behind.
+pub struct TieredSurveyReadRepository<T>
+    where T: SurveyDTOReadRepository
+{
+    local: LocalCache,
+    repo: T,
+}
+
+impl<T> TieredSurveyReadRepository<T>
+    where T: SurveyDTOReadRepository
+{
+    pub fn new(repo: T) -> TieredSurveyReadRepository<T> {
+        TieredSurveyReadRepository::with_local(repo, local_cache())
+    }
+
+    pub fn with_local(repo: T, local: LocalCache) -> TieredSurveyReadRepository<T> {
+        TieredSurveyReadRepository {
+            local,
+            repo,
+        }
+    }
+}
+
+impl<T> SurveyDTOReadRepository for TieredSurveyReadRepository<T>
+    where T: SurveyDTOReadRepository
+{
+    type Error = T::Error;
+
+    fn get_survey_for_author(&mut self, id: &String, author: &String, organization_id: Option<&String>) -> Result<Option<SurveyDTO>, Self::Error> {
+        let key = survey_key(id);
+
+        // Shared by everyone who can see the survey, like the Redis copy, so access is checked on
+        // every hit.
+        if let Some(survey) = self.local.survey(&key) {
+            if survey.is_visible_to(author, organization_id) {
+                record(KeyClass::Survey, CacheOutcome::LocalHit);
+                return Ok(Some(survey));
+            }
+        }
+
+        let generation = self.local.generation();
+        let found = self.repo.get_survey_for_author(id, author, organization_id)?;
+        if let Some(survey) = &found {
+            self.local.fill_survey(&key, survey.clone(), generation);
+        }
+
+        Ok(found)
+    }
+
+    fn get_surveys_by_author(&mut self, author: &String) -> Result<Option<SurveyDTOs>, Self::Error> {
+        let key = author_listing_key(author);
+
+        if let Some(surveys) = self.local.listing(&key) {
+            record(KeyClass::Listing, CacheOutcome::LocalHit);
+            return Ok(Some(surveys));
+        }
+
+        let generation = self.local.generation();
+        let found = self.repo.get_surveys_by_author(author)?;
+        if let Some(surveys) = &found {
+            self.local.fill_listing(&key, surveys.clone(), generation);
+        }
+
+        Ok(found)
+    }
+
+    // passthrough, for the same reasons as the Redis tier.
+    fn get_surveys_by_organization(&mut self, organization_id: &String, member: &String) -> Result<Option<SurveyDTOs>, Self::Error> {
+        self.repo.get_surveys_by_organization(organization_id, member)
+    }
+}
@@ -0,0 +1,166 @@
+use snafu::Snafu;
+
+/// Errors raised by the infrastructure layer.  Repositories hand these back through their `Error`
+/// associated type, and the application layer surfaces them as `RepoFailure`.
+#[derive(Debug, Snafu)]
+pub enum Error {
+    /// Failure to communicate with, or a query rejected by, MySQL.
+    #[snafu(display("database error: {}", source))]
+    DatabaseError {
+        source: mysql::Error,
+    },
+
+    /// MySQL can't be used at all, because it isn't configured or no connection could be had in time.
+    #[snafu(display("database unavailable: {}", reason))]
+    DatabaseUnavailable {
+        reason: String,
+    },
+
+    /// Failure to communicate with, or a query rejected by, a database other than MySQL.  Kept as
+    /// text so the variant exists whichever backends are compiled in.
+    #[snafu(display("{} error: {}", backend, reason))]
+    StorageError {
+        backend: &'static str,
+        reason: String,
+    },
+
+    /// `SURVEY_BACKEND` names a backend that doesn't exist, or wasn't compiled in.
+    #[snafu(display("unknown survey backend {}", name))]
+    UnknownBackend {
+        name: String,
+    },
+
+    /// The integrity checker only reads surveys the `mysql` backend stores, see `SurveyIntegrityChecker`.
+    #[snafu(display("surveys stored by the {} backend can't be checked, only those stored by mysql", backend))]
+    UncheckedBackend {
+        backend: &'static str,
+    },
+
+    /// A schema migration was rejected partway through, see `MysqlMigrator`.
+    #[snafu(display("migration {} {} failed: {}", version, name, source))]
+    MigrationFailed {
+        version: u32,
+        name: String,
+        source: mysql::Error,
+    },
+
+    /// A migration was edited after it was applied, so databases may no longer agree on the schema.
+    #[snafu(display("migration {} {} has changed since it was applied", version, name))]
+    MigrationModified {
+        version: u32,
+        name: String,
+    },
+
+    /// Reverting would need a migration applied by a newer build, which this one can't undo.
+    #[snafu(display("migration {} was applied by a newer build", version))]
+    UnknownMigration {
+        version: u32,
+    },
+
+    /// Another node held the migration lock for too long.
+    #[snafu(display("timed out waiting for another node to finish migrating"))]
+    MigrationLocked,
+
+    /// Failure to serialize a survey for storage, or to parse stored survey json.
+    #[snafu(display("survey data could not be (de)serialized: {}", source))]
+    SerializationError {
+        source: serde_json::Error,
+    },
+
+    /// Failure to communicate with Redis.
+    #[snafu(display("cache error: {}", source))]
+    CacheError {
+        source: redis::RedisError,
+    },
+
+    /// Redis can't be used at all, because it isn't configured or no connection could be had.
+    #[snafu(display("cache unavailable: {}", reason))]
+    CacheUnavailable {
+        reason: String,
+    },
+
+    /// The cache was skipped because it has been failing.  See `CircuitBreaker`.
+    #[snafu(display("cache bypassed after repeated failures"))]
+    CacheBypassed,
+
+    /// Failure to hand an email over for delivery.
+    #[snafu(display("mail error: {}", source))]
+    MailError {
+        source: std::io::Error,
+    },
+
+    /// A stored user row no longer makes a valid aggregate.
+    #[snafu(display("stored user {} failed to load: {}", id, source))]
+    CorruptUser {
+        id: String,
+        source: survey_manager_core::Error,
+    },
+
+    /// A stored organization row no longer makes a valid aggregate.
+    #[snafu(display("stored organization {} failed to load: {}", id, source))]
+    CorruptOrganization {
+        id: String,
+        source: survey_manager_core::Error,
+    },
+
+    /// A stored survey parsed fine but no longer makes a valid aggregate.  This happens when a row
+    /// was written by an older build and validation has since been tightened, or was edited by hand.
+    #[snafu(display("stored survey {} failed to load: {}", id, source))]
+    CorruptSurvey {
+        id: String,
+        source: survey_manager_core::Error,
+    },
+}
+
+impl From<mysql::Error> for Error {
+    fn from(err: mysql::Error) -> Self {
+        Error::DatabaseError {
+            source: err,
+        }
+    }
+}
+
+// Lets callers building handlers from these repositories report a missing connection as a repository failure.
+impl From<Error> for survey_manager_core::Error {
+    fn from(err: Error) -> Self {
+        survey_manager_core::Error::RepoFailure {
+            source: Box::new(err),
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::SerializationError {
+            source: err,
+        }
+    }
+}
+
+impl From<redis::RedisError> for Error {
+    fn from(err: redis::RedisError) -> Self {
+        Error::CacheError {
+            source: err,
+        }
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl From<pg::Error> for Error {
+    fn from(err: pg::Error) -> Self {
+        Error::StorageError {
+            backend: "postgres",
+            reason: err.to_string(),
+        }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for Error {
+    fn from(err: rusqlite::Error) -> Self {
+        Error::StorageError {
+            backend: "sqlite",
+            reason: err.to_string(),
+        }
+    }
+}
@@ -1,10 +1,23 @@
+pub mod errors;
+pub use errors::Error;
+
 pub mod mysql_repos;
+#[cfg(feature = "postgres")]
+pub mod pg_repos;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_repos;
+pub mod survey_backends;
 pub mod cache_repo_decorators;
+pub mod redis_stores;
+pub mod mailers;
 pub mod utils;
 
 #[macro_use]
 extern crate lazy_static;
 
+#[macro_use]
+extern crate serde_derive;
+
 #[cfg(test)]
 mod tests {
 //    use crate::mysql_repos::MysqlSurveyWriteRepository;
@@ -0,0 +1,52 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use chrono::{TimeZone, Utc};
+use survey_manager_core::app_services::invitations::{InvitationEmail, Mailer};
+use crate::errors::Error;
+
+/// Writes each email to its own file in a spool directory instead of sending it.  Meant for local
+/// development and tests, where the links can be read straight off disk.  Files are named after
+/// the invitation, so sending the same invitation twice overwrites the first copy.
+pub struct FileSpoolMailer {
+    dir: PathBuf,
+}
+
+impl FileSpoolMailer {
+    pub fn new(dir: impl Into<PathBuf>) -> FileSpoolMailer {
+        FileSpoolMailer {
+            dir: dir.into(),
+        }
+    }
+}
+
+impl Mailer for FileSpoolMailer {
+    type Error = Error;
+
+    fn send(&mut self, email: &InvitationEmail) -> Result<(), Error> {
+        let mail_error = |e| Error::MailError { source: e };
+        fs::create_dir_all(&self.dir).map_err(mail_error)?;
+
+        // Survey titles end up in a header, so they mustn't be able to start a new one.
+        let title: String = email.survey_title.chars()
+            .map(|c| if c.is_control() { ' ' } else { c })
+            .collect();
+        let expires = Utc.timestamp_opt(email.expires_at, 0).single()
+            .ok_or_else(|| mail_error(io::Error::new(io::ErrorKind::InvalidInput, "expiry is out of range")))?;
+
+        let message = format!(
+            "To: {}\r\nSubject: You're invited to answer \"{}\"\r\nDate: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n\
+             You've been invited to answer \"{}\".\r\n\r\n{}\r\n\r\nThe link works once and expires on {}.\r\n",
+            email.to, title, Utc::now().to_rfc2822(), title, email.link, expires.to_rfc2822(),
+        );
+
+        // Written under a temporary name first, so anything watching the spool never picks up
+        // half a message.
+        let path = self.dir.join(format!("{}.eml", email.invitation_id));
+        let partial = self.dir.join(format!("{}.eml.partial", email.invitation_id));
+        fs::write(&partial, message).map_err(mail_error)?;
+        fs::rename(&partial, &path).map_err(mail_error)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,2 @@
+pub mod file_spool_mailer;
+pub use file_spool_mailer::*;
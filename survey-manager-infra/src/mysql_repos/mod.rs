@@ -1,12 +1,50 @@
-lazy_static! {
-    static ref MYSQL_POOL: mysql::Pool = {
-        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-        mysql::Pool::new(&database_url).unwrap()
-    };
-}
+pub mod mysql_pool;
+pub use mysql_pool::*;
+
+pub mod unit_of_work;
+pub use unit_of_work::*;
 
 pub mod mysql_survey_repository;
 pub use mysql_survey_repository::*;
 
 pub mod mysql_surveydto_read_repository;
 pub use mysql_surveydto_read_repository::*;
+
+pub mod survey_diff;
+pub use survey_diff::*;
+
+pub mod mysql_normalized_survey_repository;
+pub use mysql_normalized_survey_repository::*;
+
+pub mod mysql_normalized_surveydto_read_repository;
+pub use mysql_normalized_surveydto_read_repository::*;
+
+pub mod survey_integrity;
+pub use survey_integrity::*;
+
+pub mod mysql_user_repository;
+pub use mysql_user_repository::*;
+
+pub mod mysql_refresh_token_repository;
+pub use mysql_refresh_token_repository::*;
+
+pub mod mysql_organization_repository;
+pub use mysql_organization_repository::*;
+
+pub mod mysql_audit_log;
+pub use mysql_audit_log::*;
+
+pub mod mysql_invitation_repository;
+pub use mysql_invitation_repository::*;
+
+pub mod mysql_response_repository;
+pub use mysql_response_repository::*;
+
+pub mod mysql_partial_response_repository;
+pub use mysql_partial_response_repository::*;
+
+pub mod mysql_survey_snapshot_repository;
+pub use mysql_survey_snapshot_repository::*;
+
+pub mod mysql_migrator;
+pub use mysql_migrator::*;
@@ -0,0 +1,57 @@
+use survey_manager_core::app_services::moderation::AuditEntry;
+use survey_manager_core::app_services::repository_contracts::AuditLog;
+use crate::mysql_repos::{mysql_pool, MysqlConnection};
+use crate::errors::Error;
+
+pub struct MysqlAuditLog {
+    // Shared with the other repositories of a unit of work, when there is one.
+    conn: MysqlConnection,
+}
+
+impl MysqlAuditLog {
+    /// A repository on a connection of its own from the shared pool.
+    pub fn new() -> Result<MysqlAuditLog, Error> {
+        Ok(MysqlAuditLog::with_connection(mysql_pool()?.connection()?))
+    }
+
+    pub fn with_connection(conn: MysqlConnection) -> MysqlAuditLog {
+        MysqlAuditLog {
+            conn,
+        }
+    }
+}
+
+// Only INSERT and SELECT are issued here, and triggers on the table refuse UPDATE and DELETE from
+// anyone else.
+impl AuditLog for MysqlAuditLog {
+    type Error = Error;
+
+    fn append(&mut self, entry: &AuditEntry) -> Result<(), Error> {
+        self.conn.lock().prep_exec(
+            "INSERT INTO audit_log (id, actor, action, target, reason, occurred) VALUES (?, ?, ?, ?, ?, ?)",
+            (&entry.id, &entry.actor, &entry.action, &entry.target, &entry.reason, entry.occurred)
+        )?;
+
+        Ok(())
+    }
+
+    fn list(&mut self, target: Option<&String>, page_num: usize, page_size: usize) -> Result<Vec<AuditEntry>, Error> {
+        let offset = (page_num.max(1) - 1) * page_size;
+
+        // A NULL target matches every row.
+        let mut conn = self.conn.lock();
+        let q_result = conn.prep_exec(
+            "SELECT id, actor, action, target, reason, occurred FROM audit_log \
+             WHERE (? IS NULL OR target = ?) ORDER BY occurred DESC, seq DESC LIMIT ? OFFSET ?",
+            (target, target, page_size as u64, offset as u64)
+        )?;
+
+        let mut entries = Vec::new();
+        for row_result in q_result {
+            let (id, actor, action, target, reason, occurred) = mysql::from_row(row_result?);
+            entries.push(AuditEntry { id, actor, action, target, reason, occurred });
+        }
+
+        Ok(entries)
+    }
+}
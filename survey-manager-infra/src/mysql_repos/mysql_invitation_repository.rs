@@ -0,0 +1,108 @@
+use chrono::Utc;
+use survey_manager_core::app_services::invitations::{InvitationRecord, InvitationStatus, InvitationStats};
+use survey_manager_core::app_services::repository_contracts::InvitationRepository;
+use crate::mysql_repos::{mysql_pool, MysqlConnection};
+use crate::errors::Error;
+
+pub struct MysqlInvitationRepository {
+    // Shared with the other repositories of a unit of work, when there is one.
+    conn: MysqlConnection,
+}
+
+impl MysqlInvitationRepository {
+    /// A repository on a connection of its own from the shared pool.
+    pub fn new() -> Result<MysqlInvitationRepository, Error> {
+        Ok(MysqlInvitationRepository::with_connection(mysql_pool()?.connection()?))
+    }
+
+    pub fn with_connection(conn: MysqlConnection) -> MysqlInvitationRepository {
+        MysqlInvitationRepository {
+            conn,
+        }
+    }
+}
+
+impl InvitationRepository for MysqlInvitationRepository {
+    type Error = Error;
+
+    fn insert(&mut self, invitation: &InvitationRecord) -> Result<(), Error> {
+        self.conn.lock().prep_exec(
+            "INSERT INTO invitation (id, survey_id, recipient, status, created_at, expires_at) VALUES (?, ?, ?, ?, ?, ?)",
+            (&invitation.id, &invitation.survey_id, &invitation.recipient, invitation.status.as_str(), invitation.created_at, invitation.expires_at)
+        )?;
+
+        Ok(())
+    }
+
+    fn get(&mut self, id: &String) -> Result<Option<InvitationRecord>, Error> {
+        let mut conn = self.conn.lock();
+        let mut q_result = conn.prep_exec(
+            "SELECT id, survey_id, recipient, status, created_at, expires_at FROM invitation WHERE id = ?",
+            (id,)
+        )?;
+
+        let row = match q_result.next() {
+            Some(row_result) => row_result?,
+            None => return Ok(None),
+        };
+
+        let (id, survey_id, recipient, status, created_at, expires_at): (String, String, String, String, i64, i64) = mysql::from_row(row);
+        Ok(Some(InvitationRecord {
+            id,
+            survey_id,
+            recipient,
+            status: InvitationStatus::parse(&status),
+            created_at,
+            expires_at,
+        }))
+    }
+
+    fn mark_opened(&mut self, id: &String) -> Result<bool, Error> {
+        let mut conn = self.conn.lock();
+        let result = conn.prep_exec(
+            "UPDATE invitation SET status = 'opened' WHERE id = ? AND status = 'sent'",
+            (id,)
+        )?;
+
+        Ok(result.affected_rows() == 1)
+    }
+
+    fn mark_completed(&mut self, id: &String) -> Result<bool, Error> {
+        // The status guard makes this a compare and swap, only one caller can complete a link.
+        let mut conn = self.conn.lock();
+        let result = conn.prep_exec(
+            "UPDATE invitation SET status = 'completed' WHERE id = ? AND status <> 'completed'",
+            (id,)
+        )?;
+
+        Ok(result.affected_rows() == 1)
+    }
+
+    fn stats(&mut self, survey_id: &String) -> Result<InvitationStats, Error> {
+        // SUM over no rows is NULL, hence the COALESCEs.
+        let mut conn = self.conn.lock();
+        let mut q_result = conn.prep_exec(
+            "SELECT COUNT(*), \
+                CAST(COALESCE(SUM(status = 'sent'), 0) AS UNSIGNED), \
+                CAST(COALESCE(SUM(status = 'opened'), 0) AS UNSIGNED), \
+                CAST(COALESCE(SUM(status = 'completed'), 0) AS UNSIGNED), \
+                CAST(COALESCE(SUM(status <> 'completed' AND expires_at <= ?), 0) AS UNSIGNED) \
+             FROM invitation WHERE survey_id = ?",
+            (Utc::now().timestamp(), survey_id)
+        )?;
+
+        let (total, sent, opened, completed, expired) = match q_result.next() {
+            Some(row_result) => mysql::from_row(row_result?),
+            None => (0, 0, 0, 0, 0),
+        };
+
+        Ok(InvitationStats {
+            survey_id: survey_id.clone(),
+            total,
+            sent,
+            opened,
+            completed,
+            expired,
+        })
+    }
+}
@@ -0,0 +1,382 @@
+use sha2::{Sha256, Digest};
+use crate::mysql_repos::mysql_pool;
+use crate::errors::Error;
+
+// Migrations are numbered and applied in that order.  Add new ones at the end with the next
+// number, and never edit one that has shipped, add another that changes it instead.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial_schema",
+        up: include_str!("../../migrations/mysql/0001_initial_schema.up.sql"),
+        down: include_str!("../../migrations/mysql/0001_initial_schema.down.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "survey_responses",
+        up: include_str!("../../migrations/mysql/0002_survey_responses.up.sql"),
+        down: include_str!("../../migrations/mysql/0002_survey_responses.down.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "survey_quarantine",
+        up: include_str!("../../migrations/mysql/0003_survey_quarantine.up.sql"),
+        down: include_str!("../../migrations/mysql/0003_survey_quarantine.down.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "user_accounts",
+        up: include_str!("../../migrations/mysql/0004_user_accounts.up.sql"),
+        down: include_str!("../../migrations/mysql/0004_user_accounts.down.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "refresh_tokens",
+        up: include_str!("../../migrations/mysql/0005_refresh_tokens.up.sql"),
+        down: include_str!("../../migrations/mysql/0005_refresh_tokens.down.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "survey_collaborators",
+        up: include_str!("../../migrations/mysql/0006_survey_collaborators.up.sql"),
+        down: include_str!("../../migrations/mysql/0006_survey_collaborators.down.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "organizations",
+        up: include_str!("../../migrations/mysql/0007_organizations.up.sql"),
+        down: include_str!("../../migrations/mysql/0007_organizations.down.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "survey_organizations",
+        up: include_str!("../../migrations/mysql/0008_survey_organizations.up.sql"),
+        down: include_str!("../../migrations/mysql/0008_survey_organizations.down.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "audit_log",
+        up: include_str!("../../migrations/mysql/0009_audit_log.up.sql"),
+        down: include_str!("../../migrations/mysql/0009_audit_log.down.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "invitations",
+        up: include_str!("../../migrations/mysql/0010_invitations.up.sql"),
+        down: include_str!("../../migrations/mysql/0010_invitations.down.sql"),
+    },
+    Migration {
+        version: 11,
+        name: "partial_responses",
+        up: include_str!("../../migrations/mysql/0011_partial_responses.up.sql"),
+        down: include_str!("../../migrations/mysql/0011_partial_responses.down.sql"),
+    },
+    Migration {
+        version: 12,
+        name: "survey_snapshots",
+        up: include_str!("../../migrations/mysql/0012_survey_snapshots.up.sql"),
+        down: include_str!("../../migrations/mysql/0012_survey_snapshots.down.sql"),
+    },
+    Migration {
+        version: 13,
+        name: "normalized_surveys",
+        up: include_str!("../../migrations/mysql/0013_normalized_surveys.up.sql"),
+        down: include_str!("../../migrations/mysql/0013_normalized_surveys.down.sql"),
+    },
+];
+
+// Holds which migrations have run.
+const MIGRATIONS_TABLE: &str = "schema_migrations";
+
+// Databases created by the old init/schema.sql have this table and no migrations table.
+const BASELINE_TABLE: &str = "survey";
+
+// How long to wait for another node that's migrating the same database.
+const LOCK_TIMEOUT_SECS: u64 = 60;
+
+/// The MySQL migrations built into this binary, in order.
+pub fn mysql_migrations() -> Vec<Migration> {
+    MIGRATIONS.to_vec()
+}
+
+/// One step of the schema, along with how to take it back.
+#[derive(Clone, Debug)]
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    pub up: &'static str,
+    pub down: &'static str,
+}
+
+impl Migration {
+    /// A sha256 of `up`.  It's recorded when the migration runs, so a migration edited after it
+    /// was applied is caught rather than silently leaving databases that disagree.
+    pub fn checksum(&self) -> String {
+        format!("{:x}", Sha256::digest(self.up.as_bytes()))
+    }
+
+    /// The statements in `up`, see `statements`.
+    pub fn up_statements(&self) -> Vec<String> {
+        statements(self.up)
+    }
+
+    /// The statements in `down`, see `statements`.
+    pub fn down_statements(&self) -> Vec<String> {
+        statements(self.down)
+    }
+}
+
+/// Splits a migration script into statements.  Lines starting with `--` are dropped and
+/// statements end at `;`, so a migration can't use `;` anywhere else, e.g. in a trigger with a
+/// `BEGIN ... END` body.
+pub fn statements(script: &str) -> Vec<String> {
+    let script: String = script.lines()
+        .filter(|line| !line.trim_start().starts_with("--"))
+        .collect::<Vec<&str>>()
+        .join("\n");
+
+    script.split(';')
+        .map(|statement| statement.trim())
+        .filter(|statement| !statement.is_empty())
+        .map(|statement| statement.to_string())
+        .collect()
+}
+
+/// Where a migration stands in a database.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MigrationState {
+    Pending,
+    Applied,
+    /// Applied, but the migration has been edited since.
+    Modified,
+    /// Applied by a newer build, this one doesn't know about it.
+    Unknown,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MigrationStatus {
+    pub version: u32,
+    pub name: String,
+    pub state: MigrationState,
+}
+
+// A row of the migrations table.
+struct AppliedMigration {
+    version: u32,
+    name: String,
+    checksum: String,
+}
+
+/// Brings a MySQL database's schema up to date, or takes it back, with `Migration`s.  What has
+/// been applied is kept in `schema_migrations`.
+///
+/// Runs are serialized across nodes with a named lock, so every node can migrate at startup.
+/// MySQL commits DDL as it goes, so a migration that fails partway through is left half applied,
+/// and needs putting right by hand before it's run again.
+pub struct MysqlMigrator {
+    // A connection of its own, since migrations manage their own transactions.
+    conn: mysql::PooledConn,
+    migrations: Vec<Migration>,
+    table: String,
+    baseline: Option<String>,
+}
+
+impl MysqlMigrator {
+    /// The migrations in this binary, against `DATABASE_URL`.
+    pub fn new() -> Result<MysqlMigrator, Error> {
+        let mut migrator = MysqlMigrator::with_migrations(mysql_pool()?.get_conn()?, mysql_migrations(), MIGRATIONS_TABLE);
+        migrator.baseline = Some(BASELINE_TABLE.to_string());
+        Ok(migrator)
+    }
+
+    /// Runs `migrations` over `conn`, recording them in `table`.
+    pub fn with_migrations(conn: mysql::PooledConn, mut migrations: Vec<Migration>, table: &str) -> MysqlMigrator {
+        migrations.sort_by_key(|m| m.version);
+        MysqlMigrator {
+            conn,
+            migrations,
+            table: table.to_string(),
+            baseline: None,
+        }
+    }
+
+    /// Every migration this build knows about plus any applied by a newer one, in order.
+    pub fn status(&mut self) -> Result<Vec<MigrationStatus>, Error> {
+        let applied = self.applied()?;
+        let mut statuses: Vec<MigrationStatus> = self.migrations.iter()
+            .map(|m| {
+                let state = match applied.iter().find(|a| a.version == m.version) {
+                    None => MigrationState::Pending,
+                    Some(a) if a.checksum != m.checksum() => MigrationState::Modified,
+                    Some(_) => MigrationState::Applied,
+                };
+                MigrationStatus { version: m.version, name: m.name.to_string(), state }
+            })
+            .collect();
+
+        for a in applied.iter().filter(|a| !self.is_known(a.version)) {
+            statuses.push(MigrationStatus { version: a.version, name: a.name.clone(), state: MigrationState::Unknown });
+        }
+        statuses.sort_by_key(|s| s.version);
+        Ok(statuses)
+    }
+
+    /// Applies every pending migration, and returns the versions applied.  Fails without applying
+    /// anything if an applied migration has been edited since.
+    pub fn up(&mut self) -> Result<Vec<u32>, Error> {
+        self.locked(|migrator| {
+            migrator.ensure_table()?;
+            let applied = migrator.applied()?;
+            migrator.check_unmodified(&applied)?;
+            // A newer build ran these, and an older one being rolled back to can still run alongside them.
+            for a in applied.iter().filter(|a| !migrator.is_known(a.version)) {
+                log::warn!("migration {} {} was applied by a newer build", a.version, a.name);
+            }
+
+            let pending: Vec<Migration> = migrator.migrations.iter()
+                .filter(|m| !applied.iter().any(|a| a.version == m.version))
+                .cloned()
+                .collect();
+
+            let mut done = Vec::new();
+            for migration in pending {
+                migrator.run(&migration, &migration.up_statements())?;
+                migrator.conn.prep_exec(
+                    format!("INSERT INTO {} (version, name, checksum, applied_on) VALUES (?, ?, ?, ?)", migrator.table),
+                    (migration.version, migration.name, migration.checksum(), chrono::Utc::now().timestamp())
+                )?;
+                log::info!("applied migration {} {}", migration.version, migration.name);
+                done.push(migration.version);
+            }
+            Ok(done)
+        })
+    }
+
+    /// Reverts applied migrations newer than `target`, newest first, and returns the versions
+    /// reverted.  `down_to(0)` reverts everything.
+    pub fn down_to(&mut self, target: u32) -> Result<Vec<u32>, Error> {
+        self.locked(|migrator| {
+            migrator.ensure_table()?;
+            let mut applied = migrator.applied()?;
+            migrator.check_unmodified(&applied)?;
+
+            applied.retain(|a| a.version > target);
+            applied.sort_by_key(|a| std::cmp::Reverse(a.version));
+            if let Some(a) = applied.iter().find(|a| !migrator.is_known(a.version)) {
+                return Err(Error::UnknownMigration { version: a.version });
+            }
+
+            let mut done = Vec::new();
+            for a in applied {
+                let migration = migrator.known(a.version).cloned().unwrap();
+                migrator.run(&migration, &migration.down_statements())?;
+                migrator.conn.prep_exec(format!("DELETE FROM {} WHERE version = ?", migrator.table), (migration.version,))?;
+                log::info!("reverted migration {} {}", migration.version, migration.name);
+                done.push(migration.version);
+            }
+            Ok(done)
+        })
+    }
+
+    fn run(&mut self, migration: &Migration, statements: &[String]) -> Result<(), Error> {
+        for statement in statements {
+            self.conn.query(statement).map_err(|source| Error::MigrationFailed {
+                version: migration.version,
+                name: migration.name.to_string(),
+                source,
+            })?;
+        }
+        Ok(())
+    }
+
+    // Holds the migration lock for this database while `f` runs.
+    fn locked<T, F>(&mut self, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut MysqlMigrator) -> Result<T, Error>
+    {
+        let lock = format!("{}_lock", self.table);
+        let acquired: Option<Option<i64>> = self.conn.first_exec(
+            "SELECT GET_LOCK(CONCAT(DATABASE(), '.', ?), ?)",
+            (&lock, LOCK_TIMEOUT_SECS)
+        )?;
+        if acquired != Some(Some(1)) {
+            return Err(Error::MigrationLocked);
+        }
+
+        let result = f(self);
+        self.conn.prep_exec("SELECT RELEASE_LOCK(CONCAT(DATABASE(), '.', ?))", (&lock,))?;
+        result
+    }
+
+    // Creates the migrations table if it's missing.  A database that already has the baseline
+    // table predates migrations, so it's marked as having the first one.
+    fn ensure_table(&mut self) -> Result<(), Error> {
+        if self.table_exists(&self.table.clone())? {
+            return Ok(());
+        }
+
+        let predates_migrations = match self.baseline.clone() {
+            Some(table) => self.table_exists(&table)?,
+            None => false,
+        };
+
+        self.conn.query(format!(
+            "CREATE TABLE {} (\
+                version INT UNSIGNED PRIMARY KEY, \
+                name VARCHAR(128) NOT NULL, \
+                checksum CHAR(64) NOT NULL, \
+                applied_on BIGINT NOT NULL\
+            )",
+            self.table
+        ))?;
+
+        if let (true, Some(first)) = (predates_migrations, self.migrations.first().cloned()) {
+            self.conn.prep_exec(
+                format!("INSERT INTO {} (version, name, checksum, applied_on) VALUES (?, ?, ?, ?)", self.table),
+                (first.version, first.name, first.checksum(), chrono::Utc::now().timestamp())
+            )?;
+            log::info!("existing schema found, marked migration {} {} as applied", first.version, first.name);
+        }
+        Ok(())
+    }
+
+    fn table_exists(&mut self, table: &str) -> Result<bool, Error> {
+        let found: Option<String> = self.conn.first_exec(
+            "SELECT table_name FROM information_schema.tables WHERE table_schema = DATABASE() AND table_name = ?",
+            (table,)
+        )?;
+        Ok(found.is_some())
+    }
+
+    fn applied(&mut self) -> Result<Vec<AppliedMigration>, Error> {
+        if !self.table_exists(&self.table.clone())? {
+            return Ok(vec![]);
+        }
+
+        let q_result = self.conn.query(format!("SELECT version, name, checksum FROM {} ORDER BY version", self.table))?;
+        let mut applied = Vec::new();
+        for row_result in q_result {
+            let (version, name, checksum) = mysql::from_row(row_result?);
+            applied.push(AppliedMigration { version, name, checksum });
+        }
+        Ok(applied)
+    }
+
+    fn check_unmodified(&self, applied: &[AppliedMigration]) -> Result<(), Error> {
+        for a in applied {
+            if let Some(m) = self.known(a.version) {
+                if m.checksum() != a.checksum {
+                    return Err(Error::MigrationModified { version: a.version, name: a.name.clone() });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn known(&self, version: u32) -> Option<&Migration> {
+        self.migrations.iter().find(|m| m.version == version)
+    }
+
+    fn is_known(&self, version: u32) -> bool {
+        self.known(version).is_some()
+    }
+}
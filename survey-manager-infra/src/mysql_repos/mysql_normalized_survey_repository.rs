@@ -0,0 +1,227 @@
+use domain_patterns::collections::Repository;
+use domain_patterns::models::Entity;
+use survey_manager_core::survey::Survey;
+use survey_manager_core::dtos::{SurveyDTO, QuestionDTO, ChoiceDTO, CollaboratorDTO};
+use mysql;
+use mysql::prelude::GenericConnection;
+use std::convert::TryFrom;
+use crate::mysql_repos::{diff_survey, SurveyChange, mysql_pool, MysqlConnection};
+use crate::mysql_repos::mysql_survey_repository::handle_duplicate_key;
+use crate::errors::Error;
+
+/// Stores surveys a row per survey, question, choice and collaborator, in the `normalized_*`
+/// tables.  Interchangeable with `MysqlSurveyWriteRepository`, but updates only touch the rows that
+/// changed, see `diff_survey`.
+pub struct MysqlNormalizedSurveyRepository {
+    // Shared with the other repositories of a unit of work, when there is one.
+    conn: MysqlConnection,
+}
+
+impl MysqlNormalizedSurveyRepository {
+    /// A repository on a connection of its own from the shared pool.
+    pub fn new() -> Result<MysqlNormalizedSurveyRepository, Error> {
+        Ok(MysqlNormalizedSurveyRepository::with_connection(mysql_pool()?.connection()?))
+    }
+
+    pub fn with_connection(conn: MysqlConnection) -> MysqlNormalizedSurveyRepository {
+        MysqlNormalizedSurveyRepository {
+            conn,
+        }
+    }
+}
+
+impl Repository<Survey> for MysqlNormalizedSurveyRepository {
+    type Error = Error;
+
+    fn insert(&mut self, entity: &Survey) -> Result<Option<String>, Self::Error> {
+        let survey_dto: SurveyDTO = entity.into();
+
+        // A duplicate key leaves nothing to commit.
+        self.conn.transaction(|conn| {
+            if let Err(e) = conn.prep_exec(
+                "INSERT INTO normalized_survey (id, version, author, title, description, category, created_on, organization_id, response_policy, publication) \
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                (&survey_dto.id, survey_dto.version, &survey_dto.author, &survey_dto.title, &survey_dto.description,
+                 &survey_dto.category, survey_dto.created_on, &survey_dto.organization_id, &survey_dto.response_policy,
+                 &survey_dto.publication)
+            ) {
+                return handle_duplicate_key(e);
+            };
+
+            // A new survey is every row of itself added to nothing.
+            let nothing = SurveyDTO { questions: vec![], collaborators: vec![], ..survey_dto.clone() };
+            apply_changes(conn, &survey_dto.id, &diff_survey(&nothing, &survey_dto))?;
+
+            // Success.  Return the PK back as is.
+            Ok(Some(survey_dto.id.clone()))
+        })
+    }
+
+    fn get(&mut self, key: &String) -> Result<Option<Survey>, Self::Error> {
+        let lock = self.conn.in_unit_of_work();
+        match load_survey(&mut *self.conn.lock(), key, lock)? {
+            Some(survey_dto) => Ok(Some(
+                Survey::try_from(survey_dto).map_err(|e| Error::CorruptSurvey { id: key.clone(), source: e })?
+            )),
+            None => Ok(None),
+        }
+    }
+
+    // Intentionally leaving this unimplemented.  we don't need it for command side.
+    #[allow(unused)]
+    fn get_paged(&mut self, page_num: usize, page_size: usize) -> Result<Option<Vec<Survey>>, Self::Error> {
+        unimplemented!()
+    }
+
+    fn update(&mut self, entity: &Survey) -> Result<Option<String>, Self::Error> {
+        let survey_dto: SurveyDTO = entity.into();
+
+        self.conn.transaction(|conn| {
+            // Locking the survey row keeps concurrent updates from diffing against the same rows.
+            let stored = match load_survey(conn, &entity.id(), true)? {
+                Some(stored) => stored,
+                None => return Ok(None),
+            };
+
+            apply_changes(conn, &survey_dto.id, &diff_survey(&stored, &survey_dto))?;
+
+            // Success.  Return the PK back as is.
+            Ok(Some(survey_dto.id.clone()))
+        })
+    }
+
+    // Questions, choices and collaborators go with the survey through their foreign keys.
+    fn remove(&mut self, key: &String) -> Result<Option<String>, Self::Error> {
+        let mut conn = self.conn.lock();
+        let result = conn.prep_exec(
+            "DELETE FROM normalized_survey WHERE id = ?",
+            (key,)
+        )?;
+        if result.affected_rows() == 0 {
+            return Ok(None);
+        }
+
+        // Success.  Return the PK back as is.
+        Ok(Some(key.clone()))
+    }
+}
+
+fn apply_changes<C: GenericConnection>(conn: &mut C, survey_id: &String, changes: &[SurveyChange]) -> Result<(), Error> {
+    for change in changes {
+        match change {
+            SurveyChange::Survey(s) => conn.prep_exec(
+                "UPDATE normalized_survey SET version = ?, author = ?, title = ?, description = ?, category = ?, \
+                 created_on = ?, organization_id = ?, response_policy = ?, publication = ? WHERE id = ?",
+                (s.version, &s.author, &s.title, &s.description, &s.category, s.created_on, &s.organization_id, &s.response_policy,
+                 &s.publication, survey_id)
+            )?,
+            SurveyChange::InsertQuestion { position, question } => conn.prep_exec(
+                "INSERT INTO normalized_question (id, survey_id, position, kind, title) VALUES (?, ?, ?, ?, ?)",
+                (&question.id, survey_id, *position as u32, &question.kind, &question.title)
+            )?,
+            SurveyChange::UpdateQuestion { position, question } => conn.prep_exec(
+                "UPDATE normalized_question SET position = ?, kind = ?, title = ? WHERE id = ? AND survey_id = ?",
+                (*position as u32, &question.kind, &question.title, &question.id, survey_id)
+            )?,
+            SurveyChange::DeleteQuestion { id } => conn.prep_exec(
+                "DELETE FROM normalized_question WHERE id = ? AND survey_id = ?",
+                (id, survey_id)
+            )?,
+            SurveyChange::InsertChoice { question_id, position, choice } => conn.prep_exec(
+                "INSERT INTO normalized_choice (id, question_id, position, content, content_type, title) VALUES (?, ?, ?, ?, ?, ?)",
+                (&choice.id, question_id, *position as u32, &choice.content, &choice.content_type, &choice.title)
+            )?,
+            SurveyChange::UpdateChoice { question_id, position, choice } => conn.prep_exec(
+                "UPDATE normalized_choice SET question_id = ?, position = ?, content = ?, content_type = ?, title = ? WHERE id = ?",
+                (question_id, *position as u32, &choice.content, &choice.content_type, &choice.title, &choice.id)
+            )?,
+            SurveyChange::DeleteChoice { id } => conn.prep_exec(
+                "DELETE FROM normalized_choice WHERE id = ?",
+                (id,)
+            )?,
+            SurveyChange::InsertCollaborator(c) => conn.prep_exec(
+                "INSERT INTO normalized_survey_collaborator (survey_id, username, role) VALUES (?, ?, ?)",
+                (survey_id, &c.username, &c.role)
+            )?,
+            SurveyChange::UpdateCollaborator(c) => conn.prep_exec(
+                "UPDATE normalized_survey_collaborator SET role = ? WHERE survey_id = ? AND username = ?",
+                (&c.role, survey_id, &c.username)
+            )?,
+            SurveyChange::DeleteCollaborator { username } => conn.prep_exec(
+                "DELETE FROM normalized_survey_collaborator WHERE survey_id = ? AND username = ?",
+                (survey_id, username)
+            )?,
+        };
+    }
+
+    Ok(())
+}
+
+// version, author, title, description, category, created_on, organization_id, response_policy
+// and publication, as `load_survey` selects them.
+type SurveyHeader = (u64, String, String, String, String, i64, Option<String>, String, String);
+
+/// Puts a survey back together from its rows, or `None` if there's no such survey.  `lock` holds
+/// the survey row until the surrounding transaction ends.
+pub(crate) fn load_survey<C: GenericConnection>(conn: &mut C, id: &String, lock: bool) -> Result<Option<SurveyDTO>, Error> {
+    let header: Option<SurveyHeader> = conn.first_exec(
+        format!(
+            "SELECT version, author, title, description, category, created_on, organization_id, response_policy, publication \
+             FROM normalized_survey WHERE id = ?{}",
+            if lock { " FOR UPDATE" } else { "" }
+        ),
+        (id,)
+    )?;
+    let (version, author, title, description, category, created_on, organization_id, response_policy, publication) = match header {
+        Some(header) => header,
+        None => return Ok(None),
+    };
+
+    let mut questions: Vec<QuestionDTO> = Vec::new();
+    for row_result in conn.prep_exec(
+        "SELECT id, kind, title FROM normalized_question WHERE survey_id = ? ORDER BY position",
+        (id,)
+    )? {
+        let (id, kind, title) = mysql::from_row(row_result?);
+        questions.push(QuestionDTO { id, kind, title, choices: vec![] });
+    }
+
+    let mut choices: Vec<(String, ChoiceDTO)> = Vec::new();
+    for row_result in conn.prep_exec(
+        "SELECT c.question_id, c.id, c.content, c.content_type, c.title FROM normalized_choice c \
+         JOIN normalized_question q ON q.id = c.question_id WHERE q.survey_id = ? ORDER BY c.position",
+        (id,)
+    )? {
+        let (question_id, id, content, content_type, title) = mysql::from_row(row_result?);
+        choices.push((question_id, ChoiceDTO { id, content, content_type, title }));
+    }
+    for (question_id, choice) in choices {
+        if let Some(question) = questions.iter_mut().find(|q| q.id == question_id) {
+            question.choices.push(choice);
+        }
+    }
+
+    let mut collaborators = Vec::new();
+    for row_result in conn.prep_exec(
+        "SELECT username, role FROM normalized_survey_collaborator WHERE survey_id = ? ORDER BY seq",
+        (id,)
+    )? {
+        let (username, role) = mysql::from_row(row_result?);
+        collaborators.push(CollaboratorDTO { username, role });
+    }
+
+    Ok(Some(SurveyDTO {
+        id: id.clone(),
+        version,
+        author,
+        title,
+        description,
+        created_on,
+        category,
+        questions,
+        collaborators,
+        organization_id,
+        response_policy,
+        publication,
+    }))
+}
@@ -0,0 +1,86 @@
+use survey_manager_core::dtos::{SurveyDTO, SurveyDTOs, ListViewSurveyDTO};
+use survey_manager_core::app_services::repository_contracts::SurveyDTOReadRepository;
+use crate::mysql_repos::mysql_normalized_survey_repository::load_survey;
+use crate::mysql_repos::{mysql_pool, MysqlConnection};
+use crate::errors::Error;
+
+/// The read side of `MysqlNormalizedSurveyRepository`.
+pub struct MysqlNormalizedSurveyDTOsRepository {
+    // Shared with the other repositories of a unit of work, when there is one.
+    conn: MysqlConnection,
+}
+
+impl MysqlNormalizedSurveyDTOsRepository {
+    /// A repository on a connection of its own from the shared pool.
+    pub fn new() -> Result<MysqlNormalizedSurveyDTOsRepository, Error> {
+        Ok(MysqlNormalizedSurveyDTOsRepository::with_connection(mysql_pool()?.connection()?))
+    }
+
+    pub fn with_connection(conn: MysqlConnection) -> MysqlNormalizedSurveyDTOsRepository {
+        MysqlNormalizedSurveyDTOsRepository {
+            conn,
+        }
+    }
+}
+
+impl SurveyDTOReadRepository for MysqlNormalizedSurveyDTOsRepository {
+    type Error = Error;
+
+    fn get_survey_for_author(&mut self, id: &String, author: &String, organization_id: Option<&String>) -> Result<Option<SurveyDTO>, Error> {
+        // Checked before loading the rest of the survey.  A NULL organization_id never compares
+        // equal, so personal callers only match on the first two conditions.
+        let mut conn = self.conn.lock();
+        let visible: Option<u8> = conn.first_exec(
+            "SELECT 1 FROM normalized_survey s WHERE id = ? AND (author = ? OR organization_id = ? OR EXISTS (\
+                SELECT 1 FROM normalized_survey_collaborator c WHERE c.survey_id = s.id AND c.username = ?))",
+            (id, author, organization_id, author)
+        )?;
+        if visible.is_none() {
+            return Ok(None);
+        }
+
+        load_survey(&mut *conn, id, false)
+    }
+
+    fn get_surveys_by_author(&mut self, author: &String) -> Result<Option<SurveyDTOs>, Error> {
+        // Surveys the author handed to an organization are listed through the organization instead.
+        let mut conn = self.conn.lock();
+        let q_result = conn.prep_exec(
+            "SELECT id, author, title, category, NULL FROM normalized_survey WHERE author = ? AND organization_id IS NULL \
+             UNION ALL \
+             SELECT s.id, s.author, s.title, s.category, c.role FROM normalized_survey s \
+             JOIN normalized_survey_collaborator c ON c.survey_id = s.id WHERE c.username = ?",
+            (author, author)
+        )?;
+        list_view_surveys(q_result)
+    }
+
+    fn get_surveys_by_organization(&mut self, organization_id: &String, member: &String) -> Result<Option<SurveyDTOs>, Error> {
+        // Shared surveys the organization already owns would otherwise be listed twice.
+        let mut conn = self.conn.lock();
+        let q_result = conn.prep_exec(
+            "SELECT id, author, title, category, NULL FROM normalized_survey WHERE organization_id = ? \
+             UNION ALL \
+             SELECT s.id, s.author, s.title, s.category, c.role FROM normalized_survey s \
+             JOIN normalized_survey_collaborator c ON c.survey_id = s.id \
+             WHERE c.username = ? AND (s.organization_id IS NULL OR s.organization_id <> ?)",
+            (organization_id, member, organization_id)
+        )?;
+        list_view_surveys(q_result)
+    }
+}
+
+// Rows are (id, author, title, category, role).  No rows at all comes back as `None`.
+fn list_view_surveys(q_result: mysql::QueryResult) -> Result<Option<SurveyDTOs>, Error> {
+    let mut surveys = Vec::new();
+    for row_result in q_result {
+        let (id, author, title, category, role) = mysql::from_row(row_result?);
+        surveys.push(ListViewSurveyDTO { id, author, title, category, role });
+    }
+
+    if surveys.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(SurveyDTOs { surveys }))
+    }
+}
@@ -0,0 +1,100 @@
+use domain_patterns::collections::Repository;
+use survey_manager_core::organization::Organization;
+use survey_manager_core::dtos::OrganizationDTO;
+use std::convert::TryFrom;
+use crate::mysql_repos::{mysql_pool, MysqlConnection};
+use crate::errors::Error;
+use super::handle_duplicate_key;
+
+pub struct MysqlOrganizationRepository {
+    // Shared with the other repositories of a unit of work, when there is one.
+    conn: MysqlConnection,
+}
+
+impl MysqlOrganizationRepository {
+    /// A repository on a connection of its own from the shared pool.
+    pub fn new() -> Result<MysqlOrganizationRepository, Error> {
+        Ok(MysqlOrganizationRepository::with_connection(mysql_pool()?.connection()?))
+    }
+
+    pub fn with_connection(conn: MysqlConnection) -> MysqlOrganizationRepository {
+        MysqlOrganizationRepository {
+            conn,
+        }
+    }
+}
+
+impl Repository<Organization> for MysqlOrganizationRepository {
+    type Error = Error;
+
+    fn insert(&mut self, entity: &Organization) -> Result<Option<String>, Self::Error> {
+        let org_dto: OrganizationDTO = entity.into();
+        let org_json = serde_json::to_string(&org_dto)?;
+
+        // Like surveys, organization_data holds the whole aggregate and the other columns are copies
+        // for querying.
+        if let Err(e) = self.conn.lock().prep_exec(
+            "INSERT INTO organization (id, version, name, created_on, organization_data) VALUES (?, ?, ?, ?, ?)",
+            (&org_dto.id, org_dto.version, &org_dto.name, org_dto.created_on, org_json)
+        ) {
+            return handle_duplicate_key(e);
+        };
+
+        Ok(Some(org_dto.id))
+    }
+
+    fn get(&mut self, key: &String) -> Result<Option<Organization>, Self::Error> {
+        let mut conn = self.conn.lock();
+        let mut q_result = conn.prep_exec(
+            "SELECT organization_data FROM organization WHERE id = ?",
+            (key,)
+        )?;
+
+        let row = match q_result.next() {
+            Some(row_result) => row_result?,
+            None => return Ok(None),
+        };
+
+        let org_data: String = mysql::from_row(row);
+        let org_dto: OrganizationDTO = serde_json::from_str(&org_data)?;
+
+        Organization::try_from(org_dto)
+            .map(Some)
+            .map_err(|e| Error::CorruptOrganization { id: key.clone(), source: e })
+    }
+
+    // Intentionally leaving this unimplemented.  we don't need it for command side.
+    #[allow(unused)]
+    fn get_paged(&mut self, page_num: usize, page_size: usize) -> Result<Option<Vec<Organization>>, Self::Error> {
+        unimplemented!()
+    }
+
+    fn update(&mut self, entity: &Organization) -> Result<Option<String>, Self::Error> {
+        let org_dto: OrganizationDTO = entity.into();
+        let org_json = serde_json::to_string(&org_dto)?;
+
+        let mut conn = self.conn.lock();
+        let result = conn.prep_exec(
+            "UPDATE organization SET version = ?, name = ?, organization_data = ? WHERE id = ?",
+            (org_dto.version, &org_dto.name, org_json, &org_dto.id)
+        )?;
+        if result.affected_rows() == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(org_dto.id))
+    }
+
+    fn remove(&mut self, key: &String) -> Result<Option<String>, Self::Error> {
+        let mut conn = self.conn.lock();
+        let result = conn.prep_exec(
+            "DELETE FROM organization WHERE id = ?",
+            (key,)
+        )?;
+        if result.affected_rows() == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(key.clone()))
+    }
+}
@@ -0,0 +1,113 @@
+use survey_manager_core::app_services::responses::{PartialResponse, Answer};
+use survey_manager_core::app_services::repository_contracts::PartialResponseRepository;
+use crate::mysql_repos::{mysql_pool, MysqlConnection};
+use crate::errors::Error;
+
+// Columns in the order `partial_from_row` reads them.
+const PARTIAL_COLUMNS: &str = "token_hash, survey_id, survey_version, respondent, answers, started_on, expires_at, response_id";
+
+pub struct MysqlPartialResponseRepository {
+    // Shared with the other repositories of a unit of work, when there is one.
+    conn: MysqlConnection,
+}
+
+impl MysqlPartialResponseRepository {
+    /// A repository on a connection of its own from the shared pool.
+    pub fn new() -> Result<MysqlPartialResponseRepository, Error> {
+        Ok(MysqlPartialResponseRepository::with_connection(mysql_pool()?.connection()?))
+    }
+
+    pub fn with_connection(conn: MysqlConnection) -> MysqlPartialResponseRepository {
+        MysqlPartialResponseRepository {
+            conn,
+        }
+    }
+}
+
+fn partial_from_row(row: mysql::Row) -> Result<PartialResponse, Error> {
+    let (token_hash, survey_id, survey_version, respondent, answers, started_on, expires_at, response_id):
+        (String, String, u64, String, String, i64, i64, Option<String>) = mysql::from_row(row);
+    let answers: Vec<Answer> = serde_json::from_str(&answers)?;
+
+    Ok(PartialResponse {
+        token_hash,
+        survey_id,
+        survey_version,
+        respondent,
+        answers,
+        started_on,
+        expires_at,
+        response_id,
+    })
+}
+
+impl PartialResponseRepository for MysqlPartialResponseRepository {
+    type Error = Error;
+
+    fn insert(&mut self, partial: &PartialResponse) -> Result<(), Error> {
+        let answers = serde_json::to_string(&partial.answers)?;
+        self.conn.lock().prep_exec(
+            format!("INSERT INTO survey_partial_response ({}) VALUES (?, ?, ?, ?, ?, ?, ?, ?)", PARTIAL_COLUMNS),
+            (&partial.token_hash, &partial.survey_id, partial.survey_version, &partial.respondent, answers,
+             partial.started_on, partial.expires_at, &partial.response_id)
+        )?;
+
+        Ok(())
+    }
+
+    fn get(&mut self, token_hash: &str) -> Result<Option<PartialResponse>, Error> {
+        let mut conn = self.conn.lock();
+        let mut q_result = conn.prep_exec(
+            format!("SELECT {} FROM survey_partial_response WHERE token_hash = ?", PARTIAL_COLUMNS),
+            (token_hash,)
+        )?;
+
+        match q_result.next() {
+            Some(row_result) => Ok(Some(partial_from_row(row_result?)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn update(&mut self, partial: &PartialResponse) -> Result<bool, Error> {
+        let answers = serde_json::to_string(&partial.answers)?;
+        let mut conn = self.conn.lock();
+        let result = conn.prep_exec(
+            "UPDATE survey_partial_response SET answers = ?, expires_at = ? WHERE token_hash = ? AND response_id IS NULL",
+            (answers, partial.expires_at, &partial.token_hash)
+        )?;
+
+        Ok(result.affected_rows() == 1)
+    }
+
+    fn mark_submitted(&mut self, token_hash: &str, response_id: &str) -> Result<bool, Error> {
+        // The guard makes this a compare and swap, only one submission gets to claim the partial.
+        let mut conn = self.conn.lock();
+        let result = conn.prep_exec(
+            "UPDATE survey_partial_response SET response_id = ? WHERE token_hash = ? AND response_id IS NULL",
+            (response_id, token_hash)
+        )?;
+
+        Ok(result.affected_rows() == 1)
+    }
+
+    fn list(&mut self, survey_id: &str, after: Option<&str>, limit: usize) -> Result<Vec<PartialResponse>, Error> {
+        // A missing cursor reads from the start, seq begins at 1.
+        let mut conn = self.conn.lock();
+        let q_result = conn.prep_exec(
+            format!(
+                "SELECT {} FROM survey_partial_response \
+                 WHERE survey_id = ? AND seq > COALESCE((SELECT seq FROM survey_partial_response WHERE token_hash = ?), 0) \
+                 ORDER BY seq LIMIT ?",
+                PARTIAL_COLUMNS
+            ),
+            (survey_id, after, limit as u64)
+        )?;
+
+        let mut partials = vec![];
+        for row_result in q_result {
+            partials.push(partial_from_row(row_result?)?);
+        }
+
+        Ok(partials)
+    }
+}
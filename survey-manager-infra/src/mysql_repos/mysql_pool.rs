@@ -0,0 +1,242 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use mysql::{DriverError, Opts, OptsBuilder};
+use crate::errors::Error;
+
+lazy_static! {
+    // Built on first use rather than at startup, and again on the next use if building it failed,
+    // so a database that's down when the process starts only fails requests until it's back.
+    static ref MYSQL_POOL: Mutex<Option<MysqlPool>> = Mutex::new(None);
+}
+
+/// How the MySQL pool behaves.  Read from the environment, with everything but the url optional:
+///
+/// * `DATABASE_URL` - where MySQL is, required.
+/// * `DATABASE_POOL_MIN` - connections opened up front and kept open, default 10.
+/// * `DATABASE_POOL_MAX` - connections the pool opens at most, default 100.
+/// * `DATABASE_CONNECT_TIMEOUT_MS` - milliseconds to wait for a new connection, default 5000.
+/// * `DATABASE_CHECKOUT_TIMEOUT_MS` - milliseconds to wait for a free connection once the pool is
+///   at its maximum, default 5000.
+/// * `DATABASE_QUERY_TIMEOUT_MS` - milliseconds to wait on a reply from MySQL, default 30000.
+/// * `DATABASE_RETRY_ATTEMPTS` - times a unit of work is run before a deadlock or lock wait
+///   timeout is given up on, default 3.
+/// * `DATABASE_RETRY_BACKOFF_MS` - milliseconds waited before the first retry, doubling with each
+///   one after, default 20.
+#[derive(Clone, Debug)]
+pub struct MysqlConfig {
+    pub url: String,
+    pub min_connections: usize,
+    pub max_connections: usize,
+    pub connect_timeout: Duration,
+    pub checkout_timeout: Duration,
+    pub query_timeout: Duration,
+    pub retry_attempts: u32,
+    pub retry_backoff: Duration,
+}
+
+impl MysqlConfig {
+    /// The defaults above, for the database at `url`.
+    pub fn new(url: &str) -> MysqlConfig {
+        MysqlConfig {
+            url: url.to_string(),
+            min_connections: 10,
+            max_connections: 100,
+            connect_timeout: Duration::from_millis(5000),
+            checkout_timeout: Duration::from_millis(5000),
+            query_timeout: Duration::from_millis(30000),
+            retry_attempts: 3,
+            retry_backoff: Duration::from_millis(20),
+        }
+    }
+
+    /// Fails only when `DATABASE_URL` is missing.  Anything else unset or unparseable keeps its
+    /// default.
+    pub fn from_env() -> Result<MysqlConfig, Error> {
+        let url = std::env::var("DATABASE_URL")
+            .map_err(|_| Error::DatabaseUnavailable { reason: "DATABASE_URL must be set".to_string() })?;
+        let defaults = MysqlConfig::new(&url);
+        let number = |name: &str, default: u64| std::env::var(name).ok()
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(default);
+        let millis = |name: &str, default: Duration| Duration::from_millis(number(name, default.as_millis() as u64));
+
+        Ok(MysqlConfig {
+            min_connections: number("DATABASE_POOL_MIN", defaults.min_connections as u64) as usize,
+            max_connections: number("DATABASE_POOL_MAX", defaults.max_connections as u64) as usize,
+            connect_timeout: millis("DATABASE_CONNECT_TIMEOUT_MS", defaults.connect_timeout),
+            checkout_timeout: millis("DATABASE_CHECKOUT_TIMEOUT_MS", defaults.checkout_timeout),
+            query_timeout: millis("DATABASE_QUERY_TIMEOUT_MS", defaults.query_timeout),
+            retry_attempts: number("DATABASE_RETRY_ATTEMPTS", u64::from(defaults.retry_attempts)) as u32,
+            retry_backoff: millis("DATABASE_RETRY_BACKOFF_MS", defaults.retry_backoff),
+            url,
+        })
+    }
+}
+
+/// A pool of MySQL connections.  Clones share the pool.  Repositories take their connection from
+/// `connection`, or from a `MysqlUnitOfWork` to share a transaction.
+#[derive(Clone)]
+pub struct MysqlPool {
+    pool: mysql::Pool,
+    config: Arc<MysqlConfig>,
+}
+
+impl MysqlPool {
+    /// Opens `config.min_connections` straight away, so fails if MySQL can't be reached, or the url
+    /// is malformed.
+    pub fn new(config: MysqlConfig) -> Result<MysqlPool, Error> {
+        let opts = Opts::from_url(&config.url)
+            .map_err(|e| Error::DatabaseUnavailable { reason: e.to_string() })?;
+        let mut builder = OptsBuilder::from_opts(opts);
+        builder.tcp_connect_timeout(Some(config.connect_timeout))
+            .read_timeout(Some(config.query_timeout))
+            .write_timeout(Some(config.query_timeout));
+
+        let max = config.max_connections.max(1);
+        let pool = mysql::Pool::new_manual(config.min_connections.min(max), max, builder)?;
+        Ok(MysqlPool {
+            pool,
+            config: Arc::new(config),
+        })
+    }
+
+    pub fn config(&self) -> &MysqlConfig {
+        &self.config
+    }
+
+    /// A connection of its own, for repositories that don't need to share a transaction.
+    pub fn connection(&self) -> Result<MysqlConnection, Error> {
+        Ok(MysqlConnection::new(self.get_conn()?))
+    }
+
+    /// A bare connection, for work that has to manage its own transactions, like migrations.
+    pub fn get_conn(&self) -> Result<mysql::PooledConn, Error> {
+        let timeout_ms = self.config.checkout_timeout.as_millis() as u32;
+        self.pool.try_get_conn(timeout_ms).map_err(|e| match e {
+            mysql::Error::DriverError(DriverError::Timeout) => Error::DatabaseUnavailable {
+                reason: format!("no free connection within {}ms", timeout_ms),
+            },
+            e => e.into(),
+        })
+    }
+}
+
+/// The pool at `DATABASE_URL`, shared by the whole process.
+pub fn mysql_pool() -> Result<MysqlPool, Error> {
+    let mut pool = MYSQL_POOL.lock().unwrap();
+    if let Some(pool) = pool.as_ref() {
+        return Ok(pool.clone());
+    }
+
+    let created = MysqlPool::new(MysqlConfig::from_env()?)?;
+    *pool = Some(created.clone());
+    Ok(created)
+}
+
+/// Checks `DATABASE_URL` is set and well formed, without connecting.
+pub fn check_mysql_config() -> Result<(), Error> {
+    let config = MysqlConfig::from_env()?;
+    Opts::from_url(&config.url)
+        .map(|_| ())
+        .map_err(|e| Error::DatabaseUnavailable { reason: e.to_string() })
+}
+
+/// A connection repositories can share.  Clones are handles to the same connection, which is how
+/// the repositories of a `MysqlUnitOfWork` end up in its transaction.
+#[derive(Clone)]
+pub struct MysqlConnection {
+    inner: Arc<Mutex<SharedConn>>,
+}
+
+struct SharedConn {
+    conn: mysql::PooledConn,
+    // Set while a unit of work's transaction is open on the connection.
+    in_unit_of_work: bool,
+    savepoints: u32,
+}
+
+/// Holds a `MysqlConnection` for as long as it's in use.  Derefs to the connection.
+pub struct MysqlConnGuard<'a> {
+    guard: MutexGuard<'a, SharedConn>,
+}
+
+impl<'a> Deref for MysqlConnGuard<'a> {
+    type Target = mysql::PooledConn;
+
+    fn deref(&self) -> &mysql::PooledConn {
+        &self.guard.conn
+    }
+}
+
+impl<'a> DerefMut for MysqlConnGuard<'a> {
+    fn deref_mut(&mut self) -> &mut mysql::PooledConn {
+        &mut self.guard.conn
+    }
+}
+
+impl MysqlConnection {
+    pub fn new(conn: mysql::PooledConn) -> MysqlConnection {
+        MysqlConnection {
+            inner: Arc::new(Mutex::new(SharedConn {
+                conn,
+                in_unit_of_work: false,
+                savepoints: 0,
+            })),
+        }
+    }
+
+    /// The connection, for a statement or a few that don't need to go together.
+    pub fn lock(&self) -> MysqlConnGuard<'_> {
+        MysqlConnGuard { guard: self.inner.lock().unwrap() }
+    }
+
+    /// Whether a unit of work's transaction is open on the connection.  Repositories lock what
+    /// they read while it is, so nothing can change it before the unit of work writes it back.
+    pub fn in_unit_of_work(&self) -> bool {
+        self.inner.lock().unwrap().in_unit_of_work
+    }
+
+    /// Runs `f` so its statements all happen or none do, committing if it returns `Ok`.
+    ///
+    /// Inside a unit of work this is a savepoint instead of a transaction of its own, since MySQL
+    /// would commit the unit of work on starting another.  Whether the statements stick is then
+    /// up to the unit of work.
+    pub fn transaction<T, F>(&self, f: F) -> Result<T, Error>
+        where F: FnOnce(&mut mysql::PooledConn) -> Result<T, Error>
+    {
+        let mut shared = self.inner.lock().unwrap();
+        let shared = &mut *shared;
+
+        let (begin, commit, rollback) = if shared.in_unit_of_work {
+            shared.savepoints += 1;
+            let name = format!("repository_{}", shared.savepoints);
+            (format!("SAVEPOINT {}", name), format!("RELEASE SAVEPOINT {}", name), format!("ROLLBACK TO SAVEPOINT {}", name))
+        } else {
+            ("START TRANSACTION".to_string(), "COMMIT".to_string(), "ROLLBACK".to_string())
+        };
+
+        shared.conn.query(begin)?;
+        match f(&mut shared.conn) {
+            Ok(value) => {
+                shared.conn.query(commit)?;
+                Ok(value)
+            },
+            Err(e) => {
+                // The error that caused the rollback is the one worth reporting.
+                let _ = shared.conn.query(rollback);
+                Err(e)
+            },
+        }
+    }
+
+    // Starts or ends a unit of work's transaction on this connection.
+    pub(crate) fn set_unit_of_work(&self, statement: &str, open: bool) -> Result<(), Error> {
+        let mut shared = self.inner.lock().unwrap();
+        let result = shared.conn.query(statement).map(|_| ());
+        // A transaction that failed to start isn't open, and one that failed to end is over anyway.
+        shared.in_unit_of_work = open && result.is_ok();
+        shared.savepoints = 0;
+        Ok(result?)
+    }
+}
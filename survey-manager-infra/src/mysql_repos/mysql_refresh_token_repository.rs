@@ -0,0 +1,80 @@
+use survey_manager_core::app_services::accounts::RefreshTokenRecord;
+use survey_manager_core::app_services::repository_contracts::RefreshTokenRepository;
+use crate::mysql_repos::{mysql_pool, MysqlConnection};
+use crate::errors::Error;
+
+pub struct MysqlRefreshTokenRepository {
+    // Shared with the other repositories of a unit of work, when there is one.
+    conn: MysqlConnection,
+}
+
+impl MysqlRefreshTokenRepository {
+    /// A repository on a connection of its own from the shared pool.
+    pub fn new() -> Result<MysqlRefreshTokenRepository, Error> {
+        Ok(MysqlRefreshTokenRepository::with_connection(mysql_pool()?.connection()?))
+    }
+
+    pub fn with_connection(conn: MysqlConnection) -> MysqlRefreshTokenRepository {
+        MysqlRefreshTokenRepository {
+            conn,
+        }
+    }
+}
+
+impl RefreshTokenRepository for MysqlRefreshTokenRepository {
+    type Error = Error;
+
+    fn insert(&mut self, record: &RefreshTokenRecord) -> Result<(), Error> {
+        self.conn.lock().prep_exec(
+            "INSERT INTO refresh_token (token_hash, family_id, user_id, username, organization_id, expires_at, used, revoked) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            (&record.token_hash, &record.family_id, &record.user_id, &record.username, &record.organization_id, record.expires_at, record.used, record.revoked)
+        )?;
+
+        Ok(())
+    }
+
+    fn get(&mut self, token_hash: &String) -> Result<Option<RefreshTokenRecord>, Error> {
+        let mut conn = self.conn.lock();
+        let mut q_result = conn.prep_exec(
+            "SELECT token_hash, family_id, user_id, username, organization_id, expires_at, used, revoked FROM refresh_token WHERE token_hash = ?",
+            (token_hash,)
+        )?;
+
+        let row = match q_result.next() {
+            Some(row_result) => row_result?,
+            None => return Ok(None),
+        };
+
+        let (token_hash, family_id, user_id, username, organization_id, expires_at, used, revoked) = mysql::from_row(row);
+        Ok(Some(RefreshTokenRecord { token_hash, family_id, user_id, username, organization_id, expires_at, used, revoked }))
+    }
+
+    fn mark_used(&mut self, token_hash: &String) -> Result<bool, Error> {
+        // The `used = FALSE` guard makes this a compare and swap, only one caller can flip it.
+        let mut conn = self.conn.lock();
+        let result = conn.prep_exec(
+            "UPDATE refresh_token SET used = TRUE WHERE token_hash = ? AND used = FALSE",
+            (token_hash,)
+        )?;
+
+        Ok(result.affected_rows() == 1)
+    }
+
+    fn revoke_family(&mut self, family_id: &String) -> Result<(), Error> {
+        self.conn.lock().prep_exec(
+            "UPDATE refresh_token SET revoked = TRUE WHERE family_id = ?",
+            (family_id,)
+        )?;
+
+        Ok(())
+    }
+
+    fn revoke_user(&mut self, user_id: &String) -> Result<(), Error> {
+        self.conn.lock().prep_exec(
+            "UPDATE refresh_token SET revoked = TRUE WHERE user_id = ?",
+            (user_id,)
+        )?;
+
+        Ok(())
+    }
+}
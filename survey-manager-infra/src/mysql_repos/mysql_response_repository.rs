@@ -0,0 +1,66 @@
+use survey_manager_core::app_services::responses::{ResponseRecord, Answer};
+use survey_manager_core::app_services::repository_contracts::ResponseRepository;
+use crate::mysql_repos::{mysql_pool, MysqlConnection};
+use crate::mysql_repos::mysql_survey_repository::handle_duplicate_key;
+use crate::errors::Error;
+
+pub struct MysqlResponseRepository {
+    // Shared with the other repositories of a unit of work, when there is one.
+    conn: MysqlConnection,
+}
+
+impl MysqlResponseRepository {
+    /// A repository on a connection of its own from the shared pool.
+    pub fn new() -> Result<MysqlResponseRepository, Error> {
+        Ok(MysqlResponseRepository::with_connection(mysql_pool()?.connection()?))
+    }
+
+    pub fn with_connection(conn: MysqlConnection) -> MysqlResponseRepository {
+        MysqlResponseRepository {
+            conn,
+        }
+    }
+}
+
+impl ResponseRepository for MysqlResponseRepository {
+    type Error = Error;
+
+    fn insert(&mut self, response: &ResponseRecord) -> Result<Option<String>, Error> {
+        let answers = serde_json::to_string(&response.answers)?;
+        if let Err(e) = self.conn.lock().prep_exec(
+            "INSERT INTO survey_response (id, survey_id, survey_version, respondent, answers, submitted_on) VALUES (?, ?, ?, ?, ?, ?)",
+            (&response.id, &response.survey_id, response.survey_version, &response.respondent, answers, response.submitted_on)
+        ) {
+            return handle_duplicate_key(e);
+        }
+
+        Ok(Some(response.id.clone()))
+    }
+
+    fn list(&mut self, survey_id: &String, after: Option<&String>, limit: usize) -> Result<Vec<ResponseRecord>, Error> {
+        // A missing cursor reads from the start, seq begins at 1.
+        let mut conn = self.conn.lock();
+        let q_result = conn.prep_exec(
+            "SELECT id, survey_id, survey_version, respondent, answers, submitted_on FROM survey_response \
+             WHERE survey_id = ? AND seq > COALESCE((SELECT seq FROM survey_response WHERE id = ?), 0) \
+             ORDER BY seq LIMIT ?",
+            (survey_id, after, limit as u64)
+        )?;
+
+        let mut responses = vec![];
+        for row_result in q_result {
+            let (id, survey_id, survey_version, respondent, answers, submitted_on): (String, String, Option<u64>, Option<String>, String, i64) = mysql::from_row(row_result?);
+            let answers: Vec<Answer> = serde_json::from_str(&answers)?;
+            responses.push(ResponseRecord {
+                id,
+                survey_id,
+                survey_version,
+                respondent,
+                answers,
+                submitted_on,
+            });
+        }
+
+        Ok(responses)
+    }
+}
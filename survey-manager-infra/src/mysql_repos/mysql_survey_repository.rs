@@ -3,66 +3,82 @@ use survey_manager_core::survey::Survey;
 use survey_manager_core::dtos::SurveyDTO;
 use domain_patterns::models::{Entity, AggregateRoot};
 use mysql;
-use mysql::Error;
 use mysql::error::ServerError;
+use std::convert::TryFrom;
+use crate::mysql_repos::{mysql_pool, MysqlConnection};
+use crate::errors::Error;
 
 pub struct MysqlSurveyWriteRepository {
-    // A single connection to Mysql.  Handed down from a pool likely.
-    conn: mysql::PooledConn,
+    // Shared with the other repositories of a unit of work, when there is one.
+    conn: MysqlConnection,
 }
 
 impl MysqlSurveyWriteRepository {
-    pub fn new() -> MysqlSurveyWriteRepository {
-        let pool = super::MYSQL_POOL.clone();
+    /// A repository on a connection of its own from the shared pool.
+    pub fn new() -> Result<MysqlSurveyWriteRepository, Error> {
+        Ok(MysqlSurveyWriteRepository::with_connection(mysql_pool()?.connection()?))
+    }
+
+    pub fn with_connection(conn: MysqlConnection) -> MysqlSurveyWriteRepository {
         MysqlSurveyWriteRepository {
-            conn: pool.get_conn().unwrap(),
+            conn,
         }
     }
 }
 
 impl Repository<Survey> for MysqlSurveyWriteRepository {
-    type Error = mysql::Error;
+    type Error = Error;
 
     fn insert(&mut self, entity: &Survey) -> Result<Option<String>, Self::Error> {
         let survey_dto: SurveyDTO = entity.into();
-        let survey_json = serde_json::to_string(&survey_dto).unwrap();
-
-        // In this example survey_data is json of the entire survey object.  the other fields are just useful for query purposes and duplicate data.
-        if let Err(e) = self.conn.prep_exec(
-            "INSERT INTO survey (id, version, author, title, category, created_on, survey_data) VALUES (?, ?, ?, ?, ?, ?, ?)",
-            (entity.id(), entity.version(), entity.author().to_string(), entity.title().to_string(), entity.category().to_string(), entity.created_on(), survey_json)
-        ) {
-            return handle_duplicate_key(e);
-        };
-
-        // Success.  Return the PK back as is.
-        Ok(Some(survey_dto.id))
+        let survey_json = serde_json::to_string(&survey_dto)?;
+
+        // The survey and its collaborator rows go in together.  A duplicate key leaves nothing to
+        // commit.
+        self.conn.transaction(|conn| {
+            // In this example survey_data is json of the entire survey object.  the other fields are just useful for query purposes and duplicate data.
+            if let Err(e) = conn.prep_exec(
+                "INSERT INTO survey (id, version, author, title, category, created_on, organization_id, survey_data) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+                (entity.id(), entity.version(), entity.author().to_string(), entity.title().to_string(), entity.category().to_string(), entity.created_on(), &survey_dto.organization_id, survey_json)
+            ) {
+                return handle_duplicate_key(e);
+            };
+
+            replace_collaborators(conn, &survey_dto)?;
+
+            // Success.  Return the PK back as is.
+            Ok(Some(survey_dto.id.clone()))
+        })
     }
 
     fn get(&mut self, key: &String) -> Result<Option<Survey>, Self::Error> {
-        let survey_result: Option<SurveyDTO> =
-            match self.conn.prep_exec(
-            "SELECT survey_data FROM survey WHERE id=?",
+        // In a unit of work the row stays locked until it commits, so the update that follows
+        // can't overwrite someone else's.
+        let query = if self.conn.in_unit_of_work() {
+            "SELECT survey_data FROM survey WHERE id=? FOR UPDATE"
+        } else {
+            "SELECT survey_data FROM survey WHERE id=?"
+        };
+        let survey_data: Option<String> =
+            match self.conn.lock().prep_exec(
+            query,
             (key,)
         ) {
             Ok(mut q_result) => {
                 if let Some(row_result)  = q_result.next() {
                     let row = row_result?;
-                    let survey_data: String = mysql::from_row(row);
-                    // TODO: Rather than using mysql-simple error type, create your own and attach theirs to yours
-                    // so you can include serde serialization errors.
-                    serde_json::from_str(&survey_data).unwrap()
+                    Some(mysql::from_row(row))
                 } else {
                     None
                 }
             },
             Err(e) => {
-                return Err(e);
+                return Err(e.into());
             },
         };
 
-        if let Some(survey_dto) = survey_result {
-            return Ok(Some(survey_dto.into()))
+        if let Some(data) = survey_data {
+            return Ok(Some(survey_from_json(key, &data)?))
         }
 
         Ok(None)
@@ -76,29 +92,28 @@ impl Repository<Survey> for MysqlSurveyWriteRepository {
 
     fn update(&mut self, entity: &Survey) -> Result<Option<String>, Self::Error> {
         let survey_dto: SurveyDTO = entity.into();
-        let survey_json = serde_json::to_string(&survey_dto).unwrap();
-
-        // In this example survey_data is json of the entire survey object.  the other fields are just useful for query purposes and duplicate data.
-        match self.conn.prep_exec(
-            "UPDATE survey SET version = ?, title = ?, category = ?, survey_data = ? WHERE id = ?",
-            (entity.version(), entity.title().to_string(), entity.category().to_string(), survey_json, entity.id())
-        ) {
-            Ok(result) => {
-                if result.affected_rows() == 0 {
-                    return Ok(None);
-                }
-            },
-            Err(e) => {
-                return Err(e);
+        let survey_json = serde_json::to_string(&survey_dto)?;
+
+        self.conn.transaction(|conn| {
+            // In this example survey_data is json of the entire survey object.  the other fields are just useful for query purposes and duplicate data.
+            let result = conn.prep_exec(
+                "UPDATE survey SET version = ?, author = ?, title = ?, category = ?, organization_id = ?, survey_data = ? WHERE id = ?",
+                (entity.version(), entity.author().to_string(), entity.title().to_string(), entity.category().to_string(), &survey_dto.organization_id, survey_json, entity.id())
+            )?;
+            if result.affected_rows() == 0 {
+                return Ok(None);
             }
-        };
+            drop(result);
 
-        // Success.  Return the PK back as is.
-        Ok(Some(survey_dto.id))
+            replace_collaborators(conn, &survey_dto)?;
+
+            // Success.  Return the PK back as is.
+            Ok(Some(survey_dto.id.clone()))
+        })
     }
 
     fn remove(&mut self, key: &String) -> Result<Option<String>, Self::Error> {
-        match self.conn.prep_exec(
+        match self.conn.lock().prep_exec(
             "DELETE FROM survey WHERE id = ?",
             (key,)
         ) {
@@ -108,7 +123,7 @@ impl Repository<Survey> for MysqlSurveyWriteRepository {
                 }
             },
             Err(e) => {
-                return Err(e);
+                return Err(e.into());
             }
         };
 
@@ -117,17 +132,43 @@ impl Repository<Survey> for MysqlSurveyWriteRepository {
     }
 }
 
-fn handle_duplicate_key(error: mysql::Error) -> Result<Option<String>, mysql::Error> {
-    if let Error::MySqlError(e) = error {
+// Collaborators are copied out of the survey json into their own table so shared surveys can be
+// found by username.  The json stays the source of truth.
+fn replace_collaborators(conn: &mut mysql::PooledConn, survey_dto: &SurveyDTO) -> Result<(), Error> {
+    conn.prep_exec(
+        "DELETE FROM survey_collaborator WHERE survey_id = ?",
+        (&survey_dto.id,)
+    )?;
+
+    for collaborator in &survey_dto.collaborators {
+        conn.prep_exec(
+            "INSERT INTO survey_collaborator (survey_id, username, role) VALUES (?, ?, ?)",
+            (&survey_dto.id, &collaborator.username, &collaborator.role)
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Rebuilds a `Survey` from the json stored in `survey_data`.  Rows that don't parse, or that parse
+/// but no longer pass validation, come back as an error rather than taking the worker down.
+pub(crate) fn survey_from_json(id: &String, survey_data: &str) -> Result<Survey, Error> {
+    let survey_dto: SurveyDTO = serde_json::from_str(survey_data)?;
+
+    Survey::try_from(survey_dto)
+        .map_err(|e| Error::CorruptSurvey { id: id.clone(), source: e })
+}
+
+pub(crate) fn handle_duplicate_key(error: mysql::Error) -> Result<Option<String>, Error> {
+    if let mysql::Error::MySqlError(e) = error {
         if e.code == ServerError::ER_DUP_ENTRY as u16 {
             return Ok(None);
         }
         // Some other code, so return the error.
         // TODO: Add ways to deal with other errors as we actually enounter them.
-        return Err(Error::MySqlError(e))
+        return Err(mysql::Error::MySqlError(e).into())
     }
 
     // TODO: Add ways to deal with other errors as we actually enounter them.
-    return Err(error);
+    Err(error.into())
 }
-
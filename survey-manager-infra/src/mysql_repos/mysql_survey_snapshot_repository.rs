@@ -0,0 +1,53 @@
+use survey_manager_core::app_services::repository_contracts::SurveySnapshotRepository;
+use survey_manager_core::dtos::SurveyDTO;
+use crate::mysql_repos::{mysql_pool, MysqlConnection};
+use crate::errors::Error;
+
+pub struct MysqlSurveySnapshotRepository {
+    // Shared with the other repositories of a unit of work, when there is one.
+    conn: MysqlConnection,
+}
+
+impl MysqlSurveySnapshotRepository {
+    /// A repository on a connection of its own from the shared pool.
+    pub fn new() -> Result<MysqlSurveySnapshotRepository, Error> {
+        Ok(MysqlSurveySnapshotRepository::with_connection(mysql_pool()?.connection()?))
+    }
+
+    pub fn with_connection(conn: MysqlConnection) -> MysqlSurveySnapshotRepository {
+        MysqlSurveySnapshotRepository {
+            conn,
+        }
+    }
+}
+
+impl SurveySnapshotRepository for MysqlSurveySnapshotRepository {
+    type Error = Error;
+
+    fn save(&mut self, definition: &SurveyDTO) -> Result<(), Error> {
+        // A version never changes once it's been answered, so the first snapshot of it stands.
+        let json = serde_json::to_string(definition)?;
+        self.conn.lock().prep_exec(
+            "INSERT IGNORE INTO survey_snapshot (survey_id, version, definition) VALUES (?, ?, ?)",
+            (&definition.id, definition.version, json)
+        )?;
+
+        Ok(())
+    }
+
+    fn get(&mut self, survey_id: &str, version: u64) -> Result<Option<SurveyDTO>, Error> {
+        let mut conn = self.conn.lock();
+        let mut q_result = conn.prep_exec(
+            "SELECT definition FROM survey_snapshot WHERE survey_id = ? AND version = ?",
+            (survey_id, version)
+        )?;
+
+        match q_result.next() {
+            Some(row_result) => {
+                let definition: String = mysql::from_row(row_result?);
+                Ok(Some(serde_json::from_str(&definition)?))
+            },
+            None => Ok(None),
+        }
+    }
+}
@@ -1,80 +1,108 @@
 use survey_manager_core::dtos::{SurveyDTO, SurveyDTOs, ListViewSurveyDTO};
 use survey_manager_core::app_services::repository_contracts::SurveyDTOReadRepository;
+use crate::mysql_repos::{mysql_pool, MysqlConnection};
+use crate::errors::Error;
 
 pub struct MysqlSurveyDTOsRepository {
-    // A single connection to Mysql.  Handed down from a pool likely.
-    conn: mysql::PooledConn,
+    // Shared with the other repositories of a unit of work, when there is one.
+    conn: MysqlConnection,
 }
 
 impl MysqlSurveyDTOsRepository {
-    pub fn new() -> MysqlSurveyDTOsRepository {
-        let pool = super::MYSQL_POOL.clone();
+    /// A repository on a connection of its own from the shared pool.
+    pub fn new() -> Result<MysqlSurveyDTOsRepository, Error> {
+        Ok(MysqlSurveyDTOsRepository::with_connection(mysql_pool()?.connection()?))
+    }
+
+    pub fn with_connection(conn: MysqlConnection) -> MysqlSurveyDTOsRepository {
         MysqlSurveyDTOsRepository {
-            conn: pool.get_conn().unwrap()
+            conn,
         }
     }
 }
 
 impl SurveyDTOReadRepository for MysqlSurveyDTOsRepository {
-    type Error = mysql::Error;
+    type Error = Error;
 
-    fn get_survey_for_author(&mut self, id: &String, author: &String) -> Result<Option<SurveyDTO>, mysql::Error> {
+    fn get_survey_for_author(&mut self, id: &String, author: &String, organization_id: Option<&String>) -> Result<Option<SurveyDTO>, Error> {
+        // A NULL organization_id never compares equal, so personal callers only match on the first
+        // two conditions.
         let survey_result: Option<SurveyDTO> =
-            match self.conn.prep_exec(
-                "SELECT survey_data FROM survey WHERE id=? AND author=?",
-                (id, author)
+            match self.conn.lock().prep_exec(
+                "SELECT survey_data FROM survey WHERE id = ? AND (author = ? OR organization_id = ? OR EXISTS (\
+                    SELECT 1 FROM survey_collaborator c WHERE c.survey_id = survey.id AND c.username = ?))",
+                (id, author, organization_id, author)
             ) {
                 Ok(mut q_result) => {
                     if let Some(row_result)  = q_result.next() {
                         let row = row_result?;
                         let survey_data: String = mysql::from_row(row);
-                        Some(serde_json::from_str(&survey_data).unwrap())
+                        Some(serde_json::from_str(&survey_data)?)
                     } else {
                         None
                     }
                 },
                 Err(e) => {
-                    return Err(e);
+                    return Err(e.into());
                 },
             };
 
         Ok(survey_result)
     }
 
-    fn get_surveys_by_author(&mut self, author: &String) -> Result<Option<SurveyDTOs>, mysql::Error> {
-        let survey_results: Option<SurveyDTOs> =
-            match self.conn.prep_exec(
-                "SELECT id, author, title, category FROM survey WHERE author=?",
-                (author,)
-            ) {
-                Ok(q_result) => {
-                    let mut surveys = Vec::new();
-                    for row_result in q_result {
-                        let row = row_result?;
-                        let (id, author, title, category) = mysql::from_row(row);
-                        let s_dto = ListViewSurveyDTO {
-                            id,
-                            author,
-                            title,
-                            category,
-                        };
-                        surveys.push(s_dto);
-                    }
-                    if surveys.len() == 0 {
-                        None
-                    } else {
-                        Some(
-                            SurveyDTOs {
-                                surveys,
-                            }
-                        )
-                    }
-                },
-                Err(e) => {
-                    return Err(e);
-                },
-            };
+    fn get_surveys_by_author(&mut self, author: &String) -> Result<Option<SurveyDTOs>, Error> {
+        // Surveys the author handed to an organization are listed through the organization instead.
+        match self.conn.lock().prep_exec(
+            "SELECT id, author, title, category, NULL FROM survey WHERE author = ? AND organization_id IS NULL \
+             UNION ALL \
+             SELECT s.id, s.author, s.title, s.category, c.role FROM survey s \
+             JOIN survey_collaborator c ON c.survey_id = s.id WHERE c.username = ?",
+            (author, author)
+        ) {
+            Ok(q_result) => list_view_surveys(q_result),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn get_surveys_by_organization(&mut self, organization_id: &String, member: &String) -> Result<Option<SurveyDTOs>, Error> {
+        // Shared surveys the organization already owns would otherwise be listed twice.
+        match self.conn.lock().prep_exec(
+            "SELECT id, author, title, category, NULL FROM survey WHERE organization_id = ? \
+             UNION ALL \
+             SELECT s.id, s.author, s.title, s.category, c.role FROM survey s \
+             JOIN survey_collaborator c ON c.survey_id = s.id \
+             WHERE c.username = ? AND (s.organization_id IS NULL OR s.organization_id <> ?)",
+            (organization_id, member, organization_id)
+        ) {
+            Ok(q_result) => list_view_surveys(q_result),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+// Rows are (id, author, title, category, role).  No rows at all comes back as `None`.
+fn list_view_surveys(q_result: mysql::QueryResult) -> Result<Option<SurveyDTOs>, Error> {
+    let mut surveys = Vec::new();
+    for row_result in q_result {
+        let row = row_result?;
+        let (id, author, title, category, role) = mysql::from_row(row);
+        let s_dto = ListViewSurveyDTO {
+            id,
+            author,
+            title,
+            category,
+            role,
+        };
+        surveys.push(s_dto);
+    }
 
-        Ok(survey_results)
+    if surveys.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(
+            SurveyDTOs {
+                surveys,
+            }
+        ))
     }
 }
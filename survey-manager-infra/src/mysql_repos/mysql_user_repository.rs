@@ -0,0 +1,81 @@
+use survey_manager_core::user::User;
+use survey_manager_core::dtos::UserDTO;
+use survey_manager_core::app_services::repository_contracts::UserRepository;
+use std::convert::TryFrom;
+use crate::mysql_repos::{mysql_pool, MysqlConnection};
+use crate::errors::Error;
+use super::handle_duplicate_key;
+
+pub struct MysqlUserRepository {
+    // Shared with the other repositories of a unit of work, when there is one.
+    conn: MysqlConnection,
+}
+
+impl MysqlUserRepository {
+    /// A repository on a connection of its own from the shared pool.
+    pub fn new() -> Result<MysqlUserRepository, Error> {
+        Ok(MysqlUserRepository::with_connection(mysql_pool()?.connection()?))
+    }
+
+    pub fn with_connection(conn: MysqlConnection) -> MysqlUserRepository {
+        MysqlUserRepository {
+            conn,
+        }
+    }
+}
+
+impl UserRepository for MysqlUserRepository {
+    type Error = Error;
+
+    fn insert(&mut self, user: &User) -> Result<Option<String>, Error> {
+        let user_dto: UserDTO = user.into();
+
+        // The unique index on username turns a taken name into a duplicate key error, which comes
+        // back as None.
+        if let Err(e) = self.conn.lock().prep_exec(
+            "INSERT INTO user_account (id, version, username, password_hash, created_on, admin, locked) VALUES (?, ?, ?, ?, ?, ?, ?)",
+            (&user_dto.id, user_dto.version, &user_dto.username, &user_dto.password_hash, user_dto.created_on, user_dto.admin, user_dto.locked)
+        ) {
+            return handle_duplicate_key(e);
+        };
+
+        Ok(Some(user_dto.id))
+    }
+
+    fn get_by_username(&mut self, username: &String) -> Result<Option<User>, Error> {
+        let mut conn = self.conn.lock();
+        let mut q_result = conn.prep_exec(
+            "SELECT id, version, username, password_hash, created_on, admin, locked FROM user_account WHERE username = ?",
+            (username,)
+        )?;
+
+        let row = match q_result.next() {
+            Some(row_result) => row_result?,
+            None => return Ok(None),
+        };
+
+        let (id, version, username, password_hash, created_on, admin, locked) = mysql::from_row(row);
+        let user_dto = UserDTO { id, version, username, password_hash, created_on, admin, locked };
+        let id = user_dto.id.clone();
+
+        User::try_from(user_dto)
+            .map(Some)
+            .map_err(|e| Error::CorruptUser { id, source: e })
+    }
+
+    // `admin` is left alone on purpose.  It's only ever granted directly in the database.
+    fn update(&mut self, user: &User) -> Result<Option<String>, Error> {
+        let user_dto: UserDTO = user.into();
+
+        let mut conn = self.conn.lock();
+        let result = conn.prep_exec(
+            "UPDATE user_account SET version = ?, password_hash = ?, locked = ? WHERE id = ?",
+            (user_dto.version, &user_dto.password_hash, user_dto.locked, &user_dto.id)
+        )?;
+        if result.affected_rows() == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(user_dto.id))
+    }
+}
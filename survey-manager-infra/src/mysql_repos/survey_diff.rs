@@ -0,0 +1,110 @@
+use survey_manager_core::dtos::{SurveyDTO, QuestionDTO, ChoiceDTO, CollaboratorDTO};
+
+/// One row to write when a stored survey becomes an updated one, see `diff_survey`.  Positions
+/// are where the question or choice sits in the updated survey.
+pub enum SurveyChange<'a> {
+    /// Something held on the survey's own row changed.
+    Survey(&'a SurveyDTO),
+    InsertQuestion { position: usize, question: &'a QuestionDTO },
+    UpdateQuestion { position: usize, question: &'a QuestionDTO },
+    DeleteQuestion { id: &'a str },
+    InsertChoice { question_id: &'a str, position: usize, choice: &'a ChoiceDTO },
+    /// Also covers a choice moving to another question.
+    UpdateChoice { question_id: &'a str, position: usize, choice: &'a ChoiceDTO },
+    DeleteChoice { id: &'a str },
+    InsertCollaborator(&'a CollaboratorDTO),
+    UpdateCollaborator(&'a CollaboratorDTO),
+    DeleteCollaborator { username: &'a str },
+}
+
+/// What it takes to turn `stored` into `updated`, touching only the rows that differ.  Questions,
+/// choices and collaborators are matched by id, or username.
+///
+/// Changes come in the order they can be applied in with foreign keys in place: questions are
+/// written before choices that move onto them, and deleted only after choices have moved off.
+/// Choices of deleted questions aren't deleted separately, the cascade takes them.
+pub fn diff_survey<'a>(stored: &'a SurveyDTO, updated: &'a SurveyDTO) -> Vec<SurveyChange<'a>> {
+    let mut changes = Vec::new();
+
+    if survey_row_changed(stored, updated) {
+        changes.push(SurveyChange::Survey(updated));
+    }
+
+    for (position, question) in updated.questions.iter().enumerate() {
+        match find_question(stored, &question.id) {
+            None => changes.push(SurveyChange::InsertQuestion { position, question }),
+            Some((was_at, was)) if was_at != position || was.kind != question.kind || was.title != question.title => {
+                changes.push(SurveyChange::UpdateQuestion { position, question });
+            },
+            Some(_) => {},
+        }
+    }
+
+    for question in &updated.questions {
+        for (position, choice) in question.choices.iter().enumerate() {
+            let question_id = question.id.as_str();
+            match find_choice(stored, &choice.id) {
+                None => changes.push(SurveyChange::InsertChoice { question_id, position, choice }),
+                Some((was_on, was_at, was)) if was_on != question_id || was_at != position || choice_changed(was, choice) => {
+                    changes.push(SurveyChange::UpdateChoice { question_id, position, choice });
+                },
+                Some(_) => {},
+            }
+        }
+    }
+
+    for question in stored.questions.iter().filter(|q| find_question(updated, &q.id).is_some()) {
+        for choice in question.choices.iter().filter(|c| find_choice(updated, &c.id).is_none()) {
+            changes.push(SurveyChange::DeleteChoice { id: &choice.id });
+        }
+    }
+
+    for question in stored.questions.iter().filter(|q| find_question(updated, &q.id).is_none()) {
+        changes.push(SurveyChange::DeleteQuestion { id: &question.id });
+    }
+
+    for collaborator in &updated.collaborators {
+        match stored.collaborators.iter().find(|c| c.username == collaborator.username) {
+            None => changes.push(SurveyChange::InsertCollaborator(collaborator)),
+            Some(was) if was.role != collaborator.role => changes.push(SurveyChange::UpdateCollaborator(collaborator)),
+            Some(_) => {},
+        }
+    }
+
+    for collaborator in &stored.collaborators {
+        if !updated.collaborators.iter().any(|c| c.username == collaborator.username) {
+            changes.push(SurveyChange::DeleteCollaborator { username: &collaborator.username });
+        }
+    }
+
+    changes
+}
+
+fn survey_row_changed(stored: &SurveyDTO, updated: &SurveyDTO) -> bool {
+    stored.version != updated.version
+        || stored.author != updated.author
+        || stored.title != updated.title
+        || stored.description != updated.description
+        || stored.category != updated.category
+        || stored.created_on != updated.created_on
+        || stored.organization_id != updated.organization_id
+        || stored.response_policy != updated.response_policy
+        || stored.publication != updated.publication
+}
+
+fn choice_changed(stored: &ChoiceDTO, updated: &ChoiceDTO) -> bool {
+    stored.content != updated.content
+        || stored.content_type != updated.content_type
+        || stored.title != updated.title
+}
+
+fn find_question<'a>(survey: &'a SurveyDTO, id: &str) -> Option<(usize, &'a QuestionDTO)> {
+    survey.questions.iter().enumerate().find(|(_, q)| q.id == id)
+}
+
+// The question a choice is on, where it sits, and the choice itself.
+fn find_choice<'a>(survey: &'a SurveyDTO, id: &str) -> Option<(&'a str, usize, &'a ChoiceDTO)> {
+    survey.questions.iter()
+        .flat_map(|q| q.choices.iter().enumerate().map(move |(position, c)| (q.id.as_str(), position, c)))
+        .find(|(_, _, c)| c.id == id)
+}
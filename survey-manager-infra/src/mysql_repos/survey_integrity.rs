@@ -0,0 +1,86 @@
+use chrono::Utc;
+use crate::mysql_repos::{mysql_pool, MysqlConnection};
+use crate::errors::Error;
+use crate::mysql_repos::survey_from_json;
+use crate::survey_backends::SurveyBackend;
+
+/// A stored survey that can't be loaded on the command side, and why.
+pub struct LoadFailure {
+    pub id: String,
+    pub reason: String,
+}
+
+/// Finds stored surveys that fail to load, and moves them out of the way so they stop failing
+/// requests.  Quarantined rows keep their original `survey_data` so they can be fixed by hand and
+/// copied back.
+pub struct SurveyIntegrityChecker {
+    // Shared with the other repositories of a unit of work, when there is one.
+    conn: MysqlConnection,
+}
+
+impl SurveyIntegrityChecker {
+    /// A checker on a connection of its own from the shared pool.  A missing or unreachable
+    /// database is returned as an error, so tools like survey-doctor can report it and exit.
+    ///
+    /// Only the `survey` table of the `mysql` backend is read.  Any other `SURVEY_BACKEND` is
+    /// refused, rather than reporting surveys clean that were never looked at.
+    pub fn new() -> Result<SurveyIntegrityChecker, Error> {
+        let backend = SurveyBackend::from_env()?;
+        if backend != SurveyBackend::Mysql {
+            return Err(Error::UncheckedBackend { backend: backend.name() });
+        }
+
+        Ok(SurveyIntegrityChecker::with_connection(mysql_pool()?.connection()?))
+    }
+
+    pub fn with_connection(conn: MysqlConnection) -> SurveyIntegrityChecker {
+        SurveyIntegrityChecker {
+            conn,
+        }
+    }
+
+    /// Attempts to load every stored survey exactly as the write repository would, and returns
+    /// each one that fails.
+    pub fn find_unloadable(&mut self) -> Result<Vec<LoadFailure>, Error> {
+        let mut failures = Vec::new();
+
+        for row_result in self.conn.lock().query("SELECT id, survey_data FROM survey")? {
+            let (id, survey_data): (String, String) = mysql::from_row(row_result?);
+            if let Err(e) = survey_from_json(&id, &survey_data) {
+                failures.push(LoadFailure {
+                    id,
+                    reason: e.to_string(),
+                });
+            }
+        }
+
+        Ok(failures)
+    }
+
+    /// Moves the failing row into `survey_quarantine` along with the reason it failed.  Both
+    /// statements run in one transaction so a row is never lost or left in both tables.
+    ///
+    /// Returns the author and collaborators of the row, whose cached listings still include it.
+    pub fn quarantine(&mut self, failure: &LoadFailure) -> Result<Vec<String>, Error> {
+        self.conn.transaction(|conn| {
+            let mut usernames = Vec::new();
+            for row_result in conn.prep_exec(
+                "SELECT author FROM survey WHERE id = ? UNION SELECT username FROM survey_collaborator WHERE survey_id = ?",
+                (&failure.id, &failure.id)
+            )? {
+                usernames.push(mysql::from_row(row_result?));
+            }
+
+            conn.prep_exec(
+                "INSERT INTO survey_quarantine (id, survey_data, reason, quarantined_on) SELECT id, survey_data, ?, ? FROM survey WHERE id = ?",
+                (&failure.reason, Utc::now().timestamp(), &failure.id)
+            )?;
+            conn.prep_exec(
+                "DELETE FROM survey WHERE id = ?",
+                (&failure.id,)
+            )?;
+
+            Ok(usernames)
+        })
+    }
+}
@@ -0,0 +1,114 @@
+use std::thread;
+use mysql::ServerError;
+use crate::mysql_repos::{MysqlPool, MysqlConnection};
+use crate::errors::Error;
+
+/// One transaction that several repositories take part in.  Build each repository with
+/// `with_connection(unit.connection())`, and everything they read and write commits or rolls back
+/// together.  Dropping a unit of work without committing rolls it back.
+///
+/// Usually run through `MysqlPool::in_unit_of_work`, which also retries it when MySQL gives up on it.
+pub struct MysqlUnitOfWork {
+    conn: MysqlConnection,
+    finished: bool,
+}
+
+impl MysqlUnitOfWork {
+    pub fn begin(pool: &MysqlPool) -> Result<MysqlUnitOfWork, Error> {
+        let conn = pool.connection()?;
+        conn.set_unit_of_work("START TRANSACTION", true)?;
+        Ok(MysqlUnitOfWork {
+            conn,
+            finished: false,
+        })
+    }
+
+    /// The connection the transaction is open on, for the repositories taking part.
+    pub fn connection(&self) -> MysqlConnection {
+        self.conn.clone()
+    }
+
+    pub fn commit(mut self) -> Result<(), Error> {
+        self.finished = true;
+        self.conn.set_unit_of_work("COMMIT", false)
+    }
+
+    pub fn rollback(mut self) -> Result<(), Error> {
+        self.finished = true;
+        self.conn.set_unit_of_work("ROLLBACK", false)
+    }
+}
+
+impl Drop for MysqlUnitOfWork {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.conn.set_unit_of_work("ROLLBACK", false);
+        }
+    }
+}
+
+impl MysqlPool {
+    /// Runs `work` in a unit of work, and commits it if `work` succeeds.
+    ///
+    /// When MySQL picks the transaction as a deadlock victim, or it times out waiting on a lock,
+    /// the whole of `work` is run again on a fresh unit of work, up to `retry_attempts` times in
+    /// all.  That's also how MySQL reports two serializable transactions that conflict.  `work` may
+    /// run more than once, so it should leave nothing behind outside the transaction.
+    pub fn in_unit_of_work<T, E, F>(&self, mut work: F) -> Result<T, E>
+        where F: FnMut(&MysqlUnitOfWork) -> Result<T, E>,
+              E: From<Error> + TransientFailure
+    {
+        let attempts = self.config().retry_attempts.max(1);
+        let mut backoff = self.config().retry_backoff;
+
+        let mut attempt = 1;
+        loop {
+            let result = MysqlUnitOfWork::begin(self)
+                .map_err(E::from)
+                .and_then(|unit| {
+                    let value = work(&unit)?;
+                    unit.commit()?;
+                    Ok(value)
+                });
+
+            match result {
+                Err(ref e) if attempt < attempts && e.is_transient() => {
+                    log::warn!("transaction abandoned by MySQL, retrying after {:?} (attempt {} of {})", backoff, attempt, attempts);
+                    thread::sleep(backoff);
+                    backoff *= 2;
+                    attempt += 1;
+                },
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Errors that can tell whether they came from a transaction MySQL gave up on, which is worth
+/// running again.
+pub trait TransientFailure {
+    fn is_transient(&self) -> bool;
+}
+
+impl TransientFailure for Error {
+    fn is_transient(&self) -> bool {
+        match self {
+            Error::DatabaseError { source: mysql::Error::MySqlError(e) } => {
+                e.code == ServerError::ER_LOCK_DEADLOCK as u16 || e.code == ServerError::ER_LOCK_WAIT_TIMEOUT as u16
+            },
+            _ => false,
+        }
+    }
+}
+
+// Repository errors reach command handlers' callers boxed in a `RepoFailure`.
+impl TransientFailure for survey_manager_core::Error {
+    fn is_transient(&self) -> bool {
+        match self {
+            survey_manager_core::Error::RepoFailure { source } => {
+                source.downcast_ref::<Error>().is_some_and(|e| e.is_transient())
+            },
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,18 @@
+use r2d2_postgres::PostgresConnectionManager;
+use pg::NoTls;
+
+pub(crate) type PgConn = r2d2::PooledConnection<PostgresConnectionManager<NoTls>>;
+
+lazy_static! {
+    static ref PG_POOL: r2d2::Pool<PostgresConnectionManager<NoTls>> = {
+        let postgres_url = std::env::var("POSTGRES_URL").expect("POSTGRES_URL must be set");
+        let manager = PostgresConnectionManager::new(postgres_url.parse().unwrap(), NoTls);
+        r2d2::Pool::new(manager).unwrap()
+    };
+}
+
+pub mod pg_survey_repository;
+pub use pg_survey_repository::*;
+
+pub mod pg_surveydto_read_repository;
+pub use pg_surveydto_read_repository::*;
@@ -0,0 +1,99 @@
+use domain_patterns::collections::Repository;
+use domain_patterns::models::{Entity, AggregateRoot};
+use survey_manager_core::survey::Survey;
+use survey_manager_core::dtos::SurveyDTO;
+use std::convert::TryFrom;
+use crate::errors::Error;
+use super::PgConn;
+
+/// The write side of surveys in Postgres.  `survey_data` holds the whole survey as JSONB, the
+/// other columns are copied out of it for querying, as in MySQL.  Collaborators aren't copied into
+/// a table of their own, they're found in `survey_data` through its GIN index instead.
+pub struct PgSurveyWriteRepository {
+    // A single connection to Postgres.  Handed down from a pool likely.
+    conn: PgConn,
+}
+
+impl PgSurveyWriteRepository {
+    pub fn new() -> PgSurveyWriteRepository {
+        let pool = super::PG_POOL.clone();
+        PgSurveyWriteRepository {
+            conn: pool.get().unwrap(),
+        }
+    }
+}
+
+impl Repository<Survey> for PgSurveyWriteRepository {
+    type Error = Error;
+
+    fn insert(&mut self, entity: &Survey) -> Result<Option<String>, Self::Error> {
+        let survey_dto: SurveyDTO = entity.into();
+        let survey_data = serde_json::to_value(&survey_dto)?;
+
+        // A duplicate id inserts nothing rather than failing.
+        let inserted = self.conn.execute(
+            "INSERT INTO survey (id, version, author, title, category, created_on, organization_id, survey_data) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8) ON CONFLICT (id) DO NOTHING",
+            &[&entity.id(), &(entity.version() as i64), &entity.author().to_string(), &entity.title().to_string(),
+              &entity.category().to_string(), &entity.created_on(), &survey_dto.organization_id, &survey_data]
+        )?;
+        if inserted == 0 {
+            return Ok(None);
+        }
+
+        // Success.  Return the PK back as is.
+        Ok(Some(survey_dto.id))
+    }
+
+    fn get(&mut self, key: &String) -> Result<Option<Survey>, Self::Error> {
+        let rows = self.conn.query("SELECT survey_data FROM survey WHERE id = $1", &[key])?;
+        if rows.is_empty() {
+            return Ok(None);
+        }
+
+        let survey_data: serde_json::Value = rows[0].get(0);
+        Ok(Some(survey_from_value(key, survey_data)?))
+    }
+
+    // Intentionally leaving this unimplemented.  we don't need it for command side.
+    #[allow(unused)]
+    fn get_paged(&mut self, page_num: usize, page_size: usize) -> Result<Option<Vec<Survey>>, Self::Error> {
+        unimplemented!()
+    }
+
+    fn update(&mut self, entity: &Survey) -> Result<Option<String>, Self::Error> {
+        let survey_dto: SurveyDTO = entity.into();
+        let survey_data = serde_json::to_value(&survey_dto)?;
+
+        let updated = self.conn.execute(
+            "UPDATE survey SET version = $1, author = $2, title = $3, category = $4, organization_id = $5, survey_data = $6 \
+             WHERE id = $7",
+            &[&(entity.version() as i64), &entity.author().to_string(), &entity.title().to_string(),
+              &entity.category().to_string(), &survey_dto.organization_id, &survey_data, &entity.id()]
+        )?;
+        if updated == 0 {
+            return Ok(None);
+        }
+
+        // Success.  Return the PK back as is.
+        Ok(Some(survey_dto.id))
+    }
+
+    fn remove(&mut self, key: &String) -> Result<Option<String>, Self::Error> {
+        let removed = self.conn.execute("DELETE FROM survey WHERE id = $1", &[key])?;
+        if removed == 0 {
+            return Ok(None);
+        }
+
+        // Success.  Return the PK back as is.
+        Ok(Some(key.clone()))
+    }
+}
+
+// Like `survey_from_json` for MySQL, rows that no longer load come back as an error.
+fn survey_from_value(id: &String, survey_data: serde_json::Value) -> Result<Survey, Error> {
+    let survey_dto: SurveyDTO = serde_json::from_value(survey_data)?;
+
+    Survey::try_from(survey_dto)
+        .map_err(|e| Error::CorruptSurvey { id: id.clone(), source: e })
+}
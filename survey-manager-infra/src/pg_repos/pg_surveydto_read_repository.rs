@@ -0,0 +1,94 @@
+use serde_json::json;
+use survey_manager_core::dtos::{SurveyDTO, SurveyDTOs, ListViewSurveyDTO};
+use survey_manager_core::app_services::repository_contracts::SurveyDTOReadRepository;
+use crate::errors::Error;
+use super::PgConn;
+
+pub struct PgSurveyDTOsRepository {
+    // A single connection to Postgres.  Handed down from a pool likely.
+    conn: PgConn,
+}
+
+impl PgSurveyDTOsRepository {
+    pub fn new() -> PgSurveyDTOsRepository {
+        let pool = super::PG_POOL.clone();
+        PgSurveyDTOsRepository {
+            conn: pool.get().unwrap(),
+        }
+    }
+}
+
+// Matches `survey_data` of surveys shared with `username`.  Containment is what the GIN index on
+// `survey_data` answers.
+fn shared_with(username: &String) -> serde_json::Value {
+    json!({ "collaborators": [{ "username": username }] })
+}
+
+impl SurveyDTOReadRepository for PgSurveyDTOsRepository {
+    type Error = Error;
+
+    fn get_survey_for_author(&mut self, id: &String, author: &String, organization_id: Option<&String>) -> Result<Option<SurveyDTO>, Error> {
+        // A NULL organization_id never compares equal, so personal callers only match on author or
+        // collaborators.
+        let rows = self.conn.query(
+            "SELECT survey_data FROM survey WHERE id = $1 AND (author = $2 OR organization_id = $3 OR survey_data @> $4)",
+            &[id, author, &organization_id, &shared_with(author)]
+        )?;
+        if rows.is_empty() {
+            return Ok(None);
+        }
+
+        let survey_data: serde_json::Value = rows[0].get(0);
+        Ok(Some(serde_json::from_value(survey_data)?))
+    }
+
+    fn get_surveys_by_author(&mut self, author: &String) -> Result<Option<SurveyDTOs>, Error> {
+        // Surveys the author handed to an organization are listed through the organization instead.
+        let rows = self.conn.query(
+            "SELECT id, author, title, category, NULL::TEXT FROM survey WHERE author = $1 AND organization_id IS NULL \
+             UNION ALL \
+             SELECT s.id, s.author, s.title, s.category, c->>'role' FROM survey s \
+             CROSS JOIN LATERAL jsonb_array_elements(s.survey_data->'collaborators') c \
+             WHERE s.survey_data @> $2 AND c->>'username' = $1",
+            &[author, &shared_with(author)]
+        )?;
+
+        Ok(list_view_surveys(&rows))
+    }
+
+    fn get_surveys_by_organization(&mut self, organization_id: &String, member: &String) -> Result<Option<SurveyDTOs>, Error> {
+        // Shared surveys the organization already owns would otherwise be listed twice.
+        let rows = self.conn.query(
+            "SELECT id, author, title, category, NULL::TEXT FROM survey WHERE organization_id = $1 \
+             UNION ALL \
+             SELECT s.id, s.author, s.title, s.category, c->>'role' FROM survey s \
+             CROSS JOIN LATERAL jsonb_array_elements(s.survey_data->'collaborators') c \
+             WHERE s.survey_data @> $3 AND c->>'username' = $2 \
+             AND (s.organization_id IS NULL OR s.organization_id <> $1)",
+            &[organization_id, member, &shared_with(member)]
+        )?;
+
+        Ok(list_view_surveys(&rows))
+    }
+}
+
+// Rows are (id, author, title, category, role).  No rows at all comes back as `None`.
+fn list_view_surveys(rows: &[pg::Row]) -> Option<SurveyDTOs> {
+    let surveys: Vec<ListViewSurveyDTO> = rows.iter()
+        .map(|row| ListViewSurveyDTO {
+            id: row.get(0),
+            author: row.get(1),
+            title: row.get(2),
+            category: row.get(3),
+            role: row.get(4),
+        })
+        .collect();
+
+    if surveys.is_empty() {
+        None
+    } else {
+        Some(SurveyDTOs {
+            surveys,
+        })
+    }
+}
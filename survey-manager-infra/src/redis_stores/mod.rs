@@ -0,0 +1,2 @@
+pub mod redis_revocation_list;
+pub use redis_revocation_list::*;
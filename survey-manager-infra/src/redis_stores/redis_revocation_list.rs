@@ -0,0 +1,66 @@
+use std::time::Duration;
+use survey_manager_core::app_services::repository_contracts::RevocationList;
+use crate::cache_repo_decorators::{CacheStore, GuardedCacheStore, RedisCacheStore, RevocationFallback};
+use crate::errors::Error;
+use chrono::Utc;
+
+/// Revoked access token ids, stored in Redis with a ttl matching the token's remaining lifetime so
+/// the list never grows past the tokens that are still live.
+///
+/// Redis is reached through the same breaker as the survey caches, so while it's down lookups fail
+/// straight away instead of each waiting out `CACHE_TIMEOUT_MS`.  What a failed lookup means for
+/// the token is up to the caller, see `is_revoked_or`.
+pub struct RedisRevocationList<C: CacheStore = GuardedCacheStore<RedisCacheStore>> {
+    cache: C,
+}
+
+impl RedisRevocationList {
+    /// Fails only when `CACHE_URL` is missing or malformed.
+    pub fn new() -> Result<RedisRevocationList, Error> {
+        Ok(RedisRevocationList::with_cache(GuardedCacheStore::redis()?))
+    }
+}
+
+impl<C: CacheStore> RedisRevocationList<C> {
+    pub fn with_cache(cache: C) -> RedisRevocationList<C> {
+        RedisRevocationList {
+            cache,
+        }
+    }
+
+    /// Whether `jti` was revoked, or what `fallback` says when that can't be found out.
+    pub fn is_revoked_or(&mut self, jti: &String, fallback: RevocationFallback) -> bool {
+        match self.is_revoked(jti) {
+            Ok(revoked) => revoked,
+            Err(e) => {
+                log::warn!("could not check token revocation, {:?} applies: {}", fallback, e);
+                fallback == RevocationFallback::Reject
+            },
+        }
+    }
+}
+
+fn revocation_key(jti: &String) -> String {
+    format!("revoked_token:{}", jti)
+}
+
+impl<C: CacheStore> RevocationList for RedisRevocationList<C> {
+    type Error = Error;
+
+    fn revoke(&mut self, jti: &String, expires_at: i64) -> Result<(), Error> {
+        let remaining = expires_at - Utc::now().timestamp();
+        // Already expired, so there's nothing left to revoke.
+        if remaining <= 0 {
+            return Ok(());
+        }
+
+        // Added rather than set.  A revocation that's already there is just as good, and a set the
+        // breaker lost would be queued for invalidation, i.e. deleting the revocation later.
+        self.cache.add(&revocation_key(jti), "1", Duration::from_secs(remaining as u64))
+            .map(|_| ())
+    }
+
+    fn is_revoked(&mut self, jti: &String) -> Result<bool, Error> {
+        Ok(self.cache.get(&revocation_key(jti))?.is_some())
+    }
+}
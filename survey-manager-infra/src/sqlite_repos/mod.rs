@@ -0,0 +1,80 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+use rusqlite::Connection;
+use crate::errors::Error;
+
+lazy_static! {
+    static ref SQLITE_DB: SqliteDatabase = {
+        let sqlite_path = std::env::var("SQLITE_PATH").expect("SQLITE_PATH must be set");
+        SqliteDatabase::open(&sqlite_path).unwrap()
+    };
+}
+
+// Created on open, so a new file, or `:memory:`, is ready to use straight away.  Mirrors the survey
+// tables in the MySQL migrations.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS survey (
+        id TEXT PRIMARY KEY,
+        version INTEGER NOT NULL,
+        author TEXT NOT NULL,
+        title TEXT NOT NULL,
+        category TEXT NOT NULL,
+        created_on INTEGER NOT NULL,
+        organization_id TEXT NULL,
+        survey_data TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS survey_author ON survey (author);
+    CREATE INDEX IF NOT EXISTS survey_organization ON survey (organization_id);
+
+    CREATE TABLE IF NOT EXISTS survey_collaborator (
+        survey_id TEXT NOT NULL REFERENCES survey (id) ON DELETE CASCADE,
+        username TEXT NOT NULL,
+        role TEXT NOT NULL,
+        PRIMARY KEY (survey_id, username)
+    );
+    CREATE INDEX IF NOT EXISTS survey_collaborator_username ON survey_collaborator (username);
+";
+
+/// A SQLite database holding surveys, in a file or in memory.  Clones share one connection, which
+/// suits the single node installs and tests this is for, SQLite only has one writer at a time
+/// anyway.  It also means everyone sees the same `:memory:` database.
+#[derive(Clone)]
+pub struct SqliteDatabase {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteDatabase {
+    /// Opens, or creates, the database at `path`.  `:memory:` gives a fresh in-memory database.
+    pub fn open(path: &str) -> Result<SqliteDatabase, Error> {
+        SqliteDatabase::with_connection(Connection::open(path)?)
+    }
+
+    /// A fresh database that only lives as long as its clones do.
+    pub fn in_memory() -> Result<SqliteDatabase, Error> {
+        SqliteDatabase::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<SqliteDatabase, Error> {
+        // Off by default in SQLite, and collaborators rely on them to go with their survey.
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        conn.execute_batch(SCHEMA)?;
+
+        Ok(SqliteDatabase {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    pub(crate) fn conn(&self) -> MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap()
+    }
+}
+
+/// The database at `SQLITE_PATH`, opened on first use and shared by the whole process.
+pub fn sqlite_database() -> SqliteDatabase {
+    SQLITE_DB.clone()
+}
+
+pub mod sqlite_survey_repository;
+pub use sqlite_survey_repository::*;
+
+pub mod sqlite_surveydto_read_repository;
+pub use sqlite_surveydto_read_repository::*;
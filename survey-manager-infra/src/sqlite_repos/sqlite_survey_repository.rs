@@ -0,0 +1,122 @@
+use domain_patterns::collections::Repository;
+use domain_patterns::models::{Entity, AggregateRoot};
+use rusqlite::{params, OptionalExtension, Transaction};
+use survey_manager_core::survey::Survey;
+use survey_manager_core::dtos::SurveyDTO;
+use crate::mysql_repos::mysql_survey_repository::survey_from_json;
+use crate::errors::Error;
+use super::{SqliteDatabase, sqlite_database};
+
+/// The write side of surveys in SQLite, laid out like MySQL: `survey_data` holds the whole survey
+/// as json, and the other columns and `survey_collaborator` are copied out of it for querying.
+pub struct SqliteSurveyWriteRepository {
+    db: SqliteDatabase,
+}
+
+impl SqliteSurveyWriteRepository {
+    /// Over the database at `SQLITE_PATH`.
+    pub fn new() -> SqliteSurveyWriteRepository {
+        SqliteSurveyWriteRepository::with_database(sqlite_database())
+    }
+
+    pub fn with_database(db: SqliteDatabase) -> SqliteSurveyWriteRepository {
+        SqliteSurveyWriteRepository {
+            db,
+        }
+    }
+}
+
+impl Repository<Survey> for SqliteSurveyWriteRepository {
+    type Error = Error;
+
+    fn insert(&mut self, entity: &Survey) -> Result<Option<String>, Self::Error> {
+        let survey_dto: SurveyDTO = entity.into();
+        let survey_json = serde_json::to_string(&survey_dto)?;
+
+        let mut conn = self.db.conn();
+        // Dropping the transaction without committing rolls it back.
+        let tx = conn.transaction()?;
+
+        // A duplicate id inserts nothing rather than failing.
+        let inserted = tx.execute(
+            "INSERT INTO survey (id, version, author, title, category, created_on, organization_id, survey_data) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?) ON CONFLICT (id) DO NOTHING",
+            params![entity.id(), entity.version() as i64, entity.author().to_string(), entity.title().to_string(),
+                    entity.category().to_string(), entity.created_on(), survey_dto.organization_id, survey_json]
+        )?;
+        if inserted == 0 {
+            return Ok(None);
+        }
+
+        replace_collaborators(&tx, &survey_dto)?;
+        tx.commit()?;
+
+        // Success.  Return the PK back as is.
+        Ok(Some(survey_dto.id))
+    }
+
+    fn get(&mut self, key: &String) -> Result<Option<Survey>, Self::Error> {
+        let survey_data: Option<String> = self.db.conn()
+            .query_row("SELECT survey_data FROM survey WHERE id = ?", params![key], |row| row.get(0))
+            .optional()?;
+
+        match survey_data {
+            Some(data) => Ok(Some(survey_from_json(key, &data)?)),
+            None => Ok(None),
+        }
+    }
+
+    // Intentionally leaving this unimplemented.  we don't need it for command side.
+    #[allow(unused)]
+    fn get_paged(&mut self, page_num: usize, page_size: usize) -> Result<Option<Vec<Survey>>, Self::Error> {
+        unimplemented!()
+    }
+
+    fn update(&mut self, entity: &Survey) -> Result<Option<String>, Self::Error> {
+        let survey_dto: SurveyDTO = entity.into();
+        let survey_json = serde_json::to_string(&survey_dto)?;
+
+        let mut conn = self.db.conn();
+        let tx = conn.transaction()?;
+
+        let updated = tx.execute(
+            "UPDATE survey SET version = ?, author = ?, title = ?, category = ?, organization_id = ?, survey_data = ? WHERE id = ?",
+            params![entity.version() as i64, entity.author().to_string(), entity.title().to_string(),
+                    entity.category().to_string(), survey_dto.organization_id, survey_json, entity.id()]
+        )?;
+        if updated == 0 {
+            return Ok(None);
+        }
+
+        replace_collaborators(&tx, &survey_dto)?;
+        tx.commit()?;
+
+        // Success.  Return the PK back as is.
+        Ok(Some(survey_dto.id))
+    }
+
+    fn remove(&mut self, key: &String) -> Result<Option<String>, Self::Error> {
+        // Collaborators go with it, through their foreign key.
+        let removed = self.db.conn().execute("DELETE FROM survey WHERE id = ?", params![key])?;
+        if removed == 0 {
+            return Ok(None);
+        }
+
+        // Success.  Return the PK back as is.
+        Ok(Some(key.clone()))
+    }
+}
+
+// As in MySQL, the json stays the source of truth.
+fn replace_collaborators(tx: &Transaction, survey_dto: &SurveyDTO) -> Result<(), Error> {
+    tx.execute("DELETE FROM survey_collaborator WHERE survey_id = ?", params![survey_dto.id])?;
+
+    for collaborator in &survey_dto.collaborators {
+        tx.execute(
+            "INSERT INTO survey_collaborator (survey_id, username, role) VALUES (?, ?, ?)",
+            params![survey_dto.id, collaborator.username, collaborator.role]
+        )?;
+    }
+
+    Ok(())
+}
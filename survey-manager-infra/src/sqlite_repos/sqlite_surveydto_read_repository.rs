@@ -0,0 +1,97 @@
+use rusqlite::{params, OptionalExtension, Statement, ToSql};
+use survey_manager_core::dtos::{SurveyDTO, SurveyDTOs, ListViewSurveyDTO};
+use survey_manager_core::app_services::repository_contracts::SurveyDTOReadRepository;
+use crate::errors::Error;
+use super::{SqliteDatabase, sqlite_database};
+
+pub struct SqliteSurveyDTOsRepository {
+    db: SqliteDatabase,
+}
+
+impl SqliteSurveyDTOsRepository {
+    /// Over the database at `SQLITE_PATH`.
+    pub fn new() -> SqliteSurveyDTOsRepository {
+        SqliteSurveyDTOsRepository::with_database(sqlite_database())
+    }
+
+    pub fn with_database(db: SqliteDatabase) -> SqliteSurveyDTOsRepository {
+        SqliteSurveyDTOsRepository {
+            db,
+        }
+    }
+}
+
+impl SurveyDTOReadRepository for SqliteSurveyDTOsRepository {
+    type Error = Error;
+
+    fn get_survey_for_author(&mut self, id: &String, author: &String, organization_id: Option<&String>) -> Result<Option<SurveyDTO>, Error> {
+        // A NULL organization_id never compares equal, so personal callers only match on the first
+        // two conditions.
+        let survey_data: Option<String> = self.db.conn()
+            .query_row(
+                "SELECT survey_data FROM survey WHERE id = ? AND (author = ? OR organization_id = ? OR EXISTS (\
+                    SELECT 1 FROM survey_collaborator c WHERE c.survey_id = survey.id AND c.username = ?))",
+                params![id, author, organization_id, author],
+                |row| row.get(0)
+            )
+            .optional()?;
+
+        match survey_data {
+            Some(data) => Ok(Some(serde_json::from_str(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn get_surveys_by_author(&mut self, author: &String) -> Result<Option<SurveyDTOs>, Error> {
+        // Surveys the author handed to an organization are listed through the organization instead.
+        let conn = self.db.conn();
+        let mut stmt = conn.prepare(
+            "SELECT id, author, title, category, NULL FROM survey WHERE author = ? AND organization_id IS NULL \
+             UNION ALL \
+             SELECT s.id, s.author, s.title, s.category, c.role FROM survey s \
+             JOIN survey_collaborator c ON c.survey_id = s.id WHERE c.username = ?"
+        )?;
+
+        list_view_surveys(&mut stmt, params![author, author])
+    }
+
+    fn get_surveys_by_organization(&mut self, organization_id: &String, member: &String) -> Result<Option<SurveyDTOs>, Error> {
+        // Shared surveys the organization already owns would otherwise be listed twice.
+        let conn = self.db.conn();
+        let mut stmt = conn.prepare(
+            "SELECT id, author, title, category, NULL FROM survey WHERE organization_id = ? \
+             UNION ALL \
+             SELECT s.id, s.author, s.title, s.category, c.role FROM survey s \
+             JOIN survey_collaborator c ON c.survey_id = s.id \
+             WHERE c.username = ? AND (s.organization_id IS NULL OR s.organization_id <> ?)"
+        )?;
+
+        list_view_surveys(&mut stmt, params![organization_id, member, organization_id])
+    }
+}
+
+// Rows are (id, author, title, category, role).  No rows at all comes back as `None`.
+fn list_view_surveys(stmt: &mut Statement, params: &[&dyn ToSql]) -> Result<Option<SurveyDTOs>, Error> {
+    let rows = stmt.query_map(params, |row| {
+        Ok(ListViewSurveyDTO {
+            id: row.get(0)?,
+            author: row.get(1)?,
+            title: row.get(2)?,
+            category: row.get(3)?,
+            role: row.get(4)?,
+        })
+    })?;
+
+    let mut surveys = Vec::new();
+    for survey in rows {
+        surveys.push(survey?);
+    }
+
+    if surveys.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(SurveyDTOs {
+            surveys,
+        }))
+    }
+}
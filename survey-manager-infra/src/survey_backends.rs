@@ -0,0 +1,192 @@
+use std::str::FromStr;
+use domain_patterns::collections::Repository;
+use survey_manager_core::survey::Survey;
+use survey_manager_core::dtos::{SurveyDTO, SurveyDTOs};
+use survey_manager_core::app_services::repository_contracts::SurveyDTOReadRepository;
+use crate::mysql_repos::{MysqlConnection, MysqlSurveyWriteRepository, MysqlSurveyDTOsRepository, MysqlNormalizedSurveyRepository, MysqlNormalizedSurveyDTOsRepository};
+#[cfg(feature = "postgres")]
+use crate::pg_repos::{PgSurveyWriteRepository, PgSurveyDTOsRepository};
+#[cfg(feature = "sqlite")]
+use crate::sqlite_repos::{SqliteSurveyWriteRepository, SqliteSurveyDTOsRepository};
+use crate::errors::Error;
+
+/// Where surveys are stored.  Only surveys, everything else stays in MySQL whichever is chosen.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SurveyBackend {
+    /// The `survey` tables next to everything else, at `DATABASE_URL`.
+    #[default]
+    Mysql,
+    /// The `normalized_*` tables at `DATABASE_URL`, a row per question and choice.
+    MysqlNormalized,
+    /// Postgres at `POSTGRES_URL`.  Needs the `postgres` feature.
+    #[cfg(feature = "postgres")]
+    Postgres,
+    /// A SQLite file at `SQLITE_PATH`, or `:memory:`.  Needs the `sqlite` feature.
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+}
+
+impl FromStr for SurveyBackend {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Error> {
+        match name {
+            "mysql" => Ok(SurveyBackend::Mysql),
+            "mysql-normalized" => Ok(SurveyBackend::MysqlNormalized),
+            #[cfg(feature = "postgres")]
+            "postgres" => Ok(SurveyBackend::Postgres),
+            #[cfg(feature = "sqlite")]
+            "sqlite" => Ok(SurveyBackend::Sqlite),
+            _ => Err(Error::UnknownBackend { name: name.to_string() }),
+        }
+    }
+}
+
+impl SurveyBackend {
+    /// Reads `SURVEY_BACKEND`, defaulting to MySQL when it's unset.
+    pub fn from_env() -> Result<SurveyBackend, Error> {
+        match std::env::var("SURVEY_BACKEND") {
+            Ok(name) => name.parse(),
+            Err(_) => Ok(SurveyBackend::default()),
+        }
+    }
+
+    /// The name `SURVEY_BACKEND` gives this backend.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SurveyBackend::Mysql => "mysql",
+            SurveyBackend::MysqlNormalized => "mysql-normalized",
+            #[cfg(feature = "postgres")]
+            SurveyBackend::Postgres => "postgres",
+            #[cfg(feature = "sqlite")]
+            SurveyBackend::Sqlite => "sqlite",
+        }
+    }
+
+    /// Fails when the backend is MySQL and no connection could be had.
+    pub fn write_repository(&self) -> Result<SurveyWriteRepository, Error> {
+        Ok(match self {
+            SurveyBackend::Mysql => SurveyWriteRepository::Mysql(MysqlSurveyWriteRepository::new()?),
+            SurveyBackend::MysqlNormalized => SurveyWriteRepository::MysqlNormalized(MysqlNormalizedSurveyRepository::new()?),
+            #[cfg(feature = "postgres")]
+            SurveyBackend::Postgres => SurveyWriteRepository::Postgres(PgSurveyWriteRepository::new()),
+            #[cfg(feature = "sqlite")]
+            SurveyBackend::Sqlite => SurveyWriteRepository::Sqlite(SqliteSurveyWriteRepository::new()),
+        })
+    }
+
+    /// Whether surveys are stored in MySQL, and so can share a unit of work with everything else.
+    pub fn uses_mysql(&self) -> bool {
+        match self {
+            SurveyBackend::Mysql | SurveyBackend::MysqlNormalized => true,
+            #[cfg(feature = "postgres")]
+            SurveyBackend::Postgres => false,
+            #[cfg(feature = "sqlite")]
+            SurveyBackend::Sqlite => false,
+        }
+    }
+
+    /// The write side on `conn`, to take part in a unit of work.  Fails unless `uses_mysql`, the
+    /// other backends can't share MySQL's transactions.
+    pub fn write_repository_on(&self, conn: MysqlConnection) -> Result<SurveyWriteRepository, Error> {
+        match self {
+            SurveyBackend::Mysql => Ok(SurveyWriteRepository::Mysql(MysqlSurveyWriteRepository::with_connection(conn))),
+            SurveyBackend::MysqlNormalized => Ok(SurveyWriteRepository::MysqlNormalized(MysqlNormalizedSurveyRepository::with_connection(conn))),
+            #[cfg(feature = "postgres")]
+            SurveyBackend::Postgres => Err(outside_mysql("postgres")),
+            #[cfg(feature = "sqlite")]
+            SurveyBackend::Sqlite => Err(outside_mysql("sqlite")),
+        }
+    }
+
+    /// Fails when the backend is MySQL and no connection could be had.
+    pub fn read_repository(&self) -> Result<SurveyDTOsRepository, Error> {
+        Ok(match self {
+            SurveyBackend::Mysql => SurveyDTOsRepository::Mysql(MysqlSurveyDTOsRepository::new()?),
+            SurveyBackend::MysqlNormalized => SurveyDTOsRepository::MysqlNormalized(MysqlNormalizedSurveyDTOsRepository::new()?),
+            #[cfg(feature = "postgres")]
+            SurveyBackend::Postgres => SurveyDTOsRepository::Postgres(PgSurveyDTOsRepository::new()),
+            #[cfg(feature = "sqlite")]
+            SurveyBackend::Sqlite => SurveyDTOsRepository::Sqlite(SqliteSurveyDTOsRepository::new()),
+        })
+    }
+}
+
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+fn outside_mysql(backend: &'static str) -> Error {
+    Error::StorageError { backend, reason: "surveys here can't take part in a MySQL unit of work".to_string() }
+}
+
+// Forwards a call to whichever repository `$value` holds.
+macro_rules! dispatch {
+    ($backend:ident, $value:expr, $repo:ident => $call:expr) => {
+        match $value {
+            $backend::Mysql($repo) => $call,
+            $backend::MysqlNormalized($repo) => $call,
+            #[cfg(feature = "postgres")]
+            $backend::Postgres($repo) => $call,
+            #[cfg(feature = "sqlite")]
+            $backend::Sqlite($repo) => $call,
+        }
+    };
+}
+
+/// The write side of surveys on whichever backend `SurveyBackend` picked.
+pub enum SurveyWriteRepository {
+    Mysql(MysqlSurveyWriteRepository),
+    MysqlNormalized(MysqlNormalizedSurveyRepository),
+    #[cfg(feature = "postgres")]
+    Postgres(PgSurveyWriteRepository),
+    #[cfg(feature = "sqlite")]
+    Sqlite(SqliteSurveyWriteRepository),
+}
+
+impl Repository<Survey> for SurveyWriteRepository {
+    type Error = Error;
+
+    fn insert(&mut self, entity: &Survey) -> Result<Option<String>, Self::Error> {
+        dispatch!(SurveyWriteRepository, self, repo => repo.insert(entity))
+    }
+
+    fn get(&mut self, key: &String) -> Result<Option<Survey>, Self::Error> {
+        dispatch!(SurveyWriteRepository, self, repo => repo.get(key))
+    }
+
+    fn get_paged(&mut self, page_num: usize, page_size: usize) -> Result<Option<Vec<Survey>>, Self::Error> {
+        dispatch!(SurveyWriteRepository, self, repo => repo.get_paged(page_num, page_size))
+    }
+
+    fn update(&mut self, entity: &Survey) -> Result<Option<String>, Self::Error> {
+        dispatch!(SurveyWriteRepository, self, repo => repo.update(entity))
+    }
+
+    fn remove(&mut self, key: &String) -> Result<Option<String>, Self::Error> {
+        dispatch!(SurveyWriteRepository, self, repo => repo.remove(key))
+    }
+}
+
+/// The read side of surveys on whichever backend `SurveyBackend` picked.
+pub enum SurveyDTOsRepository {
+    Mysql(MysqlSurveyDTOsRepository),
+    MysqlNormalized(MysqlNormalizedSurveyDTOsRepository),
+    #[cfg(feature = "postgres")]
+    Postgres(PgSurveyDTOsRepository),
+    #[cfg(feature = "sqlite")]
+    Sqlite(SqliteSurveyDTOsRepository),
+}
+
+impl SurveyDTOReadRepository for SurveyDTOsRepository {
+    type Error = Error;
+
+    fn get_survey_for_author(&mut self, id: &String, author: &String, organization_id: Option<&String>) -> Result<Option<SurveyDTO>, Error> {
+        dispatch!(SurveyDTOsRepository, self, repo => repo.get_survey_for_author(id, author, organization_id))
+    }
+
+    fn get_surveys_by_author(&mut self, author: &String) -> Result<Option<SurveyDTOs>, Error> {
+        dispatch!(SurveyDTOsRepository, self, repo => repo.get_surveys_by_author(author))
+    }
+
+    fn get_surveys_by_organization(&mut self, organization_id: &String, member: &String) -> Result<Option<SurveyDTOs>, Error> {
+        dispatch!(SurveyDTOsRepository, self, repo => repo.get_surveys_by_organization(organization_id, member))
+    }
+}
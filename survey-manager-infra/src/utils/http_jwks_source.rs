@@ -0,0 +1,32 @@
+use std::time::Duration;
+use survey_manager_core::app_services::token::JwksSource;
+
+/// Fetches an identity provider's JWKS over http.  Requests time out quickly, a slow provider
+/// should fall back to the keys already cached rather than stall the request being verified.
+pub struct HttpJwksSource {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl HttpJwksSource {
+    /// Fails if no http client can be built, e.g. when the system's TLS backend can't be loaded.
+    pub fn new(url: String) -> Result<HttpJwksSource, reqwest::Error> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(5))
+            .build()?;
+
+        Ok(HttpJwksSource {
+            url,
+            client,
+        })
+    }
+}
+
+impl JwksSource for HttpJwksSource {
+    fn fetch(&self) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let mut response = self.client.get(&self.url)
+            .send()?
+            .error_for_status()?;
+        Ok(response.text()?)
+    }
+}
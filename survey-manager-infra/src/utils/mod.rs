@@ -1 +1,3 @@
 pub mod redis_pool;
+pub mod http_jwks_source;
+pub use http_jwks_source::*;
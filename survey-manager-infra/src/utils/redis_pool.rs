@@ -1,14 +1,49 @@
+use std::time::Duration;
 use r2d2_redis::RedisConnectionManager;
+use crate::cache_repo_decorators::cache_config;
+use crate::errors::Error;
 
 /// Pool type is a simple wrapper over r2d2::Pool<RedisConnectionManager> -> use it to pass around your
 /// pool.
 pub type Pool = r2d2::Pool<RedisConnectionManager>;
 pub type Conn = r2d2::PooledConnection<RedisConnectionManager>;
 
-pub fn create_pool(db_url: &str) -> Pool {
-    let manager = RedisConnectionManager::new(db_url).unwrap();
+lazy_static! {
+    // Holds why there's no pool instead, since errors can't be handed out more than once.
+    static ref REDIS_POOL: Result<Pool, String> = {
+        std::env::var("CACHE_URL")
+            .map_err(|_| "CACHE_URL must be set".to_string())
+            .and_then(|cache_url| create_pool(&cache_url, cache_config().timeout).map_err(|e| e.to_string()))
+    };
+}
+
+/// Builds a pool without connecting, so an unreachable Redis doesn't stop the caller from
+/// starting.  Only a malformed `db_url` fails here.  Taking a connection gives up after `timeout`.
+pub fn create_pool(db_url: &str, timeout: Duration) -> Result<Pool, Error> {
+    let manager = RedisConnectionManager::new(db_url)?;
+
+    Ok(r2d2::Pool::builder()
+        .connection_timeout(timeout)
+        .build_unchecked(manager))
+}
+
+/// Checks `CACHE_URL` names a Redis we could connect to, without connecting.
+pub fn check_redis_config() -> Result<(), Error> {
+    REDIS_POOL.as_ref()
+        .map(|_| ())
+        .map_err(|reason| Error::CacheUnavailable { reason: reason.clone() })
+}
+
+/// A connection from the shared pool.  Commands on it fail rather than hang once the cache timeout
+/// passes, so callers never wait on Redis for long.
+pub(crate) fn redis_conn() -> Result<Conn, Error> {
+    let pool = REDIS_POOL.as_ref()
+        .map_err(|reason| Error::CacheUnavailable { reason: reason.clone() })?;
+    let conn = pool.get()
+        .map_err(|e| Error::CacheUnavailable { reason: e.to_string() })?;
 
-    r2d2::Pool::builder()
-        .build(manager)
-        .unwrap()
+    let timeout = Some(cache_config().timeout);
+    conn.set_read_timeout(timeout)?;
+    conn.set_write_timeout(timeout)?;
+    Ok(conn)
 }
@@ -0,0 +1,783 @@
+//! Exercises the survey cache decorators over in-memory storage.  Runs against the Redis named by
+//! `CACHE_URL` when it's set, and an in-process store otherwise.  Every test uses fresh survey ids
+//! and usernames, so a shared Redis is fine.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Barrier};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use domain_patterns::collections::Repository;
+use domain_patterns::models::Entity;
+use survey_manager_core::app_services::commands::{CreateSurveyCommand, CreateQuestionCommand, CreateChoiceCommand};
+use survey_manager_core::app_services::repository_contracts::SurveyDTOReadRepository;
+use survey_manager_core::dtos::{SurveyDTO, SurveyDTOs, ListViewSurveyDTO};
+use survey_manager_core::survey::Survey;
+use survey_manager_infra::Error;
+use survey_manager_infra::cache_repo_decorators::{
+    CacheStore, CacheEntry, CacheConfig, InMemoryCacheStore, RedisCacheStore, LocalCache,
+    GuardedCacheStore, CacheGuard, BreakerState,
+    RedisSurveyReadCacheRepository, RedisSurveyWriteCacheRepository, TieredSurveyReadRepository,
+    survey_key, author_listing_key, survey_miss_key, survey_eviction_keys, invalidation_channel, cache_metrics,
+    apply_invalidation, spawn_invalidation_listener,
+};
+
+// Stands in for MySQL.  The write and read sides share the same surveys, and reads are counted so
+// tests can tell a cache hit from a trip to storage.  Reads take `latency`, so concurrent ones
+// overlap.
+#[derive(Clone, Default)]
+struct FakeStorage {
+    surveys: Arc<Mutex<HashMap<String, Survey>>>,
+    reads: Arc<AtomicUsize>,
+    latency: Duration,
+}
+
+impl FakeStorage {
+    fn reads(&self) -> usize {
+        self.reads.load(Ordering::SeqCst)
+    }
+
+    fn read(&self) {
+        self.reads.fetch_add(1, Ordering::SeqCst);
+        thread::sleep(self.latency);
+    }
+}
+
+impl Repository<Survey> for FakeStorage {
+    type Error = Error;
+
+    fn insert(&mut self, entity: &Survey) -> Result<Option<String>, Error> {
+        let mut surveys = self.surveys.lock().unwrap();
+        if surveys.contains_key(&entity.id()) {
+            return Ok(None);
+        }
+        surveys.insert(entity.id(), entity.clone());
+        Ok(Some(entity.id()))
+    }
+
+    fn get(&mut self, key: &String) -> Result<Option<Survey>, Error> {
+        Ok(self.surveys.lock().unwrap().get(key).cloned())
+    }
+
+    fn get_paged(&mut self, _page_num: usize, _page_size: usize) -> Result<Option<Vec<Survey>>, Error> {
+        unimplemented!()
+    }
+
+    fn update(&mut self, entity: &Survey) -> Result<Option<String>, Error> {
+        let mut surveys = self.surveys.lock().unwrap();
+        if !surveys.contains_key(&entity.id()) {
+            return Ok(None);
+        }
+        surveys.insert(entity.id(), entity.clone());
+        Ok(Some(entity.id()))
+    }
+
+    fn remove(&mut self, key: &String) -> Result<Option<String>, Error> {
+        Ok(self.surveys.lock().unwrap().remove(key).map(|s| s.id()))
+    }
+}
+
+// Same visibility rules as the MySQL read repository.
+impl SurveyDTOReadRepository for FakeStorage {
+    type Error = Error;
+
+    fn get_survey_for_author(&mut self, id: &String, author: &String, organization_id: Option<&String>) -> Result<Option<SurveyDTO>, Error> {
+        self.read();
+        Ok(self.surveys.lock().unwrap().get(id)
+            .map(SurveyDTO::from)
+            .filter(|dto| dto.is_visible_to(author, organization_id)))
+    }
+
+    fn get_surveys_by_author(&mut self, author: &String) -> Result<Option<SurveyDTOs>, Error> {
+        self.read();
+        let surveys: Vec<ListViewSurveyDTO> = self.surveys.lock().unwrap().values()
+            .map(SurveyDTO::from)
+            .filter_map(|dto| {
+                let role = if &dto.author == author && dto.organization_id.is_none() {
+                    None
+                } else {
+                    Some(dto.collaborators.iter().find(|c| &c.username == author)?.role.clone())
+                };
+                Some(ListViewSurveyDTO { id: dto.id, author: dto.author, title: dto.title, category: dto.category, role })
+            })
+            .collect();
+
+        if surveys.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(SurveyDTOs { surveys }))
+    }
+
+    fn get_surveys_by_organization(&mut self, _organization_id: &String, _member: &String) -> Result<Option<SurveyDTOs>, Error> {
+        unimplemented!()
+    }
+}
+
+// The Redis tier of a reader, over the fake storage.
+type RedisReader = RedisSurveyReadCacheRepository<FakeStorage, Box<dyn CacheStore>>;
+
+// Everything a test needs, wired the way `generate` wires the real thing.
+struct Harness {
+    storage: FakeStorage,
+    memory: InMemoryCacheStore,
+    writes: RedisSurveyWriteCacheRepository<FakeStorage, Box<dyn CacheStore>>,
+    reads: RedisReader,
+}
+
+impl Harness {
+    fn new() -> Harness {
+        Harness::with(test_config(), Duration::from_millis(0))
+    }
+
+    fn with(config: CacheConfig, latency: Duration) -> Harness {
+        let storage = FakeStorage { latency, ..FakeStorage::default() };
+        let memory = InMemoryCacheStore::new();
+
+        Harness {
+            writes: RedisSurveyWriteCacheRepository::with_cache(storage.clone(), store(&memory), config.clone()),
+            reads: RedisSurveyReadCacheRepository::with_cache(storage.clone(), store(&memory), config),
+            storage,
+            memory,
+        }
+    }
+
+    // Another API node: a reader with its own in-process tier, over the same storage and Redis,
+    // and subscribed to invalidations.
+    fn node(&self, config: CacheConfig) -> (TieredSurveyReadRepository<RedisReader>, LocalCache) {
+        let local = LocalCache::new(&config);
+        subscribe(&self.memory, &local);
+        let redis_tier = RedisSurveyReadCacheRepository::with_cache(self.storage.clone(), store(&self.memory), config);
+        (TieredSurveyReadRepository::with_local(redis_tier, local.clone()), local)
+    }
+
+    // Another reader over the same storage and cache, for use on another thread.
+    fn reader(&self, config: CacheConfig) -> impl FnOnce() -> RedisReader {
+        let (storage, memory) = (self.storage.clone(), self.memory.clone());
+        move || RedisSurveyReadCacheRepository::with_cache(storage, store(&memory), config)
+    }
+
+    fn cached(&self, key: &str) -> Option<String> {
+        store(&self.memory).get(key).unwrap()
+    }
+}
+
+// A cache that can be taken down, counting the calls that reach it.
+#[derive(Clone)]
+struct Flaky {
+    memory: InMemoryCacheStore,
+    down: Arc<AtomicBool>,
+    calls: Arc<AtomicUsize>,
+}
+
+impl Flaky {
+    fn new(memory: &InMemoryCacheStore) -> Flaky {
+        Flaky {
+            memory: memory.clone(),
+            down: Arc::new(AtomicBool::new(false)),
+            calls: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    fn set_down(&self, down: bool) {
+        self.down.store(down, Ordering::SeqCst);
+    }
+
+    fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+
+    fn reach(&self) -> Result<Box<dyn CacheStore>, Error> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        if self.down.load(Ordering::SeqCst) {
+            return Err(Error::CacheUnavailable { reason: "taken down by the test".to_string() });
+        }
+        Ok(store(&self.memory))
+    }
+}
+
+impl CacheStore for Flaky {
+    fn get(&mut self, key: &str) -> Result<Option<String>, Error> {
+        self.reach()?.get(key)
+    }
+
+    fn set(&mut self, key: &str, value: &str, ttl: Duration) -> Result<(), Error> {
+        self.reach()?.set(key, value, ttl)
+    }
+
+    fn add(&mut self, key: &str, value: &str, ttl: Duration) -> Result<bool, Error> {
+        self.reach()?.add(key, value, ttl)
+    }
+
+    fn swap(&mut self, key: &str, current: &str, value: &str, ttl: Duration) -> Result<bool, Error> {
+        self.reach()?.swap(key, current, value, ttl)
+    }
+
+    fn delete(&mut self, keys: &[String]) -> Result<(), Error> {
+        self.reach()?.delete(keys)
+    }
+
+    fn publish(&mut self, channel: &str, message: &str) -> Result<(), Error> {
+        self.reach()?.publish(channel, message)
+    }
+}
+
+// Decorators over a cache the test can take down, behind their own breaker.
+struct Guarded {
+    cache: Flaky,
+    guard: Arc<CacheGuard>,
+    writes: RedisSurveyWriteCacheRepository<FakeStorage, GuardedCacheStore<Flaky>>,
+    reads: RedisSurveyReadCacheRepository<FakeStorage, GuardedCacheStore<Flaky>>,
+}
+
+impl Guarded {
+    fn new(threshold: u32, cooldown: Duration) -> Guarded {
+        let storage = FakeStorage::default();
+        let cache = Flaky::new(&InMemoryCacheStore::new());
+        let guard = Arc::new(CacheGuard::new(threshold, cooldown));
+        let guarded = || GuardedCacheStore::new(cache.clone(), guard.clone());
+
+        Guarded {
+            writes: RedisSurveyWriteCacheRepository::with_cache(storage.clone(), guarded(), test_config()),
+            reads: RedisSurveyReadCacheRepository::with_cache(storage.clone(), guarded(), test_config()),
+            cache,
+            guard,
+        }
+    }
+}
+
+// No jitter, so lifetimes in tests are exact.
+fn test_config() -> CacheConfig {
+    CacheConfig {
+        jitter_percent: 0,
+        ..CacheConfig::default()
+    }
+}
+
+fn store(memory: &InMemoryCacheStore) -> Box<dyn CacheStore> {
+    match std::env::var("CACHE_URL") {
+        Ok(_) => Box::new(RedisCacheStore::new().unwrap()),
+        Err(_) => Box::new(memory.clone()),
+    }
+}
+
+fn subscribe(memory: &InMemoryCacheStore, local: &LocalCache) {
+    let local = local.clone();
+    match std::env::var("CACHE_URL") {
+        Ok(_) => spawn_invalidation_listener(local).unwrap(),
+        Err(_) => memory.subscribe(&invalidation_channel(), move |message| apply_invalidation(&local, message)),
+    }
+}
+
+// Invalidations over Redis arrive asynchronously, so give them a moment.
+fn eventually<F: FnMut() -> bool>(mut check: F) -> bool {
+    for _ in 0..50 {
+        if check() {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    false
+}
+
+// Unique per call, so tests sharing a Redis never see each other's listings.
+fn username(prefix: &str) -> String {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let now = chrono::Utc::now();
+    format!("{}_{}_{}", prefix, now.timestamp_nanos() % 1_000_000_000_000, COUNTER.fetch_add(1, Ordering::SeqCst))
+}
+
+fn new_survey(author: &str) -> Survey {
+    Survey::new(&CreateSurveyCommand {
+        author: author.to_string(),
+        title: "favourite albums".to_string(),
+        description: "which albums do you keep coming back to".to_string(),
+        category: "music".to_string(),
+        questions: vec![CreateQuestionCommand {
+            question_type: "ranked".to_string(),
+            title: "pick your favourite".to_string(),
+            choices: vec![CreateChoiceCommand {
+                content: None,
+                content_type: "youtube".to_string(),
+                title: "first choice".to_string(),
+            }],
+        }],
+    }).unwrap()
+}
+
+#[test]
+fn surveys_are_cached_under_their_id() {
+    let mut h = Harness::new();
+    let author = username("owner");
+    let survey = new_survey(&author);
+
+    h.writes.insert(&survey).unwrap();
+
+    let cached: CacheEntry<SurveyDTO> = serde_json::from_str(&h.cached(&survey_key(&survey.id())).unwrap()).unwrap();
+    assert_eq!(cached.value.unwrap().id, survey.id());
+    assert!(h.cached(&author).is_none());
+}
+
+#[test]
+fn cache_hits_skip_storage() {
+    let mut h = Harness::new();
+    let author = username("owner");
+    let survey = new_survey(&author);
+    h.writes.insert(&survey).unwrap();
+
+    assert!(h.reads.get_survey_for_author(&survey.id(), &author, None).unwrap().is_some());
+    assert!(h.reads.get_survey_for_author(&survey.id(), &author, None).unwrap().is_some());
+
+    assert_eq!(h.storage.reads(), 0);
+}
+
+#[test]
+fn cached_surveys_are_not_served_to_other_authors() {
+    let mut h = Harness::new();
+    let author = username("owner");
+    let stranger = username("stranger");
+    let survey = new_survey(&author);
+    h.writes.insert(&survey).unwrap();
+
+    // Warm the cache as the author, then ask as someone else.
+    h.reads.get_survey_for_author(&survey.id(), &author, None).unwrap();
+    let found = h.reads.get_survey_for_author(&survey.id(), &stranger, None).unwrap();
+
+    assert!(found.is_none());
+    // A stranger claiming some organization gets nowhere either.
+    let org_id = "00000000-0000-0000-0000-000000000000".to_string();
+    assert!(h.reads.get_survey_for_author(&survey.id(), &stranger, Some(&org_id)).unwrap().is_none());
+}
+
+#[test]
+fn sharing_and_revoking_reach_collaborators_through_the_cache() {
+    let mut h = Harness::new();
+    let author = username("owner");
+    let collaborator = username("collab");
+    let mut survey = new_survey(&author);
+    h.writes.insert(&survey).unwrap();
+
+    // Cache the collaborator's empty-handed view first, so a stale copy would show.
+    assert!(h.reads.get_survey_for_author(&survey.id(), &collaborator, None).unwrap().is_none());
+    assert!(h.reads.get_surveys_by_author(&collaborator).unwrap().is_none());
+
+    survey.share(&collaborator, &"viewer".to_string()).unwrap();
+    h.writes.update(&survey).unwrap();
+
+    assert!(h.reads.get_survey_for_author(&survey.id(), &collaborator, None).unwrap().is_some());
+    let listing = h.reads.get_surveys_by_author(&collaborator).unwrap().unwrap();
+    assert_eq!(listing.surveys.len(), 1);
+    assert_eq!(listing.surveys[0].role.as_deref(), Some("viewer"));
+
+    survey.revoke_access(&collaborator).unwrap();
+    h.writes.update(&survey).unwrap();
+
+    assert!(h.reads.get_survey_for_author(&survey.id(), &collaborator, None).unwrap().is_none());
+    assert!(h.reads.get_surveys_by_author(&collaborator).unwrap().is_none());
+}
+
+#[test]
+fn transfer_clears_the_previous_owners_listing() {
+    let mut h = Harness::new();
+    let author = username("owner");
+    let new_owner = username("heir");
+    let mut survey = new_survey(&author);
+    h.writes.insert(&survey).unwrap();
+    assert!(h.reads.get_surveys_by_author(&author).unwrap().is_some());
+
+    survey.transfer_to_user(&new_owner).unwrap();
+    h.writes.update(&survey).unwrap();
+
+    assert!(h.reads.get_surveys_by_author(&author).unwrap().is_none());
+    assert!(h.reads.get_survey_for_author(&survey.id(), &author, None).unwrap().is_none());
+    assert!(h.reads.get_survey_for_author(&survey.id(), &new_owner, None).unwrap().is_some());
+}
+
+#[test]
+fn remove_evicts_the_survey_and_every_listing() {
+    let mut h = Harness::new();
+    let author = username("owner");
+    let collaborator = username("collab");
+    let mut survey = new_survey(&author);
+    survey.share(&collaborator, &"editor".to_string()).unwrap();
+    h.writes.insert(&survey).unwrap();
+    h.reads.get_surveys_by_author(&author).unwrap();
+    h.reads.get_surveys_by_author(&collaborator).unwrap();
+
+    h.writes.remove(&survey.id()).unwrap();
+
+    assert!(h.cached(&survey_key(&survey.id())).is_none());
+    assert!(h.cached(&author_listing_key(&author)).is_none());
+    assert!(h.cached(&author_listing_key(&collaborator)).is_none());
+    assert!(h.reads.get_survey_for_author(&survey.id(), &author, None).unwrap().is_none());
+}
+
+#[test]
+fn deferred_writes_wait_for_a_flush() {
+    let mut h = Harness::new();
+    let author = username("owner");
+    let mut survey = new_survey(&author);
+    h.writes.insert(&survey).unwrap();
+    h.reads.get_surveys_by_author(&author).unwrap();
+
+    let mut deferred = RedisSurveyWriteCacheRepository::with_cache(h.storage.clone(), store(&h.memory), test_config())
+        .deferred();
+    survey.transfer_to_user(&username("heir")).unwrap();
+    deferred.update(&survey).unwrap();
+
+    // Nothing is evicted or replaced until the unit of work would have committed.
+    assert!(h.cached(&author_listing_key(&author)).is_some());
+    let cached: CacheEntry<SurveyDTO> = serde_json::from_str(&h.cached(&survey_key(&survey.id())).unwrap()).unwrap();
+    assert_eq!(cached.value.unwrap().author, author);
+
+    deferred.flush();
+
+    assert!(h.cached(&author_listing_key(&author)).is_none());
+    assert!(h.reads.get_survey_for_author(&survey.id(), &author, None).unwrap().is_none());
+}
+
+#[test]
+fn dropping_deferred_writes_leaves_the_cache_alone() {
+    let mut h = Harness::new();
+    let author = username("owner");
+    let survey = new_survey(&author);
+
+    let mut deferred = RedisSurveyWriteCacheRepository::with_cache(h.storage.clone(), store(&h.memory), test_config())
+        .deferred();
+    deferred.insert(&survey).unwrap();
+    drop(deferred);
+
+    assert!(h.cached(&survey_key(&survey.id())).is_none());
+    assert!(h.reads.get_survey_for_author(&survey.id(), &author, None).unwrap().is_some());
+}
+
+#[test]
+fn fills_never_replace_a_newer_write() {
+    let h = Harness::new();
+    let key = survey_key(&username("race"));
+    let mut cache = store(&h.memory);
+
+    cache.set(&key, "newer", Duration::from_secs(60)).unwrap();
+
+    assert!(!cache.add(&key, "older", Duration::from_secs(60)).unwrap());
+    assert_eq!(cache.get(&key).unwrap().as_deref(), Some("newer"));
+    cache.delete(&[key]).unwrap();
+}
+
+#[test]
+fn corrupt_entries_are_treated_as_misses() {
+    let mut h = Harness::new();
+    let author = username("owner");
+    let survey = new_survey(&author);
+    h.writes.insert(&survey).unwrap();
+    store(&h.memory).set(&survey_key(&survey.id()), "{not json", Duration::from_secs(60)).unwrap();
+
+    assert!(h.reads.get_survey_for_author(&survey.id(), &author, None).unwrap().is_some());
+    assert_eq!(h.storage.reads(), 1);
+    // And the entry was replaced, so the next read is a hit again.
+    h.reads.get_survey_for_author(&survey.id(), &author, None).unwrap();
+    assert_eq!(h.storage.reads(), 1);
+}
+
+#[test]
+fn entries_expire_after_their_ttl() {
+    let config = CacheConfig { survey_ttl: Duration::from_millis(50), ..test_config() };
+    let mut h = Harness::with(config, Duration::from_millis(0));
+    let author = username("owner");
+    let survey = new_survey(&author);
+    h.writes.insert(&survey).unwrap();
+
+    h.reads.get_survey_for_author(&survey.id(), &author, None).unwrap();
+    assert_eq!(h.storage.reads(), 0);
+
+    thread::sleep(Duration::from_millis(100));
+    assert!(h.reads.get_survey_for_author(&survey.id(), &author, None).unwrap().is_some());
+    assert_eq!(h.storage.reads(), 1);
+}
+
+#[test]
+fn missing_surveys_are_remembered() {
+    let mut h = Harness::new();
+    let author = username("owner");
+    let id = username("missing");
+
+    assert!(h.reads.get_survey_for_author(&id, &author, None).unwrap().is_none());
+    assert!(h.reads.get_survey_for_author(&id, &author, None).unwrap().is_none());
+
+    assert_eq!(h.storage.reads(), 1);
+}
+
+#[test]
+fn evicting_a_survey_forgets_misses_remembered_for_it() {
+    let mut h = Harness::new();
+    let author = username("owner");
+    let id = username("missing");
+    h.reads.get_survey_for_author(&id, &author, None).unwrap();
+    assert!(h.cached(&survey_miss_key(&id, &author)).is_some());
+
+    // What survey-doctor clears when it quarantines a survey.
+    store(&h.memory).delete(&survey_eviction_keys(&id, std::iter::once(&author))).unwrap();
+
+    h.reads.get_survey_for_author(&id, &author, None).unwrap();
+    assert_eq!(h.storage.reads(), 2);
+}
+
+#[test]
+fn misses_for_organization_members_are_not_remembered() {
+    let mut h = Harness::new();
+    let member = username("member");
+    let id = username("missing");
+    let org_id = "00000000-0000-0000-0000-000000000000".to_string();
+
+    h.reads.get_survey_for_author(&id, &member, Some(&org_id)).unwrap();
+    h.reads.get_survey_for_author(&id, &member, Some(&org_id)).unwrap();
+
+    assert_eq!(h.storage.reads(), 2);
+}
+
+#[test]
+fn empty_listings_are_remembered_until_a_write() {
+    let mut h = Harness::new();
+    let author = username("owner");
+
+    assert!(h.reads.get_surveys_by_author(&author).unwrap().is_none());
+    assert!(h.reads.get_surveys_by_author(&author).unwrap().is_none());
+    assert_eq!(h.storage.reads(), 1);
+
+    h.writes.insert(&new_survey(&author)).unwrap();
+
+    assert_eq!(h.reads.get_surveys_by_author(&author).unwrap().unwrap().surveys.len(), 1);
+}
+
+#[test]
+fn concurrent_misses_go_to_storage_once() {
+    let h = Harness::with(test_config(), Duration::from_millis(100));
+    let author = username("owner");
+    let survey = new_survey(&author);
+    let id = survey.id();
+    h.storage.surveys.lock().unwrap().insert(id.clone(), survey);
+
+    let barrier = Arc::new(Barrier::new(8));
+    let readers: Vec<_> = (0..8).map(|_| {
+        let (make_reader, barrier) = (h.reader(test_config()), barrier.clone());
+        let (id, author) = (id.clone(), author.clone());
+        thread::spawn(move || {
+            let mut reader = make_reader();
+            barrier.wait();
+            reader.get_survey_for_author(&id, &author, None).unwrap().is_some()
+        })
+    }).collect();
+
+    for reader in readers {
+        assert!(reader.join().unwrap());
+    }
+    assert_eq!(h.storage.reads(), 1);
+}
+
+#[test]
+fn stale_entries_are_served_while_one_caller_reloads() {
+    let config = CacheConfig {
+        survey_ttl: Duration::from_millis(50),
+        stale_ttl: Duration::from_secs(60),
+        ..test_config()
+    };
+    let mut h = Harness::with(config.clone(), Duration::from_millis(300));
+    let author = username("owner");
+    let survey = new_survey(&author);
+    h.writes.insert(&survey).unwrap();
+    thread::sleep(Duration::from_millis(100));
+    let stale_hits = cache_metrics().surveys.stale_hits;
+
+    // The first reader finds the entry stale and reloads it, which takes a while.
+    let make_reader = h.reader(config);
+    let (id, reloader_author) = (survey.id(), author.clone());
+    let reloader = thread::spawn(move || {
+        make_reader().get_survey_for_author(&id, &reloader_author, None).unwrap().is_some()
+    });
+    thread::sleep(Duration::from_millis(50));
+
+    // Meanwhile everyone else gets the stale copy without waiting.
+    let started = Instant::now();
+    assert!(h.reads.get_survey_for_author(&survey.id(), &author, None).unwrap().is_some());
+    assert!(started.elapsed() < Duration::from_millis(200));
+    assert!(cache_metrics().surveys.stale_hits > stale_hits);
+
+    assert!(reloader.join().unwrap());
+    assert_eq!(h.storage.reads(), 1);
+
+    // And once reloaded, the entry is fresh again.
+    h.reads.get_survey_for_author(&survey.id(), &author, None).unwrap();
+    assert_eq!(h.storage.reads(), 1);
+}
+
+#[test]
+fn local_hits_skip_redis() {
+    let mut h = Harness::new();
+    let (mut node, _) = h.node(test_config());
+    let author = username("owner");
+    let survey = new_survey(&author);
+    h.writes.insert(&survey).unwrap();
+
+    assert!(node.get_survey_for_author(&survey.id(), &author, None).unwrap().is_some());
+    // Gone from Redis without an invalidation, so only the in-process copy can answer.
+    store(&h.memory).delete(&[survey_key(&survey.id())]).unwrap();
+
+    assert!(node.get_survey_for_author(&survey.id(), &author, None).unwrap().is_some());
+    assert_eq!(h.storage.reads(), 0);
+}
+
+#[test]
+fn local_hits_are_not_served_to_other_authors() {
+    let mut h = Harness::new();
+    let (mut node, _) = h.node(test_config());
+    let author = username("owner");
+    let stranger = username("stranger");
+    let survey = new_survey(&author);
+    h.writes.insert(&survey).unwrap();
+
+    node.get_survey_for_author(&survey.id(), &author, None).unwrap();
+
+    assert!(node.get_survey_for_author(&survey.id(), &stranger, None).unwrap().is_none());
+}
+
+#[test]
+fn writes_on_one_node_evict_every_other_node() {
+    let mut h = Harness::new();
+    let (mut node, _) = h.node(test_config());
+    let author = username("owner");
+    let collaborator = username("collab");
+    let mut survey = new_survey(&author);
+    survey.share(&collaborator, &"viewer".to_string()).unwrap();
+    h.writes.insert(&survey).unwrap();
+    assert!(node.get_survey_for_author(&survey.id(), &collaborator, None).unwrap().is_some());
+    assert!(node.get_surveys_by_author(&collaborator).unwrap().is_some());
+
+    survey.revoke_access(&collaborator).unwrap();
+    h.writes.update(&survey).unwrap();
+
+    assert!(eventually(|| node.get_survey_for_author(&survey.id(), &collaborator, None).unwrap().is_none()));
+    assert!(eventually(|| node.get_surveys_by_author(&collaborator).unwrap().is_none()));
+}
+
+#[test]
+fn local_entries_expire() {
+    let config = CacheConfig { local_ttl: Duration::from_millis(50), ..test_config() };
+    let mut h = Harness::new();
+    let (mut node, local) = h.node(config);
+    let author = username("owner");
+    let survey = new_survey(&author);
+    h.writes.insert(&survey).unwrap();
+
+    node.get_survey_for_author(&survey.id(), &author, None).unwrap();
+    assert!(local.survey(&survey_key(&survey.id())).is_some());
+
+    thread::sleep(Duration::from_millis(100));
+    assert!(local.survey(&survey_key(&survey.id())).is_none());
+}
+
+#[test]
+fn local_tier_drops_the_least_recently_used() {
+    let local = LocalCache::new(&CacheConfig { local_capacity: 2, ..test_config() });
+    let keys: Vec<String> = (0..3).map(|_| survey_key(&username("lru"))).collect();
+    let dto = SurveyDTO::from(&new_survey(&username("owner")));
+
+    local.fill_survey(&keys[0], dto.clone(), local.generation());
+    local.fill_survey(&keys[1], dto.clone(), local.generation());
+    local.survey(&keys[0]);
+    local.fill_survey(&keys[2], dto, local.generation());
+
+    assert!(local.survey(&keys[0]).is_some());
+    assert!(local.survey(&keys[1]).is_none());
+    assert!(local.survey(&keys[2]).is_some());
+}
+
+#[test]
+fn local_fills_racing_an_eviction_are_dropped() {
+    let local = LocalCache::new(&test_config());
+    let key = survey_key(&username("race"));
+    let dto = SurveyDTO::from(&new_survey(&username("owner")));
+
+    let generation = local.generation();
+    local.evict(std::slice::from_ref(&key));
+    local.fill_survey(&key, dto, generation);
+
+    assert!(local.survey(&key).is_none());
+}
+
+#[test]
+fn reads_and_writes_carry_on_while_the_cache_is_down() {
+    let mut g = Guarded::new(5, Duration::from_secs(60));
+    let author = username("owner");
+    let survey = new_survey(&author);
+    g.cache.set_down(true);
+
+    assert!(g.writes.insert(&survey).unwrap().is_some());
+    assert!(g.reads.get_survey_for_author(&survey.id(), &author, None).unwrap().is_some());
+    assert!(g.reads.get_surveys_by_author(&author).unwrap().is_some());
+}
+
+#[test]
+fn the_breaker_bypasses_a_failing_cache_and_probes_to_recover() {
+    let mut g = Guarded::new(2, Duration::from_millis(50));
+    let author = username("owner");
+    let survey = new_survey(&author);
+    g.writes.insert(&survey).unwrap();
+    g.cache.set_down(true);
+
+    // The first read's two lookups both fail, which opens the breaker.
+    g.reads.get_survey_for_author(&survey.id(), &author, None).unwrap();
+    assert_eq!(g.guard.state(), BreakerState::Open);
+
+    let calls = g.cache.calls();
+    assert!(g.reads.get_survey_for_author(&survey.id(), &author, None).unwrap().is_some());
+    assert_eq!(g.cache.calls(), calls);
+
+    // A probe that fails starts another cooldown.
+    thread::sleep(Duration::from_millis(100));
+    g.reads.get_survey_for_author(&survey.id(), &author, None).unwrap();
+    assert_eq!(g.guard.state(), BreakerState::Open);
+
+    g.cache.set_down(false);
+    thread::sleep(Duration::from_millis(100));
+    g.reads.get_survey_for_author(&survey.id(), &author, None).unwrap();
+    assert_eq!(g.guard.state(), BreakerState::Closed);
+}
+
+#[test]
+fn invalidations_missed_while_down_are_made_before_the_cache_is_read_again() {
+    let mut g = Guarded::new(5, Duration::from_secs(60));
+    let author = username("owner");
+    let collaborator = username("collab");
+    let mut survey = new_survey(&author);
+    survey.share(&collaborator, &"viewer".to_string()).unwrap();
+    g.writes.insert(&survey).unwrap();
+    assert!(g.reads.get_survey_for_author(&survey.id(), &collaborator, None).unwrap().is_some());
+    assert!(g.reads.get_surveys_by_author(&collaborator).unwrap().is_some());
+
+    g.cache.set_down(true);
+    survey.revoke_access(&collaborator).unwrap();
+    g.writes.update(&survey).unwrap();
+    assert!(g.guard.pending() > 0);
+
+    g.cache.set_down(false);
+
+    assert!(g.reads.get_survey_for_author(&survey.id(), &collaborator, None).unwrap().is_none());
+    assert!(g.reads.get_surveys_by_author(&collaborator).unwrap().is_none());
+    assert_eq!(g.guard.pending(), 0);
+    assert_eq!(g.guard.state(), BreakerState::Closed);
+}
+
+#[test]
+fn a_suspended_local_tier_keeps_nothing() {
+    let local = LocalCache::new(&test_config());
+    let key = survey_key(&username("suspended"));
+    let dto = SurveyDTO::from(&new_survey(&username("owner")));
+    local.fill_survey(&key, dto.clone(), local.generation());
+
+    local.suspend();
+    assert!(local.survey(&key).is_none());
+    local.fill_survey(&key, dto.clone(), local.generation());
+    assert!(local.survey(&key).is_none());
+
+    local.resume();
+    local.fill_survey(&key, dto, local.generation());
+    assert!(local.survey(&key).is_some());
+}
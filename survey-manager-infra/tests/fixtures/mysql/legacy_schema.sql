@@ -1,4 +1,4 @@
-USE survey-manager;
+-- init/schema.sql as it shipped before migrations, without its USE so it can run in any database.
 
 CREATE TABLE survey (
 	id VARCHAR(64) PRIMARY KEY,
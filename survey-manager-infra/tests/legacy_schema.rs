@@ -0,0 +1,80 @@
+//! Checks a database created by the init/schema.sql that shipped before migrations is adopted and
+//! brought up to date.  The migrating test is ignored by default, run it with
+//! `cargo test -- --ignored`.  It works in a scratch database on the server `DATABASE_URL` names,
+//! so that user needs to be allowed to create and drop databases.  Runs in a binary of its own,
+//! since it points `DATABASE_URL` at the scratch database for the whole process.
+use std::sync::atomic::{AtomicUsize, Ordering};
+use survey_manager_infra::mysql_repos::{mysql_migrations, statements, MigrationState, MysqlMigrator};
+
+const LEGACY_SCHEMA: &str = include_str!("fixtures/mysql/legacy_schema.sql");
+
+#[test]
+fn the_first_migration_is_the_legacy_schema() {
+    assert_eq!(mysql_migrations()[0].up_statements(), statements(LEGACY_SCHEMA));
+}
+
+#[test]
+#[ignore]
+fn legacy_databases_are_adopted_and_migrated() {
+    let db = ScratchDatabase::new();
+    let mut conn = db.pool.get_conn().unwrap();
+    for statement in statements(LEGACY_SCHEMA) {
+        conn.query(statement).unwrap();
+    }
+    conn.prep_exec(
+        "INSERT INTO survey (id, version, author, title, category, created_on, survey_data) \
+            VALUES ('legacy_survey', 1, 'legacy_author', 'Legacy', 'legacy', 0, '{}')",
+        ()
+    ).unwrap();
+
+    std::env::set_var("DATABASE_URL", &db.url);
+    let mut migrator = MysqlMigrator::new().unwrap();
+
+    // The legacy table is taken as migration 1, and everything after it is applied on top.
+    let after_the_first: Vec<u32> = mysql_migrations().iter().skip(1).map(|m| m.version).collect();
+    assert_eq!(migrator.up().unwrap(), after_the_first);
+    assert!(migrator.status().unwrap().iter().all(|s| s.state == MigrationState::Applied));
+
+    // Surveys already stored are kept, and pick up the columns added since.
+    let survey: Option<(String, Option<String>)> = conn.first_exec(
+        "SELECT author, organization_id FROM survey WHERE id = 'legacy_survey'",
+        ()
+    ).unwrap();
+    assert_eq!(survey, Some(("legacy_author".to_string(), None)));
+}
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// A database of its own next to the one `DATABASE_URL` names, dropped when the test is done.
+struct ScratchDatabase {
+    pool: mysql::Pool,
+    url: String,
+    name: String,
+}
+
+impl ScratchDatabase {
+    fn new() -> ScratchDatabase {
+        dotenv::dotenv().ok();
+        let url = std::env::var("DATABASE_URL")
+            .expect("DATABASE_URL must be set to run the database tests");
+        let server = &url[..url.rfind('/').expect("DATABASE_URL must name a database")];
+        let name = format!(
+            "legacy_{}_{}",
+            chrono::Utc::now().timestamp_nanos() % 1_000_000_000_000,
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        );
+
+        mysql::Pool::new(&url).unwrap().get_conn().unwrap()
+            .query(format!("CREATE DATABASE {}", name)).unwrap();
+        let url = format!("{}/{}", server, name);
+        ScratchDatabase { pool: mysql::Pool::new(&url).unwrap(), url, name }
+    }
+}
+
+impl Drop for ScratchDatabase {
+    fn drop(&mut self) {
+        if let Ok(mut conn) = self.pool.get_conn() {
+            let _ = conn.query(format!("DROP DATABASE {}", self.name));
+        }
+    }
+}
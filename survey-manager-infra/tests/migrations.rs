@@ -0,0 +1,163 @@
+//! Checks the migrations built into the crate, and the runner itself against the MySQL named by
+//! `DATABASE_URL`.  The runner tests are ignored by default, run them with `cargo test -- --ignored`.
+//! They use their own migrations and migrations table, with fresh names each time, so they never
+//! touch the real schema.
+use std::sync::atomic::{AtomicUsize, Ordering};
+use survey_manager_infra::Error;
+use survey_manager_infra::mysql_repos::{mysql_migrations, statements, Migration, MigrationState, MysqlMigrator};
+
+#[test]
+fn built_in_migrations_are_numbered_in_order_from_one() {
+    let versions: Vec<u32> = mysql_migrations().iter().map(|m| m.version).collect();
+    let expected: Vec<u32> = (1..=versions.len() as u32).collect();
+    assert_eq!(versions, expected);
+}
+
+#[test]
+fn built_in_migrations_can_be_taken_back() {
+    for migration in mysql_migrations() {
+        assert!(!migration.up_statements().is_empty(), "{} has no up statements", migration.name);
+        assert!(!migration.down_statements().is_empty(), "{} has no down statements", migration.name);
+    }
+}
+
+#[test]
+fn statements_skip_comments_and_split_at_semicolons() {
+    let script = "-- a table; with a note\nCREATE TABLE a (\n\t-- the id\n\tid INT\n);\n\nDROP TABLE b;\n";
+
+    assert_eq!(statements(script), vec!["CREATE TABLE a (\n\tid INT\n)".to_string(), "DROP TABLE b".to_string()]);
+}
+
+#[test]
+fn checksums_change_with_the_migration() {
+    let original = migration(1, "CREATE TABLE a (id INT);");
+    let edited = migration(1, "CREATE TABLE a (id BIGINT);");
+
+    assert_eq!(original.checksum(), migration(1, "CREATE TABLE a (id INT);").checksum());
+    assert_ne!(original.checksum(), edited.checksum());
+}
+
+#[test]
+#[ignore]
+fn pending_migrations_are_applied_once() {
+    let db = Scratch::new();
+    let mut migrator = db.migrator(db.migrations(2));
+
+    assert_eq!(migrator.up().unwrap(), vec![1, 2]);
+    assert_eq!(migrator.up().unwrap(), Vec::<u32>::new());
+    assert!(migrator.status().unwrap().iter().all(|s| s.state == MigrationState::Applied));
+
+    // A later build with a third migration only applies that one.
+    let mut migrator = db.migrator(db.migrations(3));
+    assert_eq!(migrator.up().unwrap(), vec![3]);
+    migrator.down_to(0).unwrap();
+}
+
+#[test]
+#[ignore]
+fn migrations_are_reverted_newest_first() {
+    let db = Scratch::new();
+    let mut migrator = db.migrator(db.migrations(3));
+    migrator.up().unwrap();
+
+    assert_eq!(migrator.down_to(1).unwrap(), vec![3, 2]);
+    let states: Vec<MigrationState> = migrator.status().unwrap().iter().map(|s| s.state).collect();
+    assert_eq!(states, vec![MigrationState::Applied, MigrationState::Pending, MigrationState::Pending]);
+
+    // Reverted migrations can be applied again.
+    assert_eq!(migrator.up().unwrap(), vec![2, 3]);
+    migrator.down_to(0).unwrap();
+}
+
+#[test]
+#[ignore]
+fn edited_migrations_are_refused() {
+    let db = Scratch::new();
+    let mut original = db.migrator(db.migrations(1));
+    original.up().unwrap();
+
+    let mut edited = db.migrations(2);
+    edited[0].up = "CREATE TABLE edited_after_the_fact (id INT)";
+    let mut migrator = db.migrator(edited);
+
+    assert_eq!(migrator.status().unwrap()[0].state, MigrationState::Modified);
+    match migrator.up() {
+        Err(Error::MigrationModified { version, .. }) => assert_eq!(version, 1),
+        other => panic!("expected the edit to be refused, got {:?}", other),
+    }
+    // Nothing else was applied.
+    assert_eq!(migrator.status().unwrap()[1].state, MigrationState::Pending);
+    original.down_to(0).unwrap();
+}
+
+#[test]
+#[ignore]
+fn migrations_from_newer_builds_are_left_alone() {
+    let db = Scratch::new();
+    let mut newer = db.migrator(db.migrations(2));
+    newer.up().unwrap();
+
+    let mut older = db.migrator(db.migrations(1));
+    assert_eq!(older.up().unwrap(), Vec::<u32>::new());
+    assert_eq!(older.status().unwrap()[1].state, MigrationState::Unknown);
+    match older.down_to(0) {
+        Err(Error::UnknownMigration { version }) => assert_eq!(version, 2),
+        other => panic!("expected the revert to be refused, got {:?}", other),
+    }
+    newer.down_to(0).unwrap();
+}
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// A migrations table and tables for the migrations to create, all unique to one test.
+struct Scratch {
+    pool: mysql::Pool,
+    prefix: String,
+}
+
+impl Scratch {
+    fn new() -> Scratch {
+        dotenv::dotenv().ok();
+        let url = std::env::var("DATABASE_URL")
+            .expect("DATABASE_URL must be set to run the database tests");
+        let prefix = format!(
+            "mt_{}_{}",
+            chrono::Utc::now().timestamp_nanos() % 1_000_000_000_000,
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        );
+        Scratch { pool: mysql::Pool::new(&url).unwrap(), prefix }
+    }
+
+    fn migrator(&self, migrations: Vec<Migration>) -> MysqlMigrator {
+        MysqlMigrator::with_migrations(self.pool.get_conn().unwrap(), migrations, &format!("{}_migrations", self.prefix))
+    }
+
+    // Migrations 1 to `count`, each creating a table of its own.
+    fn migrations(&self, count: u32) -> Vec<Migration> {
+        (1..=count)
+            .map(|version| Migration {
+                version,
+                name: "scratch_table",
+                up: leak(format!("CREATE TABLE {}_{} (id INT PRIMARY KEY);", self.prefix, version)),
+                down: leak(format!("DROP TABLE {}_{};", self.prefix, version)),
+            })
+            .collect()
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        if let Ok(mut conn) = self.pool.get_conn() {
+            let _ = conn.query(format!("DROP TABLE IF EXISTS {}_migrations", self.prefix));
+        }
+    }
+}
+
+fn migration(version: u32, up: &'static str) -> Migration {
+    Migration { version, name: "test", up, down: "" }
+}
+
+// Migrations hold `&'static str`s, like the ones built in.
+fn leak(sql: String) -> &'static str {
+    Box::leak(sql.into_boxed_str())
+}
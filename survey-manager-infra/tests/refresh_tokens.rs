@@ -0,0 +1,120 @@
+//! Checks refresh token rotation against the MySQL named by `DATABASE_URL`, which has to have the
+//! schema in place.  Ignored by default, run them with `cargo test -- --ignored`.  Every test
+//! registers a user of its own, so a shared database is fine.
+use std::sync::atomic::{AtomicUsize, Ordering};
+use domain_patterns::command::{Command, Handles};
+use survey_manager_core::Error as SMError;
+use survey_manager_core::app_services::accounts::{
+    AccountCommandsHandler, RegisterUserCommand, LoginCommand, RefreshSessionCommand, RefreshOutcome,
+    hash_refresh_token,
+};
+use survey_manager_core::app_services::repository_contracts::{RefreshTokenRepository, RevocationList};
+use survey_manager_core::app_services::token::{TokenService, TokenConfig, KeyConfig};
+use survey_manager_core::dtos::TokenPairDTO;
+use survey_manager_infra::mysql_repos::{MysqlUserRepository, MysqlRefreshTokenRepository, MysqlOrganizationRepository, mysql_pool};
+
+const PASSWORD: &str = "correct horse battery";
+
+fn check_database() {
+    dotenv::dotenv().ok();
+    if std::env::var("DATABASE_URL").is_err() {
+        panic!("DATABASE_URL must be set to run the database tests");
+    }
+}
+
+// Access tokens aren't logged out here, so revocations go nowhere.
+struct NoRevocations;
+
+impl RevocationList for NoRevocations {
+    type Error = std::fmt::Error;
+
+    fn revoke(&mut self, _jti: &String, _expires_at: i64) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn is_revoked(&mut self, _jti: &String) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+}
+
+fn token_service() -> TokenService {
+    TokenService::new(TokenConfig {
+        issuer: "https://surveys.test".to_string(),
+        audience: "survey-manager".to_string(),
+        ttl: 60,
+        refresh_ttl: 600,
+        leeway: 0,
+        signing_key: Some(KeyConfig {
+            kid: "test".to_string(),
+            algorithm: "HS256".to_string(),
+            secret: Some("a secret only the tests know".to_string()),
+            private_key_path: None,
+            public_key_path: None,
+        }),
+        verification_keys: vec![],
+        jwks: None,
+    }).unwrap()
+}
+
+type Handler<'a> = AccountCommandsHandler<'a, MysqlUserRepository, MysqlRefreshTokenRepository, NoRevocations, MysqlOrganizationRepository>;
+
+// The way the API runs account commands, each in a unit of work of its own.
+fn handle<'a, C, T>(service: &'a TokenService, cmd: C) -> Result<T, SMError> where
+    C: Command + Clone,
+    Handler<'a>: Handles<C, Result = Result<T, SMError>>,
+{
+    check_database();
+    mysql_pool().unwrap().in_unit_of_work(|unit| {
+        let conn = unit.connection();
+        AccountCommandsHandler::new(
+            MysqlUserRepository::with_connection(conn.clone()),
+            MysqlRefreshTokenRepository::with_connection(conn.clone()),
+            NoRevocations,
+            MysqlOrganizationRepository::with_connection(conn),
+            service,
+        ).handle(cmd.clone())
+    })
+}
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// Unique per call, so tests sharing a database never log in as each other.
+fn fresh_username() -> String {
+    let now = chrono::Utc::now();
+    format!("user_{}_{}", now.timestamp_nanos() % 1_000_000_000_000, COUNTER.fetch_add(1, Ordering::SeqCst))
+}
+
+fn login(service: &TokenService) -> TokenPairDTO {
+    let username = fresh_username();
+    handle::<_, String>(service, RegisterUserCommand { username: username.clone(), password: PASSWORD.to_string() }).unwrap();
+    handle(service, LoginCommand { username, password: PASSWORD.to_string(), organization: None }).unwrap()
+}
+
+fn refresh(service: &TokenService, pair: &TokenPairDTO) -> Result<RefreshOutcome, SMError> {
+    handle(service, RefreshSessionCommand { refresh_token: pair.refresh_token.clone() })
+}
+
+#[test]
+#[ignore]
+fn reusing_a_redeemed_token_revokes_its_whole_family() {
+    let service = token_service();
+    let first = login(&service);
+    let second = refresh(&service, &first).unwrap().into_pair().unwrap();
+    let third = refresh(&service, &second).unwrap().into_pair().unwrap();
+
+    match refresh(&service, &first) {
+        Ok(RefreshOutcome::Reused) => (),
+        _ => panic!("a replayed refresh token must be refused"),
+    }
+
+    // Read back outside the unit of work, so this only passes if the revocation was committed.
+    let mut refresh_tokens = MysqlRefreshTokenRepository::new().unwrap();
+    for pair in &[&first, &second, &third] {
+        let record = refresh_tokens.get(&hash_refresh_token(&pair.refresh_token)).unwrap().unwrap();
+        assert!(record.revoked);
+    }
+    match refresh(&service, &third) {
+        Err(SMError::NotAuthorized) => (),
+        _ => panic!("tokens in a revoked family must be refused"),
+    }
+}
@@ -0,0 +1,291 @@
+//! What every survey backend has to agree on.  The ones behind a database server, MySQL with both
+//! the json and the normalized tables and Postgres when built with `--features postgres`, are
+//! ignored by default.  Run them with `cargo test -- --ignored` with `DATABASE_URL` and
+//! `POSTGRES_URL` set, where they fail rather than pass quietly if the url is missing.  Each of
+//! their schemas has to be in place already.
+//!
+//! SQLite, with `--features sqlite`, always runs, each test on a fresh `:memory:` database.
+//!
+//! Every test uses fresh survey ids, usernames and organization ids, so a shared database is fine.
+use std::sync::atomic::{AtomicUsize, Ordering};
+use domain_patterns::collections::Repository;
+use domain_patterns::models::Entity;
+use survey_manager_core::app_services::commands::{CreateSurveyCommand, CreateQuestionCommand, CreateChoiceCommand, UpdateSurveyCommand, PatchQuestion, PatchChoice};
+use survey_manager_core::app_services::repository_contracts::SurveyDTOReadRepository;
+use survey_manager_core::dtos::{SurveyDTO, SurveyDTOs};
+use survey_manager_core::survey::Survey;
+use survey_manager_infra::survey_backends::{SurveyBackend, SurveyWriteRepository, SurveyDTOsRepository};
+#[cfg(feature = "sqlite")]
+use survey_manager_infra::sqlite_repos::{SqliteDatabase, SqliteSurveyWriteRepository, SqliteSurveyDTOsRepository};
+
+// Runs each check below as its own test, on the repositories `$repos` builds.  `$attr`s, such as
+// `#[ignore]`, go on every test.  When `$url` names an environment variable it has to be set.
+macro_rules! conformance_tests {
+    ($(#[$attr:meta])* $name:ident, $url:expr, $repos:expr) => {
+        mod $name {
+            use super::*;
+            conformance_tests!(@checks [$(#[$attr])*] $url, $repos;
+                inserted_surveys_load_back,
+                duplicate_inserts_store_nothing,
+                updates_replace_the_stored_survey,
+                question_and_choice_changes_load_back,
+                updating_a_missing_survey_does_nothing,
+                removed_surveys_are_gone,
+                surveys_are_only_visible_to_those_with_access,
+                author_listings_hold_personal_and_shared_surveys,
+                organization_listings_hold_owned_and_shared_surveys
+            );
+        }
+    };
+    (@checks $attrs:tt $url:expr, $repos:expr; $($check:ident),*) => {
+        $(
+            conformance_tests!(@check $attrs $url, $repos, $check);
+        )*
+    };
+    (@check [$(#[$attr:meta])*] $url:expr, $repos:expr, $check:ident) => {
+        #[test]
+        $(#[$attr])*
+        fn $check() {
+            dotenv::dotenv().ok();
+            let url: Option<&str> = $url;
+            if let Some(url) = url {
+                if std::env::var(url).is_err() {
+                    panic!("{} must be set to run the database tests", url);
+                }
+            }
+            let (mut writes, mut reads): (SurveyWriteRepository, SurveyDTOsRepository) = $repos;
+            super::$check(&mut writes, &mut reads);
+        }
+    };
+}
+
+conformance_tests!(#[ignore] mysql, Some("DATABASE_URL"), repositories(SurveyBackend::Mysql));
+
+conformance_tests!(#[ignore] mysql_normalized, Some("DATABASE_URL"), repositories(SurveyBackend::MysqlNormalized));
+
+#[cfg(feature = "postgres")]
+conformance_tests!(#[ignore] postgres, Some("POSTGRES_URL"), repositories(SurveyBackend::Postgres));
+
+#[cfg(feature = "sqlite")]
+conformance_tests!(sqlite, None, {
+    let db = SqliteDatabase::in_memory().unwrap();
+    (
+        SurveyWriteRepository::Sqlite(SqliteSurveyWriteRepository::with_database(db.clone())),
+        SurveyDTOsRepository::Sqlite(SqliteSurveyDTOsRepository::with_database(db)),
+    )
+});
+
+fn repositories(backend: SurveyBackend) -> (SurveyWriteRepository, SurveyDTOsRepository) {
+    (backend.write_repository().unwrap(), backend.read_repository().unwrap())
+}
+
+fn inserted_surveys_load_back(writes: &mut SurveyWriteRepository, _reads: &mut SurveyDTOsRepository) {
+    let survey = new_survey(&username("owner"));
+
+    assert_eq!(writes.insert(&survey).unwrap(), Some(survey.id()));
+
+    let loaded = SurveyDTO::from(&writes.get(&survey.id()).unwrap().unwrap());
+    let stored = SurveyDTO::from(&survey);
+    assert_eq!(serde_json::to_value(&loaded).unwrap(), serde_json::to_value(&stored).unwrap());
+    writes.remove(&survey.id()).unwrap();
+}
+
+fn duplicate_inserts_store_nothing(writes: &mut SurveyWriteRepository, _reads: &mut SurveyDTOsRepository) {
+    let survey = new_survey(&username("owner"));
+    writes.insert(&survey).unwrap();
+
+    assert_eq!(writes.insert(&survey).unwrap(), None);
+    writes.remove(&survey.id()).unwrap();
+}
+
+fn updates_replace_the_stored_survey(writes: &mut SurveyWriteRepository, _reads: &mut SurveyDTOsRepository) {
+    let mut survey = new_survey(&username("owner"));
+    writes.insert(&survey).unwrap();
+
+    let collaborator = username("collab");
+    survey.share(&collaborator, &"viewer".to_string()).unwrap();
+    assert_eq!(writes.update(&survey).unwrap(), Some(survey.id()));
+
+    let loaded = SurveyDTO::from(&writes.get(&survey.id()).unwrap().unwrap());
+    assert_eq!(loaded.collaborators.len(), 1);
+    assert_eq!(loaded.collaborators[0].username, collaborator);
+    writes.remove(&survey.id()).unwrap();
+}
+
+fn question_and_choice_changes_load_back(writes: &mut SurveyWriteRepository, _reads: &mut SurveyDTOsRepository) {
+    let author = username("owner");
+    let mut survey = new_survey(&author);
+    writes.insert(&survey).unwrap();
+
+    let question_id = SurveyDTO::from(&survey).questions[0].id.clone();
+    survey.try_update(UpdateSurveyCommand {
+        id: survey.id(),
+        author,
+        organization: None,
+        title: Some("favourite records".to_string()),
+        description: None,
+        category: None,
+        questions: Some(vec![
+            PatchQuestion {
+                id: Some(question_id),
+                question_type: Some("multiple_choice".to_string()),
+                title: None,
+                choices: Some(vec![patch_choice("second choice")]),
+            },
+            PatchQuestion {
+                id: None,
+                question_type: Some("ranked".to_string()),
+                title: Some("pick your least favourite".to_string()),
+                choices: Some(vec![patch_choice("only choice")]),
+            },
+        ]),
+    }).unwrap();
+    assert_eq!(writes.update(&survey).unwrap(), Some(survey.id()));
+
+    let loaded = SurveyDTO::from(&writes.get(&survey.id()).unwrap().unwrap());
+    let stored = SurveyDTO::from(&survey);
+    assert_eq!(serde_json::to_value(&loaded).unwrap(), serde_json::to_value(&stored).unwrap());
+    writes.remove(&survey.id()).unwrap();
+}
+
+fn updating_a_missing_survey_does_nothing(writes: &mut SurveyWriteRepository, _reads: &mut SurveyDTOsRepository) {
+    let survey = new_survey(&username("owner"));
+
+    assert_eq!(writes.update(&survey).unwrap(), None);
+    assert!(writes.get(&survey.id()).unwrap().is_none());
+}
+
+fn removed_surveys_are_gone(writes: &mut SurveyWriteRepository, _reads: &mut SurveyDTOsRepository) {
+    let survey = new_survey(&username("owner"));
+    writes.insert(&survey).unwrap();
+
+    assert_eq!(writes.remove(&survey.id()).unwrap(), Some(survey.id()));
+    assert!(writes.get(&survey.id()).unwrap().is_none());
+    assert_eq!(writes.remove(&survey.id()).unwrap(), None);
+}
+
+fn surveys_are_only_visible_to_those_with_access(writes: &mut SurveyWriteRepository, reads: &mut SurveyDTOsRepository) {
+    let author = username("owner");
+    let collaborator = username("collab");
+    let stranger = username("stranger");
+    let organization = organization_id();
+    let mut survey = new_survey(&author);
+    survey.share(&collaborator, &"editor".to_string()).unwrap();
+    writes.insert(&survey).unwrap();
+
+    assert!(reads.get_survey_for_author(&survey.id(), &author, None).unwrap().is_some());
+    assert!(reads.get_survey_for_author(&survey.id(), &collaborator, None).unwrap().is_some());
+    assert!(reads.get_survey_for_author(&survey.id(), &stranger, None).unwrap().is_none());
+    assert!(reads.get_survey_for_author(&survey.id(), &stranger, Some(&organization)).unwrap().is_none());
+
+    survey.transfer_to_organization(&organization).unwrap();
+    writes.update(&survey).unwrap();
+
+    assert!(reads.get_survey_for_author(&survey.id(), &stranger, Some(&organization)).unwrap().is_some());
+    writes.remove(&survey.id()).unwrap();
+}
+
+fn author_listings_hold_personal_and_shared_surveys(writes: &mut SurveyWriteRepository, reads: &mut SurveyDTOsRepository) {
+    let author = username("owner");
+    let other = username("other");
+    let organization = organization_id();
+
+    let personal = new_survey(&author);
+    let mut shared = new_survey(&other);
+    shared.share(&author, &"viewer".to_string()).unwrap();
+    let mut handed_over = new_survey(&author);
+    handed_over.transfer_to_organization(&organization).unwrap();
+    for survey in [&personal, &shared, &handed_over].iter() {
+        writes.insert(survey).unwrap();
+    }
+
+    let listing = reads.get_surveys_by_author(&author).unwrap().unwrap();
+    assert_eq!(roles(&listing), sorted(vec![
+        (personal.id(), None),
+        (shared.id(), Some("viewer".to_string())),
+    ]));
+    assert!(reads.get_surveys_by_author(&username("nobody")).unwrap().is_none());
+
+    for survey in [&personal, &shared, &handed_over].iter() {
+        writes.remove(&survey.id()).unwrap();
+    }
+}
+
+fn organization_listings_hold_owned_and_shared_surveys(writes: &mut SurveyWriteRepository, reads: &mut SurveyDTOsRepository) {
+    let member = username("member");
+    let organization = organization_id();
+
+    let mut owned = new_survey(&username("owner"));
+    owned.transfer_to_organization(&organization).unwrap();
+    let mut shared = new_survey(&username("owner"));
+    shared.share(&member, &"editor".to_string()).unwrap();
+    // Owned by the organization and shared with the member, so it must only be listed once.
+    let mut both = new_survey(&username("owner"));
+    both.transfer_to_organization(&organization).unwrap();
+    both.share(&member, &"viewer".to_string()).unwrap();
+    for survey in [&owned, &shared, &both].iter() {
+        writes.insert(survey).unwrap();
+    }
+
+    let listing = reads.get_surveys_by_organization(&organization, &member).unwrap().unwrap();
+    assert_eq!(roles(&listing), sorted(vec![
+        (owned.id(), None),
+        (shared.id(), Some("editor".to_string())),
+        (both.id(), None),
+    ]));
+
+    for survey in [&owned, &shared, &both].iter() {
+        writes.remove(&survey.id()).unwrap();
+    }
+}
+
+// Backends list surveys in no particular order, so listings are compared sorted.
+fn roles(listing: &SurveyDTOs) -> Vec<(String, Option<String>)> {
+    sorted(listing.surveys.iter().map(|s| (s.id.clone(), s.role.clone())).collect())
+}
+
+fn sorted<T: Ord>(mut items: Vec<T>) -> Vec<T> {
+    items.sort();
+    items
+}
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// Unique per call, so tests sharing a database never see each other's listings.
+fn username(prefix: &str) -> String {
+    let now = chrono::Utc::now();
+    format!("{}_{}_{}", prefix, now.timestamp_nanos() % 1_000_000_000_000, COUNTER.fetch_add(1, Ordering::SeqCst))
+}
+
+// Organization ids have to be uuids.
+fn organization_id() -> String {
+    let now = chrono::Utc::now();
+    format!("{:08x}-0000-4000-8000-{:012x}", COUNTER.fetch_add(1, Ordering::SeqCst), now.timestamp_nanos() as u64 % 0x1_0000_0000_0000)
+}
+
+fn patch_choice(title: &str) -> PatchChoice {
+    PatchChoice {
+        id: None,
+        content: Some(None),
+        content_type: Some("youtube".to_string()),
+        title: Some(title.to_string()),
+    }
+}
+
+fn new_survey(author: &str) -> Survey {
+    Survey::new(&CreateSurveyCommand {
+        author: author.to_string(),
+        title: "favourite albums".to_string(),
+        description: "which albums do you keep coming back to".to_string(),
+        category: "music".to_string(),
+        questions: vec![CreateQuestionCommand {
+            question_type: "ranked".to_string(),
+            title: "pick your favourite".to_string(),
+            choices: vec![CreateChoiceCommand {
+                content: None,
+                content_type: "youtube".to_string(),
+                title: "first choice".to_string(),
+            }],
+        }],
+    }).unwrap()
+}
@@ -0,0 +1,157 @@
+//! Checks stored responses against the MySQL named by `DATABASE_URL`, which has to have the
+//! schema in place.  Ignored by default, run them with `cargo test -- --ignored`.  Every test
+//! uses a fresh survey id, so a shared database is fine.
+use std::sync::atomic::{AtomicUsize, Ordering};
+use survey_manager_core::app_services::responses::{ResponseRecord, PartialResponse, Answer};
+use survey_manager_core::app_services::repository_contracts::{ResponseRepository, PartialResponseRepository, SurveySnapshotRepository};
+use survey_manager_core::app_services::commands::{CreateSurveyCommand, CreateQuestionCommand, CreateChoiceCommand};
+use survey_manager_core::survey::Survey;
+use survey_manager_core::dtos::SurveyDTO;
+use survey_manager_infra::mysql_repos::{MysqlResponseRepository, MysqlPartialResponseRepository, MysqlSurveySnapshotRepository};
+
+fn check_database() {
+    dotenv::dotenv().ok();
+    if std::env::var("DATABASE_URL").is_err() {
+        panic!("DATABASE_URL must be set to run the database tests");
+    }
+}
+
+fn repository() -> MysqlResponseRepository {
+    check_database();
+    MysqlResponseRepository::new().unwrap()
+}
+
+fn partial_repository() -> MysqlPartialResponseRepository {
+    check_database();
+    MysqlPartialResponseRepository::new().unwrap()
+}
+
+fn snapshot_repository() -> MysqlSurveySnapshotRepository {
+    check_database();
+    MysqlSurveySnapshotRepository::new().unwrap()
+}
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// Unique per call, so tests sharing a database never see each other's responses.
+fn fresh_id(prefix: &str) -> String {
+    let now = chrono::Utc::now();
+    format!("{}_{}_{}", prefix, now.timestamp_nanos() % 1_000_000_000_000, COUNTER.fetch_add(1, Ordering::SeqCst))
+}
+
+fn response(survey_id: &str, n: usize) -> ResponseRecord {
+    ResponseRecord {
+        id: fresh_id("response"),
+        survey_id: survey_id.to_string(),
+        survey_version: Some(n as u64),
+        respondent: Some(fresh_id("respondent")),
+        answers: vec![Answer { question_id: format!("question {}", n), choice_ids: vec![format!("choice {}", n)] }],
+        submitted_on: 1_577_836_800 + n as i64,
+    }
+}
+
+#[test]
+#[ignore]
+fn responses_page_back_in_the_order_they_were_stored() {
+    let mut responses = repository();
+    let survey_id = fresh_id("survey");
+    let stored: Vec<ResponseRecord> = (0..5).map(|n| response(&survey_id, n)).collect();
+    for r in &stored {
+        responses.insert(r).unwrap();
+    }
+    // Another survey's responses never show up.
+    responses.insert(&response(&fresh_id("survey"), 9)).unwrap();
+
+    let first = responses.list(&survey_id, None, 2).unwrap();
+    let second = responses.list(&survey_id, Some(&first[1].id), 2).unwrap();
+    let last = responses.list(&survey_id, Some(&second[1].id), 2).unwrap();
+
+    let read: Vec<String> = first.iter().chain(&second).chain(&last).map(|r| r.id.clone()).collect();
+    assert_eq!(read, stored.iter().map(|r| r.id.clone()).collect::<Vec<_>>());
+    assert_eq!(last[0].answers, stored[4].answers);
+    assert_eq!(last[0].submitted_on, stored[4].submitted_on);
+    assert_eq!(last[0].survey_version, Some(4));
+}
+
+#[test]
+#[ignore]
+fn a_respondent_answers_a_survey_once() {
+    let mut responses = repository();
+    let survey_id = fresh_id("survey");
+    let first = response(&survey_id, 0);
+    let again = ResponseRecord { id: fresh_id("response"), ..first.clone() };
+    let elsewhere = ResponseRecord { id: fresh_id("response"), survey_id: fresh_id("survey"), ..first.clone() };
+
+    assert_eq!(responses.insert(&first).unwrap(), Some(first.id.clone()));
+    assert_eq!(responses.insert(&again).unwrap(), None);
+    // The same respondent can still answer other surveys.
+    assert!(responses.insert(&elsewhere).unwrap().is_some());
+    // Responses from before policies were enforced never clash.
+    let anonymous = |n| ResponseRecord { respondent: None, ..response(&survey_id, n) };
+    assert!(responses.insert(&anonymous(1)).unwrap().is_some());
+    assert!(responses.insert(&anonymous(2)).unwrap().is_some());
+}
+
+#[test]
+#[ignore]
+fn a_partial_is_saved_until_it_is_submitted() {
+    let mut partials = partial_repository();
+    let survey_id = fresh_id("survey");
+    let mut partial = PartialResponse {
+        token_hash: fresh_id("hash"),
+        survey_id: survey_id.clone(),
+        survey_version: 3,
+        respondent: fresh_id("respondent"),
+        answers: response(&survey_id, 0).answers,
+        started_on: 1_577_836_800,
+        expires_at: 1_577_840_400,
+        response_id: None,
+    };
+    partials.insert(&partial).unwrap();
+
+    partial.answers = response(&survey_id, 1).answers;
+    partial.expires_at += 60;
+    assert!(partials.update(&partial).unwrap());
+
+    let response_id = fresh_id("response");
+    assert!(partials.mark_submitted(&partial.token_hash, &response_id).unwrap());
+    // Only the first submission claims it, and it can't be saved to after.
+    assert!(!partials.mark_submitted(&partial.token_hash, &fresh_id("response")).unwrap());
+    assert!(!partials.update(&partial).unwrap());
+
+    let stored = partials.get(&partial.token_hash).unwrap().unwrap();
+    assert_eq!(stored.answers, partial.answers);
+    assert_eq!(stored.expires_at, partial.expires_at);
+    assert_eq!(stored.survey_version, 3);
+    assert_eq!(stored.response_id, Some(response_id));
+    assert_eq!(partials.list(&survey_id, None, 10).unwrap().len(), 1);
+}
+
+#[test]
+#[ignore]
+fn the_first_snapshot_of_a_version_stands() {
+    let mut snapshots = snapshot_repository();
+    let survey = Survey::new(&CreateSurveyCommand {
+        author: "test_author".to_string(),
+        title: "favourite albums".to_string(),
+        description: "which albums do you keep coming back to".to_string(),
+        category: "music".to_string(),
+        questions: vec![CreateQuestionCommand {
+            question_type: "ranked".to_string(),
+            title: "pick your favourite".to_string(),
+            choices: vec![CreateChoiceCommand {
+                content: None,
+                content_type: "youtube".to_string(),
+                title: "first choice".to_string(),
+            }],
+        }],
+    }).unwrap();
+    let definition = SurveyDTO { id: fresh_id("survey"), ..SurveyDTO::from(&survey) };
+    snapshots.save(&definition).unwrap();
+    snapshots.save(&SurveyDTO { title: "changed title".to_string(), ..definition.clone() }).unwrap();
+
+    let stored = snapshots.get(&definition.id, definition.version).unwrap().unwrap();
+    assert_eq!(stored.title, definition.title);
+    assert_eq!(stored.questions[0].choices[0].title, "first choice");
+    assert!(snapshots.get(&definition.id, definition.version + 1).unwrap().is_none());
+}
@@ -0,0 +1,128 @@
+//! Exercises the revoked token list over an in-process store, and what it does while the store
+//! behind it is down.
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use chrono::Utc;
+use survey_manager_core::app_services::repository_contracts::RevocationList;
+use survey_manager_infra::Error;
+use survey_manager_infra::cache_repo_decorators::{
+    CacheStore, InMemoryCacheStore, GuardedCacheStore, CacheGuard, BreakerState, RevocationFallback,
+};
+use survey_manager_infra::redis_stores::RedisRevocationList;
+
+// A store that's always down, counting the calls that reach it.
+#[derive(Clone, Default)]
+struct Down {
+    calls: Arc<AtomicUsize>,
+}
+
+impl Down {
+    fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+
+    fn fail<T>(&self) -> Result<T, Error> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        Err(Error::CacheUnavailable { reason: "taken down by the test".to_string() })
+    }
+}
+
+impl CacheStore for Down {
+    fn get(&mut self, _key: &str) -> Result<Option<String>, Error> {
+        self.fail()
+    }
+
+    fn set(&mut self, _key: &str, _value: &str, _ttl: Duration) -> Result<(), Error> {
+        self.fail()
+    }
+
+    fn add(&mut self, _key: &str, _value: &str, _ttl: Duration) -> Result<bool, Error> {
+        self.fail()
+    }
+
+    fn swap(&mut self, _key: &str, _current: &str, _value: &str, _ttl: Duration) -> Result<bool, Error> {
+        self.fail()
+    }
+
+    fn delete(&mut self, _keys: &[String]) -> Result<(), Error> {
+        self.fail()
+    }
+
+    fn publish(&mut self, _channel: &str, _message: &str) -> Result<(), Error> {
+        self.fail()
+    }
+}
+
+fn in_an_hour() -> i64 {
+    Utc::now().timestamp() + 3600
+}
+
+#[test]
+fn revoked_tokens_are_revoked_and_others_are_not() {
+    let mut revocations = RedisRevocationList::with_cache(InMemoryCacheStore::new());
+    revocations.revoke(&"revoked".to_string(), in_an_hour()).unwrap();
+
+    assert!(revocations.is_revoked(&"revoked".to_string()).unwrap());
+    assert!(!revocations.is_revoked(&"live".to_string()).unwrap());
+}
+
+#[test]
+fn revoking_twice_is_fine() {
+    let mut revocations = RedisRevocationList::with_cache(InMemoryCacheStore::new());
+    revocations.revoke(&"jti".to_string(), in_an_hour()).unwrap();
+    revocations.revoke(&"jti".to_string(), in_an_hour()).unwrap();
+
+    assert!(revocations.is_revoked(&"jti".to_string()).unwrap());
+}
+
+#[test]
+fn expired_tokens_are_not_stored() {
+    let mut revocations = RedisRevocationList::with_cache(InMemoryCacheStore::new());
+    revocations.revoke(&"expired".to_string(), Utc::now().timestamp() - 1).unwrap();
+
+    assert!(!revocations.is_revoked(&"expired".to_string()).unwrap());
+}
+
+#[test]
+fn a_found_answer_ignores_the_fallback() {
+    let mut revocations = RedisRevocationList::with_cache(InMemoryCacheStore::new());
+    revocations.revoke(&"revoked".to_string(), in_an_hour()).unwrap();
+
+    for &fallback in &[RevocationFallback::Reject, RevocationFallback::Allow] {
+        assert!(revocations.is_revoked_or(&"revoked".to_string(), fallback));
+        assert!(!revocations.is_revoked_or(&"live".to_string(), fallback));
+    }
+}
+
+#[test]
+fn an_unreachable_list_applies_the_fallback() {
+    let mut revocations = RedisRevocationList::with_cache(Down::default());
+
+    assert!(revocations.is_revoked(&"jti".to_string()).is_err());
+    assert!(revocations.is_revoked_or(&"jti".to_string(), RevocationFallback::Reject));
+    assert!(!revocations.is_revoked_or(&"jti".to_string(), RevocationFallback::Allow));
+}
+
+#[test]
+fn once_the_breaker_opens_lookups_stop_reaching_the_store() {
+    let down = Down::default();
+    let guard = Arc::new(CacheGuard::new(2, Duration::from_secs(60)));
+    let mut revocations = RedisRevocationList::with_cache(GuardedCacheStore::new(down.clone(), guard.clone()));
+
+    for _ in 0..5 {
+        assert!(revocations.is_revoked_or(&"jti".to_string(), RevocationFallback::Reject));
+    }
+
+    assert_eq!(guard.state(), BreakerState::Open);
+    assert_eq!(down.calls(), 2);
+}
+
+#[test]
+fn a_revocation_lost_to_an_outage_is_not_queued_for_deletion() {
+    let guard = Arc::new(CacheGuard::new(5, Duration::from_secs(60)));
+    let mut revocations = RedisRevocationList::with_cache(GuardedCacheStore::new(Down::default(), guard.clone()));
+
+    assert!(revocations.revoke(&"jti".to_string(), in_an_hour()).is_err());
+    assert_eq!(guard.pending(), 0);
+}
@@ -0,0 +1,143 @@
+//! Checks that `diff_survey` only touches the rows that changed, and orders changes so foreign keys
+//! hold while they're applied.
+use survey_manager_core::app_services::commands::{CreateSurveyCommand, CreateQuestionCommand, CreateChoiceCommand};
+use survey_manager_core::dtos::{SurveyDTO, CollaboratorDTO};
+use survey_manager_core::survey::Survey;
+use survey_manager_infra::mysql_repos::{diff_survey, SurveyChange};
+
+#[test]
+fn an_unchanged_survey_needs_nothing() {
+    let stored = new_survey();
+
+    assert!(diff_survey(&stored, &stored.clone()).is_empty());
+}
+
+#[test]
+fn only_changed_rows_are_written() {
+    let stored = new_survey();
+    let mut updated = stored.clone();
+    updated.version += 1;
+    updated.questions[1].title = "a different question".to_string();
+    updated.questions[0].choices[1].title = "a different choice".to_string();
+
+    assert_eq!(describe(&diff_survey(&stored, &updated)), vec![
+        "update survey".to_string(),
+        format!("update question {} at 1", updated.questions[1].id),
+        format!("update choice {} on {} at 1", updated.questions[0].choices[1].id, updated.questions[0].id),
+    ]);
+}
+
+#[test]
+fn reordering_updates_positions() {
+    let stored = new_survey();
+    let mut updated = stored.clone();
+    updated.questions.swap(0, 1);
+
+    assert_eq!(describe(&diff_survey(&stored, &updated)), vec![
+        format!("update question {} at 0", updated.questions[0].id),
+        format!("update question {} at 1", updated.questions[1].id),
+    ]);
+}
+
+#[test]
+fn choices_of_removed_questions_are_left_to_the_cascade() {
+    let stored = new_survey();
+    let mut updated = stored.clone();
+    updated.questions.remove(0);
+    updated.questions[0].choices.remove(0);
+
+    assert_eq!(describe(&diff_survey(&stored, &updated)), vec![
+        format!("update question {} at 0", stored.questions[1].id),
+        format!("update choice {} on {} at 0", stored.questions[1].choices[1].id, stored.questions[1].id),
+        format!("delete choice {}", stored.questions[1].choices[0].id),
+        format!("delete question {}", stored.questions[0].id),
+    ]);
+}
+
+#[test]
+fn choices_move_before_their_old_question_is_deleted() {
+    let stored = new_survey();
+    let mut updated = stored.clone();
+    let moved = updated.questions[0].choices.remove(0);
+    updated.questions[1].choices.push(moved.clone());
+    updated.questions.remove(0);
+
+    let changes = describe(&diff_survey(&stored, &updated));
+    let moved_at = changes.iter().position(|c| c == &format!("update choice {} on {} at 2", moved.id, updated.questions[0].id));
+    let deleted_at = changes.iter().position(|c| c == &format!("delete question {}", stored.questions[0].id));
+    assert!(moved_at.unwrap() < deleted_at.unwrap(), "{:?}", changes);
+}
+
+#[test]
+fn new_questions_are_inserted_before_their_choices() {
+    let stored = new_survey();
+    let mut updated = stored.clone();
+    let mut question = updated.questions[0].clone();
+    question.id = "new-question".to_string();
+    question.choices.truncate(1);
+    question.choices[0].id = "new-choice".to_string();
+    updated.questions.push(question);
+
+    assert_eq!(describe(&diff_survey(&stored, &updated)), vec![
+        "insert question new-question at 2".to_string(),
+        "insert choice new-choice on new-question at 0".to_string(),
+    ]);
+}
+
+#[test]
+fn collaborators_are_matched_by_username() {
+    let mut stored = new_survey();
+    stored.collaborators = vec![collaborator("kept", "viewer"), collaborator("promoted", "viewer"), collaborator("gone", "viewer")];
+    let mut updated = stored.clone();
+    updated.collaborators = vec![collaborator("kept", "viewer"), collaborator("promoted", "editor"), collaborator("added", "viewer")];
+
+    assert_eq!(describe(&diff_survey(&stored, &updated)), vec![
+        "update collaborator promoted".to_string(),
+        "insert collaborator added".to_string(),
+        "delete collaborator gone".to_string(),
+    ]);
+}
+
+fn describe(changes: &[SurveyChange]) -> Vec<String> {
+    changes.iter()
+        .map(|change| match change {
+            SurveyChange::Survey(_) => "update survey".to_string(),
+            SurveyChange::InsertQuestion { position, question } => format!("insert question {} at {}", question.id, position),
+            SurveyChange::UpdateQuestion { position, question } => format!("update question {} at {}", question.id, position),
+            SurveyChange::DeleteQuestion { id } => format!("delete question {}", id),
+            SurveyChange::InsertChoice { question_id, position, choice } => format!("insert choice {} on {} at {}", choice.id, question_id, position),
+            SurveyChange::UpdateChoice { question_id, position, choice } => format!("update choice {} on {} at {}", choice.id, question_id, position),
+            SurveyChange::DeleteChoice { id } => format!("delete choice {}", id),
+            SurveyChange::InsertCollaborator(c) => format!("insert collaborator {}", c.username),
+            SurveyChange::UpdateCollaborator(c) => format!("update collaborator {}", c.username),
+            SurveyChange::DeleteCollaborator { username } => format!("delete collaborator {}", username),
+        })
+        .collect()
+}
+
+fn collaborator(username: &str, role: &str) -> CollaboratorDTO {
+    CollaboratorDTO { username: username.to_string(), role: role.to_string() }
+}
+
+// Two questions with two choices each.
+fn new_survey() -> SurveyDTO {
+    let question = |title: &str| CreateQuestionCommand {
+        question_type: "ranked".to_string(),
+        title: title.to_string(),
+        choices: vec!["first choice", "second choice"].into_iter()
+            .map(|choice| CreateChoiceCommand {
+                content: None,
+                content_type: "youtube".to_string(),
+                title: choice.to_string(),
+            })
+            .collect(),
+    };
+
+    SurveyDTO::from(&Survey::new(&CreateSurveyCommand {
+        author: "diff_owner".to_string(),
+        title: "favourite albums".to_string(),
+        description: "which albums do you keep coming back to".to_string(),
+        category: "music".to_string(),
+        questions: vec![question("pick your favourite"), question("pick your runner up")],
+    }).unwrap())
+}
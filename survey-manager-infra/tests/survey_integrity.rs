@@ -0,0 +1,22 @@
+//! Checks the integrity checker fails cleanly when it can't reach a database, or can't read the
+//! surveys.  Runs in a binary of its own, since it changes `DATABASE_URL` and `SURVEY_BACKEND` for
+//! the whole process.
+use survey_manager_infra::Error;
+use survey_manager_infra::mysql_repos::SurveyIntegrityChecker;
+
+#[test]
+fn a_missing_database_is_an_error_not_a_panic() {
+    std::env::remove_var("DATABASE_URL");
+
+    assert!(SurveyIntegrityChecker::new().is_err());
+}
+
+#[test]
+fn surveys_outside_the_survey_table_are_refused() {
+    std::env::set_var("SURVEY_BACKEND", "mysql-normalized");
+
+    match SurveyIntegrityChecker::new() {
+        Err(Error::UncheckedBackend { backend }) => assert_eq!(backend, "mysql-normalized"),
+        _ => panic!("expected the normalized backend to be refused"),
+    }
+}
@@ -0,0 +1,153 @@
+//! Checks units of work against the MySQL named by `DATABASE_URL`.  Those tests are ignored by
+//! default, run them with `cargo test -- --ignored`.  Each writes to a table of its own, dropped
+//! when it's done.
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use survey_manager_infra::Error;
+use survey_manager_infra::mysql_repos::{MysqlConfig, MysqlConnection, MysqlPool, TransientFailure};
+
+#[test]
+fn deadlocks_and_lock_waits_are_transient() {
+    assert!(server_error(1213).is_transient());
+    assert!(server_error(1205).is_transient());
+    assert!(!server_error(1062).is_transient());
+    assert!(!Error::DatabaseUnavailable { reason: "down".to_string() }.is_transient());
+}
+
+#[test]
+fn transient_repository_failures_are_seen_through_the_core_error() {
+    let failure = survey_manager_core::Error::from(server_error(1213));
+
+    assert!(failure.is_transient());
+}
+
+#[test]
+#[ignore]
+fn committed_work_is_kept() {
+    let db = Scratch::new();
+
+    db.pool.in_unit_of_work(|unit| -> Result<(), Error> {
+        db.insert(&unit.connection(), 1)?;
+        db.insert(&unit.connection(), 2)
+    }).unwrap();
+
+    assert_eq!(db.count(), 2);
+}
+
+#[test]
+#[ignore]
+fn failed_work_leaves_nothing_behind() {
+    let db = Scratch::new();
+
+    let result = db.pool.in_unit_of_work(|unit| -> Result<(), Error> {
+        db.insert(&unit.connection(), 1)?;
+        Err(Error::DatabaseUnavailable { reason: "given up by the test".to_string() })
+    });
+
+    assert!(result.is_err());
+    assert_eq!(db.count(), 0);
+}
+
+#[test]
+#[ignore]
+fn a_failed_repository_transaction_only_undoes_its_own_writes() {
+    let db = Scratch::new();
+
+    db.pool.in_unit_of_work(|unit| -> Result<(), Error> {
+        let conn = unit.connection();
+        db.insert(&conn, 1)?;
+        let failed: Result<(), Error> = conn.transaction(|c| {
+            c.query(format!("INSERT INTO {} (id) VALUES (2)", db.table))?;
+            Err(Error::DatabaseUnavailable { reason: "given up by the test".to_string() })
+        });
+        assert!(failed.is_err());
+        db.insert(&conn, 3)
+    }).unwrap();
+
+    assert_eq!(db.count(), 2);
+}
+
+#[test]
+#[ignore]
+fn connections_know_whether_a_unit_of_work_is_open() {
+    let db = Scratch::new();
+
+    assert!(!db.pool.connection().unwrap().in_unit_of_work());
+    db.pool.in_unit_of_work(|unit| -> Result<(), Error> {
+        assert!(unit.connection().in_unit_of_work());
+        Ok(())
+    }).unwrap();
+}
+
+#[test]
+#[ignore]
+fn transient_failures_are_run_again() {
+    let db = Scratch::new();
+    let attempts = Cell::new(0);
+
+    db.pool.in_unit_of_work(|unit| {
+        attempts.set(attempts.get() + 1);
+        db.insert(&unit.connection(), attempts.get())?;
+        if attempts.get() == 1 {
+            return Err(server_error(1213));
+        }
+        Ok(())
+    }).unwrap();
+
+    // Only the second attempt's row was committed.
+    assert_eq!(attempts.get(), 2);
+    assert_eq!(db.count(), 1);
+}
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+struct Scratch {
+    pool: MysqlPool,
+    table: String,
+}
+
+impl Scratch {
+    fn new() -> Scratch {
+        dotenv::dotenv().ok();
+        let url = std::env::var("DATABASE_URL")
+            .expect("DATABASE_URL must be set to run the database tests");
+        let pool = MysqlPool::new(MysqlConfig { min_connections: 1, ..MysqlConfig::new(&url) }).unwrap();
+        let table = format!(
+            "uow_{}_{}",
+            chrono::Utc::now().timestamp_nanos() % 1_000_000_000_000,
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        );
+        pool.get_conn().unwrap()
+            .query(format!("CREATE TABLE {} (id INT PRIMARY KEY) ENGINE = InnoDB", table))
+            .unwrap();
+        Scratch { pool, table }
+    }
+
+    fn insert(&self, conn: &MysqlConnection, id: i32) -> Result<(), Error> {
+        conn.lock().prep_exec(format!("INSERT INTO {} (id) VALUES (?)", self.table), (id,))?;
+        Ok(())
+    }
+
+    fn count(&self) -> u64 {
+        let count: Option<u64> = self.pool.get_conn().unwrap()
+            .first(format!("SELECT COUNT(*) FROM {}", self.table))
+            .unwrap();
+        count.unwrap()
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        if let Ok(mut conn) = self.pool.get_conn() {
+            let _ = conn.query(format!("DROP TABLE IF EXISTS {}", self.table));
+        }
+    }
+}
+
+fn server_error(code: u16) -> Error {
+    mysql::Error::MySqlError(mysql::MySqlError {
+        state: "40001".to_string(),
+        message: "raised by the test".to_string(),
+        code,
+    }).into()
+}